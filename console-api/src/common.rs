@@ -194,7 +194,7 @@ impl From<&dyn std::fmt::Debug> for field::Value {
 // or vice versa. However, this is unavoidable here, because `prost` generates
 // a struct with `#[derive(PartialEq)]`, but we cannot add`#[derive(Hash)]` to the
 // generated code.
-#[allow(clippy::derive_hash_xor_eq)]
+#[allow(clippy::derived_hash_with_manual_eq)]
 impl Hash for field::Name {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
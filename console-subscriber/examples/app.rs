@@ -98,7 +98,7 @@ async fn burn(min: u64, max: u64) {
     loop {
         for i in min..max {
             for _ in 0..i {
-                tokio::task::yield_now().await;
+                let _ = tokio::task::yield_now().await;
             }
             tokio::time::sleep(Duration::from_secs(i - min)).await;
         }
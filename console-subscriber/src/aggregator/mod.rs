@@ -7,10 +7,11 @@ use tokio::sync::{mpsc, Notify};
 
 use futures::FutureExt;
 use std::{
+    cmp,
     collections::{hash_map::Entry, HashMap, HashSet},
     convert::TryInto,
     sync::{
-        atomic::{AtomicBool, Ordering::*},
+        atomic::{AtomicBool, AtomicU64, Ordering::*},
         Arc,
     },
     time::{Duration, SystemTime},
@@ -103,6 +104,9 @@ pub(crate) struct Aggregator {
 pub(crate) struct Flush {
     pub(crate) should_flush: Notify,
     triggered: AtomicBool,
+    /// The number of events that the layer dropped because the event buffer
+    /// was full.
+    dropped_events: AtomicU64,
 }
 
 // An entity (e.g Task, Resource) that at some point in
@@ -176,6 +180,9 @@ struct Task {
     metadata: &'static Metadata<'static>,
     fields: Vec<proto::Field>,
     location: Option<proto::Location>,
+    /// The IDs of the tasks this task was spawned within, starting with the
+    /// task that spawned it.
+    parents: Vec<Id>,
 }
 
 struct TaskStats {
@@ -189,6 +196,11 @@ struct TaskStats {
     waker_drops: u64,
     self_wakes: u64,
     last_wake: Option<SystemTime>,
+    /// When the task was first woken since its last poll started, or `None`
+    /// if it hasn't been woken since.
+    pending_wake: Option<SystemTime>,
+    /// The total time the task has waited to be polled after being woken.
+    scheduled_time: Duration,
 
     poll_times_histogram: Histogram<u64>,
     poll_stats: PollStats,
@@ -231,7 +243,7 @@ impl PollStats {
     fn update_on_span_enter(&mut self, timestamp: SystemTime) {
         if self.current_polls == 0 {
             self.last_poll_started = Some(timestamp);
-            if self.first_poll == None {
+            if self.first_poll.is_none() {
                 self.first_poll = Some(timestamp);
             }
             self.polls += 1;
@@ -266,6 +278,8 @@ impl Default for TaskStats {
             waker_drops: 0,
             self_wakes: 0,
             last_wake: None,
+            pending_wake: None,
+            scheduled_time: Duration::ZERO,
             // significant figures should be in the [0-5] range and memory usage
             // grows exponentially with higher a sigfig
             poll_times_histogram: Histogram::<u64>::new(2).unwrap(),
@@ -284,6 +298,7 @@ impl Aggregator {
             flush_capacity: Arc::new(Flush {
                 should_flush: Notify::new(),
                 triggered: AtomicBool::new(false),
+                dropped_events: AtomicU64::new(0),
             }),
             rpcs,
             publish_interval: builder.publish_interval,
@@ -461,6 +476,7 @@ impl Aggregator {
             new_metadata: Some(proto::RegisterMetadata {
                 metadata: (*self.all_metadata).clone(),
             }),
+            dropped_events: self.flush_capacity.dropped_events(),
         };
 
         if subscription.update(update) {
@@ -495,7 +511,7 @@ impl Aggregator {
             {
                 self.details_watchers
                     .entry(id)
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(subscription);
             }
         }
@@ -546,6 +562,7 @@ impl Aggregator {
                     .collect(),
                 stats_update: self.async_op_stats.as_proto(Include::UpdatedOnly),
             }),
+            dropped_events: self.flush_capacity.dropped_events(),
         };
 
         self.watchers
@@ -585,8 +602,14 @@ impl Aggregator {
                 at,
                 fields,
                 location,
+                parents,
             } => {
                 let id = self.ids.id_for(id);
+                // Parents which have already been removed have no ID to send.
+                let parents = parents
+                    .iter()
+                    .filter_map(|parent| self.ids.get(parent))
+                    .collect();
                 self.tasks.insert(
                     id,
                     Task {
@@ -594,7 +617,7 @@ impl Aggregator {
                         metadata,
                         fields,
                         location,
-                        // TODO: parents
+                        parents,
                     },
                 );
 
@@ -610,6 +633,14 @@ impl Aggregator {
             Event::Enter { id, at } => {
                 let id = self.ids.id_for(id);
                 if let Some(mut task_stats) = self.task_stats.update(&id) {
+                    // Only the first enter of a poll ends the wait, since the
+                    // span may be entered again within the same poll.
+                    if task_stats.poll_stats.current_polls == 0 {
+                        if let Some(woken_at) = task_stats.pending_wake.take() {
+                            task_stats.scheduled_time +=
+                                at.duration_since(woken_at).unwrap_or_default();
+                        }
+                    }
                     task_stats.poll_stats.update_on_span_enter(at);
                 }
 
@@ -622,6 +653,14 @@ impl Aggregator {
                 let id = self.ids.id_for(id);
                 if let Some(mut task_stats) = self.task_stats.update(&id) {
                     task_stats.poll_stats.update_on_span_exit(at);
+                    // A task woken while it's being polled can't be polled
+                    // again until the poll ends, so that's when its wait
+                    // starts.
+                    if task_stats.poll_stats.current_polls == 0 {
+                        if let Some(ref mut woken_at) = task_stats.pending_wake {
+                            *woken_at = cmp::max(*woken_at, at);
+                        }
+                    }
                     if let Some(since_last_poll) = task_stats.poll_stats.since_last_poll(at) {
                         task_stats
                             .poll_times_histogram
@@ -663,6 +702,7 @@ impl Aggregator {
                         WakeOp::Wake { self_wake } | WakeOp::WakeByRef { self_wake } => {
                             task_stats.wakes += 1;
                             task_stats.last_wake = Some(at);
+                            task_stats.pending_wake.get_or_insert(at);
 
                             // If the  task has woken itself, increment the
                             // self-wake count.
@@ -831,6 +871,15 @@ impl Flush {
         }
     }
 
+    /// Records that an event was dropped because the event buffer was full.
+    pub(crate) fn dropped_event(&self) {
+        self.dropped_events.fetch_add(1, Relaxed);
+    }
+
+    fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Relaxed)
+    }
+
     /// Indicates that the buffer has been successfully flushed.
     fn has_flushed(&self) {
         let _ = self
@@ -873,7 +922,7 @@ impl ToProto for Task {
             // TODO: more kinds of tasks...
             kind: proto::tasks::task::Kind::Spawn as i32,
             metadata: Some(self.metadata.into()),
-            parents: Vec::new(), // TODO: implement parents nicely
+            parents: self.parents.iter().map(|&id| id.into()).collect(),
             fields: self.fields.clone(),
             location: self.location.clone(),
         }
@@ -893,10 +942,28 @@ impl ToProto for TaskStats {
             self_wakes: self.self_wakes,
             waker_drops: self.waker_drops,
             last_wake: self.last_wake.map(Into::into),
+            shortest_poll: self
+                .poll_time(self.poll_times_histogram.min())
+                .map(Into::into),
+            longest_poll: self
+                .poll_time(self.poll_times_histogram.max())
+                .map(Into::into),
+            scheduled_time: Some(self.scheduled_time.into()),
         }
     }
 }
 
+impl TaskStats {
+    /// Converts a value from `poll_times_histogram` to a duration, or `None`
+    /// if no polls have been recorded.
+    fn poll_time(&self, nanos: u64) -> Option<Duration> {
+        if self.poll_times_histogram.is_empty() {
+            return None;
+        }
+        Some(Duration::from_nanos(nanos))
+    }
+}
+
 impl ToProto for Resource {
     type Output = proto::resources::Resource;
 
@@ -974,6 +1041,12 @@ impl Ids {
         }
     }
 
+    /// Returns the ID already assigned to `span_id`, without assigning one if
+    /// it has none.
+    fn get(&self, span_id: &span::Id) -> Option<Id> {
+        self.id_mappings.get(span_id).copied()
+    }
+
     #[inline]
     fn remove_all(&mut self, ids: &HashSet<Id>) {
         self.id_mappings.retain(|_, id| !ids.contains(id));
@@ -1035,3 +1108,111 @@ fn update_attribute(attribute: &mut Attribute, update: AttributeUpdate) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TasksLayer;
+    use std::{convert::TryFrom, future::Future};
+    use tracing_subscriber::prelude::*;
+
+    /// Runs the future returned by `f` on a current-thread runtime whose
+    /// tasks are recorded by a `TasksLayer`, returning an aggregator which
+    /// has processed every recorded event.
+    fn record<F: Future>(f: impl FnOnce() -> F) -> Aggregator {
+        let (layer, mut server) = TasksLayer::builder().build();
+        let mut aggregator = server
+            .aggregator
+            .take()
+            .expect("the server owns the aggregator");
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("the runtime should build");
+            runtime.block_on(f());
+        });
+        while let Ok(event) = aggregator.events.try_recv() {
+            aggregator.update_state(event);
+        }
+        aggregator
+    }
+
+    /// Returns the recorded tasks, in the order they were spawned, with their
+    /// stats. The first task is the future the runtime was blocked on.
+    fn tasks(aggregator: &Aggregator) -> Vec<(proto::tasks::Task, proto::tasks::Stats)> {
+        let mut tasks = aggregator
+            .tasks
+            .all()
+            .map(|(id, task)| {
+                let stats = aggregator.task_stats.get(id).expect("tasks have stats");
+                (task.to_proto(), stats.to_proto())
+            })
+            .collect::<Vec<_>>();
+        tasks.sort_by_key(|(task, _)| task.id.as_ref().map(|id| id.id));
+        tasks
+    }
+
+    #[test]
+    fn scheduled_time_grows_between_a_wake_and_the_next_poll() {
+        const BLOCKED_FOR: Duration = Duration::from_millis(50);
+        let aggregator = record(|| async {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let waiter = tokio::spawn(async move { rx.await.unwrap() });
+            tokio::spawn(async move {
+                tx.send(()).unwrap();
+                // The woken waiter can't be polled on this thread until this
+                // task's poll ends.
+                std::thread::sleep(BLOCKED_FOR);
+            })
+            .await
+            .unwrap();
+            waiter.await.unwrap();
+        });
+
+        let tasks = tasks(&aggregator);
+        assert_eq!(tasks.len(), 3);
+        let scheduled = |stats: &proto::tasks::Stats| {
+            let scheduled = stats
+                .scheduled_time
+                .clone()
+                .expect("scheduled time is sent");
+            Duration::try_from(scheduled).expect("scheduled time is positive")
+        };
+        let (_, waiter) = &tasks[1];
+        assert!(waiter.wakes > 0);
+        assert!(scheduled(waiter) >= BLOCKED_FOR, "{:?}", waiter);
+        // The sender was never woken, so it never waited to be polled.
+        let (_, sender) = &tasks[2];
+        assert_eq!(sender.wakes, 0);
+        assert_eq!(scheduled(sender), Duration::ZERO);
+    }
+
+    #[test]
+    fn spawned_tasks_report_the_task_which_spawned_them() {
+        let aggregator = record(|| async {
+            tokio::spawn(async {
+                tokio::spawn(async {}).await.unwrap();
+            })
+            .await
+            .unwrap();
+        });
+
+        let tasks = tasks(&aggregator);
+        let ids = tasks
+            .iter()
+            .map(|(task, _)| task.id.as_ref().expect("tasks have IDs").id)
+            .collect::<Vec<_>>();
+        let parents = tasks
+            .iter()
+            .map(|(task, _)| task.parents.iter().map(|id| id.id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 3);
+        // Each task's parents start with the task which spawned it, followed
+        // by the tasks which spawned that one.
+        assert!(parents[0].is_empty());
+        assert_eq!(parents[1], [ids[0]]);
+        assert_eq!(parents[2], [ids[1], ids[0]]);
+    }
+}
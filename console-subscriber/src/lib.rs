@@ -115,6 +115,9 @@ enum Event {
         at: SystemTime,
         fields: Vec<proto::Field>,
         location: Option<proto::Location>,
+        /// The spans of the tasks this task was spawned within, starting with
+        /// the task that spawned it.
+        parents: Vec<span::Id>,
     },
     Enter {
         id: span::Id,
@@ -329,7 +332,9 @@ impl TasksLayer {
                 // this shouldn't happen, since we trigger a flush when
                 // approaching the high water line...but if the executor wait
                 // time is very high, maybe the aggregator task hasn't been
-                // polled yet. so... eek?!
+                // polled yet. so... eek?! count it, so that the console can
+                // tell that its data is incomplete.
+                self.flush.dropped_event();
             }
         }
 
@@ -361,19 +366,30 @@ where
         subscriber::Interest::always()
     }
 
-    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, _: Context<'_, S>) {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, cx: Context<'_, S>) {
         let metadata = attrs.metadata();
         if self.is_spawn(metadata) {
             let at = SystemTime::now();
             let mut task_visitor = TaskVisitor::new(metadata.into());
             attrs.record(&mut task_visitor);
             let (fields, location) = task_visitor.result();
+            // A task spawned while another task is being polled is inside
+            // that task's span, so the tasks it was spawned by are the task
+            // spans among its ancestors.
+            let parents = cx
+                .span(id)
+                .into_iter()
+                .flat_map(|span| span.scope().skip(1))
+                .filter(|span| self.is_spawn(span.metadata()))
+                .map(|span| span.id())
+                .collect();
             self.send(Event::Spawn {
                 id: id.clone(),
                 at,
                 metadata,
                 fields,
                 location,
+                parents,
             });
         } else if self.is_resource(metadata) {
             let mut resource_visitor = ResourceVisitor::default();
@@ -0,0 +1,47 @@
+//! Access to the system clipboard.
+//!
+//! Clipboard support requires the `clipboard` feature flag. When the console
+//! is built without it, every attempt to copy returns an error explaining
+//! how to enable it, so callers never need to `cfg` on the feature.
+use color_eyre::eyre::{self, WrapErr};
+
+#[derive(Default)]
+pub(crate) struct Clipboard {
+    // The clipboard handle is created lazily on the first copy, since
+    // connecting to the system clipboard may fail (e.g. on a headless machine)
+    // and we don't want that to prevent the console from starting.
+    //
+    // On some platforms (notably X11), the copied text is only available for
+    // as long as the handle is alive, so it's held for the console's lifetime.
+    #[cfg(feature = "clipboard")]
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    #[cfg(feature = "clipboard")]
+    pub(crate) fn set_text(&mut self, text: String) -> eyre::Result<()> {
+        let clipboard = match self.inner {
+            Some(ref mut clipboard) => clipboard,
+            None => self
+                .inner
+                .get_or_insert(arboard::Clipboard::new().wrap_err("failed to access clipboard")?),
+        };
+        clipboard
+            .set_text(text)
+            .wrap_err("failed to copy to the clipboard")
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    pub(crate) fn set_text(&mut self, _: String) -> eyre::Result<()> {
+        Err(eyre::eyre!(
+            "clipboard support is disabled (rebuild with `--features clipboard`)"
+        ))
+        .wrap_err("failed to copy to the clipboard")
+    }
+}
+
+impl std::fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clipboard").finish()
+    }
+}
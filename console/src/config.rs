@@ -1,8 +1,25 @@
-use crate::view::Palette;
+use crate::{
+    input,
+    state::{
+        async_ops::{self, AsyncOp},
+        locations::SpawnLocation,
+        rate::RateWindow,
+        resources::Resource,
+        tasks::{SortBy, Task, TaskKey},
+    },
+    view::{self, DurPrecision, Palette, SortDirection, Theme, TopMetric},
+    warnings::{self, Linter},
+};
 use clap::{ArgGroup, Parser as Clap, ValueHint};
+use color_eyre::eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tonic::transport::Uri;
 
 #[derive(Clap, Debug)]
@@ -61,11 +78,468 @@ pub struct Config {
     /// * `years`, `year`, `y` -- defined as 365.25 days
     #[clap(long = "retain-for", default_value = "6s")]
     retain_for: RetainFor,
+
+    /// How long an async op may exist without completing before a warning is
+    /// displayed for it.
+    ///
+    /// This is parsed as a combination of time spans, in the same format as
+    /// `--retain-for`.
+    #[clap(long = "async-op-deadline", default_value = "1m")]
+    async_op_deadline: humantime::Duration,
+
+    /// How often to redraw the screen, other than in response to key presses.
+    ///
+    /// Updates from the target are still received as often as the target
+    /// sends them, so this only changes how often they're displayed. It can
+    /// be changed while the console is running, and is limited to between
+    /// 100ms and 10s.
+    ///
+    /// This is parsed as a combination of time spans, in the same format as
+    /// `--retain-for`.
+    #[clap(long = "refresh-interval", default_value = "1s")]
+    refresh_interval: humantime::Duration,
+
+    /// How far back rates, such as how often tasks are woken, are measured
+    /// over.
+    ///
+    /// Rates measured over a longer window change less from one update to the
+    /// next. While the console is running, this can be switched between 1s,
+    /// 5s and 15s. It's limited to between 100ms and 60s, and is parsed as a
+    /// combination of time spans, in the same format as `--retain-for`.
+    #[clap(long = "rate-window", default_value = "1s")]
+    rate_window: humantime::Duration,
+
+    /// The longest to wait between attempts to connect to the target, after
+    /// the connection is lost or an attempt fails.
+    ///
+    /// The first wait is half a second, and each failed attempt doubles it, up
+    /// to this long. This is parsed as a combination of time spans, in the
+    /// same format as `--retain-for`.
+    #[clap(long = "max-reconnect-backoff", default_value = "10s")]
+    max_reconnect_backoff: humantime::Duration,
+
+    /// Give up connecting to the target after this many attempts in a row
+    /// fail.
+    ///
+    /// Once the console has given up, pressing `n` tries again. By default,
+    /// the console never gives up.
+    #[clap(long = "max-reconnect-attempts", value_name = "ATTEMPTS")]
+    pub(crate) max_reconnect_attempts: Option<u32>,
+
+    /// Briefly highlight a task's busy time or poll count when it changes by
+    /// more than this percentage between updates.
+    ///
+    /// By default, changes are not highlighted.
+    #[clap(long = "highlight-changes", value_name = "PERCENT")]
+    pub(crate) highlight_changes: Option<f64>,
+
+    /// The maximum number of bytes of strings (such as task names, targets,
+    /// and span fields) that the console will intern.
+    ///
+    /// Once this is exceeded, new strings are stored separately for each use
+    /// rather than being shared, and a warning is logged. By default, there is
+    /// no limit.
+    #[clap(long = "max-interned-bytes")]
+    pub(crate) max_interned_bytes: Option<usize>,
+
+    /// The maximum length in bytes of each string (such as a task name or a
+    /// span field) that the console will intern.
+    ///
+    /// Longer strings, which can be sent by a misbehaving target, are
+    /// truncated and end in `…`. By default, there is no limit.
+    #[clap(long = "max-interned-string-len", value_name = "BYTES")]
+    pub(crate) max_interned_string_len: Option<usize>,
+
+    /// The maximum number of tasks that the console will track.
+    ///
+    /// When there are more tasks than this, the tasks that completed longest
+    /// ago are removed, even if they completed within the `--retain-for`
+    /// period. Running, idle, and pinned tasks are never removed. By default,
+    /// there is no limit.
+    #[clap(long = "max-tasks")]
+    pub(crate) max_tasks: Option<usize>,
+
+    /// How much unused memory, in bytes, the interned strings may hold on to
+    /// after strings that are no longer used are dropped, before that memory
+    /// is released.
+    ///
+    /// Lower values release memory more aggressively, at the cost of more
+    /// frequent reallocation.
+    #[clap(long = "interner-shrink-threshold", default_value = "4096")]
+    pub(crate) interner_shrink_threshold: usize,
+
+    /// Correct durations for timestamps which are ahead of the target's
+    /// clock.
+    ///
+    /// If the target's clock jumps backwards, timestamps recorded before the
+    /// jump are in the future, and durations measured from them read as zero.
+    /// This is always detected and displayed in the header; with this flag,
+    /// durations are also measured to a time moved forward by how far ahead
+    /// the timestamps are.
+    #[clap(long = "correct-clock-skew")]
+    pub(crate) correct_clock_skew: bool,
+
+    /// Path to a TOML configuration file.
+    ///
+    /// If this is not provided, the console will use `tokio-console/console.toml`
+    /// in the user's configuration directory (such as `~/.config` on Linux),
+    /// if that file exists.
+    #[clap(long = "config", value_hint = ValueHint::FilePath)]
+    config_path: Option<PathBuf>,
+
+    /// Export the warnings detected in the target process as JSON to the
+    /// provided path, rather than running interactively.
+    ///
+    /// The console connects to the target, collects data for the duration set
+    /// by `--export-duration`, writes the warnings, and exits. The exit code
+    /// is 0 if no warnings were detected, 1 if any warnings were detected, and
+    /// 2 if the console could not connect to the target.
+    #[clap(long = "export-warnings", value_hint = ValueHint::FilePath)]
+    pub(crate) export_warnings: Option<PathBuf>,
+
+    /// Export a snapshot of the target process's tasks and async ops as JSON
+    /// to the provided path, rather than running interactively.
+    ///
+    /// Like `--export-warnings`, the console collects data for the duration
+    /// set by `--export-duration` before writing the snapshot. Both may be
+    /// exported at once.
+    #[clap(long = "export-snapshot", value_hint = ValueHint::FilePath)]
+    pub(crate) export_snapshot: Option<PathBuf>,
+
+    /// Export the total busy time of the target process's tasks, summed by
+    /// where they were spawned, to the provided path in the folded stacks
+    /// format, rather than running interactively.
+    ///
+    /// The file can be rendered as a flamegraph with `inferno-flamegraph` or
+    /// `flamegraph.pl`. Busy times are in microseconds, and tasks whose
+    /// location wasn't recorded are counted under `unknown`. Like
+    /// `--export-warnings`, the console collects data for the duration set by
+    /// `--export-duration` first, and this may be combined with the other
+    /// exports.
+    #[clap(long = "export-folded", value_hint = ValueHint::FilePath)]
+    pub(crate) export_folded: Option<PathBuf>,
+
+    /// Start each stack exported by `--export-folded`, or by the export busy
+    /// time key, with the target of the tasks spawned at its location.
+    #[clap(long = "folded-by-target")]
+    pub(crate) folded_by_target: bool,
+
+    /// Write the tasks and async ops changed by each update from the target
+    /// to the provided path as JSON lines, while running interactively.
+    ///
+    /// Each line is a JSON object with the update's time in milliseconds
+    /// since the Unix epoch, the changed tasks and async ops, and how many
+    /// lines have been dropped so far. If the file can't be written to as
+    /// fast as updates arrive, lines are dropped rather than slowing down the
+    /// console.
+    #[clap(long = "json-stream", value_hint = ValueHint::FilePath)]
+    pub(crate) json_stream: Option<PathBuf>,
+
+    /// Serve the console's own stats at `/metrics` on the provided address, in
+    /// the Prometheus text format, while running interactively.
+    ///
+    /// The stats include the number of interned strings, the number of tasks
+    /// and async ops being tracked, the number of events the target has
+    /// dropped, and how long drawing the last frame took. This requires
+    /// building the console with the `metrics` feature flag enabled.
+    #[clap(long = "metrics-addr")]
+    pub(crate) metrics_addr: Option<SocketAddr>,
+
+    /// Record the updates received from the target to the provided path, so
+    /// that they can be replayed later with `--replay`.
+    ///
+    /// Task details, such as poll time histograms, are not recorded.
+    #[clap(long = "record", value_hint = ValueHint::FilePath)]
+    pub(crate) record: Option<PathBuf>,
+
+    /// Replay the recording at the provided path, made with `--record`,
+    /// rather than connecting to a target.
+    ///
+    /// Updates are replayed with the time between them that they were
+    /// recorded with, divided by `--replay-speed`. Pausing the console pauses
+    /// the replay.
+    #[clap(long = "replay", value_hint = ValueHint::FilePath)]
+    pub(crate) replay: Option<PathBuf>,
+
+    /// How many times faster than it was recorded to replay a recording.
+    ///
+    /// This must be at least 0.001. By default, recordings are replayed at the
+    /// speed they were recorded.
+    #[clap(long = "replay-speed", value_name = "FACTOR", requires = "replay")]
+    replay_speed: Option<ReplaySpeed>,
+
+    /// Print the target process's tasks as a plain-text table to stdout, and
+    /// exit, rather than running interactively.
+    ///
+    /// The console connects to the target and waits for one update after the
+    /// target's initial state, so that the tasks' stats cover one sampling
+    /// interval. If the updates aren't received within 30 seconds, it exits
+    /// with an error.
+    #[clap(
+        long = "dump",
+        conflicts_with_all = &["export-warnings", "export-snapshot", "export-folded"]
+    )]
+    pub(crate) dump: bool,
+
+    /// Compare two snapshots exported by `--export-snapshot` from the same
+    /// run of the target, print which tasks were added, removed, or grew in
+    /// busy time or polls between them, and exit.
+    ///
+    /// Tasks whose growth is below both `--diff-min-busy` and
+    /// `--diff-min-polls` aren't listed as changed.
+    #[clap(
+        long = "diff-snapshots",
+        number_of_values = 2,
+        value_names = &["BEFORE", "AFTER"],
+        value_hint = ValueHint::FilePath,
+    )]
+    pub(crate) diff_snapshots: Vec<PathBuf>,
+
+    /// How much longer a task must have been busy in the second snapshot for
+    /// `--diff-snapshots` to list it as changed.
+    ///
+    /// This is parsed as a combination of time spans, in the same format as
+    /// `--retain-for`.
+    #[clap(long = "diff-min-busy", default_value = "1ms")]
+    diff_min_busy: humantime::Duration,
+
+    /// How many more times a task must have been polled in the second
+    /// snapshot for `--diff-snapshots` to list it as changed.
+    #[clap(long = "diff-min-polls", default_value = "1")]
+    diff_min_polls: u64,
+
+    /// The column to sort the tasks printed by `--dump` by, with the highest
+    /// values first.
+    ///
+    /// This is one of `warns`, `tid`, `state`, `name`, `total`, `busy`,
+    /// `idle`, `polls`, `poll_times`, `activity`, `wake_rate`, `target`, or
+    /// `location`. By default, tasks are sorted by `total`.
+    #[clap(long = "sort", requires = "dump")]
+    pub(crate) sort: Option<SortBy>,
+
+    /// The direction to sort each table column in the first time the table
+    /// is sorted by it, either `descending` or `ascending`.
+    ///
+    /// Each column is sorted in the direction it was last sorted in, and these
+    /// directions are saved when the console exits, so this only applies to
+    /// columns which have never been sorted by.
+    #[clap(
+        long = "sort-direction",
+        possible_values = &["descending", "ascending"],
+        default_value = "descending"
+    )]
+    pub(crate) sort_direction: SortDirection,
+
+    /// The metric that the panel of top tasks ranks tasks by, either `busy`,
+    /// `polls`, `wakes` or `scheduled`.
+    ///
+    /// The panel is displayed next to the tasks list by pressing `O`, and
+    /// `I` switches between the metrics while the console is running.
+    #[clap(
+        long = "top-tasks-by",
+        value_name = "METRIC",
+        possible_values = &["busy", "polls", "wakes", "scheduled"],
+        default_value = "busy"
+    )]
+    pub(crate) top_tasks_by: TopMetric,
+
+    /// How many tasks the panel of top tasks lists.
+    #[clap(long = "top-tasks", value_name = "COUNT", default_value = "5")]
+    pub(crate) top_tasks: usize,
+
+    /// How long to collect data for before exporting warnings, a snapshot or
+    /// busy time, when running with `--export-warnings`, `--export-snapshot`
+    /// or `--export-folded`.
+    ///
+    /// This is parsed as a combination of time spans, in the same format as
+    /// `--retain-for`.
+    #[clap(long = "export-duration", default_value = "10s")]
+    export_duration: humantime::Duration,
 }
 
+/// Settings loaded from the console's configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct ConfigFile {
+    /// Overrides for the default key bindings.
+    pub(crate) keys: HashMap<input::Action, Vec<input::Key>>,
+    /// Which warnings are displayed, and their thresholds.
+    pub(crate) warnings: WarningsConfig,
+    /// How many digits are displayed after the decimal point of durations.
+    pub(crate) precision: DurPrecision,
+    /// Which columns the tables display.
+    pub(crate) columns: ColumnsConfig,
+    /// Colors which replace the built-in colors.
+    pub(crate) theme: ThemeConfig,
+}
+
+/// The `[theme]` section of the configuration file, which replaces the
+/// built-in colors for some kinds of text.
+///
+/// ```toml
+/// [theme]
+/// warning = "light-yellow"
+/// critical = "#ff0000"
+/// time_units = ["blue", "green", "yellow", "red", "magenta"]
+/// ```
+///
+/// Any colors which aren't set keep their built-in colors.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct ThemeConfig {
+    warning: Option<String>,
+    critical: Option<String>,
+    /// The colors of picosecond, nanosecond, microsecond, millisecond and
+    /// second durations, in that order.
+    time_units: Option<Vec<String>>,
+    borders: Option<String>,
+    selection: Option<String>,
+    paused_badge: Option<String>,
+}
+
+/// The `[columns]` section of the configuration file, which sets the columns
+/// a table displays when the console starts.
+///
+/// ```toml
+/// [columns]
+/// async_ops = ["aid", "source", "total", "polls"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct ColumnsConfig {
+    /// The names of the async ops table's columns to display, or `None` to
+    /// display every column.
+    async_ops: Option<Vec<String>>,
+}
+
+/// The `[warnings]` section of the configuration file.
+///
+/// ```toml
+/// [warnings]
+/// self_wake_percent = [50, 90]
+/// lost_wakers = false
+/// ```
+///
+/// Each threshold may be a single value or a list of values, in which case a
+/// separate warning is shown for each of them.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct WarningsConfig {
+    /// Whether to warn about tasks that wake themselves too often.
+    self_wakes: bool,
+    /// The percentage of a task's wakeups that may be self-wakes before it is
+    /// warned about.
+    self_wake_percent: Thresholds<u64>,
+    /// Whether to warn about tasks that have lost their wakers.
+    lost_wakers: bool,
+    /// Whether to warn about completed tasks whose wakers still exist.
+    completed_with_wakers: bool,
+    /// Whether to warn about tasks with a high 99th percentile poll duration.
+    slow_polls: bool,
+    /// The 99th percentile poll duration, in milliseconds, that a task may
+    /// have before it is warned about.
+    max_poll_p99_ms: Thresholds<u64>,
+    /// Whether to warn about tasks which wait a long time to be polled after
+    /// being woken.
+    slow_scheduling: bool,
+    /// The average time, in milliseconds, that a task may wait to be polled
+    /// after each wake before it is warned about.
+    max_scheduled_per_wake_ms: Thresholds<u64>,
+    /// How many times a task must have been woken before it is warned about
+    /// for waiting to be polled.
+    slow_scheduling_min_wakes: u64,
+    /// Whether to warn about tasks which the scheduler isn't getting to.
+    starved: bool,
+    /// The percentage of a task's total time that it may spend waiting to be
+    /// polled before it is warned about.
+    starved_percent: Thresholds<u64>,
+    /// How long, in milliseconds, a task must have been waiting without being
+    /// polled before it is warned about for being starved.
+    starved_window_ms: u64,
+    /// Whether to warn about tasks that haven't been polled since they were
+    /// spawned.
+    never_polled: bool,
+    /// How long, in milliseconds, a task may go without being polled after it
+    /// is spawned before it is warned about.
+    never_polled_grace_ms: u64,
+    /// Whether to warn about tasks whose spawn location wasn't recorded.
+    no_location: bool,
+    /// Whether to warn about async tasks which are busy nearly all of the
+    /// time.
+    cpu_bound: bool,
+    /// The percentage of a task's total time that it may be busy for before
+    /// it is warned about.
+    cpu_bound_percent: Thresholds<u64>,
+    /// How long, in milliseconds, a task must have existed before it is
+    /// warned about for being busy.
+    cpu_bound_min_ms: u64,
+    /// Whether to warn about async tasks which run for a long time without
+    /// yielding within their first polls.
+    never_yielded: bool,
+    /// How long, in milliseconds, a task may run without yielding before it
+    /// is warned about.
+    never_yielded_ms: Thresholds<u64>,
+    /// How many of a task's first polls are checked for running without
+    /// yielding.
+    never_yielded_max_polls: u64,
+    /// How long, in milliseconds, tasks whose targets are in each module may
+    /// run without yielding, overriding `never_yielded_ms`. The longest
+    /// module path matching a task's target is used.
+    never_yielded_targets: HashMap<String, u64>,
+    /// Whether to warn about locations which spawn many short-lived tasks.
+    high_churn: bool,
+    /// The number of tasks a location may spawn within 10 seconds before it
+    /// is warned about.
+    high_churn_spawns: usize,
+    /// The average lifetime, in milliseconds, under which a location's tasks
+    /// are considered short-lived.
+    high_churn_lifetime_ms: u64,
+    /// Whether to warn about async ops that take longer than
+    /// `--async-op-deadline` to complete.
+    never_completed: bool,
+    /// Whether to warn about async ops polled far more than the other async
+    /// ops on their resource.
+    poll_outliers: bool,
+    /// How many times as often as the other async ops on its resource an
+    /// async op may be polled before it is warned about.
+    poll_outlier_factor: Thresholds<u64>,
+    /// How many times an async op must have been polled before it is warned
+    /// about for being polled more than the others on its resource.
+    poll_outlier_min_polls: u64,
+    /// Whether to warn about async ops which aren't polled while their task
+    /// is.
+    stagnant_polls: bool,
+    /// How long, in milliseconds, an async op may go without being polled
+    /// while its task is polled before it is warned about.
+    stagnant_polls_window_ms: u64,
+    /// How many times an async op's task must have been polled while the op
+    /// wasn't for the op to be warned about.
+    stagnant_polls_task_polls: u64,
+    /// Whether to warn about resources with too many pending async ops.
+    pending_async_ops: bool,
+    /// The number of pending async ops a resource may have before it is
+    /// warned about.
+    max_pending_async_ops: Thresholds<usize>,
+    /// Any keys that aren't recognized, which are logged rather than rejected
+    /// so that config files remain usable by older versions of the console.
+    #[serde(flatten)]
+    unknown: HashMap<String, toml::Value>,
+}
+
+/// A warning threshold in the config file, which may be either a single value
+/// or a list of values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Thresholds<T> {
+    One(T),
+    Many(Vec<T>),
+}
 #[derive(Debug)]
 struct RetainFor(Option<Duration>);
 
+#[derive(Debug, Copy, Clone)]
+struct ReplaySpeed(f64);
+
 #[derive(Clap, Debug, Clone)]
 #[clap(group = ArgGroup::new("colors").conflicts_with("no-colors"))]
 pub struct ViewOptions {
@@ -130,9 +604,12 @@ impl Config {
         let journald = tracing_journald::layer().ok();
 
         // Otherwise, log to stderr and rely on the user redirecting output.
+        // Span close events include how long the span was busy for, so that
+        // the console's own update spans can be used to profile it.
         let fmt = if journald.is_none() {
             Some(
                 tracing_subscriber::fmt::layer()
+                    .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
                     .with_writer(std::io::stderr)
                     .with_ansi(atty::is(atty::Stream::Stderr)),
             )
@@ -152,6 +629,528 @@ impl Config {
     pub(crate) fn retain_for(&self) -> Option<Duration> {
         self.retain_for.0
     }
+
+    pub(crate) fn export_duration(&self) -> Duration {
+        self.export_duration.into()
+    }
+
+    pub(crate) fn diff_thresholds(&self) -> crate::diff::Thresholds {
+        crate::diff::Thresholds {
+            busy: self.diff_min_busy.into(),
+            polls: self.diff_min_polls,
+        }
+    }
+
+    pub(crate) fn async_op_deadline(&self) -> Duration {
+        self.async_op_deadline.into()
+    }
+
+    pub(crate) fn refresh_interval(&self) -> Duration {
+        self.refresh_interval.into()
+    }
+
+    pub(crate) fn rate_window(&self) -> RateWindow {
+        RateWindow::new(self.rate_window.into())
+    }
+
+    pub(crate) fn max_reconnect_backoff(&self) -> Duration {
+        self.max_reconnect_backoff.into()
+    }
+
+    pub(crate) fn replay_speed(&self) -> f64 {
+        self.replay_speed.map_or(1.0, |speed| speed.0)
+    }
+
+    /// Loads the configuration file.
+    ///
+    /// It is an error if a config file was explicitly provided but cannot be
+    /// read. If no path was provided and the default config file does not
+    /// exist, the default configuration is used.
+    pub(crate) fn config_file(&self) -> color_eyre::Result<ConfigFile> {
+        let path = match self.config_path {
+            Some(ref path) => path.clone(),
+            None => match ConfigFile::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(ConfigFile::default()),
+            },
+        };
+        ConfigFile::load(&path)
+    }
+
+    /// Returns a watch on the config file, which is the file passed to
+    /// `--config`, or otherwise the default config file, even if it doesn't
+    /// exist yet.
+    pub(crate) fn config_watch(&self) -> ConfigWatch {
+        let path = self.config_path.clone().or_else(ConfigFile::default_path);
+        let modified = path.as_deref().and_then(modified_at);
+        ConfigWatch { path, modified }
+    }
+}
+
+/// Checks the config file for changes, so that the warnings can be
+/// reconfigured without restarting the console.
+///
+/// Files are checked by their modification time, rather than by watching for
+/// filesystem events, since checking once a second is cheap.
+#[derive(Debug)]
+pub(crate) struct ConfigWatch {
+    path: Option<PathBuf>,
+    /// The modification time of the file as of the last check, or `None` if
+    /// it didn't exist.
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatch {
+    /// How often the config file is checked for changes.
+    pub(crate) const INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Reloads the config file if it has been modified since the last check,
+    /// returning `None` if it hasn't been, or has been deleted.
+    pub(crate) fn check(&mut self) -> Option<color_eyre::Result<ConfigFile>> {
+        let path = self.path.as_deref()?;
+        let modified = modified_at(path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        // If the file was deleted, keep the current configuration.
+        modified.map(|_| ConfigFile::load(path))
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// State that is saved when the console exits, and restored the next time it
+/// starts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SavedState {
+    /// The targets seen most often in the last session, which are interned
+    /// when the console starts.
+    // This must come before the other fields, since TOML values must be
+    // serialized before tables.
+    pub(crate) targets: Vec<String>,
+    pub(crate) tasks: Option<SavedSort>,
+    pub(crate) resources: Option<SavedSort>,
+    pub(crate) async_ops: Option<SavedSort>,
+    // Empty arrays would be serialized as values after the tables above, so
+    // they are skipped.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) pinned: Vec<SavedPin>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) acknowledged: Vec<SavedAck>,
+}
+
+/// The column a table was sorted by, and the direction it was sorted in.
+///
+/// This also records the widths that the table's columns were resized to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedSort {
+    pub(crate) column: usize,
+    pub(crate) descending: bool,
+    /// The columns which were last sorted with the highest values at the top.
+    #[serde(default)]
+    pub(crate) highest_first: Vec<usize>,
+    /// The columns which were last sorted with the lowest values at the top.
+    #[serde(default)]
+    pub(crate) lowest_first: Vec<usize>,
+    /// The columns which were resized. This must come last, since it is
+    /// serialized as an array of tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) widths: Vec<SavedWidth>,
+}
+
+/// The width that a table's column was resized to.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedWidth {
+    pub(crate) column: usize,
+    pub(crate) width: u16,
+}
+
+/// A task that was pinned in a previous session.
+///
+/// Task IDs change when the target restarts, so the task is identified by
+/// where it was spawned instead, and the pin is restored for the first task
+/// with the same key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedPin {
+    /// When a task with this key was last seen, in seconds since the Unix
+    /// epoch, so that pins which never match a task again expire.
+    // This must come before `task`, since TOML values must be serialized
+    // before tables.
+    pub(crate) last_seen: u64,
+    pub(crate) task: TaskKey,
+}
+
+/// A warning that was acknowledged for a task in a previous session.
+///
+/// Like [`SavedPin`], this is restored for the first task with the same key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SavedAck {
+    pub(crate) last_seen: u64,
+    /// The summary of the acknowledged warning.
+    pub(crate) warning: String,
+    /// The warning's metric when it was acknowledged, so that it is only
+    /// suppressed until the metric changes, as in the previous session.
+    pub(crate) metric: u64,
+    pub(crate) task: TaskKey,
+}
+
+// === impl ConfigFile ===
+
+impl ConfigFile {
+    fn load(path: &Path) -> color_eyre::Result<Self> {
+        tracing::debug!(path = %path.display(), "loading config file");
+        let contents = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read config file `{}`", path.display()))?;
+        let mut file: ConfigFile = toml::from_str(&contents)
+            .wrap_err_with(|| format!("failed to parse config file `{}`", path.display()))?;
+        file.warnings.validate();
+        file.precision.validate();
+        Ok(file)
+    }
+
+    fn default_path() -> Option<PathBuf> {
+        let mut path = dirs::config_dir()?;
+        path.push("tokio-console");
+        path.push("console.toml");
+        Some(path)
+    }
+}
+
+// === impl WarningsConfig ===
+
+impl WarningsConfig {
+    /// Logs any unknown keys, and clamps out-of-range thresholds to the
+    /// nearest valid value.
+    fn validate(&mut self) {
+        for key in self.unknown.keys() {
+            tracing::warn!(key = %key, "ignoring unknown key in `[warnings]` config");
+        }
+
+        for percent in self.self_wake_percent.values_mut() {
+            if *percent > 100 {
+                tracing::warn!(
+                    self_wake_percent = *percent,
+                    "`self_wake_percent` is over 100%, using 100%"
+                );
+                *percent = 100;
+            }
+        }
+
+        for factor in self.poll_outlier_factor.values_mut() {
+            if *factor < 2 {
+                tracing::warn!(
+                    poll_outlier_factor = *factor,
+                    "`poll_outlier_factor` is under 2, using 2"
+                );
+                *factor = 2;
+            }
+        }
+    }
+
+    pub(crate) fn task_linters(&self) -> Vec<Linter<Task>> {
+        let mut linters = Vec::new();
+        if self.self_wakes {
+            for percent in self.self_wake_percent.values() {
+                linters.push(Linter::new(warnings::SelfWakePercent::new(percent)));
+            }
+        }
+        linters.extend(Linter::many(warnings::Wakers::new(
+            self.lost_wakers,
+            self.completed_with_wakers,
+        )));
+        if self.slow_polls {
+            for max_p99_ms in self.max_poll_p99_ms.values() {
+                linters.push(Linter::new(warnings::SlowPolls::new(
+                    Duration::from_millis(max_p99_ms),
+                )));
+            }
+        }
+        if self.slow_scheduling {
+            for max_ms in self.max_scheduled_per_wake_ms.values() {
+                linters.push(Linter::new(warnings::SlowScheduling::new(
+                    Duration::from_millis(max_ms),
+                    self.slow_scheduling_min_wakes,
+                )));
+            }
+        }
+        if self.starved {
+            for percent in self.starved_percent.values() {
+                linters.push(Linter::new(warnings::Starved::new(
+                    percent,
+                    Duration::from_millis(self.starved_window_ms),
+                )));
+            }
+        }
+        if self.never_polled {
+            linters.push(Linter::new(warnings::NeverPolled::new(
+                Duration::from_millis(self.never_polled_grace_ms),
+            )));
+        }
+        if self.no_location {
+            linters.push(Linter::new(warnings::NoLocation));
+        }
+        if self.cpu_bound {
+            for percent in self.cpu_bound_percent.values() {
+                linters.push(Linter::new(warnings::CpuBound::new(
+                    percent,
+                    Duration::from_millis(self.cpu_bound_min_ms),
+                )));
+            }
+        }
+        if self.never_yielded {
+            for min_ms in self.never_yielded_ms.values() {
+                let lint = self.never_yielded_targets.iter().fold(
+                    warnings::NeverYielded::new(
+                        Duration::from_millis(min_ms),
+                        self.never_yielded_max_polls,
+                    ),
+                    |lint, (prefix, &ms)| lint.with_override(prefix, Duration::from_millis(ms)),
+                );
+                linters.push(Linter::new(lint));
+            }
+        }
+        linters
+    }
+
+    pub(crate) fn location_linters(&self) -> Vec<Linter<SpawnLocation>> {
+        if !self.high_churn {
+            return Vec::new();
+        }
+        vec![Linter::new(warnings::HighChurn::new(
+            self.high_churn_spawns,
+            Duration::from_millis(self.high_churn_lifetime_ms),
+        ))]
+    }
+
+    pub(crate) fn resource_linters(&self) -> Vec<Linter<Resource>> {
+        if !self.pending_async_ops {
+            return Vec::new();
+        }
+        self.max_pending_async_ops
+            .values()
+            .into_iter()
+            .map(|max_pending| Linter::new(warnings::PendingAsyncOps::new(max_pending)))
+            .collect()
+    }
+
+    pub(crate) fn async_op_linters(&self, deadline: Duration) -> Vec<Linter<AsyncOp>> {
+        let mut linters = Vec::new();
+        if self.never_completed {
+            linters.push(Linter::new(warnings::NeverCompleted::new(deadline)));
+        }
+        if self.poll_outliers {
+            for factor in self.poll_outlier_factor.values() {
+                linters.push(Linter::new(warnings::PollOutlier::new(
+                    factor,
+                    self.poll_outlier_min_polls,
+                )));
+            }
+        }
+        if self.stagnant_polls {
+            linters.push(Linter::new(warnings::StagnantPolls::new(
+                Duration::from_millis(self.stagnant_polls_window_ms),
+                self.stagnant_polls_task_polls,
+            )));
+        }
+        linters
+    }
+}
+
+impl Default for WarningsConfig {
+    fn default() -> Self {
+        Self {
+            self_wakes: true,
+            self_wake_percent: Thresholds::One(warnings::SelfWakePercent::DEFAULT_PERCENT),
+            lost_wakers: true,
+            completed_with_wakers: true,
+            slow_polls: true,
+            max_poll_p99_ms: Thresholds::One(
+                warnings::SlowPolls::DEFAULT_MAX_P99.as_millis() as u64
+            ),
+            slow_scheduling: true,
+            max_scheduled_per_wake_ms: Thresholds::One(
+                warnings::SlowScheduling::DEFAULT_MAX_AVERAGE.as_millis() as u64,
+            ),
+            slow_scheduling_min_wakes: warnings::SlowScheduling::DEFAULT_MIN_WAKES,
+            starved: true,
+            starved_percent: Thresholds::One(warnings::Starved::DEFAULT_PERCENT),
+            starved_window_ms: warnings::Starved::DEFAULT_WINDOW.as_millis() as u64,
+            never_polled: true,
+            never_polled_grace_ms: warnings::NeverPolled::DEFAULT_GRACE.as_millis() as u64,
+            no_location: true,
+            cpu_bound: true,
+            cpu_bound_percent: Thresholds::One(warnings::CpuBound::DEFAULT_PERCENT),
+            cpu_bound_min_ms: warnings::CpuBound::DEFAULT_MIN_TOTAL.as_millis() as u64,
+            never_yielded: true,
+            never_yielded_ms: Thresholds::One(
+                warnings::NeverYielded::DEFAULT_MIN_DURATION.as_millis() as u64,
+            ),
+            never_yielded_max_polls: warnings::NeverYielded::DEFAULT_MAX_POLLS,
+            never_yielded_targets: HashMap::new(),
+            high_churn: true,
+            high_churn_spawns: warnings::HighChurn::DEFAULT_MIN_SPAWNS,
+            high_churn_lifetime_ms: warnings::HighChurn::DEFAULT_MAX_LIFETIME.as_millis() as u64,
+            never_completed: true,
+            poll_outliers: true,
+            poll_outlier_factor: Thresholds::One(warnings::PollOutlier::DEFAULT_FACTOR),
+            poll_outlier_min_polls: warnings::PollOutlier::DEFAULT_MIN_POLLS,
+            stagnant_polls: true,
+            stagnant_polls_window_ms: warnings::StagnantPolls::DEFAULT_WINDOW.as_millis() as u64,
+            stagnant_polls_task_polls: warnings::StagnantPolls::DEFAULT_MIN_TASK_POLLS,
+            pending_async_ops: true,
+            max_pending_async_ops: Thresholds::One(warnings::PendingAsyncOps::DEFAULT_MAX_PENDING),
+            unknown: HashMap::new(),
+        }
+    }
+}
+
+// === impl ColumnsConfig ===
+
+impl ColumnsConfig {
+    /// Returns the indices of the async ops table's columns to display, or
+    /// `None` if every column is displayed.
+    ///
+    /// Unknown column names are logged and ignored, like unknown warnings.
+    pub(crate) fn async_op_columns(&self) -> Option<Vec<usize>> {
+        let names = self.async_ops.as_ref()?;
+        let columns = names
+            .iter()
+            .filter_map(|name| match name.parse::<async_ops::SortBy>() {
+                Ok(column) => Some(column as usize),
+                Err(error) => {
+                    tracing::warn!(%error, "ignoring unknown column in `[columns]` config");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(columns).filter(|columns| !columns.is_empty())
+    }
+}
+
+// === impl ThemeConfig ===
+
+impl ThemeConfig {
+    /// Returns the theme that this section configures.
+    ///
+    /// Invalid colors are logged and ignored, keeping the built-in color, like
+    /// unknown column names.
+    pub(crate) fn theme(&self) -> Theme {
+        let parse = |key: &str, name: &str| {
+            view::parse_color(name)
+                .map_err(|error| {
+                    tracing::warn!(%error, key, "ignoring invalid color in `[theme]` config");
+                })
+                .ok()
+        };
+        let color = |key: &str, name: &Option<String>| parse(key, name.as_deref()?);
+        let time_units = self.time_units.as_ref().and_then(|names| {
+            let colors = names
+                .iter()
+                .map(|name| parse("time_units", name))
+                .collect::<Option<Vec<_>>>()?;
+            <[_; 5]>::try_from(colors)
+                .map_err(|colors| {
+                    tracing::warn!(
+                        len = colors.len(),
+                        "ignoring `time_units` in `[theme]` config, which must have 5 colors"
+                    );
+                })
+                .ok()
+        });
+        Theme {
+            warning: color("warning", &self.warning),
+            critical: color("critical", &self.critical),
+            time_units,
+            borders: color("borders", &self.borders),
+            selection: color("selection", &self.selection),
+            paused_badge: color("paused_badge", &self.paused_badge),
+        }
+    }
+}
+
+// === impl Thresholds ===
+
+impl<T: Copy + Ord> Thresholds<T> {
+    /// Returns each distinct threshold, lowest first.
+    fn values(&self) -> Vec<T> {
+        let mut values = match self {
+            Thresholds::One(value) => vec![*value],
+            Thresholds::Many(values) => values.clone(),
+        };
+        values.sort_unstable();
+        values.dedup();
+        values
+    }
+
+    fn values_mut(&mut self) -> &mut [T] {
+        match self {
+            Thresholds::One(value) => std::slice::from_mut(value),
+            Thresholds::Many(values) => values,
+        }
+    }
+}
+
+// === impl SavedState ===
+
+impl SavedState {
+    /// The maximum number of targets saved for the next session.
+    pub(crate) const MAX_TARGETS: usize = 64;
+
+    /// How long saved pins and acknowledgements are kept without a matching
+    /// task being seen.
+    pub(crate) const FORGET_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    /// Loads the state saved by a previous run of the console.
+    ///
+    /// Since the saved state is not essential, failing to load it is not an
+    /// error: it is logged, and the default state is used instead.
+    pub(crate) fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Self::default(),
+        };
+
+        let state = std::fs::read_to_string(&path)
+            .wrap_err("failed to read saved state")
+            .and_then(|contents| toml::from_str(&contents).wrap_err("failed to parse saved state"));
+        match state {
+            Ok(state) => state,
+            Err(error) => {
+                tracing::warn!(path = %path.display(), %error, "ignoring saved state");
+                Self::default()
+            }
+        }
+    }
+
+    /// Saves this state, so that it is restored the next time the console
+    /// starts.
+    pub(crate) fn save(&self) -> color_eyre::Result<()> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("failed to create `{}`", dir.display()))?;
+        }
+        let contents = toml::to_string(self).wrap_err("failed to serialize saved state")?;
+        std::fs::write(&path, contents)
+            .wrap_err_with(|| format!("failed to write `{}`", path.display()))
+    }
+
+    fn path() -> Option<PathBuf> {
+        let mut path = dirs::state_dir().or_else(dirs::data_local_dir)?;
+        path.push("tokio-console");
+        path.push("state.toml");
+        Some(path)
+    }
 }
 
 // === impl ViewOptions ===
@@ -217,6 +1216,26 @@ fn parse_true_color(s: &str) -> bool {
     s.eq_ignore_ascii_case("truecolor") || s.eq_ignore_ascii_case("24bit")
 }
 
+impl ReplaySpeed {
+    /// The slowest a recording may be replayed.
+    ///
+    /// Dividing the time between updates by a speed much closer to zero would
+    /// wait for longer than can be represented.
+    const MIN: f64 = 0.001;
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(speed) if speed.is_finite() && speed >= Self::MIN => Ok(ReplaySpeed(speed)),
+            Ok(_) => Err(format!("the replay speed must be at least {}", Self::MIN)),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+}
+
 impl FromStr for RetainFor {
     type Err = humantime::DurationError;
 
@@ -229,3 +1248,19 @@ impl FromStr for RetainFor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_speeds_too_close_to_zero_are_rejected() {
+        for speed in ["0", "-1", "0.0009", "1e-300", "NaN", "inf"] {
+            assert!(speed.parse::<ReplaySpeed>().is_err(), "{}", speed);
+        }
+        for speed in ["0.001", "0.5", "1", "100"] {
+            let parsed = speed.parse::<ReplaySpeed>().expect("speed should parse");
+            assert_eq!(parsed.0, speed.parse::<f64>().unwrap());
+        }
+    }
+}
@@ -1,16 +1,38 @@
+use crate::record::Replay;
 use console_api::instrument::{
     instrument_client::InstrumentClient, InstrumentRequest, PauseRequest, ResumeRequest,
     TaskDetailsRequest, Update,
 };
 use console_api::tasks::TaskDetails;
 use futures::stream::StreamExt;
-use std::{error::Error, pin::Pin, time::Duration};
+use std::{
+    cmp,
+    error::Error,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 use tonic::{transport::Channel, transport::Uri, Streaming};
 
 #[derive(Debug)]
 pub struct Connection {
     target: Uri,
     state: State,
+    /// When the last update was received, if one has been.
+    last_update_at: Option<Instant>,
+    /// If a recording is being replayed, updates are read from it instead of
+    /// from the target, which is never connected to.
+    replay: Option<Replay>,
+    /// Whether a connection has ever been made, so that connecting again is
+    /// displayed as reconnecting.
+    has_connected: bool,
+    /// Set when a connection is made after a previous one was lost, until it
+    /// is taken by [`Connection::take_reconnected`].
+    reconnected: bool,
+    /// The longest to wait between attempts to connect.
+    max_backoff: Duration,
+    /// The number of attempts in a row which may fail before giving up, or
+    /// `None` to never give up.
+    max_attempts: Option<u32>,
 }
 
 // clippy doesn't like that the "connected" case is much larger than the
@@ -25,7 +47,19 @@ enum State {
         client: InstrumentClient<Channel>,
         stream: Streaming<Update>,
     },
-    Disconnected(Duration),
+    /// Waiting to try connecting again.
+    ///
+    /// A deadline is stored rather than a delay, since the future waiting for
+    /// it can be dropped and recreated (such as when the screen is redrawn)
+    /// without restarting the wait.
+    Disconnected {
+        /// The number of attempts to connect which have failed in a row.
+        failures: u32,
+        retry_at: Instant,
+    },
+    /// Not trying to connect again, since the maximum number of attempts in a
+    /// row have failed, until [`Connection::reconnect`] is called.
+    GaveUp,
 }
 
 macro_rules! with_client {
@@ -38,13 +72,14 @@ macro_rules! with_client {
                         // If the error is a `h2::Error`, that indicates
                         // something went wrong at the connection level, rather
                         // than the server returning an error code. In that
-                        // case, let's try reconnecting...
+                        // case, the connection is lost, and the update stream
+                        // will reconnect...
                         Err(error) if error.source().iter().any(|src| src.is::<h2::Error>()) => {
                             tracing::warn!(
                                 error = %error,
                                 "connection error sending command"
                             );
-                            $me.state = State::Disconnected(Self::BACKOFF);
+                            $me.disconnected();
                         }
                         // Otherwise, return the error.
                         Err(e) => {
@@ -52,66 +87,189 @@ macro_rules! with_client {
                         }
                     }
                 }
-                State::Disconnected(_) => $me.connect().await,
+                // Waiting to reconnect here would block handling input until
+                // the target is back, so commands fail while disconnected.
+                State::Disconnected { .. } | State::GaveUp => {
+                    break Err(tonic::Status::unavailable("not connected to the target"));
+                }
             }
         }
     })
 }
 
 impl Connection {
+    /// How long to wait before the first attempt to reconnect. This doubles
+    /// after each failed attempt, up to the maximum backoff.
     const BACKOFF: Duration = Duration::from_millis(500);
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+    /// Updates older than this are displayed as stale.
+    const STALE_AFTER: Duration = Duration::from_secs(3);
+    /// Updates older than this are displayed as very stale.
+    const VERY_STALE_AFTER: Duration = Duration::from_secs(10);
+
     pub fn new(target: Uri) -> Self {
         Self {
             target,
-            state: State::Disconnected(Duration::from_secs(0)),
+            state: State::Disconnected {
+                failures: 0,
+                retry_at: Instant::now(),
+            },
+            last_update_at: None,
+            replay: None,
+            has_connected: false,
+            reconnected: false,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            max_attempts: None,
         }
     }
 
-    async fn connect(&mut self) {
-        const MAX_BACKOFF: Duration = Duration::from_secs(5);
+    /// Sets the longest to wait between attempts to connect, and the number
+    /// of attempts in a row which may fail before giving up, or `None` to
+    /// never give up.
+    pub fn with_reconnect(mut self, max_backoff: Duration, max_attempts: Option<u32>) -> Self {
+        self.max_backoff = max_backoff;
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Reads updates from `replay`, rather than from the target.
+    pub fn with_replay(mut self, replay: Option<Replay>) -> Self {
+        self.replay = replay;
+        self
+    }
 
-        while let State::Disconnected(backoff) = self.state {
-            if backoff == Duration::from_secs(0) {
-                tracing::debug!(to = %self.target, "connecting");
+    async fn connect(&mut self) {
+        while let State::Disconnected { failures, retry_at } = self.state {
+            tokio::time::sleep_until(retry_at.into()).await;
+            if self.has_connected {
+                tracing::debug!(to = %self.target, attempt = failures + 1, "reconnecting");
             } else {
-                tracing::debug!(reconnect_in = ?backoff, "reconnecting");
-                tokio::time::sleep(backoff).await;
+                tracing::debug!(to = %self.target, attempt = failures + 1, "connecting");
             }
-            let try_connect = async {
-                let mut client = InstrumentClient::connect(self.target.clone()).await?;
-                let request = tonic::Request::new(InstrumentRequest {});
-                let stream = client.watch_updates(request).await?.into_inner();
-                Ok::<State, Box<dyn Error + Send + Sync>>(State::Connected { client, stream })
-            };
-            self.state = match try_connect.await {
+            self.state = match Self::try_connect(self.target.clone()).await {
                 Ok(connected) => {
                     tracing::debug!("connected successfully!");
+                    self.reconnected = self.has_connected;
+                    self.has_connected = true;
                     connected
                 }
                 Err(error) => {
                     tracing::warn!(%error, "error connecting");
-                    let backoff = std::cmp::max(backoff + Self::BACKOFF, MAX_BACKOFF);
-                    State::Disconnected(backoff)
+                    let failures = failures + 1;
+                    if self.max_attempts.is_some_and(|max| failures >= max) {
+                        tracing::warn!(failures, "giving up connecting");
+                        State::GaveUp
+                    } else {
+                        State::Disconnected {
+                            failures,
+                            retry_at: Instant::now() + self.backoff(failures),
+                        }
+                    }
                 }
             };
         }
     }
 
+    /// Returns how long to wait before trying to connect again, after
+    /// `failures` attempts in a row have failed.
+    fn backoff(&self, failures: u32) -> Duration {
+        // Doubling more times than this overflows, and is longer than any
+        // maximum backoff anyone would set.
+        let doublings = cmp::min(failures.saturating_sub(1), 16);
+        cmp::min(Self::BACKOFF * 2u32.pow(doublings), self.max_backoff)
+    }
+
+    /// Records that the connection was lost, so that it is reconnected after
+    /// the shortest backoff.
+    fn disconnected(&mut self) {
+        self.state = State::Disconnected {
+            failures: 0,
+            retry_at: Instant::now() + self.backoff(0),
+        };
+    }
+
+    /// Tries to connect to the target again right away, rather than waiting
+    /// out the backoff, even if the console had given up.
+    ///
+    /// If the console is connected, the connection is dropped and made again,
+    /// such as when updates have stopped arriving.
+    pub fn reconnect(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        let failures = match self.state {
+            State::Disconnected { failures, .. } => failures,
+            State::Connected { .. } | State::GaveUp => 0,
+        };
+        tracing::debug!("reconnecting now");
+        self.state = State::Disconnected {
+            failures,
+            retry_at: Instant::now(),
+        };
+    }
+
+    /// Returns `true` if a connection was made after a previous one was lost,
+    /// since this was last called.
+    ///
+    /// The target may have restarted in between, so the updates received
+    /// since then may not describe the same tasks, even where their IDs
+    /// match.
+    pub fn take_reconnected(&mut self) -> bool {
+        std::mem::take(&mut self.reconnected)
+    }
+
+    /// Makes a single attempt to connect, without retrying on failure.
+    pub async fn connect_once(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.replay.is_some() {
+            return Ok(());
+        }
+        if let State::Disconnected { .. } | State::GaveUp = self.state {
+            tracing::debug!(to = %self.target, "connecting");
+            self.state = Self::try_connect(self.target.clone()).await?;
+            self.has_connected = true;
+        }
+        Ok(())
+    }
+
+    async fn try_connect(target: Uri) -> Result<State, Box<dyn Error + Send + Sync>> {
+        let mut client = InstrumentClient::connect(target).await?;
+        let request = tonic::Request::new(InstrumentRequest {});
+        let stream = client.watch_updates(request).await?.into_inner();
+        Ok(State::Connected { client, stream })
+    }
+
     pub async fn next_update(&mut self) -> Update {
+        if let Some(ref mut replay) = self.replay {
+            // While the replay is paused or once it has finished, there are no
+            // more updates.
+            let update = match replay.next_update().await {
+                Some(update) => update,
+                None => futures::future::pending().await,
+            };
+            self.last_update_at = Some(Instant::now());
+            return update;
+        }
+
         loop {
             match self.state {
                 State::Connected { ref mut stream, .. } => match Pin::new(stream).next().await {
-                    Some(Ok(update)) => return update,
+                    Some(Ok(update)) => {
+                        self.last_update_at = Some(Instant::now());
+                        return update;
+                    }
                     Some(Err(status)) => {
                         tracing::warn!(%status, "error from stream");
-                        self.state = State::Disconnected(Self::BACKOFF);
+                        self.disconnected();
                     }
                     None => {
                         tracing::error!("stream closed by server");
-                        self.state = State::Disconnected(Self::BACKOFF);
+                        self.disconnected();
                     }
                 },
-                State::Disconnected(_) => self.connect().await,
+                State::Disconnected { .. } => self.connect().await,
+                // There are no more updates until the console is told to
+                // reconnect.
+                State::GaveUp => futures::future::pending().await,
             }
         }
     }
@@ -121,6 +279,11 @@ impl Connection {
         &mut self,
         task_id: u64,
     ) -> Result<Streaming<TaskDetails>, tonic::Status> {
+        if self.replay.is_some() {
+            return Err(tonic::Status::unimplemented(
+                "task details are not recorded, so they can't be replayed",
+            ));
+        }
         with_client!(self, client, {
             let request = tonic::Request::new(TaskDetailsRequest {
                 id: Some(task_id.into()),
@@ -132,6 +295,10 @@ impl Connection {
 
     #[tracing::instrument(skip(self))]
     pub async fn pause(&mut self) {
+        if let Some(ref mut replay) = self.replay {
+            replay.pause();
+            return;
+        }
         let res = with_client!(self, client, {
             let request = tonic::Request::new(PauseRequest {});
             client.pause(request).await
@@ -144,6 +311,10 @@ impl Connection {
 
     #[tracing::instrument(skip(self))]
     pub async fn resume(&mut self) {
+        if let Some(ref mut replay) = self.replay {
+            replay.resume();
+            return;
+        }
         let res = with_client!(self, client, {
             let request = tonic::Request::new(ResumeRequest {});
             client.resume(request).await
@@ -154,30 +325,107 @@ impl Connection {
         }
     }
 
-    pub fn render(&self, styles: &crate::view::Styles) -> tui::text::Spans {
+    /// Returns how long ago the last update was received, if one has been.
+    pub fn last_update_age(&self) -> Option<Duration> {
+        self.last_update_at.map(|at| at.elapsed())
+    }
+
+    pub fn render(
+        &self,
+        styles: &crate::view::Styles,
+        bindings: &crate::input::KeyBindings,
+    ) -> tui::text::Spans<'_> {
         use tui::{
             style::{Color, Modifier},
             text::{Span, Spans},
         };
-        let state = match self.state {
+        let styled = |text: String, color: Color| {
+            Span::styled(text, styles.fg(color).add_modifier(Modifier::BOLD))
+        };
+        let (label, source, state) = match (&self.replay, &self.state) {
+            (Some(replay), _) => {
+                let state = match replay.finished() {
+                    Some(None) => styled("(REPLAY FINISHED)".to_owned(), Color::Yellow),
+                    Some(Some(error)) => styled(format!("(REPLAY FAILED: {})", error), Color::Red),
+                    None if replay.is_paused() => {
+                        styled("(REPLAY PAUSED)".to_owned(), Color::Yellow)
+                    }
+                    None => styled(format!("(REPLAYING AT {}x)", replay.speed()), Color::Green),
+                };
+                ("replay: ", replay.path().display().to_string(), state)
+            }
+            (None, _) => (
+                "connection: ",
+                self.target.to_string(),
+                self.render_state(styles),
+            ),
+        };
+        let mut spans = vec![Span::raw(label), Span::raw(source), Span::raw(" "), state];
+        if self.replay.is_none() && !matches!(self.state, State::Connected { .. }) {
+            let key = bindings.keys(crate::input::Action::Reconnect).first();
+            if let Some(key) = key {
+                spans.push(Span::raw(format!(" press {} to reconnect now", key)));
+            }
+        }
+        // Show how long ago the last update arrived, so that a stalled or
+        // dropped stream doesn't look like a target that isn't doing anything.
+        // A finished replay won't send any more updates, so its age means
+        // nothing.
+        let finished = matches!(self.replay, Some(ref replay) if replay.finished().is_some());
+        if let Some(age) = self.last_update_age().filter(|_| !finished) {
+            let color = if age >= Self::VERY_STALE_AFTER {
+                Color::Red
+            } else if age >= Self::STALE_AFTER {
+                Color::Yellow
+            } else {
+                Color::Green
+            };
+            spans.push(Span::raw(" last update "));
+            spans.push(Span::styled(
+                format!("{:.1}s ago", age.as_secs_f64()),
+                styles.fg(color),
+            ));
+        }
+        Spans::from(spans)
+    }
+
+    fn render_state(&self, styles: &crate::view::Styles) -> tui::text::Span<'static> {
+        use tui::{
+            style::{Color, Modifier},
+            text::Span,
+        };
+        match self.state {
             State::Connected { .. } => Span::styled(
                 "(CONNECTED)",
                 styles.fg(Color::Green).add_modifier(Modifier::BOLD),
             ),
-            State::Disconnected(d) if d == Duration::from_secs(0) => Span::styled(
+            State::Disconnected { failures: 0, .. } if !self.has_connected => Span::styled(
                 "(CONNECTING)",
                 styles.fg(Color::Yellow).add_modifier(Modifier::BOLD),
             ),
-            State::Disconnected(d) => Span::styled(
-                format!("(RECONNECTING IN {:?})", d),
-                styles.fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            State::Disconnected { failures, retry_at } => {
+                let verb = if self.has_connected {
+                    "RECONNECTING"
+                } else {
+                    "CONNECTING"
+                };
+                let wait = retry_at.saturating_duration_since(Instant::now());
+                let text = if wait.is_zero() {
+                    format!("({}, ATTEMPT {})", verb, failures + 1)
+                } else {
+                    format!(
+                        "({} IN {:.1}s, ATTEMPT {})",
+                        verb,
+                        wait.as_secs_f64(),
+                        failures + 1
+                    )
+                };
+                Span::styled(text, styles.fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            }
+            State::GaveUp => Span::styled(
+                "(DISCONNECTED)",
+                styles.fg(Color::Red).add_modifier(Modifier::BOLD),
             ),
-        };
-        Spans::from(vec![
-            Span::raw("connection: "),
-            Span::raw(self.target.to_string()),
-            Span::raw(" "),
-            state,
-        ])
+        }
     }
 }
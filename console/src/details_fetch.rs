@@ -0,0 +1,128 @@
+//! Retrying the stream of the viewed task's details when it fails.
+//!
+//! The task view's poll times are only sent on a separate stream for the
+//! task being viewed, which can fail to start or close early without the
+//! connection to the target failing, such as when the target's buffer for the
+//! stream fills up. Failures which might be transient are retried a bounded
+//! number of times, with an increasing backoff, so that a task whose details
+//! can never be fetched doesn't cause a stream of retries. A stream only
+//! counts as having recovered once it has kept sending details for a while,
+//! so that one which sends a single update and then closes is still given up
+//! on.
+use crate::state::tasks::DetailsStatus;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Tracks the attempts to fetch the details of the task being viewed.
+#[derive(Debug, Default)]
+pub(crate) struct DetailsFetch {
+    /// The ID of the task whose details are being fetched, or `None` if no
+    /// task is being viewed.
+    task_id: Option<u64>,
+    /// The number of times in a row that fetching the details has failed.
+    failures: u32,
+    /// When the current stream first sent details, or `None` if it hasn't
+    /// yet.
+    receiving_since: Option<Instant>,
+    /// When to try fetching the details again, if they failed and will be
+    /// retried.
+    retry_at: Option<Instant>,
+}
+
+impl DetailsFetch {
+    /// The number of failures in a row after which fetching is given up on.
+    const MAX_FAILURES: u32 = 5;
+    /// How long to wait before the first retry. This doubles after each
+    /// failure.
+    const BACKOFF: Duration = Duration::from_secs(1);
+    /// How long a stream must keep sending details before its earlier
+    /// failures are forgotten.
+    const HEALTHY_FOR: Duration = Duration::from_secs(30);
+
+    /// Starts fetching the details of the task with the given ID, forgetting
+    /// any failures fetching another task's details.
+    pub(crate) fn start(&mut self, task_id: u64) {
+        *self = Self {
+            task_id: Some(task_id),
+            ..Self::default()
+        };
+    }
+
+    /// Stops fetching details, since no task is being viewed.
+    pub(crate) fn stop(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns `true` if the details of the task with the given ID are being
+    /// fetched.
+    pub(crate) fn is_fetching(&self, task_id: u64) -> bool {
+        self.task_id == Some(task_id)
+    }
+
+    /// Records that details were received. Once the stream has been sending
+    /// details for long enough, a later failure starts from the shortest
+    /// backoff again.
+    pub(crate) fn succeeded(&mut self) {
+        let now = Instant::now();
+        let since = *self.receiving_since.get_or_insert(now);
+        if now.duration_since(since) >= Self::HEALTHY_FOR {
+            self.failures = 0;
+        }
+    }
+
+    /// Records that fetching failed with `status`, and returns the status to
+    /// display, which says whether it will be retried.
+    pub(crate) fn failed(&mut self, status: &tonic::Status) -> DetailsStatus {
+        self.failures += 1;
+        self.receiving_since = None;
+        self.retry_at = if is_transient(status) && self.failures < Self::MAX_FAILURES {
+            Some(Instant::now() + Self::BACKOFF * 2u32.pow(self.failures - 1))
+        } else {
+            None
+        };
+        DetailsStatus::Failed {
+            error: status.message().to_owned(),
+            retry_at: self.retry_at.map(Instant::into_std),
+        }
+    }
+
+    /// Returns the ID of the task whose details should be fetched again, and
+    /// when, if a retry is scheduled.
+    pub(crate) fn retry(&self) -> Option<(u64, Instant)> {
+        self.task_id.zip(self.retry_at)
+    }
+
+    /// Clears the scheduled retry, once it has been attempted.
+    pub(crate) fn retrying(&mut self) {
+        self.retry_at = None;
+    }
+}
+
+/// Waits until the retry returned by [`DetailsFetch::retry`] is due, and
+/// returns the ID of the task to fetch the details of.
+///
+/// If there is no retry scheduled, this never completes.
+pub(crate) async fn wait_for_retry(retry: Option<(u64, Instant)>) -> u64 {
+    match retry {
+        Some((task_id, retry_at)) => {
+            tokio::time::sleep_until(retry_at).await;
+            task_id
+        }
+        None => futures::future::pending().await,
+    }
+}
+
+/// Returns `true` if a request which failed with `status` might succeed if it
+/// is retried.
+fn is_transient(status: &tonic::Status) -> bool {
+    use tonic::Code;
+    matches!(
+        status.code(),
+        Code::Unavailable
+            | Code::Unknown
+            | Code::Internal
+            | Code::Aborted
+            | Code::DeadlineExceeded
+            | Code::ResourceExhausted
+    )
+}
@@ -0,0 +1,225 @@
+//! Comparing two snapshots exported with `--export-snapshot`, such as ones
+//! taken before and after a change, to see which tasks appeared, disappeared,
+//! or did more work in between.
+use crate::state::{tasks::TaskSnapshot, Snapshot};
+use color_eyre::eyre::WrapErr;
+use std::{collections::BTreeMap, fmt, path::Path, time::Duration};
+
+const DUR_PRECISION: usize = 4;
+
+/// How much a task's stats must grow between two snapshots for the task to
+/// count as changed, so that tasks which barely did anything are ignored.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Thresholds {
+    pub(crate) busy: Duration,
+    pub(crate) polls: u64,
+}
+
+/// The differences between two snapshots of the same target.
+#[derive(Debug)]
+pub(crate) struct SnapshotDiff<'a> {
+    /// Tasks which are only in the second snapshot, ordered by ID.
+    pub(crate) added: Vec<&'a TaskSnapshot>,
+    /// Tasks which are only in the first snapshot, ordered by ID.
+    pub(crate) removed: Vec<&'a TaskSnapshot>,
+    /// Tasks in both snapshots whose busy time or polls grew by at least the
+    /// thresholds, ordered by ID.
+    pub(crate) changed: Vec<TaskChange<'a>>,
+    pub(crate) before: Totals,
+    pub(crate) after: Totals,
+}
+
+/// A task in both snapshots, as it was in each.
+#[derive(Debug)]
+pub(crate) struct TaskChange<'a> {
+    pub(crate) before: &'a TaskSnapshot,
+    pub(crate) after: &'a TaskSnapshot,
+}
+
+/// The stats of every task in a snapshot, added together.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct Totals {
+    pub(crate) tasks: usize,
+    pub(crate) busy: Duration,
+    pub(crate) polls: u64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            busy: Duration::from_millis(1),
+            polls: 1,
+        }
+    }
+}
+
+impl Totals {
+    fn of(snapshot: &Snapshot) -> Self {
+        Self {
+            tasks: snapshot.tasks.len(),
+            busy: snapshot.tasks.iter().map(|task| task.busy).sum(),
+            polls: snapshot.tasks.iter().map(|task| task.polls).sum(),
+        }
+    }
+}
+
+impl TaskChange<'_> {
+    /// Returns how much longer the task was busy in the second snapshot.
+    pub(crate) fn busy_growth(&self) -> Duration {
+        self.after.busy.saturating_sub(self.before.busy)
+    }
+
+    /// Returns how many more times the task was polled in the second
+    /// snapshot.
+    pub(crate) fn polls_growth(&self) -> u64 {
+        self.after.polls.saturating_sub(self.before.polls)
+    }
+}
+
+/// Compares the tasks in the `before` and `after` snapshots, which are matched
+/// by their IDs.
+///
+/// Task IDs are only unique within a run of the target, so both snapshots
+/// should be taken from the same run.
+pub(crate) fn diff<'a>(
+    before: &'a Snapshot,
+    after: &'a Snapshot,
+    thresholds: Thresholds,
+) -> SnapshotDiff<'a> {
+    let by_id = |snapshot: &'a Snapshot| {
+        snapshot
+            .tasks
+            .iter()
+            .map(|task| (task.id, task))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let before_tasks = by_id(before);
+    let after_tasks = by_id(after);
+
+    let added = after_tasks
+        .iter()
+        .filter(|(id, _)| !before_tasks.contains_key(id))
+        .map(|(_, &task)| task)
+        .collect();
+    let removed = before_tasks
+        .iter()
+        .filter(|(id, _)| !after_tasks.contains_key(id))
+        .map(|(_, &task)| task)
+        .collect();
+    let changed = before_tasks
+        .iter()
+        .filter_map(|(id, &before)| {
+            let after = after_tasks.get(id)?;
+            Some(TaskChange { before, after })
+        })
+        .filter(|change| {
+            let (busy, polls) = (change.busy_growth(), change.polls_growth());
+            // Even with a threshold of zero, a task must have grown to count
+            // as changed.
+            (busy >= thresholds.busy && busy > Duration::ZERO)
+                || (polls >= thresholds.polls && polls > 0)
+        })
+        .collect();
+
+    SnapshotDiff {
+        added,
+        removed,
+        changed,
+        before: Totals::of(before),
+        after: Totals::of(after),
+    }
+}
+
+/// Reads a snapshot written by `--export-snapshot` from `path`.
+pub(crate) fn load(path: &Path) -> color_eyre::Result<Snapshot> {
+    let json = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read snapshot `{}`", path.display()))?;
+    serde_json::from_str(&json)
+        .wrap_err_with(|| format!("failed to parse snapshot `{}`", path.display()))
+}
+
+impl fmt::Display for SnapshotDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (before, after) = (self.before, self.after);
+        writeln!(
+            f,
+            "tasks: {} -> {} ({})",
+            before.tasks,
+            after.tasks,
+            signed(before.tasks as u64, after.tasks as u64)
+        )?;
+        writeln!(
+            f,
+            "busy: {:.prec$?} -> {:.prec$?} ({})",
+            before.busy,
+            after.busy,
+            signed_duration(before.busy, after.busy),
+            prec = DUR_PRECISION,
+        )?;
+        writeln!(
+            f,
+            "polls: {} -> {} ({})",
+            before.polls,
+            after.polls,
+            signed(before.polls, after.polls)
+        )?;
+
+        writeln!(f, "\nadded ({}):", self.added.len())?;
+        for task in &self.added {
+            writeln!(f, "  {}", TaskLabel(task))?;
+        }
+        writeln!(f, "\nremoved ({}):", self.removed.len())?;
+        for task in &self.removed {
+            writeln!(f, "  {}", TaskLabel(task))?;
+        }
+        writeln!(f, "\nchanged ({}):", self.changed.len())?;
+        for change in &self.changed {
+            writeln!(
+                f,
+                "  {}: busy +{:.prec$?} ({:.prec$?} -> {:.prec$?}), polls +{} ({} -> {})",
+                TaskLabel(change.after),
+                change.busy_growth(),
+                change.before.busy,
+                change.after.busy,
+                change.polls_growth(),
+                change.before.polls,
+                change.after.polls,
+                prec = DUR_PRECISION,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays a task's ID, along with its name if it has one, and where it was
+/// spawned.
+struct TaskLabel<'a>(&'a TaskSnapshot);
+
+impl fmt::Display for TaskLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let task = self.0;
+        write!(f, "{}", task.id)?;
+        if let Some(ref name) = task.name {
+            write!(f, " {}", name)?;
+        }
+        write!(f, " ({})", task.location)
+    }
+}
+
+/// Formats the change from `before` to `after`, like `+3` or `-1`.
+fn signed(before: u64, after: u64) -> String {
+    if after >= before {
+        format!("+{}", after - before)
+    } else {
+        format!("-{}", before - after)
+    }
+}
+
+/// Formats the change from `before` to `after`, like `+1.2000ms`.
+fn signed_duration(before: Duration, after: Duration) -> String {
+    if after >= before {
+        format!("+{:.*?}", DUR_PRECISION, after - before)
+    } else {
+        format!("-{:.*?}", DUR_PRECISION, before - after)
+    }
+}
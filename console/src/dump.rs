@@ -0,0 +1,143 @@
+//! Non-interactive dump of the target's tasks as a plain-text table, for use
+//! in scripts.
+use crate::{
+    conn::Connection,
+    export,
+    state::{tasks::SortBy, State},
+    view,
+};
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+/// The columns of the dumped table, and whether each is right-aligned.
+const COLUMNS: &[(&str, bool)] = &[
+    ("ID", true),
+    ("Name", false),
+    ("Target", false),
+    ("State", false),
+    ("Busy", true),
+    ("Idle", true),
+    ("Polls", true),
+];
+
+const DUR_PRECISION: usize = 4;
+
+/// How long to wait for the updates to dump after connecting, in case the
+/// target goes away and the connection keeps retrying.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connects to the target, waits for one update after its initial state,
+/// and prints its tasks to stdout, sorted by `sort_by` with the highest
+/// values first.
+///
+/// Returns the exit code the console should exit with.
+pub(crate) async fn dump(
+    conn: &mut Connection,
+    state: &mut State,
+    styles: &view::Styles,
+    sort_by: SortBy,
+) -> color_eyre::Result<i32> {
+    if let Err(error) = conn.connect_once().await {
+        eprintln!("error: failed to connect to the target: {}", error);
+        return Ok(export::CONNECTION_FAILED);
+    }
+
+    // The first update is the target's initial state, and the second has the
+    // stats for one sampling interval.
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    for _ in 0..2 {
+        let update = match tokio::time::timeout_at(deadline, conn.next_update()).await {
+            Ok(update) => update,
+            Err(_) => {
+                eprintln!(
+                    "error: no updates were received from the target within {}",
+                    humantime::format_duration(TIMEOUT)
+                );
+                return Ok(export::CONNECTION_FAILED);
+            }
+        };
+        state.update(styles, &view::ViewState::TasksList, update);
+    }
+
+    // Drop completed tasks which the interactive console would no longer be
+    // displaying.
+    state.retain_active();
+    let now = state.last_updated_at().unwrap_or_else(SystemTime::now);
+    print!("{}", format_tasks(state, sort_by, now));
+    Ok(0)
+}
+
+/// Formats the tasks in `state` as a plain-text table.
+fn format_tasks(state: &State, sort_by: SortBy, now: SystemTime) -> String {
+    let mut tasks = state.tasks_state().all_tasks().collect::<Vec<_>>();
+    sort_by.sort(now, &mut tasks);
+
+    let rows = tasks
+        .iter()
+        .rev()
+        .filter_map(|task| {
+            let task = task.upgrade()?;
+            let task = task.borrow();
+            Some(vec![
+                task.id().to_string(),
+                task.name().unwrap_or_default().to_owned(),
+                task.target().to_owned(),
+                task.state().name().to_owned(),
+                format!("{:.prec$?}", task.busy(now), prec = DUR_PRECISION),
+                format!("{:.prec$?}", task.idle(now), prec = DUR_PRECISION),
+                task.total_polls().to_string(),
+            ])
+        })
+        .collect::<Vec<_>>();
+    format_table(COLUMNS, &rows)
+}
+
+/// Formats `rows` as a plain-text table, with each of the `columns` (a header,
+/// and whether the column is right-aligned) as wide as its widest cell.
+pub(crate) fn format_table(columns: &[(&str, bool)], rows: &[Vec<String>]) -> String {
+    let mut widths = columns
+        .iter()
+        .map(|(header, _)| header.chars().count())
+        .collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    write_row(
+        &mut out,
+        columns,
+        columns.iter().map(|&(header, _)| header),
+        &widths,
+    );
+    for row in rows {
+        write_row(&mut out, columns, row.iter().map(String::as_str), &widths);
+    }
+    out
+}
+
+fn write_row<'a>(
+    out: &mut String,
+    columns: &[(&str, bool)],
+    cells: impl Iterator<Item = &'a str>,
+    widths: &[usize],
+) {
+    let mut line = String::new();
+    for (idx, (cell, &width)) in cells.zip(widths).enumerate() {
+        if idx > 0 {
+            line.push_str("  ");
+        }
+        let _ = if columns[idx].1 {
+            write!(line, "{:>width$}", cell, width = width)
+        } else {
+            write!(line, "{:<width$}", cell, width = width)
+        };
+    }
+    // Don't leave trailing whitespace after a left-aligned last column.
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
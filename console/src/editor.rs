@@ -0,0 +1,100 @@
+//! Opening a task's spawn location in the user's editor.
+use color_eyre::eyre::{eyre, WrapErr};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A source file, and the line in it to open the editor at.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct SourceLocation {
+    pub(crate) path: PathBuf,
+    pub(crate) line: Option<u32>,
+}
+
+impl SourceLocation {
+    /// Parses a task's location, like `src/main.rs:10:5`, checking that the
+    /// file exists on this machine.
+    ///
+    /// Relative paths are relative to the current directory, which is where
+    /// they usually are when the console is run from the target's workspace.
+    pub(crate) fn parse(location: &str) -> color_eyre::Result<Self> {
+        let location = location.trim_end();
+        // Strip up to two trailing `:<number>` components: the column, then
+        // the line.
+        let mut path = location;
+        let mut numbers = Vec::new();
+        for _ in 0..2 {
+            match path.rsplit_once(':') {
+                Some((rest, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+                    numbers.push(n);
+                    path = rest;
+                }
+                _ => break,
+            }
+        }
+        // If there's only one number, it's the line.
+        let line = numbers.last().and_then(|n| n.parse().ok());
+
+        if path.starts_with("<cargo>/") {
+            return Err(eyre!(
+                "`{}` is in a dependency, and its full path is unknown",
+                path
+            ));
+        }
+        let path = Path::new(path);
+        if !path.is_file() {
+            let hint = if path.is_relative() {
+                " (relative paths are opened from the current directory)"
+            } else {
+                ""
+            };
+            return Err(eyre!(
+                "`{}` does not exist on this machine{}",
+                path.display(),
+                hint
+            ));
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            line,
+        })
+    }
+
+    /// Opens this location in `$VISUAL` or `$EDITOR`, waiting for the editor
+    /// to exit.
+    ///
+    /// Editors are opened at the line with a `+<line>` argument before the
+    /// file, which most terminal editors understand. The editor variable may
+    /// include arguments, like `emacs -nw`.
+    pub(crate) fn open(&self) -> color_eyre::Result<()> {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .ok_or_else(|| eyre!("set $VISUAL or $EDITOR to open files in an editor"))?;
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        let mut command = Command::new(program);
+        command.args(words);
+        if let Some(line) = self.line {
+            command.arg(format!("+{}", line));
+        }
+        // The path comes from the target, so a relative one is prefixed with
+        // `./`, so that one starting with `-` isn't read as an option.
+        let path = if self.path.is_relative() {
+            Path::new(".").join(&self.path)
+        } else {
+            self.path.clone()
+        };
+        let status = command
+            .arg(path)
+            .status()
+            .with_context(|| format!("failed to run `{}`", editor))?;
+        if !status.success() {
+            return Err(eyre!("`{}` exited with {}", editor, status));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Non-interactive export of detected warnings, for use in CI.
+use crate::{conn::Connection, folded, state::State, view};
+use color_eyre::eyre::WrapErr;
+use serde::Serialize;
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// The exit code used when warnings were detected.
+pub(crate) const WARNINGS_DETECTED: i32 = 1;
+/// The exit code used when the console could not connect to the target.
+pub(crate) const CONNECTION_FAILED: i32 = 2;
+
+/// The maximum number of example task IDs exported for each warning.
+const MAX_EXAMPLES: usize = 10;
+
+/// What to export once data has been collected, and where to write it.
+#[derive(Debug, Default)]
+pub(crate) struct Exports<'a> {
+    pub(crate) warnings: Option<&'a Path>,
+    pub(crate) snapshot: Option<&'a Path>,
+    /// Where to write the tasks' busy time by spawn location, in the folded
+    /// stacks format.
+    pub(crate) folded: Option<&'a Path>,
+    /// Whether the folded stacks start with the tasks' targets.
+    pub(crate) folded_by_target: bool,
+}
+
+impl Exports<'_> {
+    /// Returns `true` if anything is exported.
+    pub(crate) fn any(&self) -> bool {
+        self.warnings.is_some() || self.snapshot.is_some() || self.folded.is_some()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedWarning<'a> {
+    lint_summary: &'a str,
+    count: usize,
+    example_task_ids: Vec<u64>,
+}
+
+/// Collects updates from the target for `duration`, and then writes the
+/// warnings detected for its tasks as a JSON array, a snapshot of its tasks
+/// and async ops, and its tasks' busy time by spawn location, to the paths in
+/// `exports`.
+///
+/// Returns the exit code the console should exit with.
+pub(crate) async fn export(
+    conn: &mut Connection,
+    state: &mut State,
+    styles: &view::Styles,
+    duration: Duration,
+    exports: &Exports<'_>,
+) -> color_eyre::Result<i32> {
+    if let Err(error) = conn.connect_once().await {
+        eprintln!("error: failed to connect to the target: {}", error);
+        return Ok(CONNECTION_FAILED);
+    }
+
+    let deadline = tokio::time::Instant::now() + duration;
+    while let Ok(update) = tokio::time::timeout_at(deadline, conn.next_update()).await {
+        state.update(styles, &view::ViewState::TasksList, update);
+    }
+
+    let now = state.last_updated_at().unwrap_or_else(SystemTime::now);
+    if let Some(path) = exports.snapshot {
+        write_json(path, &state.snapshot(now), "snapshot")?;
+    }
+
+    if let Some(path) = exports.folded {
+        folded::write(path, state.tasks_state(), now, exports.folded_by_target)?;
+    }
+
+    match exports.warnings {
+        Some(path) => export_warnings(state, path),
+        None => Ok(0),
+    }
+}
+
+/// Writes the warnings detected for the target's tasks to `path` as a JSON
+/// array.
+fn export_warnings(state: &State, path: &Path) -> color_eyre::Result<i32> {
+    let warnings = state
+        .tasks_state()
+        .warnings_overview()
+        .map(|(lint_summary, mut task_ids)| {
+            let count = task_ids.len();
+            task_ids.truncate(MAX_EXAMPLES);
+            ExportedWarning {
+                lint_summary,
+                count,
+                example_task_ids: task_ids,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    write_json(path, &warnings, "warnings")?;
+
+    if warnings.is_empty() {
+        Ok(0)
+    } else {
+        Ok(WARNINGS_DETECTED)
+    }
+}
+
+fn write_json(path: &Path, value: &impl Serialize, what: &str) -> color_eyre::Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .wrap_err_with(|| format!("failed to serialize {}", what))?;
+    std::fs::write(path, json)
+        .wrap_err_with(|| format!("failed to write {} to `{}`", what, path.display()))
+}
@@ -0,0 +1,64 @@
+//! Summing tasks' busy time by where they were spawned, in the folded stacks
+//! format read by `inferno` and `flamegraph.pl`, for a rough picture of where
+//! an application spends its time that can be rendered as a flamegraph.
+//!
+//! Each line of the folded format is a stack of frames separated by `;`,
+//! followed by a space and a count. Here, each stack is a spawn location,
+//! optionally under the target of the tasks spawned there, and the count is
+//! the total busy time of those tasks, in microseconds.
+use crate::state::tasks::TasksState;
+use color_eyre::eyre::WrapErr;
+use std::{collections::BTreeMap, fmt::Write, path::Path, time::SystemTime};
+
+/// The frame that tasks whose spawn location wasn't recorded by the target
+/// are counted under.
+const UNKNOWN: &str = "unknown";
+
+/// Sums the busy time of every tracked task as of `now` by spawn location,
+/// and formats the sums in the folded stacks format. If `by_target` is set,
+/// each location is under the target of its tasks.
+///
+/// Completed tasks which are still retained are included, and stacks whose
+/// tasks were never busy are left out.
+pub(crate) fn folded(tasks: &TasksState, now: SystemTime, by_target: bool) -> String {
+    let mut stacks = BTreeMap::<String, u128>::new();
+    for task in tasks.all_tasks().filter_map(|task| task.upgrade()) {
+        let task = task.borrow();
+        let location = if task.has_location() {
+            frame(task.location().trim())
+        } else {
+            UNKNOWN.to_owned()
+        };
+        let stack = if by_target {
+            format!("{};{}", frame(task.target()), location)
+        } else {
+            location
+        };
+        *stacks.entry(stack).or_default() += task.busy(now).as_micros();
+    }
+
+    let mut text = String::new();
+    for (stack, busy) in stacks.into_iter().filter(|&(_, busy)| busy > 0) {
+        // Writing to a `String` never fails.
+        let _ = writeln!(text, "{} {}", stack, busy);
+    }
+    text
+}
+
+/// Writes the busy time of every tracked task as of `now`, summed by spawn
+/// location, to `path` in the folded stacks format.
+pub(crate) fn write(
+    path: &Path,
+    tasks: &TasksState,
+    now: SystemTime,
+    by_target: bool,
+) -> color_eyre::Result<()> {
+    std::fs::write(path, folded(tasks, now, by_target))
+        .wrap_err_with(|| format!("failed to write busy time to `{}`", path.display()))
+}
+
+/// Returns `name` as a single frame, with the `;`s which would split it into
+/// several frames replaced.
+fn frame(name: &str) -> String {
+    name.replace(';', ",")
+}
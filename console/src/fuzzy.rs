@@ -0,0 +1,71 @@
+//! Fuzzy matching for searches, where the characters of the query must appear
+//! in the text in order, but not necessarily next to each other, so that a
+//! query like `wrkr` matches `worker`.
+
+/// How a query matched some text.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct FuzzyMatch {
+    /// How well the query matched, where higher is better.
+    pub(crate) score: i64,
+    /// The byte index in the text of each character that matched a character
+    /// of the query, in order.
+    pub(crate) indices: Vec<usize>,
+}
+
+/// Each matched character scores this much.
+const MATCH_SCORE: i64 = 1;
+/// A matched character directly after the previous matched character scores
+/// this much more, so that contiguous matches rank first.
+const CONTIGUOUS_BONUS: i64 = 5;
+/// A matched character at the start of a word, such as after a `:` or `_`,
+/// scores this much more.
+const WORD_START_BONUS: i64 = 8;
+/// Each unmatched character between two matched characters costs this much.
+const GAP_PENALTY: i64 = 1;
+
+/// Returns how `query` matches `text` if every character of `query` appears
+/// in `text` in order, ignoring case, or `None` if it doesn't.
+///
+/// Each occurrence of the query's first character is tried as the start of the
+/// match, matching the rest of the query as early as possible after it, and
+/// the best scoring of these is returned. This doesn't always find the best
+/// possible match, but is close enough to rank search results.
+pub(crate) fn fuzzy_match(text: &str, query: &str) -> Option<FuzzyMatch> {
+    let text = text.char_indices().collect::<Vec<_>>();
+    let query = query.chars().collect::<Vec<_>>();
+    let first = *query.first()?;
+
+    (0..text.len())
+        .filter(|&start| eq_ignore_case(text[start].1, first))
+        .filter_map(|start| match_from(&text, &query, start))
+        .max_by_key(|found| found.score)
+}
+
+/// Matches `query` against `text` greedily, starting with its first character
+/// at `text[start]`.
+fn match_from(text: &[(usize, char)], query: &[char], start: usize) -> Option<FuzzyMatch> {
+    let mut score = 0;
+    let mut indices = Vec::with_capacity(query.len());
+    let mut prev = None;
+    let mut pos = start;
+    for &expected in query {
+        let found = (pos..text.len()).find(|&i| eq_ignore_case(text[i].1, expected))?;
+        score += MATCH_SCORE;
+        match prev {
+            Some(prev) if found == prev + 1 => score += CONTIGUOUS_BONUS,
+            Some(prev) => score -= GAP_PENALTY * (found - prev - 1) as i64,
+            None => {}
+        }
+        if found == 0 || !text[found - 1].1.is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        indices.push(text[found].0);
+        prev = Some(found);
+        pos = found + 1;
+    }
+    Some(FuzzyMatch { score, indices })
+}
+
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
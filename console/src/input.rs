@@ -2,14 +2,162 @@
 // This would probably involve using `spawn_blocking` to drive their blocking
 // input-handling mechanisms in the background...
 pub use crossterm::event::*;
+use serde::{de, Deserialize, Deserializer};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// A logical action that can be triggered by a key binding.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum Action {
+    Quit,
+    /// Stops receiving updates from the target, or starts receiving them
+    /// again.
+    TogglePause,
+    /// Freezes the tables' rows and order, and stops redrawing the screen
+    /// except after key presses, while still receiving updates.
+    FreezeView,
+    /// Freezes the time that durations are displayed as of, without pausing
+    /// updates.
+    FreezeClock,
+    TasksView,
+    ResourcesView,
+    AsyncOpsView,
+    WarningsView,
+    ScrollUp,
+    ScrollDown,
+    ScrollTop,
+    ScrollBottom,
+    ScrollWarningsUp,
+    ScrollWarningsDown,
+    SelectColumnLeft,
+    SelectColumnRight,
+    InvertSort,
+    HideColumn,
+    ShowAllColumns,
+    /// Starts or stops resizing the selected column with the keys that
+    /// otherwise select columns.
+    ResizeColumns,
+    ResetColumnWidth,
+    Search,
+    ToggleFuzzySearch,
+    FilterCompleted,
+    /// Toggles hiding async ops which have never been polled.
+    FilterUnpolled,
+    CycleTaskKinds,
+    GotoTask,
+    GroupTasks,
+    RetainLonger,
+    RetainShorter,
+    RefreshLonger,
+    RefreshShorter,
+    /// Switches rates, such as how often tasks are woken, to being measured
+    /// over the next longer window, or the shortest after the longest.
+    CycleRateWindow,
+    MorePrecision,
+    LessPrecision,
+    /// Cycles the unit that the current table's durations are displayed in,
+    /// between choosing a unit for each duration and every duration in
+    /// milliseconds or microseconds.
+    CycleDurationUnit,
+    ViewDetails,
+    Back,
+    /// Tries to connect to the target again right away.
+    Reconnect,
+    CopyDetails,
+    /// Copies only the spawn location of the task being viewed.
+    CopyLocation,
+    CopyTable,
+    /// Writes the tasks' busy time by spawn location to a file, in the folded
+    /// stacks format that flamegraphs are rendered from.
+    ExportFolded,
+    OpenInEditor,
+    ResetHistogram,
+    /// Toggles drawing the poll times histogram from before it was last reset
+    /// behind the current one.
+    CompareHistogram,
+    ExportHistogram,
+    AcknowledgeWarnings,
+    ClearAcknowledged,
+    ToggleTimeDisplay,
+    CycleTaskPanes,
+    /// Toggles the task view's list of the tasks spawned by the viewed task.
+    ToggleChildren,
+    /// Views the task which spawned the viewed task.
+    ViewParent,
+    TogglePin,
+    UnpinAll,
+    /// Tags the selected task with the next of the tag colors, or removes its
+    /// tag after the last color.
+    CycleTag,
+    ClearTags,
+    /// Opens a prompt to write, edit or remove a note on the selected task.
+    EditNote,
+    ClearNotes,
+    /// Toggles the panel listing the tasks with the most of a metric, next to
+    /// the tasks list.
+    ToggleTopTasks,
+    /// Ranks the tasks in the top tasks panel by the next metric.
+    CycleTopMetric,
+    /// Restores whatever the last confirmed bulk clear, such as clearing
+    /// every tag, cleared.
+    Undo,
+    /// Opens the command palette, for running any action by its name.
+    CommandPalette,
+    /// Toggles an overlay listing the most referenced interned strings.
+    ///
+    /// This is only available in debug builds, and isn't displayed in the
+    /// controls.
+    ToggleInternerDebug,
+    /// Writes every interned string, with its reference count, to a file.
+    ///
+    /// This is only available in debug builds, and isn't displayed in the
+    /// controls.
+    DumpInterner,
+}
+
+/// Maps each [`Action`] to the keys that trigger it.
+///
+/// Key bindings may be configured in the console's config file, using the
+/// names of actions in `snake_case`:
+///
+/// ```toml
+/// [keys]
+/// quit = ["x"]
+/// scroll_down = ["down", "ctrl-n"]
+/// ```
+///
+/// Any actions that are not configured keep their default bindings.
+#[derive(Debug)]
+pub(crate) struct KeyBindings {
+    bindings: HashMap<Action, Vec<Key>>,
+}
+
+/// A key, and any modifiers that must be held when it is pressed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+#[derive(Debug)]
+pub(crate) struct InvalidKey(String);
+
+/// Returns `true` if `input` is a press of the character `c`, with no
+/// modifiers other than shift.
+pub(crate) fn is_char(input: &Event, c: char) -> bool {
+    Key {
+        code: KeyCode::Char(c),
+        modifiers: KeyModifiers::NONE,
+    }
+    .matches_event(input)
+}
 
 pub fn should_quit(input: &Event) -> bool {
     use Event::*;
     use KeyCode::*;
-    match input {
-        Key(KeyEvent {
-            code: Char('q'), ..
-        }) => true,
+    // Ctrl-C and Ctrl-D always quit, regardless of how the quit action is
+    // bound, so it's always possible to exit the console.
+    matches!(
+        input,
         Key(KeyEvent {
             code: Char('c'),
             modifiers,
@@ -17,17 +165,412 @@ pub fn should_quit(input: &Event) -> bool {
         | Key(KeyEvent {
             code: Char('d'),
             modifiers,
-        }) if modifiers.contains(KeyModifiers::CONTROL) => true,
-        _ => false,
+        }) if modifiers.contains(KeyModifiers::CONTROL)
+    )
+}
+
+// === impl Action ===
+
+impl Action {
+    /// Every action, along with the name used to configure it.
+    pub(crate) const ALL: &'static [(Action, &'static str)] = &[
+        (Action::Quit, "quit"),
+        (Action::TogglePause, "toggle_pause"),
+        (Action::FreezeView, "freeze_view"),
+        (Action::FreezeClock, "freeze_clock"),
+        (Action::TasksView, "tasks_view"),
+        (Action::ResourcesView, "resources_view"),
+        (Action::AsyncOpsView, "async_ops_view"),
+        (Action::WarningsView, "warnings_view"),
+        (Action::ScrollUp, "scroll_up"),
+        (Action::ScrollDown, "scroll_down"),
+        (Action::ScrollTop, "scroll_top"),
+        (Action::ScrollBottom, "scroll_bottom"),
+        (Action::ScrollWarningsUp, "scroll_warnings_up"),
+        (Action::ScrollWarningsDown, "scroll_warnings_down"),
+        (Action::SelectColumnLeft, "select_column_left"),
+        (Action::SelectColumnRight, "select_column_right"),
+        (Action::InvertSort, "invert_sort"),
+        (Action::HideColumn, "hide_column"),
+        (Action::ShowAllColumns, "show_all_columns"),
+        (Action::ResizeColumns, "resize_columns"),
+        (Action::ResetColumnWidth, "reset_column_width"),
+        (Action::Search, "search"),
+        (Action::ToggleFuzzySearch, "toggle_fuzzy_search"),
+        (Action::FilterCompleted, "filter_completed"),
+        (Action::FilterUnpolled, "filter_unpolled"),
+        (Action::CycleTaskKinds, "cycle_task_kinds"),
+        (Action::GotoTask, "goto_task"),
+        (Action::GroupTasks, "group_tasks"),
+        (Action::RetainLonger, "retain_longer"),
+        (Action::RetainShorter, "retain_shorter"),
+        (Action::RefreshLonger, "refresh_longer"),
+        (Action::RefreshShorter, "refresh_shorter"),
+        (Action::CycleRateWindow, "cycle_rate_window"),
+        (Action::MorePrecision, "more_precision"),
+        (Action::LessPrecision, "less_precision"),
+        (Action::CycleDurationUnit, "cycle_duration_unit"),
+        (Action::ViewDetails, "view_details"),
+        (Action::Back, "back"),
+        (Action::Reconnect, "reconnect"),
+        (Action::CopyDetails, "copy_details"),
+        (Action::CopyLocation, "copy_location"),
+        (Action::CopyTable, "copy_table"),
+        (Action::ExportFolded, "export_folded"),
+        (Action::OpenInEditor, "open_in_editor"),
+        (Action::ResetHistogram, "reset_histogram"),
+        (Action::CompareHistogram, "compare_histogram"),
+        (Action::ExportHistogram, "export_histogram"),
+        (Action::AcknowledgeWarnings, "acknowledge_warnings"),
+        (Action::ClearAcknowledged, "clear_acknowledged"),
+        (Action::ToggleTimeDisplay, "toggle_time_display"),
+        (Action::CycleTaskPanes, "cycle_task_panes"),
+        (Action::ToggleChildren, "toggle_children"),
+        (Action::ViewParent, "view_parent"),
+        (Action::TogglePin, "toggle_pin"),
+        (Action::UnpinAll, "unpin_all"),
+        (Action::CycleTag, "cycle_tag"),
+        (Action::ClearTags, "clear_tags"),
+        (Action::EditNote, "edit_note"),
+        (Action::ClearNotes, "clear_notes"),
+        (Action::ToggleTopTasks, "toggle_top_tasks"),
+        (Action::CycleTopMetric, "cycle_top_metric"),
+        (Action::Undo, "undo"),
+        (Action::CommandPalette, "command_palette"),
+        (Action::ToggleInternerDebug, "toggle_interner_debug"),
+        (Action::DumpInterner, "dump_interner"),
+    ];
+
+    /// Returns the name used to configure the action, like `toggle_pause`.
+    pub(crate) fn name(self) -> &'static str {
+        Action::ALL
+            .iter()
+            .find(|(action, _)| *action == self)
+            .map(|(_, name)| *name)
+            .expect("every action is in `Action::ALL`")
+    }
+
+    /// Returns `true` if the action does the same thing in every view, and so
+    /// isn't listed among each view's controls.
+    pub(crate) fn is_global(self) -> bool {
+        let debug_only = matches!(self, Action::ToggleInternerDebug | Action::DumpInterner);
+        matches!(
+            self,
+            Action::Quit
+                | Action::TogglePause
+                | Action::FreezeView
+                | Action::FreezeClock
+                | Action::Reconnect
+                | Action::RefreshLonger
+                | Action::RefreshShorter
+                | Action::CycleRateWindow
+                | Action::CommandPalette
+                | Action::Undo
+        ) || (debug_only && cfg!(debug_assertions))
+    }
+
+    /// Returns `true` if the action clears something that would be tedious to
+    /// recreate, like every task's tags, and so is only run once it has been
+    /// confirmed.
+    pub(crate) fn is_destructive(self) -> bool {
+        matches!(
+            self,
+            Action::ClearAcknowledged | Action::UnpinAll | Action::ClearTags | Action::ClearNotes
+        )
+    }
+
+    fn default_keys(&self) -> Vec<Key> {
+        use KeyCode::*;
+        let codes: &[KeyCode] = match self {
+            Action::Quit => &[Char('q')],
+            Action::TogglePause => &[Char(' ')],
+            Action::FreezeView => &[Char('Z')],
+            Action::FreezeClock => &[Char('z')],
+            Action::TasksView => &[Char('t')],
+            Action::ResourcesView => &[Char('r')],
+            Action::AsyncOpsView => &[Char('a')],
+            Action::WarningsView => &[Char('!')],
+            // The arrow keys come first, so that they are the keys displayed
+            // in the controls bar.
+            Action::ScrollUp => &[Up, Char('k')],
+            Action::ScrollDown => &[Down, Char('j')],
+            // In addition to these keys, `gg` always scrolls to the top, like
+            // in vim.
+            Action::ScrollTop => &[Home],
+            Action::ScrollBottom => &[End, Char('G')],
+            Action::ScrollWarningsUp => &[Char('[')],
+            Action::ScrollWarningsDown => &[Char(']')],
+            Action::SelectColumnLeft => &[Left],
+            Action::SelectColumnRight => &[Right],
+            Action::InvertSort => &[Char('i')],
+            Action::HideColumn => &[Char('h')],
+            Action::ShowAllColumns => &[Char('H')],
+            Action::ResizeColumns => &[Char('R')],
+            // This only does anything while a column is being resized.
+            Action::ResetColumnWidth => &[Backspace],
+            Action::Search => &[Char('/')],
+            Action::ToggleFuzzySearch => &[Char('F')],
+            Action::FilterCompleted => &[Char('f')],
+            // Each table only has one filter, so they share a key.
+            Action::FilterUnpolled => &[Char('f')],
+            Action::CycleTaskKinds => &[Char('b')],
+            Action::GotoTask => &[Char(':')],
+            Action::GroupTasks => &[Char('o')],
+            // `=` is on the same key as `+`, so that shift doesn't need to be
+            // held.
+            Action::RetainLonger => &[Char('+'), Char('=')],
+            Action::RetainShorter => &[Char('-')],
+            Action::RefreshLonger => &[Char('>')],
+            Action::RefreshShorter => &[Char('<')],
+            Action::CycleRateWindow => &[Char('A')],
+            // These are on the same keys as `>` and `<`, which make the
+            // screen refresh less or more often.
+            Action::MorePrecision => &[Char('.')],
+            Action::LessPrecision => &[Char(',')],
+            Action::CycleDurationUnit => &[Char('u')],
+            Action::ViewDetails => &[Enter],
+            Action::Back => &[Esc],
+            Action::Reconnect => &[Char('n')],
+            Action::CopyDetails => &[Char('c')],
+            Action::CopyLocation => &[Char('l')],
+            // Tables can't be copied from the task view, so this shares a key
+            // with copying the task's details.
+            Action::CopyTable => &[Char('c')],
+            Action::ExportFolded => &[Char('E')],
+            Action::OpenInEditor => &[Char('e')],
+            Action::ResetHistogram => &[Char('x')],
+            Action::CompareHistogram => &[Char('B')],
+            Action::ExportHistogram => &[Char('S')],
+            Action::AcknowledgeWarnings => &[Char('w')],
+            Action::ClearAcknowledged => &[Char('W')],
+            Action::ToggleTimeDisplay => &[Char('T')],
+            Action::CycleTaskPanes => &[Char('v')],
+            Action::ToggleChildren => &[Char('C')],
+            Action::ViewParent => &[Char('^')],
+            Action::TogglePin => &[Char('p')],
+            Action::UnpinAll => &[Char('P')],
+            Action::CycleTag => &[Char('m')],
+            Action::ClearTags => &[Char('M')],
+            Action::EditNote => &[Char('N')],
+            Action::ClearNotes => &[Char('D')],
+            Action::ToggleTopTasks => &[Char('O')],
+            Action::CycleTopMetric => &[Char('I')],
+            Action::Undo => &[Char('U')],
+            Action::CommandPalette => {
+                return vec![Key {
+                    code: Char('p'),
+                    modifiers: KeyModifiers::CONTROL,
+                }]
+            }
+            Action::ToggleInternerDebug => &[F(12)],
+            Action::DumpInterner => &[F(11)],
+        };
+        codes
+            .iter()
+            .map(|&code| Key {
+                code,
+                modifiers: KeyModifiers::NONE,
+            })
+            .collect()
     }
 }
 
-pub(crate) fn is_space(input: &Event) -> bool {
-    matches!(
-        input,
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // This is implemented by hand rather than derived, since the derived
+        // implementation can't be used for the keys of a TOML table.
+        let name = String::deserialize(deserializer)?;
+        Action::ALL
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(action, _)| *action)
+            .ok_or_else(|| de::Error::custom(format_args!("unknown action `{}`", name)))
+    }
+}
+
+// === impl KeyBindings ===
+
+impl KeyBindings {
+    /// Returns the default key bindings, overridden by any keys configured
+    /// in `overrides`.
+    pub(crate) fn new(overrides: HashMap<Action, Vec<Key>>) -> Self {
+        let mut bindings = Self::default();
+        for (action, keys) in overrides {
+            if keys.is_empty() {
+                tracing::warn!(?action, "no keys configured for action, using the defaults");
+                continue;
+            }
+            bindings.bindings.insert(action, keys);
+        }
+        bindings
+    }
+
+    /// Returns `true` if `event` is a key press bound to `action`.
+    pub(crate) fn is(&self, action: Action, event: &Event) -> bool {
+        self.keys(action).iter().any(|k| k.matches_event(event))
+    }
+
+    /// Returns the keys bound to `action`.
+    pub(crate) fn keys(&self, action: Action) -> &[Key] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|(action, _)| (*action, action.default_keys()))
+            .collect();
+        Self { bindings }
+    }
+}
+
+// === impl Key ===
+
+impl Key {
+    /// Returns a press of this key, such as for running an action as if its
+    /// key had been pressed.
+    pub(crate) fn to_event(self) -> Event {
         Event::Key(KeyEvent {
-            code: KeyCode::Char(' '),
-            ..
+            code: self.code,
+            modifiers: self.modifiers,
         })
-    )
+    }
+
+    /// Returns `true` if `event` is a press of this key.
+    ///
+    /// The shift modifier is ignored, since it is already reflected in the
+    /// character produced by the key press.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        event.code == self.code && modifiers == self.modifiers
+    }
+
+    fn matches_event(&self, event: &Event) -> bool {
+        match event {
+            Event::Key(key) => self.matches(key),
+            _ => false,
+        }
+    }
+
+    /// Returns a glyph representing this key, for use when UTF-8 output is
+    /// enabled.
+    pub(crate) fn glyph(&self) -> Option<&'static str> {
+        if !self.modifiers.is_empty() {
+            return None;
+        }
+        match self.code {
+            KeyCode::Up => Some("\u{2191}"),
+            KeyCode::Down => Some("\u{2193}"),
+            KeyCode::Left => Some("\u{2190}"),
+            KeyCode::Right => Some("\u{2192}"),
+            KeyCode::Enter => Some("\u{21B5}"),
+            KeyCode::Esc => Some("\u{238B} esc"),
+            _ => None,
+        }
+    }
 }
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = InvalidKey;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s.trim();
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if lower.starts_with("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+            } else if lower.starts_with("alt-") {
+                modifiers |= KeyModifiers::ALT;
+            } else {
+                break;
+            }
+            rest = &rest[lower.find('-').expect("prefix contains a dash") + 1..];
+        }
+
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name if name.starts_with('f') && name.len() > 1 => name[1..]
+                .parse()
+                .map(KeyCode::F)
+                .map_err(|_| InvalidKey(s.to_string()))?,
+            _ => {
+                // Otherwise, the key must be a single character. Use the
+                // original string rather than the lowercased one, so that
+                // uppercase characters can be bound.
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(InvalidKey(s.to_string())),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+// === impl InvalidKey ===
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key `{}`", self.0)
+    }
+}
+
+impl std::error::Error for InvalidKey {}
@@ -1,8 +1,9 @@
 use std::{
     borrow::{Borrow, Cow},
-    collections::HashSet,
+    cmp,
+    collections::{HashSet, VecDeque},
     fmt,
-    hash::Hash,
+    hash::{Hash, Hasher},
     ops::Deref,
     rc::Rc,
 };
@@ -12,15 +13,108 @@ use std::{
 /// A nicer implementation is almost certainly possible. However, this one is
 /// simple and doesn't involve any unsafe code. We could almost certainly
 /// replace it with something faster if it becomes a bottleneck.
-#[derive(Debug, Default)]
+///
+/// If a byte budget is set, strings that are seen for the first time once the
+/// interned strings exceed the budget are not interned. They are still
+/// returned as an [`InternedStr`], but each of them owns its own copy of the
+/// string, so that memory can be reclaimed as soon as it is unused.
+///
+/// If a maximum string length is set, longer strings are truncated, ending in
+/// [`Strings::TRUNCATED`], before they are interned.
+#[derive(Debug)]
 pub(crate) struct Strings {
     strings: HashSet<InternedStr>,
+    /// The total length in bytes of all currently interned strings.
+    bytes: usize,
+    max_bytes: Option<usize>,
+    /// The maximum length in bytes of each interned string, including the
+    /// truncation marker.
+    max_len: Option<usize>,
+    /// Whether a warning has been logged since the budget was last exceeded.
+    warned: bool,
+    /// The amount of unused capacity, in bytes, above which the set of
+    /// interned strings is shrunk after unreferenced strings are dropped.
+    shrink_threshold: usize,
+    /// Whether `hits` and `misses` are counted.
+    count_lookups: bool,
+    /// The number of lookups which found an already interned string.
+    hits: u64,
+    /// The number of lookups which had to intern a new string.
+    misses: u64,
 }
 
-#[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+/// Interns the strings of one update, remembering the last few of them so
+/// that repeats can be found without hashing them again.
+///
+/// Updates often contain runs of entities with the same strings, such as many
+/// new async ops with the same source, and comparing a string with a handful
+/// of recent ones is cheaper than hashing it and probing the set of interned
+/// strings.
+pub(crate) struct Batch<'a> {
+    strings: &'a mut Strings,
+    /// The most recently interned strings, most recent first.
+    recent: VecDeque<InternedStr>,
+    /// The number of strings interned with this batch.
+    len: usize,
+    /// The number of those strings which were found in `recent`.
+    cached: usize,
+}
+
+/// A reference to an interned string.
+///
+/// Interned strings are compared, ordered, and hashed by their contents, the
+/// same as the `str`s they dereference to, and never by the address of the
+/// shared string. Tables rely on this to sort columns of interned strings
+/// (such as async op sources) alphabetically, and hashing by content is
+/// required for looking up interned strings by `&str`.
+#[derive(Clone)]
 pub(crate) struct InternedStr(Rc<String>);
 
 impl Strings {
+    /// The default value for [`Strings::with_shrink_threshold`].
+    pub(crate) const DEFAULT_SHRINK_THRESHOLD: usize = 4 * 1024;
+
+    /// Appended to strings which were truncated to the maximum length.
+    pub(crate) const TRUNCATED: &'static str = "…";
+
+    /// Sets the maximum number of bytes of strings to intern, or `None` to
+    /// intern strings without limit.
+    pub(crate) fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets the maximum length in bytes of each interned string, or `None` to
+    /// intern strings of any length.
+    ///
+    /// Longer strings are cut short on a character boundary, and end in
+    /// [`Strings::TRUNCATED`], so that no interned string is longer than this.
+    pub(crate) fn with_max_len(mut self, max_len: Option<usize>) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Sets how much unused capacity, in bytes, the set of interned strings
+    /// may contain after unreferenced strings are dropped before it is shrunk
+    /// to fit.
+    ///
+    /// Lower thresholds free memory more aggressively, at the cost of
+    /// reallocating more often as strings are interned and dropped.
+    pub(crate) fn with_shrink_threshold(mut self, shrink_threshold: usize) -> Self {
+        self.shrink_threshold = shrink_threshold;
+        self
+    }
+
+    /// Sets whether to count how many lookups find an already interned string
+    /// and how many have to intern a new one, for evaluating how effective
+    /// interning is for a workload.
+    ///
+    /// This is disabled by default.
+    pub(crate) fn with_lookup_counting(mut self, count_lookups: bool) -> Self {
+        self.count_lookups = count_lookups;
+        self
+    }
+
     // NOTE(elzia): currently, we never need to use this, but we can always
     // uncomment it if we do...
 
@@ -36,39 +130,167 @@ impl Strings {
     //     self.insert(string.to_owned())
     // }
 
+    /// Returns a [`Batch`] for interning the strings of one update.
+    pub(crate) fn batch(&mut self) -> Batch<'_> {
+        Batch {
+            strings: self,
+            recent: VecDeque::with_capacity(Batch::RECENT),
+            len: 0,
+            cached: 0,
+        }
+    }
+
     pub(crate) fn string(&mut self, string: String) -> InternedStr {
+        let string = self.truncate(string);
         if let Some(s) = self.strings.get(&string) {
+            if self.count_lookups {
+                self.hits += 1;
+            }
             return s.clone();
         }
 
+        if self.count_lookups {
+            self.misses += 1;
+        }
         self.insert(string)
     }
 
+    /// Interns a batch of strings up front, such as targets that are expected
+    /// to be seen, so that they don't need to be interned as they arrive.
+    ///
+    /// Strings which are already interned are skipped.
+    pub(crate) fn warm(&mut self, strings: impl IntoIterator<Item = String>) {
+        for string in strings {
+            let string = self.truncate(string);
+            if !self.strings.contains(&string) {
+                self.insert(string);
+            }
+        }
+    }
+
+    /// Truncates `string` to the maximum length, if it's longer.
+    fn truncate(&self, mut string: String) -> String {
+        let max_len = match self.max_len {
+            Some(max_len) if string.len() > max_len => max_len,
+            _ => return string,
+        };
+        // If the maximum is too short for the marker, the string is only
+        // truncated.
+        let marker = if max_len >= Self::TRUNCATED.len() {
+            Self::TRUNCATED
+        } else {
+            ""
+        };
+        let mut end = max_len - marker.len();
+        while !string.is_char_boundary(end) {
+            end -= 1;
+        }
+        tracing::trace!(len = string.len(), max_len, "truncating interned string");
+        string.truncate(end);
+        string.push_str(marker);
+        string
+    }
+
     fn insert(&mut self, string: String) -> InternedStr {
+        if let Some(max_bytes) = self.max_bytes {
+            if self.bytes + string.len() > max_bytes {
+                if !self.warned {
+                    tracing::warn!(
+                        strings.len = self.strings.len(),
+                        strings.bytes = self.bytes,
+                        max_bytes,
+                        "interned strings exceeded the memory budget; new strings will not be interned",
+                    );
+                    self.warned = true;
+                }
+                return InternedStr(Rc::new(string));
+            }
+        }
+
+        self.bytes += string.len();
         let string = InternedStr(Rc::new(string));
         self.strings.insert(string.clone());
         string
     }
 
-    /// Drop any interned strings that are not currently referenced.
-    pub(crate) fn retain_referenced(&mut self) {
-        const FOUR_KILOBYTES: usize = 4 * 1024;
+    /// Returns the number of interned strings.
+    pub(crate) fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns the total length in bytes of all interned strings.
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Returns the number of lookups which found an already interned string,
+    /// and the number which had to intern a new one.
+    ///
+    /// These are only counted if enabled with
+    /// [`Strings::with_lookup_counting`], and are zero otherwise.
+    pub(crate) fn hit_miss_ratio(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+
+    /// Returns each interned string, along with the number of references to it
+    /// outside of the interner, in no particular order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.strings
+            .iter()
+            .map(|s| (s.as_ref(), Rc::strong_count(&s.0) - 1))
+    }
 
+    /// Returns up to `limit` interned strings, along with the number of
+    /// references to each of them outside of the interner, with the most
+    /// referenced strings first.
+    pub(crate) fn most_referenced(&self, limit: usize) -> Vec<(InternedStr, usize)> {
+        let mut strings = self
+            .strings
+            .iter()
+            .map(|s| (s, Rc::strong_count(&s.0) - 1))
+            .collect::<Vec<_>>();
+        strings.sort_unstable_by(|(a_str, a), (b_str, b)| b.cmp(a).then_with(|| a_str.cmp(b_str)));
+        strings.truncate(limit);
+        strings
+            .into_iter()
+            .map(|(s, refs)| (s.clone(), refs))
+            .collect()
+    }
+
+    /// Drop any interned strings that are not currently referenced.
+    ///
+    /// Returns the number of strings that were dropped.
+    pub(crate) fn retain_referenced(&mut self) -> usize {
         let len0 = self.strings.len();
-        self.strings.retain(|s| Rc::strong_count(&s.0) > 1);
+        let bytes = &mut self.bytes;
+        self.strings.retain(|s| {
+            let referenced = Rc::strong_count(&s.0) > 1;
+            if !referenced {
+                *bytes -= s.len();
+            }
+            referenced
+        });
 
         // Did we actually drop anything?
         let len = self.strings.len();
-        if len < len0 {
+        let dropped = len0 - len;
+        if dropped > 0 {
             // How much unused capacity does the hashmap currently contain?
             let free_cap = (self.strings.capacity() - len) * std::mem::size_of::<String>();
-            // If the hashmap has more than 4kb of free capacity, shrink it to
-            // fit the current size.
-            let should_shrink = free_cap >= FOUR_KILOBYTES;
+            // If the hashmap has more free capacity than the threshold,
+            // shrink it to fit the current size.
+            let should_shrink = free_cap >= self.shrink_threshold;
+
+            // If we're back under budget, warn again the next time it's
+            // exceeded.
+            if matches!(self.max_bytes, Some(max_bytes) if self.bytes <= max_bytes) {
+                self.warned = false;
+            }
 
             tracing::trace!(
                 strings.len = len,
-                dropped = len0 - len,
+                strings.bytes = self.bytes,
+                dropped,
                 should_shrink,
                 "dropped un-referenced strings",
             );
@@ -77,6 +299,64 @@ impl Strings {
                 self.strings.shrink_to_fit();
             }
         }
+
+        dropped
+    }
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            strings: HashSet::new(),
+            bytes: 0,
+            max_bytes: None,
+            max_len: None,
+            warned: false,
+            shrink_threshold: Self::DEFAULT_SHRINK_THRESHOLD,
+            count_lookups: false,
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
+// === impl Batch ===
+
+impl Batch<'_> {
+    /// The number of recently interned strings which are compared with each
+    /// new string.
+    const RECENT: usize = 4;
+
+    pub(crate) fn string(&mut self, string: String) -> InternedStr {
+        self.len += 1;
+        if let Some(idx) = self.recent.iter().position(|s| **s == *string) {
+            self.cached += 1;
+            if self.strings.count_lookups {
+                self.strings.hits += 1;
+            }
+            let s = self.recent.remove(idx).expect("index was just found");
+            self.recent.push_front(s.clone());
+            return s;
+        }
+
+        let s = self.strings.string(string);
+        if self.recent.len() == Self::RECENT {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(s.clone());
+        s
+    }
+}
+
+impl Drop for Batch<'_> {
+    fn drop(&mut self) {
+        if self.cached > 0 {
+            tracing::trace!(
+                strings = self.len,
+                cached = self.cached,
+                "interned strings found without hashing"
+            );
+        }
     }
 }
 
@@ -104,6 +384,32 @@ impl fmt::Display for InternedStr {
     }
 }
 
+impl PartialEq for InternedStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for InternedStr {}
+
+impl PartialOrd for InternedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternedStr {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.as_ref().cmp(other.as_ref())
+    }
+}
+
+impl Hash for InternedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
 impl Borrow<str> for InternedStr {
     fn borrow(&self) -> &str {
         self.0.deref()
@@ -133,3 +439,86 @@ impl fmt::Debug for InternedStr {
         tuple.finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retain_referenced_returns_the_number_of_strings_dropped() {
+        let mut strings = Strings::default();
+        let kept = strings.string("kept".to_owned());
+        let dropped = (0..3)
+            .map(|i| strings.string(format!("dropped {}", i)))
+            .collect::<Vec<_>>();
+        assert_eq!(strings.retain_referenced(), 0);
+
+        drop(dropped);
+        assert_eq!(strings.retain_referenced(), 3);
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings.bytes(), kept.len());
+        assert_eq!(strings.retain_referenced(), 0);
+    }
+
+    #[test]
+    fn shrink_threshold_decides_whether_capacity_is_released() {
+        fn capacity_after_dropping(shrink_threshold: usize) -> usize {
+            let mut strings = Strings::default().with_shrink_threshold(shrink_threshold);
+            let interned = (0..1000)
+                .map(|i| strings.string(i.to_string()))
+                .collect::<Vec<_>>();
+            drop(interned);
+            strings.retain_referenced();
+            strings.strings.capacity()
+        }
+
+        assert!(capacity_after_dropping(usize::MAX) >= 1000);
+        assert_eq!(capacity_after_dropping(0), 0);
+    }
+
+    #[test]
+    fn interned_strings_are_ordered_by_content() {
+        // Interning in different orders gives the shared strings different
+        // addresses relative to each other.
+        let mut first = Strings::default();
+        let a1 = first.string("a".to_owned());
+        let b1 = first.string("b".to_owned());
+        let mut second = Strings::default();
+        let b2 = second.string("b".to_owned());
+        let a2 = second.string("a".to_owned());
+
+        assert!(a1 < b1);
+        assert!(a2 < b2);
+        assert!(a1 < b2);
+        assert!(a2 < b1);
+        assert_eq!(a1, a2);
+        assert_eq!(a1.cmp(&a2), cmp::Ordering::Equal);
+
+        let mut sorted = [b1, a2, b2, a1];
+        sorted.sort();
+        let sorted = sorted.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+        assert_eq!(sorted, ["a", "a", "b", "b"]);
+    }
+
+    #[test]
+    fn long_strings_are_truncated_on_a_char_boundary() {
+        let max_len = 6;
+        let mut strings = Strings::default().with_max_len(Some(max_len));
+        // Cutting to leave room for the marker would split the first `é`.
+        let truncated = strings.string("aaééé".to_owned());
+        assert!(truncated.len() <= max_len);
+        assert!(truncated.ends_with(Strings::TRUNCATED));
+        assert_eq!(&*truncated, "aa\u{2026}");
+        assert_eq!(strings.bytes(), truncated.len());
+
+        // Strings which fit are interned unchanged.
+        assert_eq!(&*strings.string("aaéé".to_owned()), "aaéé");
+    }
+
+    #[test]
+    fn strings_are_truncated_without_a_marker_if_it_does_not_fit() {
+        let mut strings = Strings::default().with_max_len(Some(2));
+        assert_eq!(&*strings.string("éé".to_owned()), "é");
+        assert_eq!(&*strings.string("aéé".to_owned()), "a");
+    }
+}
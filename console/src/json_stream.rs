@@ -0,0 +1,138 @@
+//! Streaming the tasks and async ops changed by each update to a file as JSON
+//! lines, for feeding external dashboards while the console is running.
+use crate::state::{Snapshot, State};
+use color_eyre::eyre::WrapErr;
+use console_api as proto;
+use serde::Serialize;
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Writes JSON lines to a file on a separate thread, so that a slow file
+/// never blocks the console.
+#[derive(Debug)]
+pub(crate) struct JsonStream {
+    lines: SyncSender<String>,
+    /// The number of lines which were dropped because the writer thread had
+    /// fallen behind, or had failed.
+    dropped: u64,
+}
+
+/// The IDs of the tasks and async ops included in an update, which are
+/// recorded before the update is applied.
+#[derive(Debug, Default)]
+pub(crate) struct Changed {
+    tasks: Vec<u64>,
+    async_ops: Vec<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Line {
+    time_ms: u64,
+    #[serde(flatten)]
+    snapshot: Snapshot,
+    dropped_lines: u64,
+}
+
+impl JsonStream {
+    /// The number of lines which may be waiting to be written before new lines
+    /// are dropped.
+    const CAPACITY: usize = 64;
+
+    /// Creates (or truncates) the file at `path`, and starts the thread which
+    /// writes lines to it.
+    pub(crate) fn create(path: &Path) -> color_eyre::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create JSON stream file {}", path.display()))?;
+        let (lines, rx) = mpsc::sync_channel::<String>(Self::CAPACITY);
+        thread::Builder::new()
+            .name("json-stream".to_owned())
+            .spawn(move || {
+                let mut file = BufWriter::new(file);
+                for line in rx {
+                    let written = file
+                        .write_all(line.as_bytes())
+                        .and_then(|()| file.write_all(b"\n"))
+                        .and_then(|()| file.flush());
+                    if let Err(error) = written {
+                        // Once the writer has exited, every later line is
+                        // counted as dropped.
+                        tracing::warn!(%error, "failed to write to the JSON stream");
+                        return;
+                    }
+                }
+            })
+            .context("failed to spawn the JSON stream writer thread")?;
+        Ok(Self { lines, dropped: 0 })
+    }
+
+    /// Sends a line with the tasks and async ops in `changed`, as they are in
+    /// `state` after the update was applied.
+    pub(crate) fn send(&mut self, state: &State, changed: Changed) {
+        let now = state.last_updated_at().unwrap_or_else(SystemTime::now);
+        let line = Line {
+            time_ms: now
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis()
+                .try_into()
+                .unwrap_or(u64::MAX),
+            snapshot: state.snapshot_of(&changed.tasks, &changed.async_ops, now),
+            dropped_lines: self.dropped,
+        };
+        let line = match serde_json::to_string(&line) {
+            Ok(line) => line,
+            Err(error) => {
+                tracing::warn!(%error, "failed to serialize a JSON stream line");
+                self.dropped += 1;
+                return;
+            }
+        };
+        match self.lines.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => self.dropped += 1,
+        }
+    }
+
+    /// Returns the number of lines which have been dropped.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+// === impl Changed ===
+
+impl Changed {
+    /// Returns the IDs of the tasks and async ops which are new or have new
+    /// stats in `update`.
+    pub(crate) fn of(update: &proto::instrument::Update) -> Self {
+        let mut changed = Self::default();
+        if let Some(ref tasks) = update.task_update {
+            changed.tasks = tasks
+                .new_tasks
+                .iter()
+                .filter_map(|task| Some(task.id.as_ref()?.id))
+                .chain(tasks.stats_update.keys().copied())
+                .collect();
+        }
+        if let Some(ref async_ops) = update.async_op_update {
+            changed.async_ops = async_ops
+                .new_async_ops
+                .iter()
+                .filter_map(|async_op| Some(async_op.id.as_ref()?.id))
+                .chain(async_ops.stats_update.keys().copied())
+                .collect();
+        }
+        changed.tasks.sort_unstable();
+        changed.tasks.dedup();
+        changed.async_ops.sort_unstable();
+        changed.async_ops.dedup();
+        changed
+    }
+}
@@ -1,23 +1,39 @@
 use color_eyre::{eyre::eyre, Help, SectionExt};
 use console_api::tasks::TaskDetails;
-use state::State;
+use state::{tasks::DetailsStatus, State};
 
 use clap::Parser as Clap;
 use futures::stream::StreamExt;
-use tokio::sync::{mpsc, watch};
+use std::time::Duration;
+use tokio::{
+    sync::{mpsc, watch},
+    time,
+};
 use tui::{
     layout::{Constraint, Direction, Layout},
-    style::Color,
-    text::{Span, Spans},
+    style::Modifier,
+    text::Span,
     widgets::{Paragraph, Wrap},
 };
 
-use crate::view::{bold, UpdateKind};
+use crate::view::UpdateKind;
 
+mod clipboard;
 mod config;
 mod conn;
+mod details_fetch;
+mod diff;
+mod dump;
+mod editor;
+mod export;
+mod folded;
+mod fuzzy;
 mod input;
 mod intern;
+mod json_stream;
+mod metrics;
+mod record;
+mod render_lag;
 mod state;
 mod term;
 mod util;
@@ -28,84 +44,347 @@ mod warnings;
 async fn main() -> color_eyre::Result<()> {
     let mut args = config::Config::parse();
     let retain_for = args.retain_for();
+    let async_op_deadline = args.async_op_deadline();
+    let refresh_interval = args.refresh_interval();
+    let rate_window = args.rate_window();
+    let max_reconnect_backoff = args.max_reconnect_backoff();
+    let max_reconnect_attempts = args.max_reconnect_attempts;
+    let export_duration = args.export_duration();
+    let export_warnings = args.export_warnings.take();
+    let export_snapshot = args.export_snapshot.take();
+    let export_folded = args.export_folded.take();
+    let folded_by_target = args.folded_by_target;
+    let json_stream = args.json_stream.take();
+    let metrics_addr = args.metrics_addr;
+    let record = args.record.take();
+    let replay = args
+        .replay
+        .take()
+        .map(|path| record::Replay::open(&path, args.replay_speed()))
+        .transpose()?;
+    let max_interned_bytes = args.max_interned_bytes;
+    let max_interned_string_len = args.max_interned_string_len;
+    let max_tasks = args.max_tasks;
+    let interner_shrink_threshold = args.interner_shrink_threshold;
+    let correct_clock_skew = args.correct_clock_skew;
+    let highlight_changes = args.highlight_changes;
+    let sort_direction = args.sort_direction;
+    let (top_tasks_by, top_tasks) = (args.top_tasks_by, args.top_tasks);
+    let dump_tasks = args.dump;
+    let dump_sort = args.sort.unwrap_or_default();
     args.trace_init()?;
     tracing::debug!(?args.target_addr, ?args.view_options);
 
-    let styles = view::Styles::from_config(args.view_options);
+    if let [before, after] = &args.diff_snapshots[..] {
+        let (before, after) = (diff::load(before)?, diff::load(after)?);
+        print!("{}", diff::diff(&before, &after, args.diff_thresholds()));
+        return Ok(());
+    }
+
+    // Start watching the config file before it's loaded, so that no change
+    // made while it's loaded is missed.
+    let mut config_watch = args.config_watch();
+    let mut config_file = args.config_file()?;
+    let bindings = input::KeyBindings::new(std::mem::take(&mut config_file.keys));
+
+    let styles = view::Styles::from_config(args.view_options)
+        .with_precision(config_file.precision)
+        .with_theme(config_file.theme.theme());
     styles.error_init()?;
 
     let target = args.target_addr;
     tracing::info!(?target, "using target addr");
 
+    let mut saved_state = config::SavedState::load();
+    let mut conn = conn::Connection::new(target)
+        .with_replay(replay)
+        .with_reconnect(max_reconnect_backoff, max_reconnect_attempts);
+    let mut state = State::default()
+        .with_task_linters(config_file.warnings.task_linters())
+        .with_location_linters(config_file.warnings.location_linters())
+        .with_resource_linters(config_file.warnings.resource_linters())
+        .with_async_op_linters(config_file.warnings.async_op_linters(async_op_deadline))
+        .with_retain_for(retain_for)
+        .with_rate_window(rate_window)
+        .with_max_interned_bytes(max_interned_bytes)
+        .with_max_interned_string_len(max_interned_string_len)
+        .with_max_tasks(max_tasks)
+        .with_interner_shrink_threshold(interner_shrink_threshold)
+        .with_clock_skew_correction(correct_clock_skew)
+        // Lookups are only counted for the interned strings debug overlay,
+        // which is only available in debug builds.
+        .with_interner_lookup_counting(cfg!(debug_assertions))
+        .with_warm_strings(std::mem::take(&mut saved_state.targets))
+        .with_saved_tasks(
+            std::mem::take(&mut saved_state.pinned),
+            std::mem::take(&mut saved_state.acknowledged),
+        );
+
+    if dump_tasks {
+        let code = dump::dump(&mut conn, &mut state, &styles, dump_sort).await?;
+        std::process::exit(code);
+    }
+
+    let exports = export::Exports {
+        warnings: export_warnings.as_deref(),
+        snapshot: export_snapshot.as_deref(),
+        folded: export_folded.as_deref(),
+        folded_by_target,
+    };
+    if exports.any() {
+        let code =
+            export::export(&mut conn, &mut state, &styles, export_duration, &exports).await?;
+        std::process::exit(code);
+    }
+
+    // Create the file before taking over the terminal, so that an error
+    // creating it is displayed normally.
+    let mut json_stream = json_stream
+        .as_deref()
+        .map(json_stream::JsonStream::create)
+        .transpose()?;
+    let recorder = record
+        .as_deref()
+        .map(record::Recorder::create)
+        .transpose()?;
+    let exporter = match metrics_addr {
+        Some(addr) => Some(metrics::serve(addr).await?),
+        None => None,
+    };
+
     let (mut terminal, _cleanup) = term::init_crossterm()?;
     terminal.clear()?;
-    let mut conn = conn::Connection::new(target);
     // A channel to send the outcome of `View::update_input` to the watch_details_stream task.
     let (update_tx, update_rx) = watch::channel(UpdateKind::Other);
     // A channel to send the task details update stream (no need to keep outdated details in the memory)
-    let (details_tx, mut details_rx) = mpsc::channel::<TaskDetails>(2);
+    let (details_tx, mut details_rx) = mpsc::channel::<DetailsUpdate>(2);
+    let mut details_fetch = details_fetch::DetailsFetch::default();
 
-    let mut state = State::default()
-        // TODO(eliza): allow configuring the list of linters via the
-        // CLI/possibly a config file?
-        .with_task_linters(vec![
-            warnings::Linter::new(warnings::SelfWakePercent::default()),
-            warnings::Linter::new(warnings::LostWaker),
-        ])
-        .with_retain_for(retain_for);
     let mut input = input::EventStream::new();
-    let mut view = view::View::new(styles);
+    let mut pending_input = None;
+    let mut view = view::View::new(styles, bindings, &saved_state)
+        .with_async_op_columns(config_file.columns.async_op_columns())
+        .with_sort_direction(sort_direction)
+        .with_top_tasks(top_tasks_by, top_tasks)
+        .with_change_highlights(highlight_changes)
+        .with_folded_by_target(folded_by_target)
+        .with_refresh_interval(refresh_interval);
+    let mut refresh = refresh_timer(view.refresh_interval());
+    let mut config_timer = tokio::time::interval(config::ConfigWatch::INTERVAL);
+    let mut render_lag = render_lag::RenderLag::default();
+    // The location to open in an editor, once the key press has been handled.
+    let mut open_in_editor = None;
 
     loop {
+        let details_retry = details_fetch.retry();
         tokio::select! { biased;
-            input = input.next() => {
+            input = next_input(&mut input, &mut pending_input) => {
                 let input = input
                     .ok_or_else(|| eyre!("keyboard input stream ended early"))
                     .with_section(|| "this is probably a bug".header("Note:"))??;
-                if input::should_quit(&input) {
+                // While text is being typed, keys other than Ctrl-C and Ctrl-D are
+                // only handled by the view.
+                let editing_text = view.is_editing_text();
+                if input::should_quit(&input)
+                    || (!editing_text && view.bindings.is(input::Action::Quit, &input))
+                {
+                    let saved_state = config::SavedState {
+                        targets: state.frequent_targets(config::SavedState::MAX_TARGETS),
+                        pinned: state.tasks_state().saved_pins(),
+                        acknowledged: state.tasks_state().saved_acks(),
+                        ..view.saved_state()
+                    };
+                    if let Err(error) = saved_state.save() {
+                        tracing::warn!(%error, "failed to save state");
+                    }
                     return Ok(());
                 }
 
-                if input::is_space(&input) {
-                    if state.is_paused() {
-                        conn.resume().await;
-                        state.resume();
-                    } else {
-                        conn.pause().await;
-                        state.pause();
+                if !editing_text {
+                    if view.bindings.is(input::Action::TogglePause, &input) {
+                        if state.is_paused() {
+                            conn.resume().await;
+                            state.resume();
+                        } else {
+                            conn.pause().await;
+                            state.pause();
+                        }
+                    }
+
+                    if view.bindings.is(input::Action::Reconnect, &input) {
+                        conn.reconnect();
+                    }
+
+                    if view.bindings.is(input::Action::FreezeView, &input) {
+                        state.toggle_frozen_view();
+                    }
+
+                    if view.bindings.is(input::Action::FreezeClock, &input) {
+                        state.toggle_frozen_clock();
+                    }
+
+                    if view.bindings.is(input::Action::CycleRateWindow, &input) {
+                        state.cycle_rate_window();
+                    }
+
+                    if view.bindings.is(input::Action::RetainLonger, &input) {
+                        state.retain_longer();
+                    } else if view.bindings.is(input::Action::RetainShorter, &input) {
+                        state.retain_shorter();
+                    } else if view.bindings.is(input::Action::AcknowledgeWarnings, &input) {
+                        if let Some(task_id) = view.current_task_id() {
+                            state.acknowledge_task_warnings(task_id);
+                        }
+                    } else if view.bindings.is(input::Action::TogglePin, &input) {
+                        if let Some(task_id) = view.selected_task_id() {
+                            state.tasks_state_mut().toggle_pinned(task_id);
+                        }
+                    } else if view.bindings.is(input::Action::Undo, &input) {
+                        view.undo(&mut state);
+                    } else if view.bindings.is(input::Action::OpenInEditor, &input) {
+                        open_in_editor = view
+                            .current_task_id()
+                            .and_then(|task_id| state.tasks_state().task(task_id))
+                            .map(|task| task.borrow().location().to_owned());
                     }
                 }
 
+                let refresh_interval = view.refresh_interval();
                 let update_kind = view.update_input(input, &state);
+                // An action chosen in the command palette is handled as the
+                // next key press.
+                pending_input = view.take_palette_action();
+                // Destructive actions are confirmed in the view, but most of
+                // them clear the state, so they're run here.
+                view.run_confirmed(&mut state);
+                if view.refresh_interval() != refresh_interval {
+                    refresh = refresh_timer(view.refresh_interval());
+                }
                 // Using the result of update_input to manage the details watcher task
                 let _ = update_tx.send(update_kind);
                 match update_kind {
                     UpdateKind::SelectTask(task_id) => {
+                        details_fetch.start(task_id);
+                        state.set_task_details_status(DetailsStatus::Pending);
                         match conn.watch_details(task_id).await {
                             Ok(stream) => {
                                 tokio::spawn(watch_details_stream(task_id, stream, update_rx.clone(), details_tx.clone()));
                             },
                             Err(error) => {
                                 tracing::warn!(%error, "error watching task details");
-                                state.unset_task_details();
-                        }
+                                state.set_task_details_status(details_fetch.failed(&error));
+                            }
                         }
                     },
                     UpdateKind::ExitTaskView => {
+                        details_fetch.stop();
                         state.unset_task_details();
                     }
                     _ => {}
                 }
             },
+            // Redraw on a timer as well as after key presses, so that updates
+            // are only displayed as often as the refresh interval.
+            // While the view is frozen, it's only redrawn after key presses.
+            _ = refresh.tick() => {
+                if state.is_view_frozen() {
+                    continue;
+                }
+            }
+            // Only the warnings are reloaded when the config file changes,
+            // since the rest of it only sets the initial state of the view.
+            _ = config_timer.tick() => {
+                match config_watch.check() {
+                    Some(Ok(config_file)) => {
+                        tracing::info!("config file changed, reloading warnings");
+                        let warnings = config_file.warnings;
+                        state.set_linters(
+                            warnings.task_linters(),
+                            warnings.location_linters(),
+                            warnings.resource_linters(),
+                            warnings.async_op_linters(async_op_deadline),
+                        );
+                    }
+                    Some(Err(error)) => {
+                        let error = format!("{:#}", error);
+                        tracing::warn!(%error, "failed to reload config file, keeping the previous warnings");
+                        continue;
+                    }
+                    None => continue,
+                }
+            }
             instrument_update = conn.next_update() => {
+                if conn.take_reconnected() {
+                    // The target may have restarted, so start over rather
+                    // than mixing its new tasks with the ones it had before.
+                    tracing::info!("reconnected, discarding the previous connection's state");
+                    state.reset();
+                    view.reset();
+                    details_fetch.stop();
+                    let _ = update_tx.send(UpdateKind::ExitTaskView);
+                }
+                if let Some(ref recorder) = recorder {
+                    recorder.record(&instrument_update);
+                }
+                let changed = json_stream
+                    .as_ref()
+                    .map(|_| json_stream::Changed::of(&instrument_update));
                 state.update(&view.styles,view.current_view(), instrument_update);
+                if let (Some(json_stream), Some(changed)) = (json_stream.as_mut(), changed) {
+                    json_stream.send(&state, changed);
+                }
+                continue;
             }
             details_update = details_rx.recv() => {
-                if let Some(details_update) = details_update {
-                    state.update_task_details(details_update);
+                match details_update {
+                    // A stream for a previously viewed task may still send
+                    // details before it notices it's no longer needed.
+                    Some((task_id, Ok(details_update))) if details_fetch.is_fetching(task_id) => {
+                        details_fetch.succeeded();
+                        state.update_task_details(details_update);
+                    }
+                    Some((task_id, Err(error))) if details_fetch.is_fetching(task_id) => {
+                        tracing::warn!(%error, task_id, "task details stream failed");
+                        state.set_task_details_status(details_fetch.failed(&error));
+                    }
+                    _ => {}
                 }
+                continue;
             },
+            task_id = details_fetch::wait_for_retry(details_retry) => {
+                details_fetch.retrying();
+                tracing::debug!(task_id, "retrying task details");
+                match conn.watch_details(task_id).await {
+                    Ok(stream) => {
+                        tokio::spawn(watch_details_stream(task_id, stream, update_rx.clone(), details_tx.clone()));
+                    },
+                    Err(error) => {
+                        tracing::warn!(%error, "error watching task details");
+                        state.set_task_details_status(details_fetch.failed(&error));
+                    }
+                }
+                continue;
+            },
+        }
+        if let Some(location) = open_in_editor.take() {
+            let opened = match editor::SourceLocation::parse(&location) {
+                Ok(location) => {
+                    // The input stream reads from the terminal in the
+                    // background, so it's replaced to stop it from reading the
+                    // editor's input.
+                    drop(input);
+                    let opened = term::suspend(&mut terminal, || location.open());
+                    input = input::EventStream::new();
+                    opened.and_then(|opened| opened)
+                }
+                Err(error) => Err(error),
+            };
+            if let Err(error) = opened {
+                tracing::warn!(%error, %location, "failed to open location in editor");
+                view.set_notice(view::Notice::error(format!("{:#}", error)));
+            }
         }
+        let draw_started = std::time::Instant::now();
         terminal.draw(|f| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
@@ -120,32 +399,121 @@ async fn main() -> color_eyre::Result<()> {
                 )
                 .split(f.size());
 
-            let mut header_text = conn.render(&view.styles);
-            if state.is_paused() {
+            let mut header_text = conn.render(&view.styles, &view.bindings);
+            if let Some(retain_for) = state.retain_for() {
+                header_text.0.push(Span::raw(format!(
+                    " retaining dropped for {}",
+                    humantime::format_duration(retain_for)
+                )));
+            }
+            header_text.0.push(Span::raw(format!(
+                " refreshing every {}",
+                humantime::format_duration(view.refresh_interval())
+            )));
+            header_text
+                .0
+                .push(Span::raw(format!(" rates over {}", state.rate_window())));
+            let dropped_events = state.dropped_events();
+            if dropped_events > 0 {
+                // Dropped events mean that every other metric is incomplete,
+                // so make this hard to miss while events are still being
+                // dropped.
+                let text = format!("dropped {} events", dropped_events);
+                header_text.0.push(Span::raw(" "));
+                if state.dropped_events_increased() {
+                    header_text.0.push(view.styles.warning_narrow());
+                    header_text
+                        .0
+                        .push(Span::styled(text, view.styles.warning()));
+                } else {
+                    header_text.0.push(Span::raw(text));
+                }
+            }
+            if let Some(dropped) = json_stream.as_ref().map(json_stream::JsonStream::dropped) {
+                if dropped > 0 {
+                    header_text.0.push(Span::styled(
+                        format!(" JSON stream dropped {} lines", dropped),
+                        view.styles.warning(),
+                    ));
+                }
+            }
+            if let Some(skew) = state.clock_skew().estimate() {
+                let text = if state.clock_skew().is_corrected() {
+                    format!("timestamps are {:.1?} ahead (corrected)", skew)
+                } else {
+                    format!(
+                        "timestamps are {:.1?} ahead, so durations may read as zero",
+                        skew
+                    )
+                };
+                header_text.0.push(Span::raw(" "));
+                header_text.0.push(view.styles.warning_narrow());
                 header_text
                     .0
-                    .push(Span::styled(" PAUSED", view.styles.fg(Color::Red)));
+                    .push(Span::styled(text, view.styles.warning()));
+            }
+            if let Some(frame_time) = render_lag.behind_by() {
+                header_text.0.push(Span::styled(
+                    format!(
+                        " rendering is falling behind (frames take {:.1?})",
+                        frame_time
+                    ),
+                    view.styles.warning(),
+                ));
+            }
+            if state.is_paused() {
+                header_text.0.push(Span::styled(
+                    " PAUSED",
+                    view.styles
+                        .fg_or(view.styles.paused_color(), Modifier::BOLD),
+                ));
             }
             let header = Paragraph::new(header_text).wrap(Wrap { trim: true });
-            let view_controls = Paragraph::new(Spans::from(vec![
-                Span::raw("views: "),
-                bold("t"),
-                Span::raw(" = tasks, "),
-                bold("r"),
-                Span::raw(" = resources"),
-            ]))
-            .wrap(Wrap { trim: true });
+            let view_controls = Paragraph::new(view.views_controls()).wrap(Wrap { trim: true });
 
             f.render_widget(header, chunks[0]);
             f.render_widget(view_controls, chunks[1]);
             view.render(f, chunks[2], &mut state);
         })?;
+        let draw_time = draw_started.elapsed();
+        render_lag.record(draw_time, view.refresh_interval());
+        if let Some(ref exporter) = exporter {
+            exporter.update(metrics::Metrics::sample(&state, draw_time));
+        }
+    }
+}
+
+/// Returns a timer which ticks immediately, and then every `period`.
+///
+/// If drawing falls behind, ticks are delayed rather than bunched up.
+fn refresh_timer(period: Duration) -> time::Interval {
+    let mut timer = time::interval(period);
+    timer.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    timer
+}
+
+/// Returns `pending`, if there's a key press waiting to be handled, or else
+/// the next key press from `input`.
+async fn next_input(
+    input: &mut input::EventStream,
+    pending: &mut Option<input::Event>,
+) -> Option<std::io::Result<input::Event>> {
+    match pending.take() {
+        Some(event) => Some(Ok(event)),
+        None => input.next().await,
     }
 }
 
+/// An update from the details stream of the task with the given ID, or the
+/// reason the stream ended.
+type DetailsUpdate = (u64, Result<TaskDetails, tonic::Status>);
+
 /// Given the task details stream for the given task id, sends the updates
 /// to the `details_tx` channel until the currently-viewed task changes.
 ///
+/// If the stream fails or is closed by the target, that is sent to the
+/// channel too, so that it can be retried.
+///
 /// This is a separate task from the main program loop mainly because there isn't
 /// always a details stream to poll and we need to react to user inputs to
 /// replace the details stream with another one.
@@ -153,20 +521,19 @@ async fn watch_details_stream(
     task_id: u64,
     mut details_stream: tonic::Streaming<TaskDetails>,
     mut watch_rx: watch::Receiver<UpdateKind>,
-    details_tx: mpsc::Sender<TaskDetails>,
+    details_tx: mpsc::Sender<DetailsUpdate>,
 ) {
     loop {
         tokio::select! { biased;
             details = details_stream.next() => {
-                match details {
-                    Some(Ok(details)) => {
-                        if details_tx.send(details).await.is_err() {
-                            break;
-                        }
-                    },
-                    _ => {
-                        break;
-                    }
+                let details = match details {
+                    Some(Ok(details)) => Ok(details),
+                    Some(Err(status)) => Err(status),
+                    None => Err(tonic::Status::unavailable("the target closed the task details stream")),
+                };
+                let ended = details.is_err();
+                if details_tx.send((task_id, details)).await.is_err() || ended {
+                    break;
                 }
             },
             update = watch_rx.changed() => {
@@ -0,0 +1,225 @@
+//! Serving the console's own stats over HTTP, in the Prometheus text format,
+//! so that a long-running console can be monitored like any other service.
+//!
+//! Serving metrics requires the `metrics` feature flag, so that the default
+//! build doesn't listen on a socket it never needs. When the console is built
+//! without it, starting the server returns an error explaining how to enable
+//! it, so callers never need to `cfg` on the feature.
+//!
+//! The console's state isn't shared with the server's task. Instead, the main
+//! loop publishes a snapshot of its stats after each frame is drawn, and each
+//! request is answered with the latest snapshot.
+#![cfg_attr(not(feature = "metrics"), allow(dead_code))]
+use crate::state::State;
+use color_eyre::eyre;
+use std::{fmt, net::SocketAddr, time::Duration};
+
+/// A snapshot of the console's stats.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metrics {
+    interned_strings: usize,
+    interned_bytes: usize,
+    tasks: usize,
+    async_ops: usize,
+    dropped_events: u64,
+    render_time: Duration,
+}
+
+/// Publishes snapshots of the console's stats to the metrics server.
+#[derive(Debug)]
+pub(crate) struct Exporter {
+    #[cfg(feature = "metrics")]
+    tx: tokio::sync::watch::Sender<Metrics>,
+}
+
+impl Metrics {
+    /// Takes a snapshot of `state`'s stats, where drawing the last frame took
+    /// `render_time`.
+    pub(crate) fn sample(state: &State, render_time: Duration) -> Self {
+        let strings = state.strings();
+        Self {
+            interned_strings: strings.len(),
+            interned_bytes: strings.bytes(),
+            tasks: state.tasks_state().counts().total,
+            async_ops: state.async_ops_state().len(),
+            dropped_events: state.dropped_events(),
+            render_time,
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn metric(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            kind: &str,
+            help: &str,
+            value: impl fmt::Display,
+        ) -> fmt::Result {
+            writeln!(f, "# HELP tokio_console_{} {}", name, help)?;
+            writeln!(f, "# TYPE tokio_console_{} {}", name, kind)?;
+            writeln!(f, "tokio_console_{} {}", name, value)
+        }
+
+        metric(
+            f,
+            "interned_strings",
+            "gauge",
+            "The number of strings interned by the console.",
+            self.interned_strings,
+        )?;
+        metric(
+            f,
+            "interned_bytes",
+            "gauge",
+            "The total length in bytes of the strings interned by the console.",
+            self.interned_bytes,
+        )?;
+        metric(
+            f,
+            "tasks",
+            "gauge",
+            "The number of tasks tracked by the console, including completed tasks which are still retained.",
+            self.tasks,
+        )?;
+        metric(
+            f,
+            "async_ops",
+            "gauge",
+            "The number of async ops tracked by the console, including dropped async ops which are still retained.",
+            self.async_ops,
+        )?;
+        metric(
+            f,
+            "dropped_events_total",
+            "counter",
+            "The number of events the target has dropped because its event buffer was full.",
+            self.dropped_events,
+        )?;
+        metric(
+            f,
+            "render_duration_seconds",
+            "gauge",
+            "How long drawing the console's last frame took.",
+            self.render_time.as_secs_f64(),
+        )
+    }
+}
+
+impl Exporter {
+    /// Publishes `metrics` as the snapshot to answer requests with.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn update(&self, metrics: Metrics) {
+        // The server's task holds a receiver for as long as it is running, so
+        // this only fails if the server has stopped, and there's no one to
+        // publish to.
+        let _ = self.tx.send(metrics);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    pub(crate) fn update(&self, _: Metrics) {}
+}
+
+/// Starts serving the console's stats at `/metrics` on `addr`, on a
+/// background task.
+#[cfg(feature = "metrics")]
+pub(crate) async fn serve(addr: SocketAddr) -> eyre::Result<Exporter> {
+    use color_eyre::eyre::WrapErr;
+    use tokio::{net::TcpListener, sync::watch};
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .wrap_err_with(|| format!("failed to serve metrics on {}", addr))?;
+    tracing::info!(%addr, "serving metrics");
+    let (tx, rx) = watch::channel(Metrics::default());
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(error) => {
+                    tracing::warn!(%error, "failed to accept a metrics connection");
+                    // Errors like running out of file descriptors won't
+                    // resolve themselves right away, so don't spin on them.
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let rx = rx.clone();
+            tokio::spawn(async move {
+                if let Err(error) = respond(stream, &rx).await {
+                    tracing::debug!(%error, %peer, "failed to respond to a metrics request");
+                }
+            });
+        }
+    });
+    Ok(Exporter { tx })
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) async fn serve(_: SocketAddr) -> eyre::Result<Exporter> {
+    Err(eyre::eyre!(
+        "metrics support is disabled (rebuild with `--features metrics`)"
+    ))
+}
+
+/// How long to wait after failing to accept a connection, before accepting
+/// another.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How long a client may take to send its request before the connection is
+/// closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most of a request that is read. Only the request line is needed, and
+/// requests to a metrics endpoint have no body, so anything longer is cut off
+/// rather than buffered.
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+
+/// Answers a single HTTP request on `stream`, and closes the connection.
+///
+/// This is only as much of HTTP as a Prometheus scraper (or `curl`) needs:
+/// the request's headers are ignored, and every response closes the
+/// connection.
+#[cfg(feature = "metrics")]
+async fn respond(
+    mut stream: tokio::net::TcpStream,
+    metrics: &tokio::sync::watch::Receiver<Metrics>,
+) -> std::io::Result<()> {
+    use std::io;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut request = Vec::new();
+    let read = async {
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < MAX_REQUEST_LEN {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        Ok::<_, io::Error>(())
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read)
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let request_line = request.split(|&b| b == b'\n').next().unwrap_or_default();
+    let mut words = std::str::from_utf8(request_line)
+        .unwrap_or_default()
+        .split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.borrow().to_string()),
+        (Some("GET"), Some(_)) => ("404 Not Found", "not found\n".to_owned()),
+        _ => ("405 Method Not Allowed", "method not allowed\n".to_owned()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,236 @@
+//! Recording the updates received from a target to a file, and replaying them
+//! later without a running target.
+//!
+//! A recording starts with [`MAGIC`], followed by each update's protobuf
+//! encoding, prefixed by its length. Updates carry the target's timestamp, so
+//! replaying them reproduces the same durations as the recorded session.
+use color_eyre::eyre::{eyre, WrapErr};
+use console_api::instrument::Update;
+use prost::Message;
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, SystemTime},
+};
+use tokio::time::Instant;
+
+/// The first bytes of every recording.
+const MAGIC: &[u8] = b"tokio-console recording v1\n";
+
+/// Writes updates to a recording on a separate thread, so that writing the
+/// file never blocks the console.
+///
+/// Unlike the JSON stream, updates are never dropped, since a replay with
+/// gaps in it would show incorrect stats.
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    updates: mpsc::Sender<Vec<u8>>,
+}
+
+/// Replays the updates in a recording, at the speed they were recorded or
+/// faster.
+#[derive(Debug)]
+pub(crate) struct Replay {
+    path: PathBuf,
+    data: Vec<u8>,
+    /// The offset in `data` of the next update to decode.
+    pos: usize,
+    speed: f64,
+    /// The next update, which has been decoded but not yet returned, and when
+    /// it should be returned.
+    next: Option<(Update, Instant)>,
+    /// The target's timestamp of the last update returned, and when it was
+    /// returned.
+    last: Option<(SystemTime, Instant)>,
+    /// When the replay was paused and, if an update was waiting to be
+    /// returned, how long it still had to wait.
+    paused: Option<Option<Duration>>,
+    /// Set when the end of the recording is reached, along with an error if
+    /// the rest of it couldn't be read.
+    finished: Option<Option<String>>,
+}
+
+// === impl Recorder ===
+
+impl Recorder {
+    /// Creates (or truncates) the recording at `path`, and starts the thread
+    /// which writes updates to it.
+    pub(crate) fn create(path: &Path) -> color_eyre::Result<Self> {
+        let mut file = File::create(path)
+            .map(BufWriter::new)
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        file.write_all(MAGIC)
+            .and_then(|()| file.flush())
+            .with_context(|| format!("failed to write to recording {}", path.display()))?;
+        let (updates, rx) = mpsc::channel::<Vec<u8>>();
+        thread::Builder::new()
+            .name("recorder".to_owned())
+            .spawn(move || {
+                for update in rx {
+                    if let Err(error) = file.write_all(&update).and_then(|()| file.flush()) {
+                        tracing::warn!(%error, "failed to write to the recording");
+                        return;
+                    }
+                }
+            })
+            .context("failed to spawn the recorder thread")?;
+        Ok(Self { updates })
+    }
+
+    pub(crate) fn record(&self, update: &Update) {
+        // If the writer thread has failed, it has already logged why.
+        let _ = self.updates.send(update.encode_length_delimited_to_vec());
+    }
+}
+
+// === impl Replay ===
+
+impl Replay {
+    /// Opens the recording at `path`, to be replayed `speed` times faster than
+    /// it was recorded.
+    pub(crate) fn open(path: &Path, speed: f64) -> color_eyre::Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("failed to read recording {}", path.display()))?;
+        if !data.starts_with(MAGIC) {
+            return Err(eyre!("{} is not a tokio-console recording", path.display()));
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            data,
+            pos: MAGIC.len(),
+            speed,
+            next: None,
+            last: None,
+            paused: None,
+            finished: None,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Returns `None` if the replay hasn't finished, or whether an error
+    /// ended it early.
+    pub(crate) fn finished(&self) -> Option<Option<&str>> {
+        self.finished.as_ref().map(Option::as_deref)
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Returns the next update, once as much time has passed since the last
+    /// update as had passed when they were recorded (divided by the replay's
+    /// speed), or `None` once the recording has ended.
+    ///
+    /// This is cancel-safe: if the returned future is dropped while waiting,
+    /// the update is returned by the next call instead.
+    pub(crate) async fn next_update(&mut self) -> Option<Update> {
+        if self.paused.is_some() || self.finished.is_some() {
+            return None;
+        }
+
+        if self.next.is_none() {
+            let update = match self.decode_next() {
+                Ok(Some(update)) => update,
+                Ok(None) => {
+                    self.finished = Some(None);
+                    return None;
+                }
+                Err(error) => {
+                    tracing::warn!(%error, path = %self.path.display(), "failed to read recording");
+                    self.finished = Some(Some(error));
+                    return None;
+                }
+            };
+            let deadline = self.deadline(&update);
+            self.next = Some((update, deadline));
+        }
+
+        let deadline = self.next.as_ref().map(|&(_, deadline)| deadline)?;
+        tokio::time::sleep_until(deadline).await;
+        let (update, _) = self.next.take()?;
+        let now = update
+            .now
+            .clone()
+            .and_then(|now| SystemTime::try_from(now).ok());
+        self.last = now.map(|now| (now, Instant::now()));
+        Some(update)
+    }
+
+    /// Stops returning updates until [`Replay::resume`] is called.
+    pub(crate) fn pause(&mut self) {
+        if self.paused.is_none() {
+            let now = Instant::now();
+            let remaining = self
+                .next
+                .as_ref()
+                .map(|&(_, deadline)| deadline.saturating_duration_since(now));
+            self.paused = Some(remaining);
+        }
+    }
+
+    pub(crate) fn resume(&mut self) {
+        if let Some(remaining) = self.paused.take() {
+            let now = Instant::now();
+            if let (Some((_, deadline)), Some(remaining)) = (self.next.as_mut(), remaining) {
+                *deadline = now + remaining;
+            }
+            // Measure the time until later updates from now, rather than
+            // from before the replay was paused.
+            if let Some((_, ref mut returned_at)) = self.last {
+                *returned_at = now;
+            }
+        }
+    }
+
+    /// Decodes the next update in the recording, returning `None` at the end
+    /// of the recording, or a description of the problem if the rest of the
+    /// recording is truncated or corrupt.
+    fn decode_next(&mut self) -> Result<Option<Update>, String> {
+        let mut rest = &self.data[self.pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let len = prost::decode_length_delimiter(&mut rest)
+            .map_err(|error| format!("corrupt update length: {}", error))?;
+        if rest.len() < len {
+            return Err(format!(
+                "truncated update: expected {} bytes, but only {} remain",
+                len,
+                rest.len()
+            ));
+        }
+        let update =
+            Update::decode(&rest[..len]).map_err(|error| format!("corrupt update: {}", error))?;
+        self.pos = self.data.len() - rest.len() + len;
+        Ok(Some(update))
+    }
+
+    /// Returns when `update` should be returned: as long after the last update
+    /// was returned as the time between their timestamps, divided by the
+    /// replay's speed.
+    fn deadline(&self, update: &Update) -> Instant {
+        let now = Instant::now();
+        let (last_now, returned_at) = match self.last {
+            Some(last) => last,
+            None => return now,
+        };
+        let elapsed = update
+            .now
+            .clone()
+            .and_then(|now| SystemTime::try_from(now).ok())
+            .and_then(|now| now.duration_since(last_now).ok())
+            .unwrap_or_default();
+        returned_at + Duration::from_secs_f64(elapsed.as_secs_f64() / self.speed)
+    }
+}
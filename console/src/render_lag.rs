@@ -0,0 +1,55 @@
+//! Detecting when the console's own rendering can't keep up with how often
+//! the screen is redrawn.
+//!
+//! The main loop prefers redrawing over applying updates from the target, so
+//! once drawing a frame takes longer than the refresh interval, updates queue
+//! up and the displayed data falls behind the target. This is unrelated to
+//! the target's dropped events, which are lost before they are sent.
+use std::time::Duration;
+
+/// Tracks a moving average of how long frames take to draw.
+#[derive(Debug, Default)]
+pub(crate) struct RenderLag {
+    /// The exponentially weighted moving average of frame durations, or
+    /// `None` if no frames have been drawn yet.
+    average: Option<Duration>,
+    is_behind: bool,
+}
+
+impl RenderLag {
+    /// How much each new frame's duration contributes to the average, so that
+    /// a single slow frame doesn't count as falling behind.
+    const WEIGHT: f64 = 0.2;
+
+    /// Rendering is falling behind once the average frame takes longer than
+    /// this fraction of the refresh interval...
+    const BEHIND_AT: f64 = 1.0;
+
+    /// ...and catches up again once it takes less than this fraction, so
+    /// that the banner doesn't flicker while frames take about as long as the
+    /// refresh interval.
+    const CAUGHT_UP_AT: f64 = 0.5;
+
+    /// Records that a frame took `took` to draw, when frames are drawn every
+    /// `refresh_interval`.
+    pub(crate) fn record(&mut self, took: Duration, refresh_interval: Duration) {
+        let average = match self.average {
+            Some(average) => average.mul_f64(1.0 - Self::WEIGHT) + took.mul_f64(Self::WEIGHT),
+            None => took,
+        };
+        self.average = Some(average);
+
+        let ratio = average.as_secs_f64() / refresh_interval.as_secs_f64();
+        if ratio > Self::BEHIND_AT {
+            self.is_behind = true;
+        } else if ratio < Self::CAUGHT_UP_AT {
+            self.is_behind = false;
+        }
+    }
+
+    /// Returns the average frame duration if rendering is falling behind, or
+    /// `None` if it is keeping up.
+    pub(crate) fn behind_by(&self) -> Option<Duration> {
+        self.average.filter(|_| self.is_behind)
+    }
+}
@@ -0,0 +1,1133 @@
+use crate::intern::{self, InternedStr};
+use crate::state::{
+    parse_column,
+    rate::{RateHistory, RateWindow},
+    resources::{Resource, ResourcesState},
+    tasks::TasksState,
+    Metadata, UnknownColumn, Visibility,
+};
+use crate::warnings::Linter;
+use console_api as proto;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    rc::{Rc, Weak},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+#[derive(Default, Debug)]
+pub(crate) struct AsyncOpsState {
+    async_ops: HashMap<u64, Rc<RefCell<AsyncOp>>>,
+    new_async_ops: Vec<AsyncOpRef>,
+    pub(crate) linters: Vec<Linter<AsyncOp>>,
+    /// The total number of dropped async ops removed by `retain_active`.
+    pruned: usize,
+    /// The polls of the async ops on each resource, as of the last time they
+    /// were sampled, keyed by resource ID, or `None` for async ops whose
+    /// resource is unknown.
+    resource_polls: HashMap<Option<u64>, ResourcePolls>,
+    /// How many times the async ops on each resource were polled as of each
+    /// sample within the rate window, keyed like `resource_polls`.
+    poll_histories: HashMap<Option<u64>, RateHistory>,
+    /// How far back poll rates are measured over.
+    rate_window: RateWindow,
+}
+
+/// The polls of all of the retained async ops on one resource.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct ResourcePolls {
+    /// The number of retained async ops on the resource.
+    pub(crate) async_ops: usize,
+    /// The total number of times those async ops have been polled.
+    pub(crate) polls: u64,
+    /// The number of times per second those async ops were polled over the
+    /// rate window, or `None` if they have been sampled fewer than two times.
+    pub(crate) rate: Option<f64>,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(usize)]
+pub(crate) enum SortBy {
+    Warns = 0,
+    #[default]
+    Aid = 1,
+    Source = 2,
+    Rid = 3,
+    Tid = 4,
+    Total = 5,
+    Busy = 6,
+    Idle = 7,
+    Polls = 8,
+    SinceLastPoll = 9,
+    IdleSince = 10,
+    Target = 11,
+}
+
+#[derive(Debug)]
+pub(crate) struct AsyncOp {
+    id: u64,
+    source: InternedStr,
+    target: InternedStr,
+    stats: AsyncOpStats,
+    /// Currently active warnings for this async op.
+    warnings: Vec<Linter<AsyncOp>>,
+    /// The async op's total poll count as of the last time the polls of each
+    /// resource were sampled, or `None` if it has not been sampled yet.
+    last_sampled_polls: Option<u64>,
+    /// The median poll count of the other async ops on the same resource, as
+    /// of the last time async ops were linted, or `None` if there are no
+    /// others, or the resource isn't known.
+    typical_sibling_polls: Option<u64>,
+    /// How long the async op's poll count has stayed the same while its task
+    /// is alive, as of the last time async ops were linted, or `None` if its
+    /// task isn't known, or it has been dropped.
+    stagnation: Option<Stagnation>,
+}
+
+/// A stretch of time over which an async op hasn't been polled, along with
+/// how many times its task was polled in the meantime.
+#[derive(Debug, Clone)]
+pub(crate) struct Stagnation {
+    /// The async op's poll count, which hasn't changed since `since`.
+    polls: u64,
+    since: SystemTime,
+    /// The ID of the task awaiting the async op.
+    task_id: u64,
+    /// The name of the task awaiting the async op, if it has one.
+    task_name: Option<String>,
+    /// The task's poll count at `since`.
+    task_polls_at_start: u64,
+    /// The number of times the task has been polled since `since`.
+    task_polls: u64,
+}
+
+pub(crate) type AsyncOpRef = Weak<RefCell<AsyncOp>>;
+
+/// A point-in-time copy of an async op's stats, which can be serialized and
+/// read back.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AsyncOpSnapshot {
+    pub(crate) id: u64,
+    pub(crate) source: String,
+    pub(crate) resource_id: Option<u64>,
+    pub(crate) task_id: Option<u64>,
+    pub(crate) polls: u64,
+    pub(crate) total: Duration,
+}
+
+#[derive(Debug)]
+struct AsyncOpStats {
+    created_at: SystemTime,
+    dropped_at: Option<SystemTime>,
+    total: Option<Duration>,
+    busy: Duration,
+    last_poll_started: Option<SystemTime>,
+    last_poll_ended: Option<SystemTime>,
+    polls: u64,
+    /// The ID of the resource this op is associated with.
+    ///
+    /// This is `None` until the op has been polled.
+    resource_id: Option<u64>,
+    /// The ID of the task awaiting this op.
+    ///
+    /// This is `None` until the op has been polled.
+    task_id: Option<u64>,
+}
+
+impl SortBy {
+    pub fn sort(&self, now: SystemTime, async_ops: &mut [Weak<RefCell<AsyncOp>>]) {
+        match self {
+            Self::Warns => sort_by_key(async_ops, |op| op.warnings().len()),
+            Self::Aid => sort_by_key(async_ops, |_| ()),
+            // Interned strings are ordered by their contents, so this sorts
+            // sources alphabetically.
+            Self::Source => sort_by_key(async_ops, |op| op.source.clone()),
+            Self::Rid => sort_by_key(async_ops, |op| op.resource_id()),
+            Self::Tid => sort_by_key(async_ops, |op| op.task_id()),
+            Self::Total => sort_by_key(async_ops, |op| op.total(now)),
+            Self::Busy => sort_by_key(async_ops, |op| op.busy(now)),
+            Self::Idle => sort_by_key(async_ops, |op| op.idle(now)),
+            Self::Polls => sort_by_key(async_ops, |op| op.polls()),
+            Self::SinceLastPoll => sort_by_key(async_ops, |op| op.since_last_poll(now)),
+            Self::IdleSince => sort_by_key(async_ops, |op| op.idle_since(now)),
+            Self::Target => sort_by_key(async_ops, |op| op.target.clone()),
+        }
+    }
+}
+
+/// Sorts `async_ops` by the key returned by `f`, breaking ties by async op ID
+/// so that ops with equal keys don't change places between updates.
+fn sort_by_key<K: Ord>(async_ops: &mut [Weak<RefCell<AsyncOp>>], f: impl Fn(&AsyncOp) -> K) {
+    async_ops.sort_unstable_by_key(|op| {
+        op.upgrade().map(|op| {
+            let op = op.borrow();
+            (f(&op), op.id)
+        })
+    })
+}
+
+impl TryFrom<usize> for SortBy {
+    type Error = ();
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        match idx {
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
+            idx if idx == Self::Aid as usize => Ok(Self::Aid),
+            idx if idx == Self::Source as usize => Ok(Self::Source),
+            idx if idx == Self::Rid as usize => Ok(Self::Rid),
+            idx if idx == Self::Tid as usize => Ok(Self::Tid),
+            idx if idx == Self::Total as usize => Ok(Self::Total),
+            idx if idx == Self::Busy as usize => Ok(Self::Busy),
+            idx if idx == Self::Idle as usize => Ok(Self::Idle),
+            idx if idx == Self::Polls as usize => Ok(Self::Polls),
+            idx if idx == Self::SinceLastPoll as usize => Ok(Self::SinceLastPoll),
+            idx if idx == Self::IdleSince as usize => Ok(Self::IdleSince),
+            idx if idx == Self::Target as usize => Ok(Self::Target),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SortBy {
+    /// The name of each column, ignoring case when parsed.
+    pub(super) const NAMES: &'static [(SortBy, &'static str)] = &[
+        (Self::Warns, "warns"),
+        (Self::Aid, "aid"),
+        (Self::Source, "source"),
+        (Self::Rid, "rid"),
+        (Self::Tid, "tid"),
+        (Self::Total, "total"),
+        (Self::Busy, "busy"),
+        (Self::Idle, "idle"),
+        (Self::Polls, "polls"),
+        (Self::SinceLastPoll, "since_last_poll"),
+        (Self::IdleSince, "idle_since"),
+        (Self::Target, "target"),
+    ];
+}
+
+impl FromStr for SortBy {
+    type Err = UnknownColumn;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_column(Self::NAMES, s)
+    }
+}
+
+impl crate::view::SortBy for SortBy {
+    fn as_column(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl AsyncOpsState {
+    /// Forgets every async op, keeping the linters.
+    pub(crate) fn reset(&mut self) {
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+            linter.reset();
+        }
+        *self = Self {
+            linters: std::mem::take(&mut self.linters),
+            rate_window: self.rate_window,
+            ..Self::default()
+        };
+    }
+
+    /// Returns any new async ops that were added since the last async op
+    /// update.
+    pub(crate) fn take_new_async_ops(&mut self) -> impl Iterator<Item = AsyncOpRef> + '_ {
+        self.new_async_ops.drain(..)
+    }
+
+    /// Returns the async ops awaited by the task with the given ID.
+    pub(crate) fn task_async_ops(
+        &self,
+        task_id: u64,
+    ) -> impl Iterator<Item = &Rc<RefCell<AsyncOp>>> + '_ {
+        self.async_ops
+            .values()
+            .filter(move |async_op| async_op.borrow().task_id() == Some(task_id))
+    }
+
+    /// Returns the async ops performed on `resource`.
+    ///
+    /// Async ops which were created before `resource` are left out: the
+    /// target may reuse a resource's ID once it's dropped, so those were
+    /// performed on an earlier resource with the same ID, which has since
+    /// been pruned.
+    pub(crate) fn resource_async_ops<'a>(
+        &'a self,
+        resource: &'a Resource,
+    ) -> impl Iterator<Item = &'a Rc<RefCell<AsyncOp>>> + 'a {
+        self.async_ops.values().filter(move |async_op| {
+            let async_op = async_op.borrow();
+            async_op.resource_id() == Some(resource.id())
+                && async_op.created_at() >= resource.created_at()
+        })
+    }
+
+    /// Returns `true` if the async op with the given ID has not been pruned.
+    pub(crate) fn contains(&self, id: u64) -> bool {
+        self.async_ops.contains_key(&id)
+    }
+
+    /// Returns the number of async ops being tracked, including dropped async
+    /// ops which are still being retained.
+    pub(crate) fn len(&self) -> usize {
+        self.async_ops.len()
+    }
+
+    /// Returns the number of async ops which have not yet been dropped.
+    pub(crate) fn active_count(&self) -> usize {
+        self.async_ops
+            .values()
+            .filter(|async_op| !async_op.borrow().dropped())
+            .count()
+    }
+
+    /// Returns the number of async ops which have been dropped, but are still
+    /// being retained.
+    pub(crate) fn dropped_count(&self) -> usize {
+        self.async_ops
+            .values()
+            .filter(|async_op| async_op.borrow().dropped())
+            .count()
+    }
+
+    /// Returns a snapshot of every async op's stats as of `now`, ordered by
+    /// async op ID.
+    pub(crate) fn snapshot(&self, now: SystemTime) -> Vec<AsyncOpSnapshot> {
+        let mut async_ops = self
+            .async_ops
+            .values()
+            .map(|async_op| async_op.borrow().snapshot(now))
+            .collect::<Vec<_>>();
+        async_ops.sort_unstable_by_key(|async_op| async_op.id);
+        async_ops
+    }
+
+    /// Returns a snapshot of the stats of the async ops with the given IDs as
+    /// of `now`, in the order of `ids`.
+    ///
+    /// Async ops which no longer exist are skipped.
+    pub(crate) fn snapshot_of(&self, ids: &[u64], now: SystemTime) -> Vec<AsyncOpSnapshot> {
+        ids.iter()
+            .filter_map(|id| self.async_ops.get(id))
+            .map(|async_op| async_op.borrow().snapshot(now))
+            .collect()
+    }
+
+    /// Returns the number of async ops which have not yet been dropped on
+    /// each resource, keyed by resource ID.
+    pub(crate) fn pending_by_resource(&self) -> HashMap<u64, usize> {
+        let mut pending = HashMap::new();
+        for async_op in self.async_ops.values() {
+            let async_op = async_op.borrow();
+            if let (Some(resource_id), false) = (async_op.resource_id(), async_op.dropped()) {
+                *pending.entry(resource_id).or_insert(0) += 1;
+            }
+        }
+        pending
+    }
+
+    /// Returns the polls of the async ops on each resource, as of the last
+    /// update, keyed by resource ID.
+    ///
+    /// Async ops whose resource isn't known, because it was never seen or has
+    /// already been pruned, are counted together under `None`.
+    pub(crate) fn polls_by_resource(&self) -> &HashMap<Option<u64>, ResourcePolls> {
+        &self.resource_polls
+    }
+
+    /// Sets how far back poll rates are measured over, and measures each
+    /// resource's poll rate over it.
+    pub(crate) fn set_rate_window(&mut self, window: RateWindow) {
+        self.rate_window = window;
+        for (resource_id, polls) in &mut self.resource_polls {
+            polls.rate = self
+                .poll_histories
+                .get(resource_id)
+                .and_then(|history| history.rate(window));
+        }
+    }
+
+    /// Totals the polls of the async ops on each resource, and measures how
+    /// often they were polled over the rate window.
+    ///
+    /// This should be called on every update, after both resources and async
+    /// ops have been updated. As with tasks' poll counts, the first sample of
+    /// an async op only records its poll count, since it may have been polled
+    /// any number of times before the console connected. Async ops pruned
+    /// since the last sample don't count towards the rate.
+    pub(crate) fn sample_resource_polls(&mut self, resources: &ResourcesState, now: SystemTime) {
+        let mut resource_polls = HashMap::<_, (ResourcePolls, u64)>::new();
+        for async_op in self.async_ops.values() {
+            let mut async_op = async_op.borrow_mut();
+            let polls = async_op.polls();
+            let new_polls = async_op
+                .last_sampled_polls
+                .replace(polls)
+                .map(|last| polls.saturating_sub(last))
+                .unwrap_or(0);
+            let resource_id = async_op
+                .resource_id()
+                .filter(|_| async_op.resource_known(resources));
+            let (totals, new) = resource_polls.entry(resource_id).or_default();
+            totals.async_ops += 1;
+            totals.polls += polls;
+            *new += new_polls;
+        }
+
+        // Resources without any retained async ops are no longer listed, so
+        // their histories are forgotten.
+        self.poll_histories
+            .retain(|resource_id, _| resource_polls.contains_key(resource_id));
+        let (histories, window) = (&mut self.poll_histories, self.rate_window);
+        self.resource_polls = resource_polls
+            .into_iter()
+            .map(|(resource_id, (totals, new_polls))| {
+                let history = histories.entry(resource_id).or_default();
+                history.record(new_polls, now, window);
+                let rate = history.rate(window);
+                (resource_id, ResourcePolls { rate, ..totals })
+            })
+            .collect();
+    }
+
+    pub(crate) fn update_async_ops(
+        &mut self,
+        strings: &mut intern::Strings,
+        metas: &HashMap<u64, Metadata>,
+        update: proto::async_ops::AsyncOpUpdate,
+        visibility: Visibility,
+    ) {
+        // These spans are at the debug level, and their fields are only
+        // computed when they're enabled, so that they're cheap unless the
+        // console's own diagnostics are enabled.
+        let _span = tracing::debug_span!(
+            "update_async_ops",
+            new = update.new_async_ops.len(),
+            updated = update.stats_update.len(),
+            dropped = update
+                .stats_update
+                .values()
+                .filter(|stats| stats.dropped_at.is_some())
+                .count(),
+        )
+        .entered();
+
+        let mut stats_update = update.stats_update;
+        // New async ops often share their sources, so intern them as a batch.
+        let mut strings = strings.batch();
+        let new_list = &mut self.new_async_ops;
+        // The table drains the new list each time it is drawn, but the screen
+        // may be redrawn less often than updates arrive, so only drop entries
+        // that no longer exist, rather than ones the table hasn't seen yet.
+        if matches!(visibility, Visibility::Show) {
+            new_list.retain(|async_op| async_op.upgrade().is_some());
+        }
+
+        let new_async_ops = update.new_async_ops.into_iter().filter_map(|async_op| {
+            if async_op.id.is_none() {
+                tracing::warn!(?async_op, "skipping async op with no id");
+            }
+
+            let meta_id = match async_op.metadata.as_ref() {
+                Some(id) => id.id,
+                None => {
+                    tracing::warn!(?async_op, "async op has no metadata ID, skipping");
+                    return None;
+                }
+            };
+            let meta = match metas.get(&meta_id) {
+                Some(meta) => meta,
+                None => {
+                    tracing::warn!(?async_op, meta_id, "no metadata for async op, skipping");
+                    return None;
+                }
+            };
+
+            let id = async_op.id?.id;
+            let stats = AsyncOpStats::from_proto(id, stats_update.remove(&id)?)?;
+
+            let async_op = AsyncOp {
+                id,
+                source: strings.string(async_op.source),
+                target: meta.target.clone(),
+                stats,
+                warnings: Vec::new(),
+                last_sampled_polls: None,
+                typical_sibling_polls: None,
+                stagnation: None,
+            };
+            let async_op = Rc::new(RefCell::new(async_op));
+            new_list.push(Rc::downgrade(&async_op));
+            Some((id, async_op))
+        });
+        {
+            let _span = tracing::debug_span!("new_async_ops").entered();
+            self.async_ops.extend(new_async_ops);
+        }
+
+        let _span = tracing::debug_span!("parse_stats", count = stats_update.len()).entered();
+        for (id, stats) in stats_update {
+            if let Some(async_op) = self.async_ops.get_mut(&id) {
+                // If the new stats are invalid, keep the last valid stats.
+                if let Some(stats) = AsyncOpStats::from_proto(id, stats) {
+                    async_op.borrow_mut().stats = stats;
+                }
+            }
+        }
+    }
+
+    /// Lints every async op, after recording the typical poll count of the
+    /// other async ops on each op's resource, so that ops can be compared
+    /// with their siblings, and how long each op has gone unpolled while its
+    /// task was polled.
+    ///
+    /// An async op that is stuck never has its stats updated, so every op is
+    /// linted on each update, rather than only the ops whose stats changed.
+    /// This should be called on every update, after both resources and async
+    /// ops have been updated.
+    pub(crate) fn lint(&mut self, resources: &ResourcesState, tasks: &TasksState, now: SystemTime) {
+        let _span = tracing::debug_span!("lint", count = self.async_ops.len()).entered();
+
+        let mut polls_by_resource = HashMap::<u64, Vec<u64>>::new();
+        for async_op in self.async_ops.values() {
+            let async_op = async_op.borrow();
+            if let Some(resource_id) = async_op
+                .resource_id()
+                .filter(|_| async_op.resource_known(resources))
+            {
+                polls_by_resource
+                    .entry(resource_id)
+                    .or_default()
+                    .push(async_op.polls());
+            }
+        }
+        for polls in polls_by_resource.values_mut() {
+            polls.sort_unstable();
+        }
+
+        for async_op in self.async_ops.values() {
+            let mut async_op = async_op.borrow_mut();
+            async_op.typical_sibling_polls = async_op
+                .resource_id()
+                .and_then(|id| polls_by_resource.get(&id))
+                .and_then(|polls| median_without(polls, async_op.polls()));
+            async_op.update_stagnation(tasks, now);
+            async_op.lint(&self.linters, now);
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<AsyncOp>> {
+        self.linters.iter().filter(|linter| linter.is_active())
+    }
+
+    /// Replaces the linters async ops are checked with, and re-lints every
+    /// async op, so that the warnings they hold from the previous linters are
+    /// dropped.
+    pub(crate) fn set_linters(&mut self, linters: Vec<Linter<AsyncOp>>, now: SystemTime) {
+        self.linters = linters;
+        for async_op in self.async_ops.values() {
+            async_op.borrow_mut().lint(&self.linters, now);
+        }
+    }
+
+    /// Clears all acknowledged warnings, so that they are displayed again.
+    ///
+    /// Since every async op is linted on each update, the warnings reappear
+    /// with the next update.
+    pub(crate) fn clear_acknowledged(&mut self) {
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+        }
+    }
+
+    /// Acknowledges the warnings of each linter for the async ops listed at
+    /// that linter's index in `acks`, with the metrics listed with them.
+    ///
+    /// Like [`AsyncOpsState::clear_acknowledged`], this takes effect with the
+    /// next update.
+    pub(crate) fn restore_acknowledged(&mut self, acks: &[Vec<(u64, u64)>]) {
+        for (linter, acks) in self.linters.iter().zip(acks) {
+            for &(id, metric) in acks {
+                linter.acknowledge_with(id, metric);
+            }
+        }
+    }
+
+    /// Returns the total number of dropped async ops that have been removed
+    /// after being retained for the retention period.
+    pub(crate) fn pruned_count(&self) -> usize {
+        self.pruned
+    }
+
+    pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
+        let len0 = self.async_ops.len();
+        let linters = &self.linters;
+        self.async_ops.retain(|id, async_op| {
+            let async_op = async_op.borrow();
+
+            let retain = async_op
+                .stats
+                .dropped_at
+                .map(|d| {
+                    // If the target's clock has gone back to before `d`, it hasn't
+                    // been dropped for long enough to be pruned yet.
+                    now.duration_since(d)
+                        .map_or(true, |dropped_for| retain_for > dropped_for)
+                })
+                .unwrap_or(true);
+            if !retain {
+                for linter in linters {
+                    linter.forget(*id);
+                }
+            }
+            retain
+        });
+
+        let pruned = len0 - self.async_ops.len();
+        if pruned > 0 {
+            self.pruned += pruned;
+            tracing::debug!(
+                pruned,
+                async_ops.len = self.async_ops.len(),
+                async_ops.pruned_total = self.pruned,
+                "pruned dropped async ops",
+            );
+        }
+    }
+}
+
+impl AsyncOp {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub(crate) fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub(crate) fn resource_id(&self) -> Option<u64> {
+        self.stats.resource_id
+    }
+
+    /// Returns `true` if the resource the async op is performed on is
+    /// currently known.
+    ///
+    /// The console may never have seen the resource, or it may have been
+    /// pruned while the async op is still retained. If the async op's resource
+    /// isn't known yet, since it has not been polled, this returns `false`.
+    pub(crate) fn resource_known(&self, resources: &ResourcesState) -> bool {
+        self.resource_id()
+            .map(|id| resources.contains(id))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn task_id(&self) -> Option<u64> {
+        self.stats.task_id
+    }
+
+    pub(crate) fn created_at(&self) -> SystemTime {
+        self.stats.created_at
+    }
+
+    fn snapshot(&self, now: SystemTime) -> AsyncOpSnapshot {
+        AsyncOpSnapshot {
+            id: self.id,
+            source: self.source().to_owned(),
+            resource_id: self.resource_id(),
+            task_id: self.task_id(),
+            polls: self.polls(),
+            total: self.total(now),
+        }
+    }
+
+    /// Returns how long this async op existed for, or has existed for so far.
+    ///
+    /// If `since` is before the async op was created, such as when the
+    /// target's clock has gone backwards, this returns zero rather than
+    /// panicking.
+    pub(crate) fn total(&self, since: SystemTime) -> Duration {
+        self.stats.total.unwrap_or_else(|| {
+            since
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns `true` if this async op is currently being polled.
+    pub(crate) fn is_polling(&self) -> bool {
+        matches!(
+            (self.stats.last_poll_started, self.stats.last_poll_ended),
+            (Some(_), None)
+        )
+    }
+
+    pub(crate) fn busy(&self, since: SystemTime) -> Duration {
+        super::busy_including_current_poll(
+            self.stats.busy,
+            self.stats.last_poll_started,
+            self.stats.last_poll_ended,
+            since,
+        )
+    }
+
+    pub(crate) fn idle(&self, since: SystemTime) -> Duration {
+        self.total(since).saturating_sub(self.busy(since))
+    }
+
+    pub(crate) fn polls(&self) -> u64 {
+        self.stats.polls
+    }
+
+    /// Returns the median number of times the other async ops on this async
+    /// op's resource have been polled, or `None` if it's the only async op on
+    /// its resource, or its resource isn't known.
+    pub(crate) fn typical_sibling_polls(&self) -> Option<u64> {
+        self.typical_sibling_polls
+    }
+
+    /// Returns how long the async op's poll count has stayed the same while
+    /// its task is alive, along with how many times the task was polled in
+    /// the meantime, as of the last time async ops were linted.
+    ///
+    /// This is `None` if the async op has been dropped, or has no task, or
+    /// its task isn't known or has completed.
+    pub(crate) fn stagnation(&self) -> Option<&Stagnation> {
+        self.stagnation.as_ref()
+    }
+
+    /// Records whether the async op has been polled since the last update,
+    /// and how many times its task has been polled since the op was last
+    /// polled.
+    ///
+    /// Since the console only sees new poll counts, the op's stagnation starts
+    /// from the first update where its count was seen, rather than its last
+    /// poll.
+    fn update_stagnation(&mut self, tasks: &TasksState, now: SystemTime) {
+        let task = match self.task_id().and_then(|id| tasks.task(id)) {
+            Some(task) if !self.dropped() => task,
+            _ => {
+                self.stagnation = None;
+                return;
+            }
+        };
+        let task = task.borrow();
+        if task.is_completed() {
+            self.stagnation = None;
+            return;
+        }
+
+        let polls = self.polls();
+        let task_polls = task.total_polls();
+        match self.stagnation {
+            Some(ref mut stagnation)
+                if stagnation.polls == polls && stagnation.task_id == task.id() =>
+            {
+                stagnation.task_polls = task_polls.saturating_sub(stagnation.task_polls_at_start);
+            }
+            _ => {
+                self.stagnation = Some(Stagnation {
+                    polls,
+                    since: now,
+                    task_id: task.id(),
+                    task_name: task.name().map(String::from),
+                    task_polls_at_start: task_polls,
+                    task_polls: 0,
+                });
+            }
+        }
+    }
+
+    /// Returns how long it has been since this async op was last polled, or
+    /// `None` if it has never been polled.
+    ///
+    /// If the async op is currently being polled, this is the time since the
+    /// current poll started.
+    pub(crate) fn since_last_poll(&self, since: SystemTime) -> Option<Duration> {
+        let last_poll = match (self.stats.last_poll_started, self.stats.last_poll_ended) {
+            (Some(started), None) => started,
+            (_, Some(ended)) => ended,
+            (None, None) => return None,
+        };
+        Some(since.duration_since(last_poll).unwrap_or_default())
+    }
+
+    /// Returns how long the async op has been waiting since its last poll
+    /// ended, or `None` if it is currently being polled, or has never been
+    /// polled.
+    ///
+    /// Unlike [`AsyncOp::idle`], which is the total time the async op has
+    /// spent idle, this is only its current stretch of idleness. Once the
+    /// async op is dropped, this stops at the time it was dropped.
+    pub(crate) fn idle_since(&self, since: SystemTime) -> Option<Duration> {
+        let ended = match (self.stats.last_poll_started, self.stats.last_poll_ended) {
+            // It has never been polled.
+            (None, _) => return None,
+            // Its first poll is still in progress.
+            (Some(_), None) => return None,
+            // It was polled again after its last poll ended, and that poll is
+            // still in progress.
+            (Some(started), Some(ended)) if started > ended => return None,
+            (Some(_), Some(ended)) => ended,
+        };
+        let until = self.stats.dropped_at.unwrap_or(since);
+        Some(until.duration_since(ended).unwrap_or_default())
+    }
+
+    pub(crate) fn dropped(&self) -> bool {
+        self.stats.total.is_some()
+    }
+
+    pub(crate) fn warnings(&self) -> &[Linter<AsyncOp>] {
+        &self.warnings[..]
+    }
+
+    fn lint(&mut self, linters: &[Linter<AsyncOp>], now: SystemTime) {
+        self.warnings = Linter::lint_all(linters, self.id, self, now);
+    }
+}
+
+impl Stagnation {
+    /// Returns the async op's poll count, which hasn't changed.
+    pub(crate) fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Returns how long the async op's poll count has stayed the same.
+    pub(crate) fn duration(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.since).unwrap_or_default()
+    }
+
+    pub(crate) fn task_id(&self) -> u64 {
+        self.task_id
+    }
+
+    pub(crate) fn task_name(&self) -> Option<&str> {
+        self.task_name.as_deref()
+    }
+
+    /// Returns how many times the async op's task has been polled while the
+    /// async op wasn't.
+    pub(crate) fn task_polls(&self) -> u64 {
+        self.task_polls
+    }
+}
+
+impl AsyncOpStats {
+    /// Converts the protobuf representation of an async op's stats.
+    ///
+    /// Returns `None` if the async op's creation time is missing or invalid.
+    /// Other timestamps and durations which are invalid, such as negative
+    /// durations, are treated as though they were not sent.
+    fn from_proto(id: u64, pb: proto::async_ops::Stats) -> Option<Self> {
+        fn pb_duration(
+            id: u64,
+            field: &'static str,
+            duration: Option<prost_types::Duration>,
+        ) -> Option<Duration> {
+            let duration = duration?;
+            match (
+                u64::try_from(duration.seconds),
+                u64::try_from(duration.nanos),
+            ) {
+                (Ok(secs), Ok(nanos)) => {
+                    Some(Duration::from_secs(secs) + Duration::from_nanos(nanos))
+                }
+                _ => {
+                    tracing::warn!(id, field, ?duration, "negative async op duration");
+                    None
+                }
+            }
+        }
+
+        fn pb_timestamp(
+            id: u64,
+            field: &'static str,
+            timestamp: Option<prost_types::Timestamp>,
+        ) -> Option<SystemTime> {
+            let timestamp = timestamp?;
+            match timestamp.clone().try_into() {
+                Ok(time) => Some(time),
+                Err(error) => {
+                    tracing::warn!(id, field, ?timestamp, %error, "invalid async op timestamp");
+                    None
+                }
+            }
+        }
+
+        let created_at = match pb.created_at {
+            Some(created_at) => pb_timestamp(id, "created_at", Some(created_at))?,
+            None => {
+                tracing::warn!(id, "async op span was never created");
+                return None;
+            }
+        };
+        let dropped_at = pb_timestamp(id, "dropped_at", pb.dropped_at);
+        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap_or_default());
+
+        let poll_stats = pb.poll_stats.unwrap_or_default();
+        Some(Self {
+            created_at,
+            dropped_at,
+            total,
+            busy: pb_duration(id, "busy_time", poll_stats.busy_time).unwrap_or_default(),
+            last_poll_started: pb_timestamp(id, "last_poll_started", poll_stats.last_poll_started),
+            last_poll_ended: pb_timestamp(id, "last_poll_ended", poll_stats.last_poll_ended),
+            polls: poll_stats.polls,
+            resource_id: pb.resource_id.map(|id| id.id),
+            task_id: pb.task_id.map(|id| id.id),
+        })
+    }
+}
+
+/// Builds an [`AsyncOp`] with explicit timestamps and poll state, so that its
+/// durations can be tested without a proto round trip.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct AsyncOpBuilder {
+    id: u64,
+    stats: AsyncOpStats,
+}
+
+#[cfg(test)]
+impl AsyncOpBuilder {
+    /// Returns a builder for an async op which was created at `created_at`
+    /// and has never been polled.
+    pub(crate) fn new(id: u64, created_at: SystemTime) -> Self {
+        Self {
+            id,
+            stats: AsyncOpStats {
+                created_at,
+                dropped_at: None,
+                total: None,
+                busy: Duration::ZERO,
+                last_poll_started: None,
+                last_poll_ended: None,
+                polls: 0,
+                resource_id: None,
+                task_id: None,
+            },
+        }
+    }
+
+    pub(crate) fn dropped_at(mut self, dropped_at: SystemTime) -> Self {
+        self.stats.dropped_at = Some(dropped_at);
+        self.stats.total = Some(
+            dropped_at
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default(),
+        );
+        self
+    }
+
+    /// Sets the number of completed polls, and the time spent in them.
+    pub(crate) fn polled(mut self, polls: u64, busy: Duration) -> Self {
+        self.stats.polls = polls;
+        self.stats.busy = busy;
+        self
+    }
+
+    /// Sets when the last poll started, and when it ended, or `None` if it is
+    /// still in progress.
+    pub(crate) fn last_poll(mut self, started: SystemTime, ended: Option<SystemTime>) -> Self {
+        self.stats.last_poll_started = Some(started);
+        self.stats.last_poll_ended = ended;
+        self
+    }
+
+    pub(crate) fn build(self, strings: &mut intern::Strings) -> AsyncOp {
+        AsyncOp {
+            id: self.id,
+            source: strings.string("test::op".to_owned()),
+            target: strings.string("test".to_owned()),
+            stats: self.stats,
+            warnings: Vec::new(),
+            last_sampled_polls: None,
+            typical_sibling_polls: None,
+            stagnation: None,
+        }
+    }
+}
+
+/// Returns the median of the `sorted` poll counts, once the count `polls`
+/// (which must be one of them) is left out, or `None` if there are no others.
+///
+/// If an even number of counts is left, the lower of the middle two is
+/// returned.
+fn median_without(sorted: &[u64], polls: u64) -> Option<u64> {
+    let left_out = sorted.binary_search(&polls).ok()?;
+    let others = sorted.len().checked_sub(1).filter(|&n| n > 0)?;
+    let middle = (others - 1) / 2;
+    // Skip over the left out count.
+    let idx = if middle < left_out {
+        middle
+    } else {
+        middle + 1
+    };
+    sorted.get(idx).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_util::at;
+
+    fn build(builder: AsyncOpBuilder) -> AsyncOp {
+        builder.build(&mut intern::Strings::default())
+    }
+
+    #[test]
+    fn durations_are_zero_before_the_op_was_created() {
+        let op = build(AsyncOpBuilder::new(1, at(5_000)));
+        let now = at(2_000);
+        assert_eq!(op.total(now), Duration::ZERO);
+        assert_eq!(op.busy(now), Duration::ZERO);
+        assert_eq!(op.idle(now), Duration::ZERO);
+    }
+
+    #[test]
+    fn total_is_zero_if_dropped_before_created() {
+        let op = build(AsyncOpBuilder::new(1, at(5_000)).dropped_at(at(3_000)));
+        assert_eq!(op.total(at(10_000)), Duration::ZERO);
+        assert_eq!(op.idle(at(10_000)), Duration::ZERO);
+    }
+
+    #[test]
+    fn busy_includes_the_poll_in_progress() {
+        let op = build(
+            AsyncOpBuilder::new(1, at(0))
+                .polled(2, Duration::from_secs(1))
+                .last_poll(at(2_000), None),
+        );
+        assert!(op.is_polling());
+        let now = at(5_000);
+        assert_eq!(op.busy(now), Duration::from_secs(4));
+        assert_eq!(op.idle(now), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn busy_skips_a_poll_which_started_after_now() {
+        let op = build(
+            AsyncOpBuilder::new(1, at(0))
+                .polled(2, Duration::from_secs(1))
+                .last_poll(at(8_000), None),
+        );
+        assert_eq!(op.busy(at(5_000)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn busy_excludes_the_time_since_the_last_poll_ended() {
+        let op = build(
+            AsyncOpBuilder::new(1, at(0))
+                .polled(3, Duration::from_secs(2))
+                .last_poll(at(1_000), Some(at(2_000))),
+        );
+        assert!(!op.is_polling());
+        assert_eq!(op.busy(at(5_000)), Duration::from_secs(2));
+        assert_eq!(op.idle(at(5_000)), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn idle_is_zero_if_busy_exceeds_total() {
+        // A skewed clock can make the op look busier than it is old.
+        let op = build(AsyncOpBuilder::new(1, at(0)).polled(5, Duration::from_secs(10)));
+        assert_eq!(op.total(at(5_000)), Duration::from_secs(5));
+        assert_eq!(op.idle(at(5_000)), Duration::ZERO);
+    }
+
+    /// Timestamps at the edges of what the target can send, which can't be
+    /// represented as a `SystemTime` on some platforms.
+    fn extreme_timestamps() -> Vec<prost_types::Timestamp> {
+        vec![
+            prost_types::Timestamp {
+                seconds: i64::MAX,
+                nanos: 999_999_999,
+            },
+            prost_types::Timestamp {
+                seconds: i64::MIN + 1,
+                nanos: 0,
+            },
+            prost_types::Timestamp {
+                seconds: 0,
+                nanos: i32::MIN,
+            },
+        ]
+    }
+
+    #[test]
+    fn ops_without_a_valid_creation_time_are_skipped() {
+        let stats = proto::async_ops::Stats::default();
+        assert!(AsyncOpStats::from_proto(1, stats).is_none());
+
+        for timestamp in extreme_timestamps() {
+            let valid = SystemTime::try_from(timestamp.clone()).is_ok();
+            let stats = proto::async_ops::Stats {
+                created_at: Some(timestamp),
+                ..Default::default()
+            };
+            assert_eq!(AsyncOpStats::from_proto(1, stats).is_some(), valid);
+        }
+    }
+
+    #[test]
+    fn invalid_optional_timestamps_are_ignored() {
+        for timestamp in extreme_timestamps() {
+            let expected = SystemTime::try_from(timestamp.clone()).ok();
+            let stats = proto::async_ops::Stats {
+                created_at: Some(at(1_000).into()),
+                dropped_at: Some(timestamp.clone()),
+                poll_stats: Some(proto::PollStats {
+                    polls: 1,
+                    last_poll_started: Some(timestamp.clone()),
+                    last_poll_ended: Some(timestamp),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let stats = AsyncOpStats::from_proto(1, stats).expect("the op should be kept");
+            assert_eq!(stats.created_at, at(1_000));
+            assert_eq!(stats.dropped_at, expected);
+            assert_eq!(stats.last_poll_started, expected);
+            assert_eq!(stats.last_poll_ended, expected);
+            assert_eq!(stats.polls, 1);
+        }
+    }
+
+    #[test]
+    fn negative_durations_are_ignored() {
+        let negative = [
+            prost_types::Duration {
+                seconds: -1,
+                nanos: 0,
+            },
+            prost_types::Duration {
+                seconds: 0,
+                nanos: -1,
+            },
+        ];
+        for duration in negative {
+            let stats = proto::async_ops::Stats {
+                created_at: Some(at(1_000).into()),
+                poll_stats: Some(proto::PollStats {
+                    polls: 1,
+                    busy_time: Some(duration),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let stats = AsyncOpStats::from_proto(1, stats).expect("the op should be kept");
+            assert_eq!(stats.busy, Duration::ZERO);
+            assert_eq!(stats.polls, 1);
+        }
+    }
+}
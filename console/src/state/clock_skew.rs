@@ -0,0 +1,135 @@
+//! Detecting timestamps from the target which are ahead of the target's own
+//! `now`.
+//!
+//! Durations are measured from the target's timestamps to the `now` of the
+//! update they arrived in, and any which would be negative are treated as
+//! zero. This is harmless when a timestamp is a little ahead, since the
+//! target records stats while it is building an update, but if the target's
+//! clock jumps backwards, every earlier timestamp is far ahead of `now`, and
+//! every duration measured from one silently reads as zero.
+use console_api as proto;
+use std::{
+    cmp,
+    convert::TryFrom,
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct ClockSkew {
+    /// The furthest ahead of `now` that a timestamp has recently been, if it
+    /// was more than [`ClockSkew::THRESHOLD`] ahead.
+    estimate: Option<Duration>,
+    /// The number of updates in a row without a timestamp that was more than
+    /// [`ClockSkew::THRESHOLD`] ahead.
+    updates_since_skewed: usize,
+    /// Whether `now` is moved forward by the estimate when durations are
+    /// measured.
+    correct: bool,
+}
+
+impl ClockSkew {
+    /// How far ahead of `now` a timestamp may be without counting as skewed.
+    const THRESHOLD: Duration = Duration::from_millis(500);
+
+    /// How many updates in a row without a skewed timestamp it takes for the
+    /// estimate to be forgotten.
+    ///
+    /// Only the stats which changed are sent in each update, so a task with a
+    /// skewed timestamp may not be in every update while it is still tracked.
+    const FORGET_AFTER_UPDATES: usize = 10;
+
+    pub(crate) fn with_correction(self, correct: bool) -> Self {
+        Self { correct, ..self }
+    }
+
+    /// Checks each of the timestamps in `update` against its `now`, and
+    /// returns the `now` that durations should be measured to.
+    pub(crate) fn observe(
+        &mut self,
+        update: &proto::instrument::Update,
+        now: SystemTime,
+    ) -> SystemTime {
+        let ahead = timestamps(update)
+            .filter_map(|timestamp| SystemTime::try_from(timestamp.clone()).ok())
+            .filter_map(|timestamp| timestamp.duration_since(now).ok())
+            .max()
+            .filter(|&ahead| ahead > Self::THRESHOLD);
+
+        match ahead {
+            Some(ahead) => {
+                if self.estimate.is_none() {
+                    tracing::warn!(?ahead, "target timestamps are ahead of its clock");
+                }
+                self.estimate = Some(cmp::max(self.estimate.unwrap_or_default(), ahead));
+                self.updates_since_skewed = 0;
+            }
+            None if self.estimate.is_some() => {
+                self.updates_since_skewed += 1;
+                if self.updates_since_skewed >= Self::FORGET_AFTER_UPDATES {
+                    tracing::debug!("target timestamps are no longer ahead of its clock");
+                    self.estimate = None;
+                }
+            }
+            None => {}
+        }
+
+        match self.estimate {
+            Some(estimate) if self.correct => now + estimate,
+            _ => now,
+        }
+    }
+
+    /// Returns how far ahead of the target's clock its timestamps have
+    /// recently been, or `None` if they haven't been ahead by enough to
+    /// matter.
+    pub(crate) fn estimate(&self) -> Option<Duration> {
+        self.estimate
+    }
+
+    /// Returns `true` if durations are measured to a `now` corrected by the
+    /// estimate.
+    pub(crate) fn is_corrected(&self) -> bool {
+        self.correct
+    }
+}
+
+/// Returns each of the task, resource and async op timestamps in `update`.
+fn timestamps(
+    update: &proto::instrument::Update,
+) -> impl Iterator<Item = &prost_types::Timestamp> + '_ {
+    let tasks = update
+        .task_update
+        .iter()
+        .flat_map(|update| update.stats_update.values())
+        .flat_map(|stats| {
+            let poll_stats = stats.poll_stats.as_ref();
+            stats
+                .created_at
+                .iter()
+                .chain(&stats.dropped_at)
+                .chain(&stats.last_wake)
+                .chain(poll_stats.and_then(|poll| poll.first_poll.as_ref()))
+                .chain(poll_stats.and_then(|poll| poll.last_poll_started.as_ref()))
+                .chain(poll_stats.and_then(|poll| poll.last_poll_ended.as_ref()))
+        });
+    let resources = update
+        .resource_update
+        .iter()
+        .flat_map(|update| update.stats_update.values())
+        .flat_map(|stats| stats.created_at.iter().chain(&stats.dropped_at));
+    let async_ops = update
+        .async_op_update
+        .iter()
+        .flat_map(|update| update.stats_update.values())
+        .flat_map(|stats| {
+            let poll_stats = stats.poll_stats.as_ref();
+            stats
+                .created_at
+                .iter()
+                .chain(&stats.dropped_at)
+                .chain(poll_stats.and_then(|poll| poll.first_poll.as_ref()))
+                .chain(poll_stats.and_then(|poll| poll.last_poll_started.as_ref()))
+                .chain(poll_stats.and_then(|poll| poll.last_poll_ended.as_ref()))
+        });
+    tasks.chain(resources).chain(async_ops)
+}
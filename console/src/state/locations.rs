@@ -0,0 +1,183 @@
+//! Stats about the tasks spawned at each location, for warnings about a spawn
+//! location as a whole rather than about any one of its tasks.
+use crate::{state::tasks::Task, warnings::Linter};
+use std::{
+    cmp,
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct LocationsState {
+    /// The locations which have spawned or completed a task within the last
+    /// [`SpawnLocation::WINDOW`], keyed by location.
+    locations: HashMap<String, SpawnLocation>,
+    pub(crate) linters: Vec<Linter<SpawnLocation>>,
+    /// The ID of the next location to be tracked, which identifies it to its
+    /// warnings.
+    next_id: u64,
+}
+
+/// The tasks recently spawned at one location.
+///
+/// Since tasks are removed once they have been completed for longer than
+/// `--retain-for`, the tasks which completed are recorded here when they
+/// complete, rather than counted from the tasks which are still retained.
+#[derive(Debug)]
+pub(crate) struct SpawnLocation {
+    id: u64,
+    location: String,
+    /// When each task spawned here within the window was spawned, oldest
+    /// first.
+    spawned: VecDeque<SystemTime>,
+    /// When each task spawned here which completed within the window was
+    /// dropped, oldest first, along with how long the task lived.
+    completed: VecDeque<(SystemTime, Duration)>,
+    /// Currently active warnings for this location.
+    warnings: Vec<Linter<SpawnLocation>>,
+}
+
+// === impl LocationsState ===
+
+impl LocationsState {
+    /// Forgets every location, keeping the linters.
+    pub(crate) fn reset(&mut self) {
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+            linter.reset();
+        }
+        *self = Self {
+            linters: std::mem::take(&mut self.linters),
+            ..Self::default()
+        };
+    }
+
+    /// Records that `task` was spawned, and that it completed if it has
+    /// already completed.
+    pub(crate) fn record_spawn(&mut self, task: &Task, now: SystemTime) {
+        if is_in_window(task.created_at(), now) {
+            self.location(task).spawned.push_back(task.created_at());
+        }
+        self.record_completion(task, now);
+    }
+
+    /// Records that `task` completed, if it has completed.
+    ///
+    /// This should only be called once for each task which completes.
+    pub(crate) fn record_completion(&mut self, task: &Task, now: SystemTime) {
+        let dropped_at = match task.dropped_at() {
+            Some(dropped_at) if is_in_window(dropped_at, now) => dropped_at,
+            _ => return,
+        };
+        let lifetime = task.total(now);
+        self.location(task)
+            .completed
+            .push_back((dropped_at, lifetime));
+    }
+
+    /// Forgets any spawns and completions from before the window, and checks
+    /// every location for warnings.
+    ///
+    /// The window moves even when no tasks are spawned, so this should be
+    /// called on every update.
+    pub(crate) fn update(&mut self, now: SystemTime) {
+        let linters = &self.linters;
+        self.locations.retain(|_, location| {
+            location.prune(now);
+            location.warnings = Linter::lint_all(linters, location.id, location, now);
+            !location.spawned.is_empty() || !location.completed.is_empty()
+        });
+    }
+
+    /// Returns the linters for location warnings which any location currently
+    /// has.
+    pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<SpawnLocation>> {
+        self.linters.iter().filter(|linter| linter.is_active())
+    }
+
+    /// Returns the locations with the warning `linter`, worst first.
+    pub(crate) fn warned(&self, linter: &Linter<SpawnLocation>) -> Vec<&SpawnLocation> {
+        let mut warned = self
+            .locations
+            .values()
+            .filter_map(|location| {
+                let warning = location
+                    .warnings
+                    .iter()
+                    .find(|warning| warning.is(linter))?;
+                Some((cmp::Reverse(warning.score(location)), location))
+            })
+            .collect::<Vec<_>>();
+        warned.sort_by(|(a_score, a), (b_score, b)| {
+            (a_score, &a.location).cmp(&(b_score, &b.location))
+        });
+        warned.into_iter().map(|(_, location)| location).collect()
+    }
+
+    fn location(&mut self, task: &Task) -> &mut SpawnLocation {
+        let next_id = &mut self.next_id;
+        self.locations
+            .entry(task.location().to_owned())
+            .or_insert_with(|| {
+                let id = *next_id;
+                *next_id += 1;
+                SpawnLocation {
+                    id,
+                    location: task.location().to_owned(),
+                    spawned: VecDeque::new(),
+                    completed: VecDeque::new(),
+                    warnings: Vec::new(),
+                }
+            })
+    }
+}
+
+// === impl SpawnLocation ===
+
+impl SpawnLocation {
+    /// How far back spawns and completions are counted.
+    pub(crate) const WINDOW: Duration = Duration::from_secs(10);
+
+    pub(crate) fn location(&self) -> &str {
+        self.location.trim_end()
+    }
+
+    /// Returns the number of tasks spawned here within the window.
+    pub(crate) fn spawns(&self) -> usize {
+        self.spawned.len()
+    }
+
+    /// Returns the average number of tasks spawned here per second within the
+    /// window.
+    pub(crate) fn spawn_rate(&self) -> f64 {
+        self.spawns() as f64 / Self::WINDOW.as_secs_f64()
+    }
+
+    /// Returns the average lifetime of the tasks spawned here which completed
+    /// within the window, or `None` if none have.
+    pub(crate) fn average_lifetime(&self) -> Option<Duration> {
+        if self.completed.is_empty() {
+            return None;
+        }
+        let total = self
+            .completed
+            .iter()
+            .map(|&(_, lifetime)| lifetime)
+            .sum::<Duration>();
+        Some(total / self.completed.len() as u32)
+    }
+
+    fn prune(&mut self, now: SystemTime) {
+        while matches!(self.spawned.front(), Some(&at) if !is_in_window(at, now)) {
+            self.spawned.pop_front();
+        }
+        while matches!(self.completed.front(), Some(&(at, _)) if !is_in_window(at, now)) {
+            self.completed.pop_front();
+        }
+    }
+}
+
+/// Returns `true` if `time` is within [`SpawnLocation::WINDOW`] of `now`.
+fn is_in_window(time: SystemTime, now: SystemTime) -> bool {
+    now.duration_since(time).unwrap_or_default() <= SpawnLocation::WINDOW
+}
@@ -1,10 +1,18 @@
-use self::resources::ResourcesState;
+use self::{
+    async_ops::{AsyncOp, AsyncOpSnapshot, AsyncOpsState},
+    clock_skew::ClockSkew,
+    locations::SpawnLocation,
+    rate::RateWindow,
+    resources::{Resource, ResourcesState},
+};
 use crate::{
+    config::{SavedAck, SavedPin},
     intern::{self, InternedStr},
     view,
     warnings::Linter,
 };
 use console_api as proto;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
     collections::HashMap,
@@ -14,14 +22,20 @@ use std::{
     rc::Rc,
     time::{Duration, SystemTime},
 };
-use tasks::{Details, Task, TasksState};
+use tasks::{Details, DetailsStatus, Task, TaskSnapshot, TasksState};
 use tui::{
     style::{Color, Modifier},
     text::Span,
 };
 
+pub mod async_ops;
+pub mod clock_skew;
+pub mod locations;
+pub mod rate;
 pub mod resources;
 pub mod tasks;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 pub(crate) type DetailsRef = Rc<RefCell<Option<Details>>>;
 
@@ -32,15 +46,42 @@ pub(crate) struct State {
     temporality: Temporality,
     tasks_state: TasksState,
     resources_state: ResourcesState,
+    async_ops_state: AsyncOpsState,
     current_task_details: DetailsRef,
+    current_task_details_status: DetailsStatus,
     retain_for: Option<Duration>,
+    /// The time of the update as of which inactive entities were last
+    /// removed, or `None` if they never have been.
+    last_retained_at: Option<SystemTime>,
     strings: intern::Strings,
+    /// The total number of events the target's subscriber has dropped, as of
+    /// the last update.
+    dropped_events: u64,
+    /// The total number of dropped events as of the update before the last
+    /// one.
+    prev_dropped_events: u64,
+    clock_skew: ClockSkew,
+    /// The time that durations are displayed as of while the clock is
+    /// frozen, or `None` if it isn't.
+    frozen_at: Option<SystemTime>,
+    /// Whether the view is frozen, so that updates are still received, but
+    /// the tables keep their rows and order, and the screen is only redrawn
+    /// after key presses.
+    view_frozen: bool,
 }
 pub(crate) enum Visibility {
     Show,
     Hide,
 }
 
+/// A point-in-time copy of the tasks and async ops in the target process,
+/// which can be serialized and read back.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) tasks: Vec<TaskSnapshot>,
+    pub(crate) async_ops: Vec<AsyncOpSnapshot>,
+}
+
 #[derive(Debug)]
 pub(crate) struct Metadata {
     field_names: Vec<InternedStr>,
@@ -64,39 +105,285 @@ pub(crate) enum FieldValue {
     Debug(String),
 }
 
-#[derive(Debug)]
+/// The warnings which were acknowledged for each task and async op, as
+/// cleared by [`State::clear_acknowledged_warnings`], so that they can be
+/// acknowledged again.
+#[derive(Debug, Default)]
+pub(crate) struct Acknowledgements {
+    /// The acknowledgements of each task linter's warning, in the order of
+    /// the linters.
+    tasks: Vec<Vec<(u64, u64)>>,
+    /// The acknowledgements of each async op linter's warning, in the order
+    /// of the linters.
+    async_ops: Vec<Vec<(u64, u64)>>,
+}
+
+#[derive(Debug, Default)]
 enum Temporality {
+    #[default]
     Live,
     Paused,
 }
 
 impl State {
+    /// How much [`State::retain_longer`] and [`State::retain_shorter`] change
+    /// the retention period by.
+    const RETAIN_FOR_STEP: Duration = Duration::from_secs(5);
+
+    /// How often inactive entities are removed, in terms of the target's
+    /// clock.
+    ///
+    /// Removing them means checking every task, resource and async op, so
+    /// this isn't done every time the screen is redrawn. Entities are
+    /// therefore displayed for up to this long after they pass the retention
+    /// period.
+    const RETAIN_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Sets the maximum number of bytes of strings that will be interned, or
+    /// `None` to intern strings without limit.
+    pub(crate) fn with_max_interned_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.strings = std::mem::take(&mut self.strings).with_max_bytes(max_bytes);
+        self
+    }
+
+    /// Sets the maximum length in bytes of each interned string, or `None` to
+    /// intern strings of any length. Longer strings are truncated.
+    pub(crate) fn with_max_interned_string_len(mut self, max_len: Option<usize>) -> Self {
+        self.strings = std::mem::take(&mut self.strings).with_max_len(max_len);
+        self
+    }
+
+    /// Sets the maximum number of tasks to track, or `None` to track tasks
+    /// without limit.
+    ///
+    /// Once there are more tasks than this, the tasks which completed longest
+    /// ago are removed, even if they're still within the retention period.
+    pub(crate) fn with_max_tasks(mut self, max_tasks: Option<usize>) -> Self {
+        self.tasks_state.set_max_tasks(max_tasks);
+        self
+    }
+
+    /// Sets how much unused capacity, in bytes, the interned strings may
+    /// retain before it is released.
+    pub(crate) fn with_interner_shrink_threshold(mut self, shrink_threshold: usize) -> Self {
+        self.strings = std::mem::take(&mut self.strings).with_shrink_threshold(shrink_threshold);
+        self
+    }
+
+    /// Sets whether the interner counts lookup hits and misses.
+    pub(crate) fn with_interner_lookup_counting(mut self, count_lookups: bool) -> Self {
+        self.strings = std::mem::take(&mut self.strings).with_lookup_counting(count_lookups);
+        self
+    }
+
+    /// Interns `strings` up front, so that they are already interned when the
+    /// target sends them.
+    pub(crate) fn with_warm_strings(mut self, strings: impl IntoIterator<Item = String>) -> Self {
+        self.strings.warm(strings);
+        self
+    }
+
+    /// Returns up to `limit` of the targets used by the most metadata, with
+    /// the most common targets first.
+    pub(crate) fn frequent_targets(&self, limit: usize) -> Vec<String> {
+        let mut counts = HashMap::<&str, usize>::new();
+        for meta in self.metas.values() {
+            *counts.entry(&meta.target).or_default() += 1;
+        }
+
+        let mut targets = counts.into_iter().collect::<Vec<_>>();
+        targets.sort_unstable_by(|(a_target, a), (b_target, b)| {
+            b.cmp(a).then_with(|| a_target.cmp(b_target))
+        });
+        targets
+            .into_iter()
+            .take(limit)
+            .map(|(target, _)| target.to_owned())
+            .collect()
+    }
+
+    /// Sets whether durations are corrected for timestamps which are ahead
+    /// of the target's clock, by measuring them to a `now` moved forward by
+    /// how far ahead the timestamps are.
+    pub(crate) fn with_clock_skew_correction(mut self, correct: bool) -> Self {
+        self.clock_skew = std::mem::take(&mut self.clock_skew).with_correction(correct);
+        self
+    }
+
     pub(crate) fn with_retain_for(mut self, retain_for: Option<Duration>) -> Self {
         self.retain_for = retain_for;
         self
     }
 
+    /// Returns how long completed tasks, dropped resources, and dropped async
+    /// ops are displayed for, or `None` if they are never removed.
+    pub(crate) fn retain_for(&self) -> Option<Duration> {
+        self.retain_for
+    }
+
+    /// Increases how long completed tasks, dropped resources, and dropped
+    /// async ops are displayed for.
+    ///
+    /// This takes effect the next time inactive entities are removed. Note
+    /// that retaining entities for longer means more of them are held in
+    /// memory, along with any interned strings they reference.
+    ///
+    /// If entities are never removed, this does nothing.
+    pub(crate) fn retain_longer(&mut self) {
+        if let Some(ref mut retain_for) = self.retain_for {
+            *retain_for += Self::RETAIN_FOR_STEP;
+        }
+    }
+
+    /// Sets how far back rates, such as how often tasks are woken, are
+    /// measured over.
+    pub(crate) fn with_rate_window(mut self, window: RateWindow) -> Self {
+        self.set_rate_window(window);
+        self
+    }
+
+    pub(crate) fn rate_window(&self) -> RateWindow {
+        self.tasks_state.rate_window()
+    }
+
+    /// Switches to measuring rates over the next longer window, or the
+    /// shortest one after the longest.
+    ///
+    /// Rates are measured over the updates already received, so a longer
+    /// window takes effect right away, but only covers the whole window once
+    /// enough updates have been received.
+    pub(crate) fn cycle_rate_window(&mut self) {
+        self.set_rate_window(self.rate_window().next());
+    }
+
+    fn set_rate_window(&mut self, window: RateWindow) {
+        self.tasks_state.set_rate_window(window);
+        self.async_ops_state.set_rate_window(window);
+    }
+
+    /// Decreases how long completed tasks, dropped resources, and dropped
+    /// async ops are displayed for, down to a minimum of zero.
+    ///
+    /// This takes effect the next time inactive entities are removed.
+    ///
+    /// If entities are never removed, this does nothing.
+    pub(crate) fn retain_shorter(&mut self) {
+        if let Some(ref mut retain_for) = self.retain_for {
+            *retain_for = retain_for.saturating_sub(Self::RETAIN_FOR_STEP);
+        }
+    }
+
     pub(crate) fn with_task_linters(
         mut self,
         linters: impl IntoIterator<Item = Linter<Task>>,
     ) -> Self {
-        self.tasks_state.linters.extend(linters.into_iter());
+        self.tasks_state.linters.extend(linters);
+        self
+    }
+
+    /// Restores the pins and acknowledgements saved by a previous session, as
+    /// tasks with the same keys are seen.
+    pub(crate) fn with_saved_tasks(mut self, pins: Vec<SavedPin>, acks: Vec<SavedAck>) -> Self {
+        self.tasks_state.set_saved(pins, acks);
+        self
+    }
+
+    pub(crate) fn with_location_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<SpawnLocation>>,
+    ) -> Self {
+        self.tasks_state.locations.linters.extend(linters);
+        self
+    }
+
+    pub(crate) fn with_resource_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<Resource>>,
+    ) -> Self {
+        self.resources_state.linters.extend(linters);
         self
     }
 
+    pub(crate) fn with_async_op_linters(
+        mut self,
+        linters: impl IntoIterator<Item = Linter<AsyncOp>>,
+    ) -> Self {
+        self.async_ops_state.linters.extend(linters);
+        self
+    }
+
+    /// Replaces the linters for each kind of entity, such as after the config
+    /// file's `[warnings]` section changed, and re-checks every entity with
+    /// them.
+    ///
+    /// Warnings held by entities from the previous linters are dropped, so
+    /// that warning counts only reflect the new linters. Acknowledgements are
+    /// forgotten.
+    pub(crate) fn set_linters(
+        &mut self,
+        tasks: Vec<Linter<Task>>,
+        locations: Vec<Linter<SpawnLocation>>,
+        resources: Vec<Linter<Resource>>,
+        async_ops: Vec<Linter<AsyncOp>>,
+    ) {
+        let now = self.last_updated_at.unwrap_or_else(SystemTime::now);
+        self.tasks_state.set_linters(tasks, now);
+        self.tasks_state.locations.linters = locations;
+        self.tasks_state.locations.update(now);
+        self.resources_state.linters = resources;
+        let pending_async_ops = self.async_ops_state.pending_by_resource();
+        self.resources_state.lint(&pending_async_ops, now);
+        self.async_ops_state.set_linters(async_ops, now);
+    }
+
     pub(crate) fn last_updated_at(&self) -> Option<SystemTime> {
         self.last_updated_at
     }
 
+    /// Returns the time that durations should be displayed as of: the time
+    /// of the last update, or the time the clock was frozen at if it's
+    /// frozen.
+    ///
+    /// Warnings are still checked and described as of the time of each
+    /// update, so a task may gain or lose a warning while its durations stay
+    /// put.
+    pub(crate) fn display_now(&self) -> Option<SystemTime> {
+        self.frozen_at.or(self.last_updated_at)
+    }
+
+    /// Returns the total number of events the target's subscriber has dropped
+    /// because its event buffer was full.
+    ///
+    /// If this is nonzero, the console's data is incomplete.
+    pub(crate) fn dropped_events(&self) -> u64 {
+        self.dropped_events
+    }
+
+    /// Returns `true` if more events were dropped between the last two
+    /// updates.
+    pub(crate) fn dropped_events_increased(&self) -> bool {
+        self.dropped_events > self.prev_dropped_events
+    }
+
+    pub(crate) fn clock_skew(&self) -> &ClockSkew {
+        &self.clock_skew
+    }
+
     pub(crate) fn update(
         &mut self,
         styles: &view::Styles,
         current_view: &view::ViewState,
         update: proto::instrument::Update,
     ) {
-        if let Some(now) = update.now.map(|v| v.try_into().unwrap()) {
-            self.last_updated_at = Some(now);
+        let _span =
+            tracing::debug_span!("update", dropped_events = update.dropped_events).entered();
+        if let Some(now) = update.now.clone().map(|v| v.try_into().unwrap()) {
+            self.last_updated_at = Some(self.clock_skew.observe(&update, now));
         }
+        let now = self.last_updated_at.unwrap_or_else(SystemTime::now);
+
+        self.prev_dropped_events = self.dropped_events;
+        self.dropped_events = update.dropped_events;
 
         let strings = &mut self.strings;
         if let Some(new_metadata) = update.new_metadata {
@@ -120,6 +407,7 @@ impl State {
                 &self.metas,
                 tasks_update,
                 visibility,
+                now,
             )
         }
 
@@ -137,28 +425,170 @@ impl State {
                 visibility,
             )
         }
+
+        if let Some(async_ops_update) = update.async_op_update {
+            let visibility = if matches!(current_view, view::ViewState::AsyncOpsList) {
+                Visibility::Show
+            } else {
+                Visibility::Hide
+            };
+            self.async_ops_state.update_async_ops(
+                &mut self.strings,
+                &self.metas,
+                async_ops_update,
+                visibility,
+            )
+        }
+
+        self.async_ops_state
+            .sample_resource_polls(&self.resources_state, now);
+        self.async_ops_state
+            .lint(&self.resources_state, &self.tasks_state, now);
+        let pending_async_ops = self.async_ops_state.pending_by_resource();
+        self.resources_state.lint(&pending_async_ops, now);
+    }
+
+    /// Forgets every task, resource and async op, such as after reconnecting
+    /// to a target which may have restarted, since the IDs it sends may now
+    /// refer to different entities.
+    ///
+    /// The configuration, such as the linters and the retention period, is
+    /// kept, and pinned tasks and acknowledged warnings are restored once
+    /// tasks with the same keys are seen, as they are between sessions. A
+    /// new connection isn't paused, so neither is the state, and its view
+    /// isn't frozen, since the frozen rows have been forgotten.
+    pub(crate) fn reset(&mut self) {
+        self.metas.clear();
+        self.last_updated_at = None;
+        self.last_retained_at = None;
+        self.temporality = Temporality::Live;
+        self.frozen_at = None;
+        self.view_frozen = false;
+        self.tasks_state.reset();
+        self.resources_state.reset();
+        self.async_ops_state.reset();
+        self.unset_task_details();
+        self.dropped_events = 0;
+        self.prev_dropped_events = 0;
     }
 
+    /// Removes the completed tasks, dropped resources and dropped async ops
+    /// which have been inactive for longer than the retention period, along
+    /// with any interned strings which are no longer referenced.
+    ///
+    /// This may be called as often as the screen is drawn, but only does
+    /// anything once [`State::RETAIN_INTERVAL`] has passed since it last did.
     pub(crate) fn retain_active(&mut self) {
-        if self.is_paused() {
+        // Removing rows from a frozen view would shift the rows after them.
+        if self.is_paused() || self.view_frozen {
             return;
         }
 
+        if let Some(now) = self.last_updated_at {
+            let due = self
+                .last_retained_at
+                .and_then(|last| now.duration_since(last).ok())
+                .map(|elapsed| elapsed >= Self::RETAIN_INTERVAL)
+                .unwrap_or(true);
+            if !due {
+                return;
+            }
+            self.last_retained_at = Some(now);
+        }
+
         if let (Some(now), Some(retain_for)) = (self.last_updated_at(), self.retain_for) {
             self.tasks_state.retain_active(now, retain_for);
             self.resources_state.retain_active(now, retain_for);
+            self.async_ops_state.retain_active(now, retain_for);
         }
 
-        // After dropping idle tasks & resources, prune any interned strings
-        // that are no longer referenced.
-        self.strings.retain_referenced();
+        // After dropping idle tasks, resources, and async ops, prune any interned strings
+        // that are no longer referenced. Until the first update has been
+        // received, the only interned strings are those that were warmed up
+        // front, so keep them around until they can be used.
+        if self.last_updated_at.is_some() {
+            let dropped = self.strings.retain_referenced();
+            if dropped > 0 {
+                tracing::debug!(
+                    dropped,
+                    strings.len = self.strings.len(),
+                    "pruned unreferenced interned strings"
+                );
+            }
+        }
+    }
+
+    /// Acknowledges the current warnings for the task with the given ID.
+    pub(crate) fn acknowledge_task_warnings(&mut self, task_id: u64) {
+        self.tasks_state.acknowledge_warnings(task_id);
+    }
+
+    /// Clears all acknowledged warnings for tasks and async ops, returning
+    /// the acknowledgements which were cleared.
+    pub(crate) fn clear_acknowledged_warnings(&mut self) -> Acknowledgements {
+        let now = self.last_updated_at.unwrap_or_else(SystemTime::now);
+        let acks = self.acknowledgements();
+        self.tasks_state.clear_acknowledged(now);
+        self.async_ops_state.clear_acknowledged();
+        acks
+    }
+
+    /// Acknowledges the warnings cleared by
+    /// [`State::clear_acknowledged_warnings`] again, along with any that have
+    /// been acknowledged since.
+    pub(crate) fn restore_acknowledged_warnings(&mut self, acks: Acknowledgements) {
+        let now = self.last_updated_at.unwrap_or_else(SystemTime::now);
+        self.tasks_state.restore_acknowledged(&acks.tasks, now);
+        self.async_ops_state.restore_acknowledged(&acks.async_ops);
+    }
+
+    /// Returns the warnings which are currently acknowledged for each task
+    /// and async op.
+    pub(crate) fn acknowledgements(&self) -> Acknowledgements {
+        fn of<T>(linters: &[Linter<T>]) -> Vec<Vec<(u64, u64)>> {
+            linters.iter().map(Linter::acknowledgements).collect()
+        }
+        Acknowledgements {
+            tasks: of(&self.tasks_state.linters),
+            async_ops: of(&self.async_ops_state.linters),
+        }
+    }
+
+    pub(crate) fn strings(&self) -> &intern::Strings {
+        &self.strings
     }
 
     pub(crate) fn task_details_ref(&self) -> DetailsRef {
         self.current_task_details.clone()
     }
 
-    pub(crate) fn tasks_state(&mut self) -> &TasksState {
+    /// Returns a snapshot of the tasks and async ops in the target process as
+    /// of `now`.
+    ///
+    /// Unlike the state itself, the snapshot owns all of its data, so that it
+    /// can be serialized or sent elsewhere.
+    pub(crate) fn snapshot(&self, now: SystemTime) -> Snapshot {
+        Snapshot {
+            tasks: self.tasks_state.snapshot(now),
+            async_ops: self.async_ops_state.snapshot(now),
+        }
+    }
+
+    /// Returns a snapshot of the tasks and async ops with the given IDs as of
+    /// `now`.
+    pub(crate) fn snapshot_of(
+        &self,
+        task_ids: &[u64],
+        async_op_ids: &[u64],
+        now: SystemTime,
+    ) -> Snapshot {
+        Snapshot {
+            tasks: self.tasks_state.snapshot_of(task_ids, now),
+            async_ops: self.async_ops_state.snapshot_of(async_op_ids, now),
+        }
+    }
+
+    pub(crate) fn tasks_state(&self) -> &TasksState {
         &self.tasks_state
     }
 
@@ -166,10 +596,22 @@ impl State {
         &mut self.tasks_state
     }
 
+    pub(crate) fn resources_state(&self) -> &ResourcesState {
+        &self.resources_state
+    }
+
     pub(crate) fn resources_state_mut(&mut self) -> &mut ResourcesState {
         &mut self.resources_state
     }
 
+    pub(crate) fn async_ops_state(&self) -> &AsyncOpsState {
+        &self.async_ops_state
+    }
+
+    pub(crate) fn async_ops_state_mut(&mut self) -> &mut AsyncOpsState {
+        &mut self.async_ops_state
+    }
+
     pub(crate) fn update_task_details(&mut self, update: proto::tasks::TaskDetails) {
         if let Some(id) = update.task_id {
             let details = Details {
@@ -182,12 +624,30 @@ impl State {
                 // last_updated_at: update.now.map(|now| now.try_into().unwrap()),
             };
 
+            if let Some(histogram) = details.poll_times_histogram() {
+                let p99 = Duration::from_nanos(histogram.value_at_quantile(0.99));
+                let now = self.last_updated_at.unwrap_or_else(SystemTime::now);
+                self.tasks_state.update_poll_p99(id.id, p99, now);
+            }
+
             *self.current_task_details.borrow_mut() = Some(details);
+            self.current_task_details_status = DetailsStatus::Ready;
         }
     }
 
     pub(crate) fn unset_task_details(&mut self) {
         *self.current_task_details.borrow_mut() = None;
+        self.current_task_details_status = DetailsStatus::Pending;
+    }
+
+    /// Returns whether the details of the task being viewed have been
+    /// received.
+    pub(crate) fn task_details_status(&self) -> &DetailsStatus {
+        &self.current_task_details_status
+    }
+
+    pub(crate) fn set_task_details_status(&mut self, status: DetailsStatus) {
+        self.current_task_details_status = status;
     }
 
     // temporality methods
@@ -203,11 +663,48 @@ impl State {
     pub(crate) fn is_paused(&self) -> bool {
         matches!(self.temporality, Temporality::Paused)
     }
+
+    /// Freezes the clock that durations are displayed as of at the time of
+    /// the last update, or unfreezes it if it's already frozen.
+    ///
+    /// Unlike pausing, updates keep being received while the clock is
+    /// frozen, so new tasks and changes to existing ones are still
+    /// displayed. Durations that end after the clock was frozen are
+    /// displayed as if they're still ongoing as of when it was frozen.
+    pub(crate) fn toggle_frozen_clock(&mut self) {
+        self.frozen_at = match self.frozen_at {
+            Some(_) => None,
+            None => self.last_updated_at,
+        };
+    }
+
+    pub(crate) fn is_clock_frozen(&self) -> bool {
+        self.frozen_at.is_some()
+    }
+
+    /// Freezes the view, or unfreezes it if it's already frozen.
+    ///
+    /// Unlike pausing, which stops updates from being received, updates keep
+    /// being processed while the view is frozen, so that stats such as
+    /// totals and histograms stay accurate. Only the display stops changing:
+    /// the tables don't add new rows or re-sort, and inactive entities aren't
+    /// removed, until the view is unfrozen.
+    pub(crate) fn toggle_frozen_view(&mut self) {
+        self.view_frozen = !self.view_frozen;
+    }
+
+    pub(crate) fn is_view_frozen(&self) -> bool {
+        self.view_frozen
+    }
 }
 
-impl Default for Temporality {
-    fn default() -> Self {
-        Self::Live
+// === impl Acknowledgements ===
+
+impl Acknowledgements {
+    /// Returns the number of acknowledged warnings, counting each warning
+    /// once for each task or async op it's acknowledged for.
+    pub(crate) fn len(&self) -> usize {
+        self.tasks.iter().chain(&self.async_ops).map(Vec::len).sum()
     }
 }
 
@@ -230,6 +727,8 @@ impl Metadata {
 impl Field {
     const SPAWN_LOCATION: &'static str = "spawn.location";
     const NAME: &'static str = "task.name";
+    const KIND: &'static str = "kind";
+    const KIND_BLOCKING: &'static str = "blocking";
 
     /// Converts a wire-format `Field` into an internal `Field` representation,
     /// using the provided `Metadata` for the task span that the field came
@@ -301,32 +800,21 @@ impl Field {
         Some(Self { name, value })
     }
 
-    fn make_formatted(styles: &view::Styles, fields: &mut Vec<Field>) -> Vec<Vec<Span<'static>>> {
-        use std::cmp::Ordering;
-
+    fn make_formatted(styles: &view::Styles, fields: &mut [Field]) -> Vec<Vec<Span<'static>>> {
         let key_style = styles.fg(Color::LightBlue).add_modifier(Modifier::BOLD);
         let delim_style = styles.fg(Color::LightBlue).add_modifier(Modifier::DIM);
         let val_style = styles.fg(Color::Yellow);
 
-        fields.sort_unstable_by(|left, right| {
-            if &*left.name == Field::NAME {
-                return Ordering::Less;
-            }
-
-            if &*right.name == Field::NAME {
-                return Ordering::Greater;
-            }
-
-            if &*left.name == Field::SPAWN_LOCATION {
-                return Ordering::Greater;
-            }
-
-            if &*right.name == Field::SPAWN_LOCATION {
-                return Ordering::Less;
-            }
-
-            left.name.cmp(&right.name)
-        });
+        // The name comes first and the spawn location last, with the other
+        // fields sorted by name in between. The sort is stable, so that
+        // fields with the same name (which a target may send more than once)
+        // stay in the order they were sent.
+        let rank = |field: &Field| match &*field.name {
+            Field::NAME => 0,
+            Field::SPAWN_LOCATION => 2,
+            _ => 1,
+        };
+        fields.sort_by(|left, right| (rank(left), &left.name).cmp(&(rank(right), &right.name)));
 
         let mut formatted = Vec::with_capacity(fields.len());
         let mut fields = fields.iter();
@@ -409,13 +897,49 @@ fn truncate_registry_path(s: String) -> String {
             .expect("failed to compile regex")
     });
 
-    return match regex.replace(&s, "<cargo>/") {
+    match regex.replace(&s, "<cargo>/") {
         Cow::Owned(s) => s,
         // String was not modified, return the original.
         Cow::Borrowed(_) => s.to_string(),
-    };
+    }
 }
 
+/// Parses a column name, ignoring case, as one of the `names` of a table's
+/// [`SortBy`](view::SortBy) variants.
+fn parse_column<S: Copy>(names: &[(S, &'static str)], s: &str) -> Result<S, UnknownColumn> {
+    names
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(s))
+        .map(|&(sort_by, _)| sort_by)
+        .ok_or_else(|| UnknownColumn {
+            name: s.to_owned(),
+            expected: names.iter().map(|&(_, name)| name).collect(),
+        })
+}
+
+/// The error returned when parsing a `SortBy` from an unknown column name.
+#[derive(Debug)]
+pub(crate) struct UnknownColumn {
+    name: String,
+    expected: Vec<&'static str>,
+}
+
+impl fmt::Display for UnknownColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown column `{}`, expected one of: {}",
+            self.name,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownColumn {}
+
+/// The location displayed for tasks whose spawn location wasn't recorded.
+const UNKNOWN_LOCATION: &str = "<unknown location>";
+
 fn format_location(loc: Option<proto::Location>) -> String {
     loc.map(|mut l| {
         if let Some(file) = l.file.take() {
@@ -424,5 +948,204 @@ fn format_location(loc: Option<proto::Location>) -> String {
         }
         format!("{} ", l)
     })
-    .unwrap_or_else(|| "<unknown location>".to_string())
+    .unwrap_or_else(|| UNKNOWN_LOCATION.to_string())
+}
+
+/// Returns the time spent in polls, given the `busy` time of all completed
+/// polls, including the time spent so far in the current poll if one is in
+/// progress.
+///
+/// If `now` is before the current poll started (for example, if the clocks
+/// of the console and the instrumented process disagree), the current poll
+/// is not counted.
+fn busy_including_current_poll(
+    busy: Duration,
+    last_started: Option<SystemTime>,
+    last_ended: Option<SystemTime>,
+    now: SystemTime,
+) -> Duration {
+    match (last_started, last_ended) {
+        // in this case a poll is in progress at the moment
+        (Some(last_started), None) => busy + now.duration_since(last_started).unwrap_or_default(),
+        _ => busy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_util::*, *};
+
+    /// Sends an update at `now` with the async op `id`, created at zero and
+    /// dropped at `dropped_at`.
+    fn send_dropped_op(state: &mut State, now: SystemTime, id: u64, dropped_at: SystemTime) {
+        let mut update = update(now);
+        update.async_op_update = Some(proto::async_ops::AsyncOpUpdate {
+            new_async_ops: vec![async_op(id)],
+            stats_update: std::iter::once((
+                id,
+                proto::async_ops::Stats {
+                    dropped_at: Some(timestamp(dropped_at)),
+                    ..async_op_stats(at(0))
+                },
+            ))
+            .collect(),
+        });
+        state.update(&styles(), &view::ViewState::AsyncOpsList, update);
+    }
+
+    #[test]
+    fn dropped_async_ops_are_pruned_within_an_interval_of_retain_for() {
+        let retain_for = Duration::from_secs(5);
+        let mut state = State::default().with_retain_for(Some(retain_for));
+        let dropped_at = at(1_000);
+        send_dropped_op(&mut state, dropped_at, 1, dropped_at);
+
+        let expired_at = dropped_at + retain_for;
+        let mut pruned_at = None;
+        // Updates arrive more often than inactive entities are removed.
+        for millis in (1_100..=10_000).step_by(100) {
+            let now = at(millis);
+            state.update(&styles(), &view::ViewState::AsyncOpsList, update(now));
+            state.retain_active();
+            if !state.async_ops_state().contains(1) {
+                pruned_at = Some(now);
+                break;
+            }
+        }
+
+        let pruned_at = pruned_at.expect("the dropped async op should have been pruned");
+        assert!(pruned_at >= expired_at, "pruned before `retain_for` passed");
+        assert!(
+            pruned_at <= expired_at + State::RETAIN_INTERVAL,
+            "kept for {:?} after `retain_for` passed",
+            pruned_at.duration_since(expired_at).unwrap(),
+        );
+    }
+
+    #[test]
+    fn dropped_async_ops_are_kept_if_the_clock_goes_backwards() {
+        let mut state = State::default().with_retain_for(Some(Duration::from_secs(5)));
+        // The op was dropped after the `now` of the update it arrived in.
+        send_dropped_op(&mut state, at(1_000), 1, at(60_000));
+        state.retain_active();
+        assert!(state.async_ops_state().contains(1));
+    }
+
+    #[test]
+    fn busy_includes_the_poll_in_progress() {
+        let busy = Duration::from_secs(1);
+        assert_eq!(
+            busy_including_current_poll(busy, Some(at(2_000)), None, at(5_000)),
+            Duration::from_secs(4)
+        );
+    }
+
+    #[test]
+    fn busy_excludes_the_time_since_the_last_poll_ended() {
+        let busy = Duration::from_secs(1);
+        assert_eq!(
+            busy_including_current_poll(busy, Some(at(2_000)), Some(at(3_000)), at(5_000)),
+            busy
+        );
+        assert_eq!(
+            busy_including_current_poll(busy, None, None, at(5_000)),
+            busy
+        );
+    }
+
+    #[test]
+    fn busy_excludes_a_poll_which_started_after_now() {
+        // The target's clock is ahead of the `now` the poll is measured to.
+        let busy = Duration::from_secs(1);
+        assert_eq!(
+            busy_including_current_poll(busy, Some(at(8_000)), None, at(5_000)),
+            busy
+        );
+    }
+
+    /// Checks that each of a table's column `names` parses, ignoring case,
+    /// as the column at its index, and that every column is named.
+    fn assert_columns_parse<S>(names: &[(S, &'static str)])
+    where
+        S: view::SortBy + std::str::FromStr<Err = UnknownColumn> + std::convert::TryFrom<usize>,
+    {
+        for (idx, &(_, name)) in names.iter().enumerate() {
+            let parsed = name.parse::<S>().expect("column names should parse");
+            assert_eq!(parsed.as_column(), idx, "{}", name);
+            let parsed = name.to_uppercase().parse::<S>().unwrap();
+            assert_eq!(parsed.as_column(), idx, "{}", name);
+            assert_eq!(names[idx].0.as_column(), idx, "{}", name);
+        }
+        let count = (0..).take_while(|&idx| S::try_from(idx).is_ok()).count();
+        assert_eq!(count, names.len());
+    }
+
+    #[test]
+    fn sort_columns_are_parsed_by_name() {
+        assert_columns_parse(tasks::SortBy::NAMES);
+        assert_columns_parse(async_ops::SortBy::NAMES);
+    }
+
+    #[test]
+    fn unknown_sort_columns_are_rejected() {
+        let error = "bogus".parse::<tasks::SortBy>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown column `bogus`, expected one of: warns, tid, state, name, total, busy, \
+             idle, polls, poll_times, activity, wake_rate, target, location"
+        );
+        let error = "bogus".parse::<async_ops::SortBy>().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown column `bogus`, expected one of: warns, aid, source, rid, tid, total, \
+             busy, idle, polls, since_last_poll, idle_since, target"
+        );
+    }
+
+    #[test]
+    fn fields_with_the_same_name_keep_their_order() {
+        let mut strings = intern::Strings::default();
+        let mut field = |name: &str, value: &str| Field {
+            name: strings.string(name.to_owned()),
+            value: FieldValue::Str(value.to_owned()),
+        };
+        let mut fields = [
+            field(Field::SPAWN_LOCATION, "b.rs"),
+            field("kind", "task"),
+            field(Field::NAME, "first"),
+            field(Field::SPAWN_LOCATION, "a.rs"),
+            field(Field::NAME, "second"),
+        ];
+        Field::make_formatted(&styles(), &mut fields);
+        let values = fields
+            .iter()
+            .map(|field| field.value.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["first", "second", "task", "b.rs", "a.rs"]);
+    }
+
+    #[test]
+    fn warnings_are_described_as_of_the_update_while_the_clock_is_frozen() {
+        let linters = vec![crate::warnings::Linter::new(
+            crate::warnings::NeverPolled::default(),
+        )];
+        let mut state = State::default().with_task_linters(linters);
+        state.update(&styles(), &view::ViewState::TasksList, update(at(1_000)));
+        state.toggle_frozen_clock();
+
+        send_task(&mut state, at(30_000), 1, task_stats(at(30_000)));
+        let mut update = update(at(60_000));
+        update.task_update = Some(proto::tasks::TaskUpdate::default());
+        state.update(&styles(), &view::ViewState::TasksList, update);
+        assert_eq!(state.display_now(), Some(at(1_000)));
+
+        let task = state.tasks_state().task(1).unwrap();
+        let task = task.borrow();
+        assert_eq!(task.warnings().len(), 1);
+        let now = state.last_updated_at().unwrap();
+        assert_eq!(
+            task.warnings()[0].format(&task, now),
+            "This task has never been polled since it was spawned 30s ago"
+        );
+    }
 }
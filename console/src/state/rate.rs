@@ -0,0 +1,132 @@
+//! Rates, such as how often tasks are woken, measured over a window of recent
+//! updates rather than between the last two, so that they don't jump around
+//! with every update.
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+/// How far back rates are measured over.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct RateWindow(Duration);
+
+/// How many times something happened as of each of the most recent samples,
+/// for measuring how often it happens over a [`RateWindow`].
+///
+/// Only as many samples as the window spans are kept. If the samples span
+/// less time than the window, such as just after the console connects, rates
+/// are measured over the samples there are.
+#[derive(Debug, Default)]
+pub(crate) struct RateHistory {
+    /// The time of each sample, and how many times the thing happened between
+    /// the oldest sample and that one, oldest first.
+    samples: VecDeque<(SystemTime, u64)>,
+}
+
+// === impl RateWindow ===
+
+impl RateWindow {
+    /// The windows that can be switched between while the console is running,
+    /// shortest first.
+    const WINDOWS: &'static [Duration] = &[
+        Duration::from_secs(1),
+        Duration::from_secs(5),
+        Duration::from_secs(15),
+    ];
+
+    /// The shortest window, since rates shorter than a few updates apart
+    /// can't be measured.
+    const MIN: Duration = Duration::from_millis(100);
+
+    /// The longest window, since every tracked task keeps a sample for each
+    /// update within the window.
+    const MAX: Duration = Duration::from_secs(60);
+
+    /// Returns a window of `window`, clamped to between 100ms and 60s.
+    pub(crate) fn new(window: Duration) -> Self {
+        Self(window.clamp(Self::MIN, Self::MAX))
+    }
+
+    /// Returns the next longer of the windows that can be switched between,
+    /// or the shortest one after the longest.
+    pub(crate) fn next(self) -> Self {
+        let next = Self::WINDOWS
+            .iter()
+            .find(|&&window| window > self.0)
+            .unwrap_or(&Self::WINDOWS[0]);
+        Self(*next)
+    }
+}
+
+impl Default for RateWindow {
+    fn default() -> Self {
+        Self(Self::WINDOWS[0])
+    }
+}
+
+impl fmt::Display for RateWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&humantime::format_duration(self.0), f)
+    }
+}
+
+// === impl RateHistory ===
+
+impl RateHistory {
+    /// Records that the thing happened `new` times since the last sample, as
+    /// of `at`, and forgets the samples which are no longer needed to measure
+    /// rates over `window`.
+    ///
+    /// The first sample only marks when counting started, so `new` is
+    /// ignored. If `at` is before the last sample, such as if the target's
+    /// clock went backwards, the earlier samples can't be compared with it,
+    /// so they are forgotten.
+    pub(crate) fn record(&mut self, new: u64, at: SystemTime, window: RateWindow) {
+        match self.samples.back_mut() {
+            Some(&mut (last_at, ref mut count)) if last_at == at => *count += new,
+            Some(&mut (last_at, count)) if last_at < at => {
+                self.samples.push_back((at, count + new))
+            }
+            _ => {
+                self.samples.clear();
+                self.samples.push_back((at, 0));
+            }
+        }
+        // Keep the newest sample which is at least a window old, so that the
+        // samples span the whole window.
+        while self
+            .samples
+            .get(1)
+            .and_then(|&(second_at, _)| at.duration_since(second_at).ok())
+            .is_some_and(|age| age >= window.0)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Forgets every sample, such as when the count being sampled is reset.
+    pub(crate) fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns how many times per second the thing happened over the last
+    /// `window`, or over all of the samples if they span less time than that.
+    ///
+    /// Returns `None` if fewer than two samples at different times have been
+    /// recorded.
+    pub(crate) fn rate(&self, window: RateWindow) -> Option<f64> {
+        let &(last_at, last) = self.samples.back()?;
+        let &(first_at, first) = self
+            .samples
+            .iter()
+            .rev()
+            .find(|&&(at, _)| last_at.duration_since(at).is_ok_and(|age| age >= window.0))
+            .or_else(|| self.samples.front())?;
+        let elapsed = last_at.duration_since(first_at).ok()?;
+        if elapsed == Duration::ZERO {
+            return None;
+        }
+        Some((last - first) as f64 / elapsed.as_secs_f64())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::intern::{self, InternedStr};
 use crate::state::{format_location, Field, Metadata, Visibility};
 use crate::view;
+use crate::warnings::Linter;
 use console_api as proto;
 use std::{
     cell::RefCell,
@@ -18,16 +19,19 @@ use tui::{
 pub(crate) struct ResourcesState {
     resources: HashMap<u64, Rc<RefCell<Resource>>>,
     new_resources: Vec<ResourceRef>,
+    pub(crate) linters: Vec<Linter<Resource>>,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 #[repr(usize)]
 pub(crate) enum SortBy {
-    Rid = 0,
-    Kind = 1,
-    ConcreteType = 2,
-    Target = 3,
-    Total = 4,
+    Warns = 0,
+    #[default]
+    Rid = 1,
+    Kind = 2,
+    Total = 3,
+    Target = 4,
+    ConcreteType = 5,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -45,6 +49,11 @@ pub(crate) struct Resource {
     target: InternedStr,
     concrete_type: InternedStr,
     location: String,
+    /// The number of async ops on this resource which have not yet been
+    /// dropped, as of the last update.
+    pending_async_ops: usize,
+    /// Currently active warnings for this resource.
+    warnings: Vec<Linter<Resource>>,
 }
 
 pub(crate) type ResourceRef = Weak<RefCell<Resource>>;
@@ -66,37 +75,35 @@ struct ResourceStats {
     formatted_attributes: Vec<Vec<Span<'static>>>,
 }
 
-impl Default for SortBy {
-    fn default() -> Self {
-        Self::Rid
-    }
-}
-
 impl SortBy {
-    pub fn sort(&self, now: SystemTime, resources: &mut Vec<Weak<RefCell<Resource>>>) {
+    pub fn sort(&self, now: SystemTime, resources: &mut [Weak<RefCell<Resource>>]) {
         match self {
-            Self::Rid => {
-                resources.sort_unstable_by_key(|resource| resource.upgrade().map(|r| r.borrow().id))
-            }
-            Self::Kind => resources.sort_unstable_by_key(|resource| {
-                resource.upgrade().map(|r| r.borrow().kind.clone())
-            }),
-            Self::ConcreteType => resources.sort_unstable_by_key(|resource| {
-                resource.upgrade().map(|r| r.borrow().concrete_type.clone())
-            }),
-            Self::Target => resources.sort_unstable_by_key(|resource| {
-                resource.upgrade().map(|r| r.borrow().target.clone())
-            }),
-            Self::Total => resources
-                .sort_unstable_by_key(|resource| resource.upgrade().map(|r| r.borrow().total(now))),
+            Self::Warns => sort_by_key(resources, |r| r.warnings().len()),
+            Self::Rid => sort_by_key(resources, |_| ()),
+            Self::Kind => sort_by_key(resources, |r| r.kind.clone()),
+            Self::ConcreteType => sort_by_key(resources, |r| r.concrete_type.clone()),
+            Self::Target => sort_by_key(resources, |r| r.target.clone()),
+            Self::Total => sort_by_key(resources, |r| r.total(now)),
         }
     }
 }
 
+/// Sorts `resources` by the key returned by `f`, breaking ties by resource ID
+/// so that resources with equal keys don't change places between updates.
+fn sort_by_key<K: Ord>(resources: &mut [Weak<RefCell<Resource>>], f: impl Fn(&Resource) -> K) {
+    resources.sort_unstable_by_key(|resource| {
+        resource.upgrade().map(|r| {
+            let r = r.borrow();
+            (f(&r), r.id)
+        })
+    })
+}
+
 impl TryFrom<usize> for SortBy {
     type Error = ();
     fn try_from(idx: usize) -> Result<Self, Self::Error> {
         match idx {
+            idx if idx == Self::Warns as usize => Ok(Self::Warns),
             idx if idx == Self::Rid as usize => Ok(Self::Rid),
             idx if idx == Self::Kind as usize => Ok(Self::Kind),
             idx if idx == Self::ConcreteType as usize => Ok(Self::ConcreteType),
@@ -114,6 +121,28 @@ impl view::SortBy for SortBy {
 }
 
 impl ResourcesState {
+    /// Forgets every resource, keeping the linters.
+    pub(crate) fn reset(&mut self) {
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+            linter.reset();
+        }
+        *self = Self {
+            linters: std::mem::take(&mut self.linters),
+            ..Self::default()
+        };
+    }
+
+    /// Returns `true` if the resource with the given ID is known, i.e. its
+    /// metadata has been received and it has not been pruned.
+    pub(crate) fn contains(&self, id: u64) -> bool {
+        self.resources.contains_key(&id)
+    }
+
+    pub(crate) fn resource(&self, id: u64) -> Option<Rc<RefCell<Resource>>> {
+        self.resources.get(&id).cloned()
+    }
+
     pub(crate) fn take_new_resources(&mut self) -> impl Iterator<Item = ResourceRef> + '_ {
         self.new_resources.drain(..)
     }
@@ -128,8 +157,11 @@ impl ResourcesState {
     ) {
         let mut stats_update = update.stats_update;
         let new_list = &mut self.new_resources;
+        // The table drains the new list each time it is drawn, but the screen
+        // may be redrawn less often than updates arrive, so only drop entries
+        // that no longer exist, rather than ones the table hasn't seen yet.
         if matches!(visibility, Visibility::Show) {
-            new_list.clear();
+            new_list.retain(|resource| resource.upgrade().is_some());
         }
 
         let new_resources = update.new_resources.into_iter().filter_map(|resource| {
@@ -171,6 +203,8 @@ impl ResourcesState {
                 concrete_type: strings.string(resource.concrete_type),
                 meta_id,
                 location,
+                pending_async_ops: 0,
+                warnings: Vec::new(),
             };
             let resource = Rc::new(RefCell::new(resource));
             new_list.push(Rc::downgrade(&resource));
@@ -188,18 +222,44 @@ impl ResourcesState {
         }
     }
 
+    /// Lints every resource, given the number of pending async ops on each
+    /// resource, keyed by resource ID.
+    ///
+    /// Since a resource's pending async ops aren't part of its own stats,
+    /// this is called after both resources and async ops have been updated.
+    pub(crate) fn lint(&mut self, pending_async_ops: &HashMap<u64, usize>, now: SystemTime) {
+        for (id, resource) in &self.resources {
+            let mut resource = resource.borrow_mut();
+            resource.pending_async_ops = pending_async_ops.get(id).copied().unwrap_or(0);
+            resource.warnings = Linter::lint_all(&self.linters, *id, &*resource, now);
+        }
+    }
+
+    pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<Resource>> {
+        self.linters.iter().filter(|linter| linter.is_active())
+    }
+
     pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
-        self.resources.retain(|_, resource| {
+        let linters = &self.linters;
+        self.resources.retain(|id, resource| {
             let resource = resource.borrow();
 
-            resource
+            let retain = resource
                 .stats
                 .dropped_at
                 .map(|d| {
-                    let dropped_for = now.duration_since(d).unwrap();
-                    retain_for > dropped_for
+                    // If the target's clock has gone back to before `d`, it hasn't
+                    // been dropped for long enough to be pruned yet.
+                    now.duration_since(d)
+                        .map_or(true, |dropped_for| retain_for > dropped_for)
                 })
-                .unwrap_or(true)
+                .unwrap_or(true);
+            if !retain {
+                for linter in linters {
+                    linter.forget(*id);
+                }
+            }
+            retain
         })
     }
 }
@@ -229,9 +289,15 @@ impl Resource {
     }
 
     pub(crate) fn total(&self, since: SystemTime) -> Duration {
-        self.stats
-            .total
-            .unwrap_or_else(|| since.duration_since(self.stats.created_at).unwrap())
+        self.stats.total.unwrap_or_else(|| {
+            since
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default()
+        })
+    }
+
+    pub(crate) fn created_at(&self) -> SystemTime {
+        self.stats.created_at
     }
 
     pub(crate) fn dropped(&self) -> bool {
@@ -241,6 +307,16 @@ impl Resource {
     pub(crate) fn location(&self) -> &str {
         &self.location
     }
+
+    /// Returns the number of async ops on this resource which have not yet
+    /// been dropped.
+    pub(crate) fn pending_async_ops(&self) -> usize {
+        self.pending_async_ops
+    }
+
+    pub(crate) fn warnings(&self) -> &[Linter<Resource>] {
+        &self.warnings[..]
+    }
 }
 
 impl ResourceStats {
@@ -271,7 +347,7 @@ impl ResourceStats {
             .try_into()
             .unwrap();
         let dropped_at: Option<SystemTime> = pb.dropped_at.map(|v| v.try_into().unwrap());
-        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap());
+        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap_or_default());
 
         Self {
             created_at,
@@ -303,12 +379,12 @@ impl Kind {
 impl Attribute {
     fn make_formatted(
         styles: &view::Styles,
-        attributes: &mut Vec<Attribute>,
+        attributes: &mut [Attribute],
     ) -> Vec<Vec<Span<'static>>> {
         let key_style = styles.fg(Color::LightBlue).add_modifier(Modifier::BOLD);
         let delim_style = styles.fg(Color::LightBlue).add_modifier(Modifier::DIM);
         let val_style = styles.fg(Color::Yellow);
-        let unit_style = styles.fg(Color::LightBlue);
+        let unit_style = styles.fg(Color::LightBlue).add_modifier(Modifier::ITALIC);
 
         let mut formatted = Vec::with_capacity(attributes.len());
         let attributes = attributes.iter();
@@ -319,11 +395,58 @@ impl Attribute {
                 Span::styled(format!("{}", attr.field.value), val_style),
             ];
 
-            if let Some(unit) = &attr.unit {
-                elems.push(Span::styled(unit.clone(), unit_style))
+            // Units are separated from the value, like `capacity=64 permits`,
+            // and each attribute is followed by a space, like fields are, so
+            // that attributes don't run together.
+            match attr.unit.as_deref() {
+                Some(unit) if !unit.is_empty() => {
+                    elems.push(Span::raw(" "));
+                    elems.push(Span::styled(format!("{} ", unit), unit_style));
+                }
+                _ => elems.push(Span::raw(" ")),
             }
             formatted.push(elems)
         }
         formatted
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{test_util::styles, FieldValue};
+
+    fn attribute(strings: &mut intern::Strings, name: &str, unit: Option<&str>) -> Attribute {
+        Attribute {
+            field: Field {
+                name: strings.string(name.to_owned()),
+                value: FieldValue::U64(64),
+            },
+            unit: unit.map(str::to_owned),
+        }
+    }
+
+    fn text(spans: &[Span<'_>]) -> String {
+        spans.iter().map(|span| span.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn attributes_are_formatted_with_their_units() {
+        let mut strings = intern::Strings::default();
+        let mut attributes = [
+            attribute(&mut strings, "capacity", Some("permits")),
+            attribute(&mut strings, "permits", None),
+            attribute(&mut strings, "waiters", Some("")),
+        ];
+        let formatted = Attribute::make_formatted(&styles(), &mut attributes);
+
+        assert_eq!(text(&formatted[0]), "capacity=64 permits ");
+        let value = &formatted[0][2];
+        let unit = formatted[0].last().unwrap();
+        assert_eq!(unit.content, "permits ");
+        assert_ne!(unit.style, value.style);
+
+        assert_eq!(text(&formatted[1]), "permits=64 ");
+        assert_eq!(text(&formatted[2]), "waiters=64 ");
+    }
+}
@@ -1,18 +1,28 @@
 use crate::{
+    config::{SavedAck, SavedPin, SavedState},
     intern::{self, InternedStr},
-    state::{format_location, Field, Metadata, Visibility},
+    state::{
+        format_location,
+        locations::LocationsState,
+        parse_column,
+        rate::{RateHistory, RateWindow},
+        Field, Metadata, UnknownColumn, Visibility, UNKNOWN_LOCATION,
+    },
     util::Percentage,
     view,
     warnings::Linter,
 };
 use console_api as proto;
 use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     convert::{TryFrom, TryInto},
     rc::{Rc, Weak},
-    time::{Duration, SystemTime},
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
 };
 use tui::{style::Color, text::Span};
 
@@ -21,6 +31,60 @@ pub(crate) struct TasksState {
     tasks: HashMap<u64, Rc<RefCell<Task>>>,
     new_tasks: Vec<TaskRef>,
     pub(crate) linters: Vec<Linter<Task>>,
+    /// The IDs of the tasks that currently have each warning, along with
+    /// each task's score for that warning, keyed by the warning's index in
+    /// `linters`.
+    warned: HashMap<usize, BTreeMap<u64, u64>>,
+    /// The IDs of tasks which have been pinned, and so are never removed by
+    /// `retain_active`, even after they complete.
+    pinned: HashSet<u64>,
+    /// The maximum number of tasks to track, or `None` if there is no limit.
+    max_tasks: Option<usize>,
+    /// Recent spawns and completions at each spawn location.
+    pub(crate) locations: LocationsState,
+    /// Pins saved by a previous session which haven't been restored, since no
+    /// task with the same key has been seen yet.
+    saved_pins: Vec<SavedPin>,
+    /// Acknowledgements saved by a previous session which haven't been
+    /// restored yet.
+    saved_acks: Vec<SavedAck>,
+    /// The total wake count of all tracked tasks, and the time of the update
+    /// it was sampled at, as of the last update.
+    last_sampled_wakes: Option<(u64, SystemTime)>,
+    /// The number of times per second any task was woken between each of the
+    /// most recent updates, oldest first.
+    recent_wake_rates: VecDeque<f64>,
+    /// How many times any task was woken as of each update within the rate
+    /// window.
+    wake_history: RateHistory,
+    /// How far back wake rates are measured over.
+    rate_window: RateWindow,
+    /// The IDs of the tracked tasks spawned by each task, keyed by the ID of
+    /// the task that spawned them.
+    ///
+    /// Children are removed from this when they are removed from `tasks`, but
+    /// a parent's entry is kept for as long as it has tracked children, even
+    /// if the parent itself has been removed.
+    children: HashMap<u64, BTreeSet<u64>>,
+}
+
+/// Identifies a task across sessions, since task IDs change when the target
+/// restarts.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TaskKey {
+    target: String,
+    location: String,
+    name: Option<String>,
+}
+
+/// One bucket of a histogram of durations, in nanoseconds.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct HistogramBucket {
+    /// The shortest duration counted in this bucket.
+    pub(crate) lower: u64,
+    /// The longest duration counted in this bucket.
+    pub(crate) upper: u64,
+    pub(crate) count: u64,
 }
 
 #[derive(Debug, Default)]
@@ -30,19 +94,69 @@ pub(crate) struct Details {
     // pub(crate) last_updated_at: Option<SystemTime>,
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Whether the details of the task being viewed have been received.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum DetailsStatus {
+    /// The details have been requested, but none have been received yet.
+    #[default]
+    Pending,
+    Ready,
+    /// Fetching the details failed, and will be retried at `retry_at` unless
+    /// it is `None`.
+    Failed {
+        error: String,
+        retry_at: Option<Instant>,
+    },
+}
+
+/// A point-in-time copy of a task's stats, which can be serialized and read
+/// back.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TaskSnapshot {
+    pub(crate) id: u64,
+    pub(crate) name: Option<String>,
+    pub(crate) target: String,
+    pub(crate) location: String,
+    pub(crate) total: Duration,
+    pub(crate) busy: Duration,
+    pub(crate) idle: Duration,
+    pub(crate) polls: u64,
+    /// Descriptions of the task's active warnings.
+    pub(crate) warnings: Vec<String>,
+}
+
+/// The number of tracked tasks in each state.
+///
+/// Blocking tasks which haven't completed are counted separately rather than
+/// as running or idle, since they are excluded from the busy and idle totals.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct TaskCounts {
+    pub(crate) total: usize,
+    pub(crate) running: usize,
+    pub(crate) idle: usize,
+    pub(crate) completed: usize,
+    pub(crate) blocking: usize,
+    /// The number of tasks with at least one warning.
+    pub(crate) warned: usize,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
 #[repr(usize)]
 pub(crate) enum SortBy {
     Warns = 0,
     Tid = 1,
     State = 2,
     Name = 3,
+    #[default]
     Total = 4,
     Busy = 5,
     Idle = 6,
     Polls = 7,
-    Target = 8,
-    Location = 9,
+    PollTimes = 8,
+    Activity = 9,
+    WakeRate = 10,
+    Target = 11,
+    Location = 12,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -52,6 +166,15 @@ pub(crate) enum TaskState {
     Running,
 }
 
+/// Whether a task has recently been polled more or less often than it was
+/// before, or tasks have recently been woken more or less often.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum PollTrend {
+    Rising,
+    Falling,
+    Steady,
+}
+
 pub(crate) type TaskRef = Weak<RefCell<Task>>;
 
 #[derive(Debug)]
@@ -65,6 +188,30 @@ pub(crate) struct Task {
     /// Currently active warnings for this task.
     warnings: Vec<Linter<Task>>,
     location: String,
+    /// Whether this task was spawned with `spawn_blocking`.
+    is_blocking: bool,
+    /// The number of times the task was polled in each of the most recent
+    /// updates, oldest first.
+    recent_polls: VecDeque<u64>,
+    /// The task's total poll count as of the last time `recent_polls` was
+    /// sampled, or `None` if it has not been sampled yet.
+    last_sampled_polls: Option<u64>,
+    /// The task's total wake count as of the last time `wake_history` was
+    /// sampled, or `None` if it has not been sampled yet.
+    last_sampled_wakes: Option<u64>,
+    /// How many times the task was woken as of each update within the rate
+    /// window.
+    wake_history: RateHistory,
+    /// The number of times per second the task was woken over the rate
+    /// window, as of the last sample, or `None` if it has been sampled fewer
+    /// than two times.
+    wake_rate: Option<f64>,
+    /// The 99th percentile of the task's poll durations, as of the last time
+    /// its details were received, or `None` if they never have been.
+    poll_p99: Option<Duration>,
+    /// The ID of the task that spawned this task, or `None` if it wasn't
+    /// spawned by a task, or the target doesn't record parents.
+    parent_id: Option<u64>,
 }
 
 #[derive(Debug)]
@@ -91,6 +238,15 @@ struct TaskStats {
     last_wake: Option<SystemTime>,
     /// Total number of times the task has woken itself.
     self_wakes: u64,
+
+    // === poll time stats ===
+    /// The durations of the task's shortest and longest polls, or `None` if
+    /// the target didn't send them.
+    shortest_poll: Option<Duration>,
+    longest_poll: Option<Duration>,
+    /// The total time the task has waited to be polled after being woken, or
+    /// `None` if the target didn't send it.
+    scheduled: Option<Duration>,
 }
 
 impl TasksState {
@@ -99,6 +255,39 @@ impl TasksState {
         self.new_tasks.drain(..)
     }
 
+    /// Returns every task which has not been pruned.
+    pub(crate) fn all_tasks(&self) -> impl ExactSizeIterator<Item = TaskRef> + '_ {
+        self.tasks.values().map(Rc::downgrade)
+    }
+
+    /// Returns a snapshot of every task's stats as of `now`, ordered by task
+    /// ID.
+    pub(crate) fn snapshot(&self, now: SystemTime) -> Vec<TaskSnapshot> {
+        let mut tasks = self
+            .tasks
+            .values()
+            .map(|task| task.borrow().snapshot(now))
+            .collect::<Vec<_>>();
+        tasks.sort_unstable_by_key(|task| task.id);
+        tasks
+    }
+
+    /// Returns a snapshot of the stats of the tasks with the given IDs as of
+    /// `now`, in the order of `ids`.
+    ///
+    /// Tasks which no longer exist are skipped.
+    pub(crate) fn snapshot_of(&self, ids: &[u64], now: SystemTime) -> Vec<TaskSnapshot> {
+        ids.iter()
+            .filter_map(|id| self.tasks.get(id))
+            .map(|task| task.borrow().snapshot(now))
+            .collect()
+    }
+
+    /// Returns the task with the given ID, if it exists.
+    pub(crate) fn task(&self, id: u64) -> Option<Rc<RefCell<Task>>> {
+        self.tasks.get(&id).cloned()
+    }
+
     pub(crate) fn update_tasks(
         &mut self,
         styles: &view::Styles,
@@ -106,14 +295,31 @@ impl TasksState {
         metas: &HashMap<u64, Metadata>,
         update: proto::tasks::TaskUpdate,
         visibility: Visibility,
+        now: SystemTime,
     ) {
+        let _span = tracing::debug_span!(
+            "update_tasks",
+            new = update.new_tasks.len(),
+            updated = update.stats_update.len(),
+        )
+        .entered();
+
         let mut stats_update = update.stats_update;
         let new_list = &mut self.new_tasks;
+        // The table drains the new list each time it is drawn, but the screen
+        // may be redrawn less often than updates arrive, so only drop entries
+        // that no longer exist, rather than ones the table hasn't seen yet.
         if matches!(visibility, Visibility::Show) {
-            new_list.clear();
+            new_list.retain(|task| task.upgrade().is_some());
         }
 
         let linters = &self.linters;
+        let warned = &mut self.warned;
+        let locations = &mut self.locations;
+        let pinned = &mut self.pinned;
+        let saved_pins = &mut self.saved_pins;
+        let saved_acks = &mut self.saved_acks;
+        let children = &mut self.children;
 
         let new_tasks = update.new_tasks.into_iter().filter_map(|mut task| {
             if task.id.is_none() {
@@ -135,6 +341,7 @@ impl TasksState {
                 }
             };
             let mut name = None;
+            let mut is_blocking = false;
             let mut fields = task
                 .fields
                 .drain(..)
@@ -145,6 +352,11 @@ impl TasksState {
                         name = Some(strings.string(field.value.to_string()));
                         return None;
                     }
+                    if &*field.name == Field::KIND
+                        && field.value.to_string() == Field::KIND_BLOCKING
+                    {
+                        is_blocking = true;
+                    }
                     Some(field)
                 })
                 .collect::<Vec<_>>();
@@ -153,6 +365,8 @@ impl TasksState {
             let id = task.id?.id;
             let stats = stats_update.remove(&id)?.into();
             let location = format_location(task.location);
+            // The first parent is the task this task was spawned by.
+            let parent_id = task.parents.first().map(|parent| parent.id);
 
             let mut task = Task {
                 name,
@@ -163,8 +377,22 @@ impl TasksState {
                 target: meta.target.clone(),
                 warnings: Vec::new(),
                 location,
+                is_blocking,
+                recent_polls: VecDeque::with_capacity(Task::POLL_SAMPLES),
+                last_sampled_polls: None,
+                last_sampled_wakes: None,
+                wake_history: RateHistory::default(),
+                wake_rate: None,
+                poll_p99: None,
+                parent_id,
             };
-            task.lint(linters);
+            if let Some(parent_id) = parent_id {
+                children.entry(parent_id).or_default().insert(id);
+            }
+            restore_saved(saved_pins, saved_acks, pinned, linters, &task);
+            task.lint(linters, now);
+            record_warnings(warned, linters, &task);
+            locations.record_spawn(&task, now);
             let task = Rc::new(RefCell::new(task));
             new_list.push(Rc::downgrade(&task));
             Some((id, task))
@@ -174,28 +402,516 @@ impl TasksState {
             if let Some(task) = self.tasks.get_mut(&id) {
                 let mut task = task.borrow_mut();
                 tracing::trace!(?task, "processing stats update for");
+                let was_completed = task.is_completed();
                 task.stats = stats.into();
-                task.lint(linters);
+                if !was_completed {
+                    self.locations.record_completion(&task, now);
+                }
+                task.lint(linters, now);
+                record_warnings(warned, linters, &task);
+            }
+        }
+
+        // Some warnings can start applying to a task without it being
+        // updated, such as a task that is never polled, so those tasks are
+        // linted on every update.
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            if linters.iter().any(|linter| linter.needs_recheck(&task)) {
+                task.lint(linters, now);
+                record_warnings(warned, linters, &task);
             }
         }
+
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            if !task.is_completed() {
+                task.sample_polls();
+                task.sample_wakes(now, self.rate_window);
+            }
+        }
+
+        self.sample_wakes(now);
+        self.locations.update(now);
+        self.evict_over_max();
+    }
+
+    /// Records the rate at which all tasks were woken since the last update.
+    ///
+    /// When tasks are removed, the total wake count can go down, since the
+    /// removed tasks' wakes are no longer counted. The change is clamped to
+    /// zero rather than counted as negative wakes.
+    fn sample_wakes(&mut self, now: SystemTime) {
+        let wakes = self.tasks.values().map(|task| task.borrow().wakes()).sum();
+        let last = self.last_sampled_wakes.replace((wakes, now));
+        let new_wakes = last.map_or(0, |(last_wakes, _)| wakes.saturating_sub(last_wakes));
+        self.wake_history.record(new_wakes, now, self.rate_window);
+        if let Some((last_wakes, last_sampled_at)) = last {
+            match now.duration_since(last_sampled_at) {
+                Ok(elapsed) if elapsed > Duration::ZERO => {
+                    if self.recent_wake_rates.len() == Task::POLL_SAMPLES {
+                        self.recent_wake_rates.pop_front();
+                    }
+                    let rate = wakes.saturating_sub(last_wakes) as f64 / elapsed.as_secs_f64();
+                    self.recent_wake_rates.push_back(rate);
+                }
+                // If no time has passed, keep the last rate.
+                Ok(_) => {}
+                // If the target's clock went backwards, earlier rates can't be
+                // compared with later ones.
+                Err(_) => self.recent_wake_rates.clear(),
+            }
+        }
+    }
+
+    /// Returns the number of times per second any task was woken over the
+    /// rate window, or `None` if there haven't been enough updates yet.
+    pub(crate) fn wake_rate(&self) -> Option<f64> {
+        self.wake_history.rate(self.rate_window)
+    }
+
+    pub(crate) fn rate_window(&self) -> RateWindow {
+        self.rate_window
+    }
+
+    /// Sets how far back wake rates are measured over, and measures every
+    /// task's wake rate over it.
+    ///
+    /// Only the updates already sampled are used, so a longer window is only
+    /// filled in as more updates are received.
+    pub(crate) fn set_rate_window(&mut self, window: RateWindow) {
+        self.rate_window = window;
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            task.wake_rate = task.wake_history.rate(window);
+        }
+    }
+
+    /// Returns whether tasks were woken more or less often in the last few
+    /// updates than in the few updates before those, or `None` if there
+    /// haven't been enough updates yet.
+    ///
+    /// This uses the same thresholds as [`Task::poll_trend`].
+    pub(crate) fn wake_trend(&self) -> Option<PollTrend> {
+        let len = self.recent_wake_rates.len();
+        if len < Task::TREND_SAMPLES * 2 {
+            return None;
+        }
+        let sum = |skip: usize| -> f64 {
+            self.recent_wake_rates
+                .iter()
+                .skip(skip)
+                .take(Task::TREND_SAMPLES)
+                .sum()
+        };
+        let previous = sum(len - Task::TREND_SAMPLES * 2);
+        let recent = sum(len - Task::TREND_SAMPLES);
+        let trend = if recent * 4.0 > previous * 5.0 {
+            PollTrend::Rising
+        } else if recent * 5.0 < previous * 4.0 {
+            PollTrend::Falling
+        } else {
+            PollTrend::Steady
+        };
+        Some(trend)
+    }
+
+    /// Sets the maximum number of tasks to track, or `None` to track tasks
+    /// without limit.
+    pub(crate) fn set_max_tasks(&mut self, max_tasks: Option<usize>) {
+        self.max_tasks = max_tasks;
+    }
+
+    /// Forgets every task, keeping the linters and the maximum number of
+    /// tasks.
+    ///
+    /// Pins and acknowledgements are kept the same way as between sessions,
+    /// so that they are restored once tasks with the same keys are seen.
+    pub(crate) fn reset(&mut self) {
+        let (pins, acks) = (self.saved_pins(), self.saved_acks());
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+            linter.reset();
+        }
+        self.locations.reset();
+        *self = Self {
+            linters: std::mem::take(&mut self.linters),
+            max_tasks: self.max_tasks,
+            locations: std::mem::take(&mut self.locations),
+            rate_window: self.rate_window,
+            ..Self::default()
+        };
+        self.set_saved(pins, acks);
+    }
+
+    /// If more tasks are tracked than the maximum, removes completed tasks,
+    /// in the order they completed, until there are no more than the maximum.
+    ///
+    /// Running and idle tasks are never removed, even if that leaves more
+    /// tasks than the maximum, and neither are pinned tasks.
+    fn evict_over_max(&mut self) {
+        let max_tasks = match self.max_tasks {
+            Some(max_tasks) if self.tasks.len() > max_tasks => max_tasks,
+            _ => return,
+        };
+
+        let mut completed = self
+            .tasks
+            .iter()
+            .filter(|(id, _)| !self.pinned.contains(id))
+            .filter_map(|(&id, task)| Some((task.borrow().stats.dropped_at?, id)))
+            .collect::<Vec<_>>();
+        completed.sort_unstable();
+
+        let evicted = completed.len().min(self.tasks.len() - max_tasks);
+        for (_, id) in &completed[..evicted] {
+            if let Some(task) = self.tasks.remove(id) {
+                forget_child(&mut self.children, &task.borrow());
+            }
+            for ids in self.warned.values_mut() {
+                ids.remove(id);
+            }
+            for linter in &self.linters {
+                linter.forget(*id);
+            }
+        }
+
+        if evicted > 0 {
+            tracing::info!(
+                evicted,
+                tasks.len = self.tasks.len(),
+                max_tasks,
+                "evicted the oldest completed tasks over the maximum",
+            );
+        }
     }
 
     pub(crate) fn retain_active(&mut self, now: SystemTime, retain_for: Duration) {
-        self.tasks.retain(|_, task| {
+        let warned = &mut self.warned;
+        let pinned = &self.pinned;
+        let children = &mut self.children;
+        let linters = &self.linters;
+        self.tasks.retain(|id, task| {
+            if pinned.contains(id) {
+                return true;
+            }
             let task = task.borrow();
 
-            task.stats
+            let retain = task
+                .stats
                 .dropped_at
                 .map(|d| {
-                    let dropped_for = now.duration_since(d).unwrap();
-                    retain_for > dropped_for
+                    // If the target's clock has gone back to before `d`, it hasn't
+                    // been dropped for long enough to be pruned yet.
+                    now.duration_since(d)
+                        .map_or(true, |dropped_for| retain_for > dropped_for)
                 })
-                .unwrap_or(true)
+                .unwrap_or(true);
+            if !retain {
+                for ids in warned.values_mut() {
+                    ids.remove(id);
+                }
+                for linter in linters {
+                    linter.forget(*id);
+                }
+                forget_child(children, &task);
+            }
+            retain
         })
     }
 
+    /// Returns how many tracked tasks have each name, so that tasks which
+    /// share a name can be told apart. Tasks without names aren't counted.
+    pub(crate) fn name_counts(&self) -> HashMap<InternedStr, usize> {
+        let mut counts = HashMap::new();
+        for task in self.tasks.values() {
+            if let Some(ref name) = task.borrow().name {
+                *counts.entry(name.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the IDs of the tracked tasks spawned by the task with the given
+    /// ID, ordered by ID.
+    pub(crate) fn children(&self, task_id: u64) -> impl Iterator<Item = u64> + '_ {
+        self.children.get(&task_id).into_iter().flatten().copied()
+    }
+
+    /// Pins the task with the given ID if it isn't pinned, or unpins it if it
+    /// is. Pinned tasks are displayed until they are unpinned, regardless of
+    /// how long ago they completed.
+    pub(crate) fn toggle_pinned(&mut self, task_id: u64) {
+        if !self.pinned.remove(&task_id) && self.tasks.contains_key(&task_id) {
+            self.pinned.insert(task_id);
+        }
+    }
+
+    /// Unpins every pinned task, so that completed tasks are removed once
+    /// the retention period has passed, returning the IDs of the tasks which
+    /// were pinned.
+    pub(crate) fn unpin_all(&mut self) -> HashSet<u64> {
+        std::mem::take(&mut self.pinned)
+    }
+
+    /// Pins each of the tasks with the given IDs which are still tracked, such
+    /// as to restore the pins removed by [`TasksState::unpin_all`].
+    pub(crate) fn pin_all(&mut self, task_ids: impl IntoIterator<Item = u64>) {
+        let tasks = &self.tasks;
+        self.pinned
+            .extend(task_ids.into_iter().filter(|id| tasks.contains_key(id)));
+    }
+
+    /// Returns the number of pinned tasks.
+    pub(crate) fn pinned_count(&self) -> usize {
+        self.pinned.len()
+    }
+
+    pub(crate) fn is_pinned(&self, task_id: u64) -> bool {
+        self.pinned.contains(&task_id)
+    }
+
+    /// Sets the pins and acknowledgements saved by a previous session, which
+    /// are restored as tasks with the same keys are seen.
+    ///
+    /// Any which haven't matched a task for [`SavedState::FORGET_AFTER`] are
+    /// forgotten.
+    pub(crate) fn set_saved(&mut self, pins: Vec<SavedPin>, acks: Vec<SavedAck>) {
+        let cutoff =
+            unix_secs(SystemTime::now()).saturating_sub(SavedState::FORGET_AFTER.as_secs());
+        self.saved_pins = pins
+            .into_iter()
+            .filter(|pin| pin.last_seen >= cutoff)
+            .collect();
+        self.saved_acks = acks
+            .into_iter()
+            .filter(|ack| ack.last_seen >= cutoff)
+            .collect();
+    }
+
+    /// Returns the pinned tasks, to be saved for the next session, along with
+    /// any saved pins which haven't been restored yet.
+    pub(crate) fn saved_pins(&self) -> Vec<SavedPin> {
+        let last_seen = unix_secs(SystemTime::now());
+        let mut pinned = self.pinned.iter().copied().collect::<Vec<_>>();
+        pinned.sort_unstable();
+        pinned
+            .into_iter()
+            .filter_map(|id| {
+                let task = self.tasks.get(&id)?.borrow().key();
+                Some(SavedPin { last_seen, task })
+            })
+            .chain(self.saved_pins.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the acknowledged warnings of every task, to be saved for the
+    /// next session, along with any saved acknowledgements which haven't been
+    /// restored yet.
+    pub(crate) fn saved_acks(&self) -> Vec<SavedAck> {
+        let last_seen = unix_secs(SystemTime::now());
+        let mut acks = Vec::new();
+        for linter in &self.linters {
+            for (id, metric) in linter.acknowledgements() {
+                if let Some(task) = self.tasks.get(&id) {
+                    acks.push(SavedAck {
+                        last_seen,
+                        warning: linter.summary().to_owned(),
+                        metric,
+                        task: task.borrow().key(),
+                    });
+                }
+            }
+        }
+        acks.extend(self.saved_acks.iter().cloned());
+        acks
+    }
+
     pub(crate) fn warnings(&self) -> impl Iterator<Item = &Linter<Task>> {
-        self.linters.iter().filter(|linter| linter.count() > 0)
+        self.linters.iter().filter(|linter| linter.is_active())
+    }
+
+    /// Returns the summary of each warning that currently applies to any
+    /// tasks, along with the IDs of those tasks.
+    ///
+    /// The tasks are ranked by their [score] for the warning, with the worst
+    /// offenders first. Tasks with the same score are in ascending order of
+    /// ID.
+    ///
+    /// [score]: crate::warnings::Warn::score
+    pub(crate) fn warnings_overview(&self) -> impl Iterator<Item = (&str, Vec<u64>)> + '_ {
+        self.linters
+            .iter()
+            .enumerate()
+            .filter_map(move |(idx, linter)| {
+                let scores = self.warned.get(&idx).filter(|scores| !scores.is_empty())?;
+                let mut ranked = scores.iter().collect::<Vec<_>>();
+                // The scores are iterated in order of ID, so a stable sort
+                // keeps tasks with the same score in that order.
+                ranked.sort_by_key(|&(_, score)| cmp::Reverse(*score));
+                Some((
+                    linter.summary(),
+                    ranked.into_iter().map(|(&id, _)| id).collect(),
+                ))
+            })
+    }
+
+    /// Returns the number of tasks with the warning `linter` at each spawn
+    /// location, with the most common locations first.
+    pub(crate) fn warning_locations(&self, linter: &Linter<Task>) -> Vec<(String, usize)> {
+        let mut locations = HashMap::<&str, usize>::new();
+        let tasks = self
+            .tasks
+            .values()
+            .map(|task| task.borrow())
+            .collect::<Vec<_>>();
+        for task in &tasks {
+            if task.warnings().iter().any(|warning| warning.is(linter)) {
+                *locations.entry(task.location()).or_default() += 1;
+            }
+        }
+
+        let mut locations = locations
+            .into_iter()
+            .map(|(location, count)| (location.to_owned(), count))
+            .collect::<Vec<_>>();
+        locations
+            .sort_unstable_by(|(a_loc, a), (b_loc, b)| b.cmp(a).then_with(|| a_loc.cmp(b_loc)));
+        locations
+    }
+
+    /// Returns up to `k` of the most common spawn locations of the tasks with
+    /// the warning `linter`, along with how many other locations there are.
+    pub(crate) fn top_warning_locations(
+        &self,
+        linter: &Linter<Task>,
+        k: usize,
+    ) -> (Vec<(String, usize)>, usize) {
+        let mut locations = self.warning_locations(linter);
+        let rest = locations.len().saturating_sub(k);
+        locations.truncate(k);
+        (locations, rest)
+    }
+
+    /// Replaces the linters tasks are checked with, and re-lints every task,
+    /// so that the warnings tasks hold from the previous linters are dropped.
+    ///
+    /// Acknowledgements belong to the linters, so they are forgotten.
+    pub(crate) fn set_linters(&mut self, linters: Vec<Linter<Task>>, now: SystemTime) {
+        self.linters = linters;
+        self.warned.clear();
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            task.lint(&self.linters, now);
+            record_warnings(&mut self.warned, &self.linters, &task);
+        }
+    }
+
+    /// Clears all acknowledged warnings, so that they are displayed again.
+    pub(crate) fn clear_acknowledged(&mut self, now: SystemTime) {
+        for linter in &self.linters {
+            linter.clear_acknowledged();
+        }
+        // Tasks are only linted when their stats change, so re-lint every
+        // task now, rather than waiting for them to change.
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            task.lint(&self.linters, now);
+            record_warnings(&mut self.warned, &self.linters, &task);
+        }
+    }
+
+    /// Acknowledges the warnings of each linter for the tasks listed at that
+    /// linter's index in `acks`, with the metrics listed with them, and
+    /// re-lints every task.
+    pub(crate) fn restore_acknowledged(&mut self, acks: &[Vec<(u64, u64)>], now: SystemTime) {
+        for (linter, acks) in self.linters.iter().zip(acks) {
+            for &(id, metric) in acks {
+                linter.acknowledge_with(id, metric);
+            }
+        }
+        for task in self.tasks.values() {
+            let mut task = task.borrow_mut();
+            task.lint(&self.linters, now);
+            record_warnings(&mut self.warned, &self.linters, &task);
+        }
+    }
+
+    /// Records the 99th percentile of the poll durations of the task with the
+    /// given ID, from its details, and re-lints the task.
+    ///
+    /// Details are only sent for the task being inspected, so this is the only
+    /// task whose poll percentiles are kept up to date.
+    pub(crate) fn update_poll_p99(&mut self, task_id: u64, p99: Duration, now: SystemTime) {
+        if let Some(task) = self.tasks.get(&task_id) {
+            let mut task = task.borrow_mut();
+            if task.poll_p99 == Some(p99) {
+                return;
+            }
+            task.poll_p99 = Some(p99);
+            task.lint(&self.linters, now);
+            record_warnings(&mut self.warned, &self.linters, &task);
+        }
+    }
+
+    /// Acknowledges each of the current warnings for the task with the given
+    /// ID, so that they are suppressed until the values they're based on
+    /// change.
+    pub(crate) fn acknowledge_warnings(&mut self, task_id: u64) {
+        if let Some(task) = self.tasks.get(&task_id) {
+            let mut task = task.borrow_mut();
+            task.acknowledge_warnings();
+            record_warnings(&mut self.warned, &self.linters, &task);
+        }
+    }
+
+    /// Returns the total time spent busy by all tracked tasks, excluding
+    /// blocking tasks.
+    pub(crate) fn total_busy(&self, now: SystemTime) -> Duration {
+        self.non_blocking_tasks().map(|task| task.busy(now)).sum()
+    }
+
+    /// Returns the total time spent idle by all tracked tasks, excluding
+    /// blocking tasks.
+    pub(crate) fn total_idle(&self, now: SystemTime) -> Duration {
+        self.non_blocking_tasks().map(|task| task.idle(now)).sum()
+    }
+
+    /// Returns the number of tracked blocking tasks.
+    pub(crate) fn blocking_count(&self) -> usize {
+        self.tasks
+            .values()
+            .filter(|task| task.borrow().is_blocking())
+            .count()
+    }
+
+    /// Returns the number of tracked tasks in each state.
+    pub(crate) fn counts(&self) -> TaskCounts {
+        let mut counts = TaskCounts::default();
+        for task in self.tasks.values() {
+            let task = task.borrow();
+            counts.total += 1;
+            match task.state() {
+                TaskState::Completed => counts.completed += 1,
+                _ if task.is_blocking() => counts.blocking += 1,
+                TaskState::Running => counts.running += 1,
+                TaskState::Idle => counts.idle += 1,
+            }
+            if !task.warnings().is_empty() {
+                counts.warned += 1;
+            }
+        }
+        counts
+    }
+
+    // Blocking tasks are excluded from the busy and idle totals, since they
+    // are only polled once, and are never idle in the same sense as an async
+    // task waiting to be woken.
+    fn non_blocking_tasks(&self) -> impl Iterator<Item = std::cell::Ref<'_, Task>> + '_ {
+        self.tasks
+            .values()
+            .map(|task| task.borrow())
+            .filter(|task| !task.is_blocking())
     }
 }
 
@@ -207,9 +923,37 @@ impl Details {
     pub(crate) fn poll_times_histogram(&self) -> Option<&Histogram<u64>> {
         self.poll_times_histogram.as_ref()
     }
+
+    /// Returns the buckets of the poll times histogram which any polls were
+    /// counted in, shortest first.
+    pub(crate) fn poll_time_buckets(&self) -> Vec<HistogramBucket> {
+        let histogram = match self.poll_times_histogram() {
+            Some(histogram) => histogram,
+            None => return Vec::new(),
+        };
+        histogram
+            .iter_recorded()
+            .map(|value| {
+                let value_at = value.value_iterated_to();
+                HistogramBucket {
+                    lower: histogram.lowest_equivalent(value_at),
+                    upper: histogram.highest_equivalent(value_at),
+                    count: value.count_at_value(),
+                }
+            })
+            .collect()
+    }
 }
 
 impl Task {
+    /// The number of recent updates for which the task's poll counts are
+    /// kept.
+    pub(crate) const POLL_SAMPLES: usize = 16;
+
+    /// The number of the most recent poll samples which are compared with the
+    /// samples before them to decide a task's [`PollTrend`].
+    const TREND_SAMPLES: usize = 4;
+
     pub(crate) fn id(&self) -> u64 {
         self.id
     }
@@ -218,6 +962,12 @@ impl Task {
         &self.target
     }
 
+    /// Returns the ID of the task which spawned this one, if it was spawned
+    /// by a task.
+    pub(crate) fn parent_id(&self) -> Option<u64> {
+        self.parent_id
+    }
+
     pub(crate) fn name(&self) -> Option<&str> {
         self.name.as_ref().map(AsRef::as_ref)
     }
@@ -231,6 +981,11 @@ impl Task {
         self.stats.last_poll_started > self.stats.last_poll_ended
     }
 
+    /// Returns `true` if this task was spawned with `spawn_blocking`.
+    pub(crate) fn is_blocking(&self) -> bool {
+        self.is_blocking
+    }
+
     pub(crate) fn is_completed(&self) -> bool {
         self.stats.total.is_some()
     }
@@ -247,27 +1002,47 @@ impl Task {
         TaskState::Idle
     }
 
-    pub(crate) fn total(&self, since: SystemTime) -> Duration {
+    /// Returns how long the task has been in its current [`TaskState`], at
+    /// the time `now`.
+    ///
+    /// This is measured from the task's poll timestamps, so a task whose
+    /// state just changed has been in it for close to zero time. A completed
+    /// task is idle between the end of its last poll and being dropped, so
+    /// this returns how long it was idle before completing, which no longer
+    /// changes.
+    pub(crate) fn time_in_state(&self, now: SystemTime) -> Duration {
+        let since = match self.stats.last_poll_started {
+            Some(started) if self.is_running() && !self.is_completed() => started,
+            _ => self.stats.last_poll_ended.unwrap_or(self.stats.created_at),
+        };
         self.stats
-            .total
-            .unwrap_or_else(|| since.duration_since(self.stats.created_at).unwrap())
+            .dropped_at
+            .unwrap_or(now)
+            .duration_since(since)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn total(&self, since: SystemTime) -> Duration {
+        self.stats.total.unwrap_or_else(|| {
+            since
+                .duration_since(self.stats.created_at)
+                .unwrap_or_default()
+        })
     }
 
     pub(crate) fn busy(&self, since: SystemTime) -> Duration {
-        if let (Some(last_poll_started), None) =
-            (self.stats.last_poll_started, self.stats.last_poll_ended)
-        {
-            // in this case the task is being polled at the moment
-            let current_time_in_poll = since.duration_since(last_poll_started).unwrap();
-            return self.stats.busy + current_time_in_poll;
-        }
-        self.stats.busy
+        super::busy_including_current_poll(
+            self.stats.busy,
+            self.stats.last_poll_started,
+            self.stats.last_poll_ended,
+            since,
+        )
     }
 
     pub(crate) fn idle(&self, since: SystemTime) -> Duration {
         self.stats
             .idle
-            .unwrap_or_else(|| self.total(since) - self.busy(since))
+            .unwrap_or_else(|| self.total(since).saturating_sub(self.busy(since)))
     }
 
     /// Returns the total number of times the task has been polled.
@@ -275,20 +1050,122 @@ impl Task {
         self.stats.polls
     }
 
-    /// Returns the elapsed time since the task was last woken, relative to
-    /// given `now` timestamp.
+    /// Returns the average duration of the task's polls, or `None` if it has
+    /// never been polled.
+    pub(crate) fn average_poll(&self, since: SystemTime) -> Option<Duration> {
+        if self.stats.polls == 0 {
+            return None;
+        }
+        let polls = u32::try_from(self.stats.polls).unwrap_or(u32::MAX);
+        Some(self.busy(since) / polls)
+    }
+
+    /// Returns the durations of the task's shortest and longest polls, or
+    /// `None` if the target doesn't send them or no poll has completed yet.
+    pub(crate) fn poll_range(&self) -> Option<(Duration, Duration)> {
+        self.stats.shortest_poll.zip(self.stats.longest_poll)
+    }
+
+    /// Returns the number of times the task was polled in each of the most
+    /// recent updates (up to [`Task::POLL_SAMPLES`] of them), oldest first.
+    pub(crate) fn recent_polls(&self) -> &VecDeque<u64> {
+        &self.recent_polls
+    }
+
+    /// Returns whether the task was polled more or less often in its last few
+    /// samples than in the few samples before those, or `None` if the task
+    /// has completed or hasn't been sampled enough times yet.
+    pub(crate) fn poll_trend(&self) -> Option<PollTrend> {
+        let len = self.recent_polls.len();
+        if self.is_completed() || len < Self::TREND_SAMPLES * 2 {
+            return None;
+        }
+        let sum = |skip: usize| -> u64 {
+            self.recent_polls
+                .iter()
+                .skip(skip)
+                .take(Self::TREND_SAMPLES)
+                .sum()
+        };
+        let previous = sum(len - Self::TREND_SAMPLES * 2);
+        let recent = sum(len - Self::TREND_SAMPLES);
+        // Changes of less than a quarter are treated as steady, so that the
+        // trend doesn't flip back and forth on every update.
+        let trend = if recent * 4 > previous * 5 {
+            PollTrend::Rising
+        } else if recent * 5 < previous * 4 {
+            PollTrend::Falling
+        } else {
+            PollTrend::Steady
+        };
+        Some(trend)
+    }
+
+    /// Records the number of times the task was polled since the last sample.
     ///
-    /// Returns `None` if the task has never been woken, or if it was last woken
-    /// more recently than `now` (which *shouldn't* happen as long as `now` is the
-    /// timestamp of the last stats update...)
-    pub(crate) fn since_wake(&self, now: SystemTime) -> Option<Duration> {
-        now.duration_since(self.last_wake()?).ok()
+    /// The first sample taken for a task only records its poll count, since
+    /// the task may have been polled any number of times before the console
+    /// connected.
+    fn sample_polls(&mut self) {
+        let polls = self.stats.polls;
+        if let Some(last) = self.last_sampled_polls.replace(polls) {
+            if self.recent_polls.len() == Self::POLL_SAMPLES {
+                self.recent_polls.pop_front();
+            }
+            self.recent_polls.push_back(polls.saturating_sub(last));
+        }
+    }
+
+    /// Returns the number of times per second the task was woken over the
+    /// rate window, or `None` if the task has completed or hasn't been sampled
+    /// enough times yet.
+    pub(crate) fn wake_rate(&self) -> Option<f64> {
+        if self.is_completed() {
+            return None;
+        }
+        self.wake_rate
+    }
+
+    /// Records how many times the task was woken since the last sample, and
+    /// measures its wake rate over `window`.
+    ///
+    /// As with [`Task::sample_polls`], the first sample only records the
+    /// task's wake count. If the wake count went backwards, there's no
+    /// meaningful rate, so counting starts over from this sample.
+    fn sample_wakes(&mut self, now: SystemTime, window: RateWindow) {
+        let wakes = self.stats.wakes;
+        match self.last_sampled_wakes.replace(wakes) {
+            Some(last_wakes) if wakes >= last_wakes => {
+                self.wake_history.record(wakes - last_wakes, now, window);
+            }
+            Some(_) => {
+                self.wake_history.clear();
+                self.wake_history.record(0, now, window);
+            }
+            None => self.wake_history.record(0, now, window),
+        }
+        self.wake_rate = self.wake_history.rate(window);
+    }
+
+    /// Returns the 99th percentile of the task's poll durations, if its
+    /// details have been received.
+    pub(crate) fn poll_p99(&self) -> Option<Duration> {
+        self.poll_p99
     }
 
     pub(crate) fn last_wake(&self) -> Option<SystemTime> {
         self.stats.last_wake
     }
 
+    pub(crate) fn created_at(&self) -> SystemTime {
+        self.stats.created_at
+    }
+
+    /// Returns when the task was dropped, or `None` if it hasn't completed.
+    pub(crate) fn dropped_at(&self) -> Option<SystemTime> {
+        self.stats.dropped_at
+    }
+
     /// Returns the current number of wakers for this task.
     pub(crate) fn waker_count(&self) -> u64 {
         self.waker_clones().saturating_sub(self.waker_drops())
@@ -309,12 +1186,47 @@ impl Task {
         self.stats.wakes
     }
 
+    /// Returns the total time this task has spent waiting to be polled after
+    /// being woken, as of `now`, or `None` if the target doesn't record it.
+    ///
+    /// If the task has been woken and is still waiting to be polled, the time
+    /// since it was last woken is included.
+    pub(crate) fn scheduled(&self, now: SystemTime) -> Option<Duration> {
+        let scheduled = self.stats.scheduled?;
+        let waiting = match self.last_wake() {
+            Some(woken)
+                if !self.is_completed()
+                    && self.total_polls() > 0
+                    && self.is_awakened()
+                    && !self.is_running() =>
+            {
+                now.duration_since(woken).unwrap_or_default()
+            }
+            _ => Duration::ZERO,
+        };
+        Some(scheduled + waiting)
+    }
+
+    /// Returns the average time this task has waited to be polled after each
+    /// wake, as of `now`, or `None` if it has never been woken or the target
+    /// doesn't record how long it waits.
+    pub(crate) fn scheduled_per_wake(&self, now: SystemTime) -> Option<Duration> {
+        let wakes = self.wakes();
+        if wakes == 0 {
+            return None;
+        }
+        let nanos = self.scheduled(now)?.as_nanos() / u128::from(wakes);
+        Some(Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX)))
+    }
+
     /// Returns the total number of times this task has woken itself.
     pub(crate) fn self_wakes(&self) -> u64 {
         self.stats.self_wakes
     }
 
-    /// Returns the percentage of this task's total wakeups that were self-wakes.
+    /// Returns the percentage of this task's wakeups which were self-wakes.
+    ///
+    /// If the task has never been woken, this is 0.
     pub(crate) fn self_wake_percent(&self) -> u64 {
         self.self_wakes().percent_of(self.wakes())
     }
@@ -332,30 +1244,80 @@ impl Task {
         &self.warnings[..]
     }
 
-    fn lint(&mut self, linters: &[Linter<Task>]) {
-        self.warnings.clear();
-        for lint in linters {
-            tracing::debug!(?lint, task = ?self, "checking...");
-            if let Some(warning) = lint.check(self) {
-                tracing::info!(?warning, task = ?self, "found a warning!");
-                self.warnings.push(warning)
-            }
+    fn snapshot(&self, now: SystemTime) -> TaskSnapshot {
+        TaskSnapshot {
+            id: self.id,
+            name: self.name().map(String::from),
+            target: self.target().to_owned(),
+            location: self.location.trim_end().to_owned(),
+            total: self.total(now),
+            busy: self.busy(now),
+            idle: self.idle(now),
+            polls: self.total_polls(),
+            warnings: self
+                .warnings
+                .iter()
+                .map(|warning| warning.format(self, now))
+                .collect(),
         }
     }
 
+    /// Acknowledges each of this task's current warnings, so that they are
+    /// suppressed until the values they're based on change.
+    fn acknowledge_warnings(&mut self) {
+        for warning in &self.warnings {
+            warning.acknowledge(self.id);
+        }
+        self.warnings.clear();
+    }
+
+    fn lint(&mut self, linters: &[Linter<Task>], now: SystemTime) {
+        self.warnings = Linter::lint_all(linters, self.id, self, now);
+    }
+
     pub(crate) fn location(&self) -> &str {
         &self.location
     }
+
+    /// Returns `false` if the target didn't record where this task was
+    /// spawned, in which case [`Task::location`] is empty or a placeholder.
+    pub(crate) fn has_location(&self) -> bool {
+        let location = self.location.trim_end();
+        !(location.is_empty()
+            || location == UNKNOWN_LOCATION
+            || location.eq_ignore_ascii_case("unknown"))
+    }
+
+    /// Returns the key identifying this task across sessions.
+    pub(crate) fn key(&self) -> TaskKey {
+        TaskKey {
+            target: self.target.to_string(),
+            location: self.location.clone(),
+            name: self.name.as_ref().map(|name| name.to_string()),
+        }
+    }
 }
 
 impl From<proto::tasks::Stats> for TaskStats {
     fn from(pb: proto::tasks::Stats) -> Self {
-        fn pb_duration(dur: prost_types::Duration) -> Duration {
-            let secs =
-                u64::try_from(dur.seconds).expect("a task should not have a negative duration!");
-            let nanos =
-                u64::try_from(dur.nanos).expect("a task should not have a negative duration!");
-            Duration::from_secs(secs) + Duration::from_nanos(nanos)
+        // Negative durations are treated as though they were not sent.
+        fn pb_duration(
+            field: &'static str,
+            duration: Option<prost_types::Duration>,
+        ) -> Option<Duration> {
+            let duration = duration?;
+            match (
+                u64::try_from(duration.seconds),
+                u64::try_from(duration.nanos),
+            ) {
+                (Ok(secs), Ok(nanos)) => {
+                    Some(Duration::from_secs(secs) + Duration::from_nanos(nanos))
+                }
+                _ => {
+                    tracing::warn!(field, ?duration, "negative task duration");
+                    None
+                }
+            }
         }
 
         let created_at = pb
@@ -365,11 +1327,11 @@ impl From<proto::tasks::Stats> for TaskStats {
             .unwrap();
 
         let dropped_at: Option<SystemTime> = pb.dropped_at.map(|v| v.try_into().unwrap());
-        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap());
+        let total = dropped_at.map(|d| d.duration_since(created_at).unwrap_or_default());
 
         let poll_stats = pb.poll_stats.expect("task should have poll stats");
-        let busy = poll_stats.busy_time.map(pb_duration).unwrap_or_default();
-        let idle = total.map(|total| total - busy);
+        let busy = pb_duration("busy_time", poll_stats.busy_time).unwrap_or_default();
+        let idle = total.map(|total| total.saturating_sub(busy));
         Self {
             total,
             idle,
@@ -384,49 +1346,49 @@ impl From<proto::tasks::Stats> for TaskStats {
             waker_drops: pb.waker_drops,
             last_wake: pb.last_wake.map(|v| v.try_into().unwrap()),
             self_wakes: pb.self_wakes,
+            shortest_poll: pb_duration("shortest_poll", pb.shortest_poll),
+            longest_poll: pb_duration("longest_poll", pb.longest_poll),
+            scheduled: pb_duration("scheduled_time", pb.scheduled_time),
         }
     }
 }
 
-impl Default for SortBy {
-    fn default() -> Self {
-        Self::Total
-    }
-}
-
 impl SortBy {
-    pub fn sort(&self, now: SystemTime, tasks: &mut Vec<Weak<RefCell<Task>>>) {
+    pub fn sort(&self, now: SystemTime, tasks: &mut [Weak<RefCell<Task>>]) {
         match self {
-            Self::Tid => tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().id)),
-            Self::Name => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().name.clone()))
+            Self::Tid => sort_by_key(tasks, |_| ()),
+            Self::Name => sort_by_key(tasks, |t| t.name.clone()),
+            Self::State => sort_by_key(tasks, |t| t.state()),
+            Self::Warns => sort_by_key(tasks, |t| t.warnings().len()),
+            Self::Total => sort_by_key(tasks, |t| t.total(now)),
+            Self::Idle => sort_by_key(tasks, |t| t.idle(now)),
+            Self::Busy => sort_by_key(tasks, |t| t.busy(now)),
+            Self::Polls => sort_by_key(tasks, |t| t.stats.polls),
+            // The longest poll is what stands out when looking for tasks
+            // which block the executor.
+            Self::PollTimes => sort_by_key(tasks, |t| (t.stats.longest_poll, t.average_poll(now))),
+            Self::Activity => sort_by_key(tasks, |t| t.recent_polls.iter().sum::<u64>()),
+            // Rates aren't `Ord`, so sort by thousandths of a wake per second.
+            Self::WakeRate => {
+                sort_by_key(tasks, |t| t.wake_rate().map(|rate| (rate * 1000.0) as u64))
             }
-            Self::State => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().state()))
-            }
-            Self::Warns => tasks
-                .sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().warnings().len())),
-            Self::Total => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().total(now)))
-            }
-            Self::Idle => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().idle(now)))
-            }
-            Self::Busy => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().busy(now)))
-            }
-            Self::Polls => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().stats.polls))
-            }
-            Self::Target => {
-                tasks.sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().target.clone()))
-            }
-            Self::Location => tasks
-                .sort_unstable_by_key(|task| task.upgrade().map(|t| t.borrow().location.clone())),
+            Self::Target => sort_by_key(tasks, |t| t.target.clone()),
+            Self::Location => sort_by_key(tasks, |t| t.location.clone()),
         }
     }
 }
 
+/// Sorts `tasks` by the key returned by `f`, breaking ties by task ID so that
+/// tasks with equal keys don't change places between updates.
+fn sort_by_key<K: Ord>(tasks: &mut [Weak<RefCell<Task>>], f: impl Fn(&Task) -> K) {
+    tasks.sort_unstable_by_key(|task| {
+        task.upgrade().map(|t| {
+            let t = t.borrow();
+            (f(&t), t.id)
+        })
+    })
+}
+
 impl view::SortBy for SortBy {
     fn as_column(&self) -> usize {
         *self as usize
@@ -445,6 +1407,9 @@ impl TryFrom<usize> for SortBy {
             idx if idx == Self::Busy as usize => Ok(Self::Busy),
             idx if idx == Self::Idle as usize => Ok(Self::Idle),
             idx if idx == Self::Polls as usize => Ok(Self::Polls),
+            idx if idx == Self::PollTimes as usize => Ok(Self::PollTimes),
+            idx if idx == Self::Activity as usize => Ok(Self::Activity),
+            idx if idx == Self::WakeRate as usize => Ok(Self::WakeRate),
             idx if idx == Self::Target as usize => Ok(Self::Target),
             idx if idx == Self::Location as usize => Ok(Self::Location),
             _ => Err(()),
@@ -452,7 +1417,114 @@ impl TryFrom<usize> for SortBy {
     }
 }
 
+impl SortBy {
+    /// The name of each column, as accepted by the `--sort` flag.
+    pub(super) const NAMES: &'static [(SortBy, &'static str)] = &[
+        (Self::Warns, "warns"),
+        (Self::Tid, "tid"),
+        (Self::State, "state"),
+        (Self::Name, "name"),
+        (Self::Total, "total"),
+        (Self::Busy, "busy"),
+        (Self::Idle, "idle"),
+        (Self::Polls, "polls"),
+        (Self::PollTimes, "poll_times"),
+        (Self::Activity, "activity"),
+        (Self::WakeRate, "wake_rate"),
+        (Self::Target, "target"),
+        (Self::Location, "location"),
+    ];
+}
+
+impl FromStr for SortBy {
+    type Err = UnknownColumn;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_column(Self::NAMES, s)
+    }
+}
+
+/// Removes `task` from the children of the task that spawned it, once it is
+/// no longer tracked.
+fn forget_child(children: &mut HashMap<u64, BTreeSet<u64>>, task: &Task) {
+    let parent_id = match task.parent_id {
+        Some(parent_id) => parent_id,
+        None => return,
+    };
+    if let Some(siblings) = children.get_mut(&parent_id) {
+        siblings.remove(&task.id);
+        if siblings.is_empty() {
+            children.remove(&parent_id);
+        }
+    }
+}
+
+/// Restores the first of the pins and acknowledgements saved by a previous
+/// session which match the newly seen `task`, removing them from `saved_pins`
+/// and `saved_acks` so that each is only restored once.
+fn restore_saved(
+    saved_pins: &mut Vec<SavedPin>,
+    saved_acks: &mut Vec<SavedAck>,
+    pinned: &mut HashSet<u64>,
+    linters: &[Linter<Task>],
+    task: &Task,
+) {
+    if saved_pins.is_empty() && saved_acks.is_empty() {
+        return;
+    }
+    let key = task.key();
+    if let Some(idx) = saved_pins.iter().position(|pin| pin.task == key) {
+        saved_pins.remove(idx);
+        pinned.insert(task.id);
+    }
+    for linter in linters {
+        let idx = saved_acks
+            .iter()
+            .position(|ack| ack.task == key && ack.warning == linter.summary());
+        if let Some(idx) = idx {
+            let ack = saved_acks.remove(idx);
+            linter.acknowledge_with(task.id, ack.metric);
+        }
+    }
+}
+
+/// Returns `time` in whole seconds since the Unix epoch.
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Records which of `linters` currently apply to `task` in `warned`, along
+/// with the task's score for each of them.
+fn record_warnings(
+    warned: &mut HashMap<usize, BTreeMap<u64, u64>>,
+    linters: &[Linter<Task>],
+    task: &Task,
+) {
+    for (idx, linter) in linters.iter().enumerate() {
+        let ids = warned.entry(idx).or_default();
+        match task.warnings().iter().find(|warning| warning.is(linter)) {
+            Some(warning) => {
+                ids.insert(task.id, warning.score(task));
+            }
+            None => {
+                ids.remove(&task.id);
+            }
+        }
+    }
+}
+
 impl TaskState {
+    /// Returns the state's name, for plain-text output.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Idle => "idle",
+            Self::Completed => "completed",
+        }
+    }
+
     pub(crate) fn render(self, styles: &crate::view::Styles) -> Span<'static> {
         const RUNNING_UTF8: &str = "\u{25B6}";
         const IDLE_UTF8: &str = "\u{23F8}";
@@ -467,3 +1539,57 @@ impl TaskState {
         }
     }
 }
+
+impl PollTrend {
+    pub(crate) fn render(self, styles: &crate::view::Styles) -> Span<'static> {
+        let color = match self {
+            Self::Rising => Color::LightGreen,
+            Self::Falling => Color::LightRed,
+            Self::Steady => Color::Blue,
+        };
+        Span::styled(self.symbol(styles), styles.fg(color))
+    }
+
+    /// Returns the arrow displayed for this trend, without any styling.
+    pub(crate) fn symbol(self, styles: &crate::view::Styles) -> &'static str {
+        match self {
+            Self::Rising => styles.if_utf8("\u{2191}", "^"),
+            Self::Falling => styles.if_utf8("\u{2193}", "v"),
+            Self::Steady => styles.if_utf8("\u{2192}", "-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_util::{at, task_stats};
+
+    #[test]
+    fn negative_durations_are_ignored() {
+        let negative = Some(prost_types::Duration {
+            seconds: -1,
+            nanos: 0,
+        });
+        let stats = proto::tasks::Stats {
+            shortest_poll: negative.clone(),
+            longest_poll: Some(prost_types::Duration {
+                seconds: 0,
+                nanos: -1,
+            }),
+            scheduled_time: negative.clone(),
+            poll_stats: Some(proto::PollStats {
+                polls: 1,
+                busy_time: negative,
+                ..Default::default()
+            }),
+            ..task_stats(at(0))
+        };
+        let stats = TaskStats::from(stats);
+        assert_eq!(stats.busy, Duration::ZERO);
+        assert_eq!(stats.shortest_poll, None);
+        assert_eq!(stats.longest_poll, None);
+        assert_eq!(stats.scheduled, None);
+        assert_eq!(stats.polls, 1);
+    }
+}
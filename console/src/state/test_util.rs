@@ -0,0 +1,94 @@
+//! Building the updates the target sends, for tests which drive [`State`]
+//! and the stores it owns the same way a connection does.
+//!
+//! [`State`]: super::State
+use super::State;
+use crate::{config, view};
+use clap::Parser;
+use console_api as proto;
+use std::time::{Duration, SystemTime};
+
+/// The ID of the metadata registered by [`update`], which every task and
+/// async op these helpers build refers to.
+pub(crate) const META_ID: u64 = 1;
+
+/// Returns the time `millis` milliseconds after the Unix epoch, so that tests
+/// can use small, readable timestamps.
+pub(crate) fn at(millis: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+pub(crate) fn timestamp(time: SystemTime) -> prost_types::Timestamp {
+    time.into()
+}
+
+pub(crate) fn styles() -> view::Styles {
+    view::Styles::from_config(config::ViewOptions::parse_from(["tokio-console"]))
+}
+
+/// Returns an update sent at `now`, registering the metadata with the ID
+/// [`META_ID`] and the target `test`.
+pub(crate) fn update(now: SystemTime) -> proto::instrument::Update {
+    let metadata = proto::register_metadata::NewMetadata {
+        id: Some(proto::MetaId { id: META_ID }),
+        metadata: Some(proto::Metadata {
+            name: "test".to_owned(),
+            target: "test".to_owned(),
+            ..Default::default()
+        }),
+    };
+    proto::instrument::Update {
+        now: Some(timestamp(now)),
+        new_metadata: Some(proto::RegisterMetadata {
+            metadata: vec![metadata],
+        }),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn task(id: u64) -> proto::tasks::Task {
+    proto::tasks::Task {
+        id: Some(proto::Id { id }),
+        metadata: Some(proto::MetaId { id: META_ID }),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn task_stats(created_at: SystemTime) -> proto::tasks::Stats {
+    proto::tasks::Stats {
+        created_at: Some(timestamp(created_at)),
+        poll_stats: Some(proto::PollStats::default()),
+        ..Default::default()
+    }
+}
+
+/// Sends `state` an update at `now` with `stats` for the task `id`, which is
+/// new if `state` doesn't have it yet.
+pub(crate) fn send_task(state: &mut State, now: SystemTime, id: u64, stats: proto::tasks::Stats) {
+    let new_tasks = if state.tasks_state().task(id).is_some() {
+        Vec::new()
+    } else {
+        vec![task(id)]
+    };
+    let mut update = update(now);
+    update.task_update = Some(proto::tasks::TaskUpdate {
+        new_tasks,
+        stats_update: std::iter::once((id, stats)).collect(),
+    });
+    state.update(&styles(), &view::ViewState::TasksList, update);
+}
+
+pub(crate) fn async_op(id: u64) -> proto::async_ops::AsyncOp {
+    proto::async_ops::AsyncOp {
+        id: Some(proto::Id { id }),
+        metadata: Some(proto::MetaId { id: META_ID }),
+        source: "test::op".to_owned(),
+    }
+}
+
+pub(crate) fn async_op_stats(created_at: SystemTime) -> proto::async_ops::Stats {
+    proto::async_ops::Stats {
+        created_at: Some(timestamp(created_at)),
+        ..Default::default()
+    }
+}
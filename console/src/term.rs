@@ -28,6 +28,41 @@ pub fn init_crossterm() -> color_eyre::Result<(Terminal<CrosstermBackend<io::Std
     Ok((term, cleanup))
 }
 
+/// Restores the terminal to how it was before the console started while `f`
+/// runs, so that another program can use it, and then takes it over again
+/// and clears it, so that the console is redrawn from scratch.
+pub fn suspend<T>(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    f: impl FnOnce() -> T,
+) -> color_eyre::Result<T> {
+    use crossterm::{
+        event::{DisableMouseCapture, EnableMouseCapture},
+        terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    };
+    crossterm::execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .wrap_err("Failed to disable crossterm alternate screen and mouse capture")?;
+    terminal::disable_raw_mode().wrap_err("Failed to disable crossterm raw mode")?;
+    terminal
+        .show_cursor()
+        .wrap_err("Failed to show the cursor")?;
+
+    let result = f();
+
+    terminal::enable_raw_mode().wrap_err("Failed to enable crossterm raw mode")?;
+    crossterm::execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableMouseCapture
+    )
+    .wrap_err("Failed to enable crossterm alternate screen and mouse capture")?;
+    terminal.clear().wrap_err("Failed to clear the terminal")?;
+    Ok(result)
+}
+
 pub struct OnShutdown {
     action: fn() -> color_eyre::Result<()>,
 }
@@ -1,3 +1,5 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 pub(crate) trait Percentage {
     // Using an extension trait for this is maybe a bit excessive, but making it
     // a method has the nice advantage of making it *really* obvious which is
@@ -30,5 +32,128 @@ pub(crate) fn percentage(total: f64, amount: f64) -> f64 {
         total,
         amount
     );
+    // If the total is zero, so is the amount, and dividing would produce NaN.
+    if total == 0.0 {
+        return 0.0;
+    }
     (amount / total) * 100.0
 }
+
+/// Truncates `text` to at most `max_width` columns by replacing its middle
+/// with `ellipsis`, keeping up to `leading` columns from the start of the
+/// text and filling the remaining width from its end.
+///
+/// Widths are display widths, so wide characters, such as CJK, count as two
+/// columns. A wide character which would straddle the cut is left out, so the
+/// result may be a column narrower than `max_width`.
+///
+/// This is useful for paths, where both the crate and the file name are
+/// interesting, like `myapp/…/worker.rs:42`.
+pub(crate) fn truncate_middle<'a>(
+    text: &'a str,
+    max_width: usize,
+    leading: usize,
+    ellipsis: &str,
+) -> std::borrow::Cow<'a, str> {
+    if text.width() <= max_width {
+        return text.into();
+    }
+
+    let ellipsis_width = ellipsis.width();
+    if max_width <= ellipsis_width {
+        let (start, _) = take_width(text.chars(), max_width);
+        return start.into();
+    }
+
+    let available = max_width - ellipsis_width;
+    let leading = std::cmp::min(leading, available);
+    let (start, start_width) = take_width(text.chars(), leading);
+    let (end, _) = take_width(text.chars().rev(), available - start_width);
+    let mut truncated = String::with_capacity(text.len());
+    truncated.push_str(&start);
+    truncated.push_str(ellipsis);
+    truncated.extend(end.chars().rev());
+    truncated.into()
+}
+
+/// Collects the characters from `chars` that fit in `max_width` columns,
+/// returning them along with their width.
+fn take_width(chars: impl Iterator<Item = char>, max_width: usize) -> (String, usize) {
+    let mut taken = String::new();
+    let mut width = 0;
+    for c in chars {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width {
+            break;
+        }
+        width += char_width;
+        taken.push(c);
+    }
+    (taken, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTF8: &str = "\u{2026}";
+    const ASCII: &str = "...";
+
+    #[test]
+    fn truncate_middle_keeps_text_which_fits() {
+        for ellipsis in [UTF8, ASCII] {
+            // Exactly `max_width` columns, but more bytes.
+            assert_eq!(
+                truncate_middle("crâte/wörker.rs", 15, 5, ellipsis),
+                "crâte/wörker.rs"
+            );
+            assert_eq!(truncate_middle("", 0, 0, ellipsis), "");
+        }
+    }
+
+    #[test]
+    fn truncate_middle_replaces_the_middle_of_text_one_over() {
+        let text = "crâte/wörker.rs";
+        assert_eq!(truncate_middle(text, 14, 5, UTF8), "crâte\u{2026}örker.rs");
+        assert_eq!(truncate_middle(text, 14, 5, ASCII), "crâte...ker.rs");
+        for ellipsis in [UTF8, ASCII] {
+            assert_eq!(truncate_middle(text, 14, 5, ellipsis).width(), 14);
+        }
+    }
+
+    #[test]
+    fn truncate_middle_splits_multibyte_text_on_char_boundaries() {
+        // Most characters are several bytes, so byte offsets computed from
+        // character counts would land inside them.
+        let text = "ééééééééé/日本語/ÿÿÿÿ.rs";
+        for ellipsis in [UTF8, ASCII] {
+            for max_width in 0..=text.width() {
+                for leading in 0..=max_width {
+                    let truncated = truncate_middle(text, max_width, leading, ellipsis).width();
+                    // Only a wide character straddling a cut leaves a gap.
+                    assert!(truncated <= max_width && truncated + 1 >= max_width);
+                }
+            }
+        }
+        assert_eq!(truncate_middle(text, 8, 2, UTF8), "éé\u{2026}ÿÿ.rs");
+        assert_eq!(truncate_middle(text, 8, 2, ASCII), "éé....rs");
+    }
+
+    #[test]
+    fn truncate_middle_counts_wide_characters_as_two_columns() {
+        let text = "日本語のパス/ファイル.rs";
+        assert_eq!(truncate_middle(text, 12, 6, UTF8), "日本語\u{2026}ル.rs");
+        assert_eq!(truncate_middle(text, 12, 6, ASCII), "日本語....rs");
+        // A wide character which would straddle the cut is left out.
+        assert_eq!(truncate_middle(text, 11, 5, UTF8), "日本\u{2026}ル.rs");
+        assert_eq!(truncate_middle(text, 11, 5, UTF8).width(), 10);
+        assert_eq!(truncate_middle("日本語", 3, 3, ASCII), "日");
+    }
+
+    #[test]
+    fn truncate_middle_cuts_text_too_narrow_for_the_ellipsis() {
+        assert_eq!(truncate_middle("wörker.rs", 3, 1, ASCII), "wör");
+        assert_eq!(truncate_middle("wörker.rs", 1, 1, UTF8), "w");
+        assert_eq!(truncate_middle("wörker.rs", 2, 1, UTF8), "w\u{2026}");
+    }
+}
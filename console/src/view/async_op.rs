@@ -0,0 +1,191 @@
+use crate::{
+    input::{Action, KeyBindings},
+    state::{async_ops::AsyncOp, State},
+    util::Percentage,
+    view::{
+        self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
+        TimeDisplay,
+    },
+};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+use tui::{
+    layout::{self, Layout},
+    style::Color,
+    text::{Span, Spans},
+    widgets::{List, ListItem, Paragraph},
+};
+
+pub(crate) struct AsyncOpView {
+    /// The async op being inspected.
+    ///
+    /// This keeps the async op alive if it's pruned while the view is open,
+    /// so that its last stats can still be displayed.
+    async_op: Rc<RefCell<AsyncOp>>,
+}
+
+impl AsyncOpView {
+    pub(super) fn new(async_op: Rc<RefCell<AsyncOp>>) -> Self {
+        Self { async_op }
+    }
+
+    /// Returns the ID of the task awaiting the async op, if it's known.
+    pub(crate) fn task_id(&self) -> Option<u64> {
+        self.async_op.borrow().task_id()
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &self,
+        styles: &view::Styles,
+        bindings: &KeyBindings,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &State,
+        time_display: TimeDisplay,
+    ) -> Vec<(Action, bool)> {
+        let now = state
+            .display_now()
+            .expect("async op view implies we've received an update");
+        // Warnings are formatted as of the update they were checked at, even
+        // while the clock is frozen.
+        let lint_now = state.last_updated_at().unwrap_or(now);
+        let async_op = &*self.async_op.borrow();
+        let pruned = !state.async_ops_state().contains(async_op.id());
+
+        let controls = Controls::new(
+            &view_controls(bindings, async_op),
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Hide,
+        );
+
+        let warnings = async_op
+            .warnings()
+            .iter()
+            .map(|linter| {
+                ListItem::new(Spans::from(vec![
+                    styles.warning_wide(),
+                    Span::from(linter.format(async_op, lint_now)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        // add 2 for top and bottom borders
+        let warnings_height = if warnings.is_empty() {
+            0
+        } else {
+            warnings.len() as u16 + 2
+        };
+
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Length(warnings_height),
+                    layout::Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let warnings_area = chunks[1];
+        let stats_area = chunks[2];
+
+        let format_id = |id: Option<u64>| {
+            id.map(|id| id.to_string())
+                .unwrap_or_else(|| "unknown (not yet polled)".to_owned())
+        };
+
+        let mut id = vec![bold("ID: "), Span::raw(async_op.id().to_string())];
+        if pruned {
+            id.push(Span::styled(" (pruned)", styles.terminated()));
+        } else if async_op.dropped() {
+            id.push(Span::styled(" (dropped)", styles.terminated()));
+        } else if async_op.is_polling() {
+            id.push(Span::styled(" (polling)", styles.fg(Color::Green)));
+        }
+
+        let total = async_op.total(now);
+        let dur_percent = |name: &'static str, amt: Duration| -> Spans {
+            let percent = amt.as_secs_f64().percent_of(total.as_secs_f64());
+            Spans::from(vec![
+                bold(name),
+                dur(styles, amt),
+                Span::from(format!(" ({:.2}%)", percent)),
+            ])
+        };
+
+        let mut resource = vec![
+            bold("Resource: "),
+            Span::raw(format_id(async_op.resource_id())),
+        ];
+        if async_op.resource_id().is_some() && !async_op.resource_known(state.resources_state()) {
+            resource.push(Span::styled(" (pruned)", styles.terminated()));
+        }
+
+        let mut overview = vec![
+            Spans::from(id),
+            Spans::from(vec![bold("Source: "), Span::raw(async_op.source())]),
+            Spans::from(vec![bold("Target: "), Span::raw(async_op.target())]),
+            Spans::from(resource),
+            Spans::from(vec![
+                bold("Task: "),
+                Span::raw(format_id(async_op.task_id())),
+            ]),
+            Spans::from(vec![
+                bold("Created: "),
+                Span::raw(time_display.format(now, async_op.created_at())),
+            ]),
+            Spans::from(vec![bold("Total Time: "), dur(styles, total)]),
+            dur_percent("Busy: ", async_op.busy(now)),
+            dur_percent("Idle: ", async_op.idle(now)),
+        ];
+
+        let mut polls = vec![bold("Polls: "), Span::raw(async_op.polls().to_string())];
+        if let Some(since) = async_op.since_last_poll(now) {
+            polls.push(Span::raw(", "));
+            polls.push(bold("last polled: "));
+            polls.push(dur(styles, since));
+            polls.push(Span::raw(" ago"));
+        }
+        overview.push(Spans::from(polls));
+
+        let stats_widget = Paragraph::new(overview).block(styles.border_block().title("Async Op"));
+
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        if !warnings.is_empty() {
+            let block = styles
+                .border_block()
+                .title(Spans::from(vec![bold("Warnings")]));
+            frame.render_widget(List::new(warnings).block(block), warnings_area);
+        }
+        frame.render_widget(stats_widget, stats_area);
+        actions
+    }
+}
+
+fn view_controls(bindings: &KeyBindings, async_op: &AsyncOp) -> Vec<ControlDisplay> {
+    vec![
+        ControlDisplay::new("return to async ops", &[Action::Back], bindings),
+        // The op's task is only known once it has been polled.
+        ControlDisplay::new("view task", &[Action::ViewDetails], bindings)
+            .enabled(async_op.task_id().is_some()),
+        ControlDisplay::new(
+            "absolute/relative times",
+            &[Action::ToggleTimeDisplay],
+            bindings,
+        ),
+        ControlDisplay::new(
+            "duration precision (more/fewer digits)",
+            &[Action::MorePrecision, Action::LessPrecision],
+            bindings,
+        ),
+    ]
+}
+
+fn dur(styles: &view::Styles, dur: Duration) -> Span<'static> {
+    styles.time_units(format!("{:.prec$?}", dur, prec = styles.precision.detail))
+}
@@ -0,0 +1,558 @@
+use crate::{
+    input::{Action, KeyBindings},
+    state::{
+        async_ops::{AsyncOp, SortBy},
+        State,
+    },
+    util,
+    view::{
+        self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
+        table::{self, TableList, TableListState},
+    },
+};
+use std::time::SystemTime;
+use tui::{
+    layout,
+    style::{self, Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Cell, List, ListItem, Paragraph, Row, Table},
+};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Default)]
+pub(crate) struct AsyncOpsTable {}
+
+/// A broad category of async op, derived from the op's source.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum SourceCategory {
+    Timer,
+    Sync,
+    Io,
+}
+
+/// Maps prefixes of async op sources to the category of op they represent.
+///
+/// Sources are matched against these prefixes in order, so more specific
+/// prefixes should come before less specific ones.
+const SOURCE_CATEGORIES: &[(&str, SourceCategory)] = &[
+    ("tokio::time", SourceCategory::Timer),
+    ("Sleep", SourceCategory::Timer),
+    ("Interval", SourceCategory::Timer),
+    ("Timeout", SourceCategory::Timer),
+    ("tokio::sync", SourceCategory::Sync),
+    ("Semaphore", SourceCategory::Sync),
+    ("Mutex", SourceCategory::Sync),
+    ("RwLock", SourceCategory::Sync),
+    ("Notify", SourceCategory::Sync),
+    ("Barrier", SourceCategory::Sync),
+    ("Sender", SourceCategory::Sync),
+    ("Receiver", SourceCategory::Sync),
+    ("tokio::net", SourceCategory::Io),
+    ("tokio::io", SourceCategory::Io),
+    ("tokio::fs", SourceCategory::Io),
+    ("TcpStream", SourceCategory::Io),
+    ("TcpListener", SourceCategory::Io),
+    ("UdpSocket", SourceCategory::Io),
+    ("UnixStream", SourceCategory::Io),
+    ("UnixListener", SourceCategory::Io),
+    ("File", SourceCategory::Io),
+];
+
+impl TableList for AsyncOpsTable {
+    type Row = AsyncOp;
+    type Sort = SortBy;
+
+    const HEADER: &'static [&'static str] = &[
+        "Warn",
+        "ID",
+        "Source",
+        "Resource",
+        "Task",
+        "Total",
+        "Busy",
+        "Idle",
+        "Polls",
+        "Since Poll",
+        "Idle Since",
+        "Target",
+    ];
+    const RIGHT_ALIGNED: &'static [usize] = &[1, 3, 4, 5, 6, 7, 8, 9, 10];
+
+    /// When filtered, async ops which have never been polled are hidden, since
+    /// some sources create async ops which are dropped before they're polled.
+    fn filter(async_op: &AsyncOp) -> bool {
+        async_op.polls() > 0
+    }
+
+    fn text_row(
+        async_op: &AsyncOp,
+        styles: &view::Styles,
+        unit: view::DurationUnit,
+        now: SystemTime,
+        state: &State,
+    ) -> Vec<String> {
+        let warnings = match async_op.warnings().len() {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        let resource = match async_op.resource_id() {
+            Some(id) if async_op.resource_known(state.resources_state()) => id.to_string(),
+            Some(id) => format!("{}{}", id, PRUNED),
+            None => String::new(),
+        };
+        vec![
+            warnings,
+            async_op.id().to_string(),
+            async_op.source().to_owned(),
+            resource,
+            async_op
+                .task_id()
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            table::dur_text(styles, unit, async_op.total(now)),
+            table::dur_text(styles, unit, async_op.busy(now)),
+            table::dur_text(styles, unit, async_op.idle(now)),
+            async_op.polls().to_string(),
+            async_op
+                .since_last_poll(now)
+                .map(|dur| table::dur_text(styles, unit, dur))
+                .unwrap_or_else(|| "n/a".to_owned()),
+            async_op
+                .idle_since(now)
+                .map(|dur| table::dur_text(styles, unit, dur))
+                .unwrap_or_else(|| "n/a".to_owned()),
+            async_op.target().to_owned(),
+        ]
+    }
+
+    fn render<B: tui::backend::Backend>(
+        table_list_state: &mut TableListState<Self>,
+        styles: &view::Styles,
+        bindings: &KeyBindings,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &mut State,
+    ) -> Vec<(Action, bool)> {
+        let now = if let Some(now) = state.display_now() {
+            now
+        } else {
+            // If we have never gotten an update yet, skip...
+            return Vec::new();
+        };
+        let unit = table_list_state.dur_unit();
+
+        // A frozen view keeps its rows and their order until it's unfrozen.
+        let frozen = state.is_view_frozen();
+        if !frozen {
+            table_list_state
+                .sorted_items
+                .extend(state.async_ops_state_mut().take_new_async_ops());
+        }
+        table_list_state.apply_search();
+        if !frozen {
+            table_list_state
+                .sort_by
+                .sort(now, &mut table_list_state.sorted_items);
+        }
+
+        let mut warn_width = view::Width::new(table_list_state.header_width(0));
+        let mut id_width = view::Width::new(table_list_state.header_width(1));
+        let mut source_width = view::Width::new(table_list_state.header_width(2));
+        let mut resource_width = view::Width::new(table_list_state.header_width(3));
+        let mut task_width = view::Width::new(table_list_state.header_width(4));
+        let mut polls_width = view::Width::new(table_list_state.header_width(8));
+        let mut target_width = view::Width::new(table_list_state.header_width(11));
+        // The "Since Poll" and "Idle Since" headers are as wide as the
+        // durations, so leave room for the sort arrow when they are selected.
+        let since_poll_width =
+            (styles.precision.list_len(unit) as u16).max(table_list_state.header_width(9));
+        let idle_since_width =
+            (styles.precision.list_len(unit) as u16).max(table_list_state.header_width(10));
+
+        let resources_state = state.resources_state();
+        // The source column takes up whatever width the other columns leave,
+        // so measure them before truncating any sources.
+        for async_op in table_list_state.sorted_items.iter() {
+            let async_op = match async_op.upgrade() {
+                Some(async_op) => async_op,
+                None => continue,
+            };
+            let async_op = async_op.borrow();
+            let n_warnings = async_op.warnings().len();
+            if n_warnings > 0 {
+                warn_width.update_len(n_warnings.to_string().len() + 2);
+            }
+            id_width.update_len(async_op.id().to_string().len());
+            if let Some(id) = async_op.resource_id() {
+                let mut len = id.to_string().len();
+                if !async_op.resource_known(resources_state) {
+                    len += PRUNED.len();
+                }
+                resource_width.update_len(len);
+            }
+            if let Some(id) = async_op.task_id() {
+                task_width.update_len(id.to_string().len());
+            }
+            polls_width.update_len(async_op.polls().to_string().len());
+            target_width.update_len(async_op.target().len());
+        }
+        let is_visible = |idx: usize| table_list_state.is_column_visible(idx);
+        let dur_width = styles.precision.list_len(unit) as u16;
+        let other_columns_width = [
+            (0, warn_width.chars()),
+            (1, id_width.chars()),
+            (3, resource_width.chars()),
+            (4, task_width.chars()),
+            (5, dur_width),
+            (6, dur_width),
+            (7, dur_width),
+            (8, polls_width.chars()),
+            (9, since_poll_width),
+            (10, idle_since_width),
+            (11, target_width.chars()),
+        ]
+        .iter()
+        .filter(|&&(idx, _)| is_visible(idx))
+        .map(|&(_, width)| width)
+        .sum::<u16>();
+        // add 2 for the left and right borders, and 1 for the space between
+        // each pair of columns.
+        let table_chrome_width = 2
+            + view::TABLE_HIGHLIGHT_SYMBOL.len() as u16
+            + (table_list_state.visible_columns() as u16 - 1);
+        let source_max_width = area
+            .width
+            .saturating_sub(other_columns_width + table_chrome_width)
+            .max(table_list_state.header_width(2)) as usize;
+        let ellipsis = styles.if_utf8("\u{2026}", "...");
+
+        let rows = {
+            let warn_width = &mut warn_width;
+            let id_width = &mut id_width;
+            let source_width = &mut source_width;
+            let resource_width = &mut resource_width;
+            let task_width = &mut task_width;
+            let polls_width = &mut polls_width;
+            let target_width = &mut target_width;
+
+            table_list_state
+                .sorted_items
+                .iter()
+                .filter_map(move |async_op| {
+                    let async_op = async_op.upgrade()?;
+                    let async_op = async_op.borrow();
+                    // The resource and task IDs are only known once the op
+                    // has been polled.
+                    let format_id =
+                        |id: Option<u64>| id.map(|id| id.to_string()).unwrap_or_default();
+
+                    let n_warnings = async_op.warnings().len();
+                    let warnings = if n_warnings > 0 {
+                        let n_warnings = n_warnings.to_string();
+                        warn_width.update_len(n_warnings.len() + 2); // add 2 for the warning icon + whitespace
+                        Cell::from(Spans::from(vec![
+                            styles.warning_narrow(),
+                            Span::from(n_warnings),
+                        ]))
+                    } else {
+                        Cell::from("")
+                    };
+
+                    // Keep the start of the source, which names the type, as
+                    // well as the end, which names the method.
+                    let source = util::truncate_middle(
+                        async_op.source(),
+                        source_max_width,
+                        source_max_width / 2,
+                        ellipsis,
+                    );
+                    source_width.update_len(source.width());
+                    let source = source.into_owned();
+
+                    let cells = vec![
+                        warnings,
+                        Cell::from(id_width.update_str(format!(
+                            "{:>width$}",
+                            async_op.id(),
+                            width = id_width.chars() as usize
+                        ))),
+                        Cell::from(Span::styled(
+                            source,
+                            SourceCategory::of(async_op.source())
+                                .map(|category| category.style(styles))
+                                .unwrap_or_default(),
+                        )),
+                        Cell::from(resource_cell(
+                            resource_width,
+                            async_op.resource_id(),
+                            async_op.resource_known(resources_state),
+                            styles,
+                        )),
+                        Cell::from(task_width.update_str(format!(
+                            "{:>width$}",
+                            format_id(async_op.task_id()),
+                            width = task_width.chars() as usize
+                        ))),
+                        Cell::from(styles.time_units(format!(
+                            "{:>width$}",
+                            styles.precision.list_dur(unit, async_op.total(now)),
+                            width = styles.precision.list_len(unit)
+                        ))),
+                        Cell::from(styles.time_units(format!(
+                            "{:>width$}",
+                            styles.precision.list_dur(unit, async_op.busy(now)),
+                            width = styles.precision.list_len(unit)
+                        ))),
+                        Cell::from(styles.time_units(format!(
+                            "{:>width$}",
+                            styles.precision.list_dur(unit, async_op.idle(now)),
+                            width = styles.precision.list_len(unit)
+                        ))),
+                        Cell::from(polls_width.update_str(format!(
+                            "{:>width$}",
+                            async_op.polls(),
+                            width = polls_width.chars() as usize
+                        ))),
+                        Cell::from(match async_op.since_last_poll(now) {
+                            Some(since) => styles.time_units(format!(
+                                "{:>width$}",
+                                styles.precision.list_dur(unit, since),
+                                width = styles.precision.list_len(unit)
+                            )),
+                            None => Span::raw(format!(
+                                "{:>width$}",
+                                "n/a",
+                                width = styles.precision.list_len(unit)
+                            )),
+                        }),
+                        Cell::from(match async_op.idle_since(now) {
+                            Some(idle) => styles.time_units(format!(
+                                "{:>width$}",
+                                styles.precision.list_dur(unit, idle),
+                                width = idle_since_width as usize
+                            )),
+                            None => Span::raw(format!(
+                                "{:>width$}",
+                                "n/a",
+                                width = idle_since_width as usize
+                            )),
+                        }),
+                        Cell::from(target_width.update_str(async_op.target()).to_owned()),
+                    ];
+                    let mut row = Row::new(
+                        cells
+                            .into_iter()
+                            .enumerate()
+                            .filter(|&(idx, _)| is_visible(idx))
+                            .map(|(_, cell)| cell),
+                    );
+
+                    if async_op.dropped() {
+                        row = row.style(styles.dropped());
+                    } else if async_op.is_polling() {
+                        row = row.style(styles.fg_or(Color::Green, Modifier::BOLD));
+                    }
+
+                    Some(row)
+                })
+        };
+
+        let header = table_list_state.header(styles);
+
+        let table = if table_list_state.sort_descending {
+            Table::new(rows)
+        } else {
+            Table::new(rows.rev())
+        };
+
+        let mut title = vec![bold(format!("Async Ops ({}) ", table_list_state.len()))];
+        if let Some(sort_column) = table_list_state.hidden_sort_column() {
+            title.push(bold(format!("sorted by {} (hidden) ", sort_column)));
+        }
+        let block = styles.border_block().title(title);
+
+        let warnings = state
+            .async_ops_state()
+            .warnings()
+            .map(|warning| {
+                ListItem::new(Text::from(Spans::from(vec![
+                    styles.warning_wide(),
+                    Span::from(format!("{} {}", warning.count(), warning.summary())),
+                ])))
+            })
+            .collect::<Vec<_>>();
+
+        let layout = layout::Layout::default()
+            .direction(layout::Direction::Vertical)
+            .margin(0);
+
+        let mut view_controls = table::view_controls(bindings, "view details");
+        view_controls.push(ControlDisplay::new(
+            "hide column (selected/show all)",
+            &[Action::HideColumn, Action::ShowAllColumns],
+            bindings,
+        ));
+        let filter = if table_list_state.is_filtered() {
+            "show never polled"
+        } else {
+            "hide never polled"
+        };
+        view_controls.push(ControlDisplay::new(
+            filter,
+            &[Action::FilterUnpolled],
+            bindings,
+        ));
+        if table_list_state.is_resizing() {
+            view_controls = table::resize_controls(bindings);
+        }
+        let controls = Controls::new(
+            &view_controls,
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Dim,
+        )
+        .with_notice(table_list_state.notice(), styles);
+        // add 2 for the top and bottom borders
+        let warnings_height = if warnings.is_empty() {
+            0
+        } else {
+            warnings.len() as u16 + 2
+        };
+        let chunks = layout
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Length(warnings_height),
+                    layout::Constraint::Min(
+                        area.height
+                            .saturating_sub(controls.height() + warnings_height + 1),
+                    ),
+                    layout::Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let warnings_area = chunks[1];
+        let async_ops_area = chunks[2];
+        let summary_area = chunks[3];
+
+        let async_ops_state = state.async_ops_state();
+        let mut summary = vec![
+            bold("active: "),
+            Span::raw(async_ops_state.active_count().to_string()),
+            Span::raw(", "),
+            bold("dropped: "),
+            Span::raw(async_ops_state.dropped_count().to_string()),
+            Span::raw(", "),
+            bold("pruned: "),
+            Span::raw(async_ops_state.pruned_count().to_string()),
+        ];
+        // Say how many async ops are hidden, so that they don't seem to have
+        // disappeared.
+        if table_list_state.is_filtered() {
+            summary.push(Span::raw(", "));
+            summary.push(bold("hidden (never polled): "));
+            summary.push(Span::raw(table_list_state.hidden_len().to_string()));
+        }
+        let summary = Paragraph::new(Spans::from(summary));
+
+        let widths = [
+            warn_width.constraint(),
+            id_width.constraint(),
+            source_width.constraint(),
+            resource_width.constraint(),
+            task_width.constraint(),
+            layout::Constraint::Length(dur_width),
+            layout::Constraint::Length(dur_width),
+            layout::Constraint::Length(dur_width),
+            polls_width.constraint(),
+            layout::Constraint::Length(since_poll_width),
+            layout::Constraint::Length(idle_since_width),
+            target_width.constraint(),
+        ];
+        let widths = table_list_state.column_widths(&widths, async_ops_area.width);
+
+        let table = table
+            .header(header)
+            .block(block)
+            .widths(&widths)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+
+        frame.render_stateful_widget(table, async_ops_area, &mut table_list_state.table_state);
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        frame.render_widget(summary, summary_area);
+        if !warnings.is_empty() {
+            let block = styles
+                .border_block()
+                .title(Spans::from(vec![bold("Warnings")]));
+            frame.render_widget(List::new(warnings).block(block), warnings_area);
+        }
+
+        table_list_state
+            .sorted_items
+            .retain(|t| t.upgrade().is_some());
+        actions
+    }
+}
+
+/// Marks the IDs of resources that are no longer (or were never) known to the
+/// console.
+const PRUNED: &str = " (pruned)";
+
+/// Renders the ID of the resource an async op is performed on, noting when the
+/// resource is no longer (or was never) known to the console.
+fn resource_cell(
+    width: &mut view::Width,
+    resource_id: Option<u64>,
+    known: bool,
+    styles: &view::Styles,
+) -> Spans<'static> {
+    let id = match resource_id {
+        Some(id) => id.to_string(),
+        None => return Spans::default(),
+    };
+    if known {
+        return Spans::from(width.update_str(format!(
+            "{:>width$}",
+            id,
+            width = width.chars() as usize
+        )));
+    }
+
+    let id = format!(
+        "{:>width$}",
+        id,
+        width = (width.chars() as usize).saturating_sub(PRUNED.len())
+    );
+    width.update_len(id.len() + PRUNED.len());
+    Spans::from(vec![
+        Span::raw(id),
+        Span::styled(PRUNED, styles.terminated()),
+    ])
+}
+
+impl SourceCategory {
+    /// Returns the category of the async op with the given source, or `None`
+    /// if the source isn't recognized.
+    fn of(source: &str) -> Option<Self> {
+        SOURCE_CATEGORIES
+            .iter()
+            .find(|(prefix, _)| source.starts_with(prefix))
+            .map(|&(_, category)| category)
+    }
+
+    fn style(self, styles: &view::Styles) -> Style {
+        let color = match self {
+            Self::Timer => Color::LightBlue,
+            Self::Sync => Color::LightMagenta,
+            Self::Io => Color::LightCyan,
+        };
+        styles.fg(color)
+    }
+}
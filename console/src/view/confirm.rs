@@ -0,0 +1,162 @@
+//! Confirming actions which clear something that would be tedious to recreate,
+//! like every task's tags, before they're run, and undoing the last one.
+//!
+//! Only the last confirmed clear can be undone, since each one replaces what
+//! the one before it cleared.
+use crate::{
+    input::{self, Action, KeyCode},
+    state::Acknowledgements,
+    view::{
+        self, bold,
+        table::{RowNotes, RowTags},
+    },
+};
+use std::collections::HashSet;
+use tui::{
+    layout,
+    text::{Span, Spans, Text},
+    widgets::{Clear, Paragraph},
+};
+
+/// A prompt asking whether to run a destructive action.
+#[derive(Debug)]
+pub(crate) struct Confirmation {
+    action: Action,
+    /// How many things the action will clear.
+    count: usize,
+}
+
+/// The outcome of a key press while a confirmation prompt is open.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum ConfirmInput {
+    /// The prompt stays open.
+    Continue,
+    /// The prompt was closed without running the action.
+    Cancel,
+    /// The prompt was closed to run the action.
+    Confirm(Action),
+}
+
+/// What the last confirmed destructive action cleared, which undoing it
+/// restores.
+#[derive(Debug)]
+pub(crate) enum Cleared {
+    Tags(RowTags),
+    Notes(RowNotes),
+    /// The IDs of the tasks which were pinned.
+    Pins(HashSet<u64>),
+    Acknowledged(Acknowledgements),
+}
+
+impl Confirmation {
+    /// Returns a prompt asking whether to run `action`, which will clear
+    /// `count` things.
+    pub(in crate::view) fn new(action: Action, count: usize) -> Self {
+        debug_assert!(action.is_destructive());
+        Self { action, count }
+    }
+
+    /// Handles a key press. `y` or enter runs the action, while `n` or escape
+    /// closes the prompt without running it, and every other key is ignored.
+    pub(in crate::view) fn update_input(&self, event: &input::Event) -> ConfirmInput {
+        let key = match event {
+            input::Event::Key(key) => key,
+            _ => return ConfirmInput::Continue,
+        };
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                ConfirmInput::Confirm(self.action)
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => ConfirmInput::Cancel,
+            _ => ConfirmInput::Continue,
+        }
+    }
+
+    pub(in crate::view) fn render<B: tui::backend::Backend>(
+        &self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+    ) {
+        let (verb, _) = verbs(self.action);
+        let question = format!("{} {}?", verb, things(self.action, self.count));
+        let text = Text::from(vec![
+            Spans::from(question),
+            Spans::from(vec![
+                bold("y"),
+                Span::raw("/"),
+                bold("enter"),
+                Span::raw(" = yes, "),
+                bold("n"),
+                Span::raw("/"),
+                bold("esc"),
+                Span::raw(" = no"),
+            ]),
+        ]);
+
+        // Add 2 for the borders.
+        let width = (text.width() as u16 + 2).max(30);
+        let width = width.min(area.width);
+        let height = (text.height() as u16 + 2).min(area.height);
+        let overlay_area = layout::Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let block = styles
+            .border_block()
+            .title(Spans::from(vec![bold("Confirm ")]));
+        frame.render_widget(Clear, overlay_area);
+        frame.render_widget(Paragraph::new(text).block(block), overlay_area);
+    }
+}
+
+impl Cleared {
+    /// Returns the destructive action which cleared this.
+    pub(in crate::view) fn action(&self) -> Action {
+        match self {
+            Cleared::Tags(_) => Action::ClearTags,
+            Cleared::Notes(_) => Action::ClearNotes,
+            Cleared::Pins(_) => Action::UnpinAll,
+            Cleared::Acknowledged(_) => Action::ClearAcknowledged,
+        }
+    }
+
+    /// Describes what was cleared, like `3 tags`.
+    pub(in crate::view) fn describe(&self) -> String {
+        let count = match self {
+            Cleared::Tags(tags) => tags.len(),
+            Cleared::Notes(notes) => notes.len(),
+            Cleared::Pins(pins) => pins.len(),
+            Cleared::Acknowledged(acks) => acks.len(),
+        };
+        things(self.action(), count)
+    }
+}
+
+/// Returns the verb used to describe running the destructive `action`, and
+/// its past tense, like `clear` and `cleared`.
+pub(in crate::view) fn verbs(action: Action) -> (&'static str, &'static str) {
+    match action {
+        Action::UnpinAll => ("remove", "removed"),
+        _ => ("clear", "cleared"),
+    }
+}
+
+/// Describes `count` of the things cleared by the destructive `action`, like
+/// `3 tags` or `no tags`.
+pub(in crate::view) fn things(action: Action, count: usize) -> String {
+    let (one, many) = match action {
+        Action::ClearTags => ("tag", "tags"),
+        Action::ClearNotes => ("note", "notes"),
+        Action::UnpinAll => ("pin", "pins"),
+        Action::ClearAcknowledged => ("acknowledged warning", "acknowledged warnings"),
+        _ => ("thing", "things"),
+    };
+    match count {
+        0 => format!("no {}", many),
+        1 => format!("1 {}", one),
+        _ => format!("{} {}", count, many),
+    }
+}
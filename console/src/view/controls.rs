@@ -0,0 +1,337 @@
+use crate::{
+    input::{Action, Key, KeyBindings},
+    state,
+    view::{self, bold, Notice},
+};
+use std::cmp;
+use tui::{
+    layout,
+    text::{Span, Spans, Text},
+    widgets::Paragraph,
+};
+
+/// A list of controls that can be rendered as a paragraph, wrapping onto as
+/// many lines as are needed to fit the available width.
+pub(crate) struct Controls {
+    lines: Vec<Spans<'static>>,
+    /// The number of lines the controls need on the narrowest terminal that
+    /// displays them in full, which is reserved if the height is stable.
+    full_height: u16,
+    /// Whether the controls reserve `full_height` lines, rather than only as
+    /// many lines as they need at the current width.
+    stable_height: bool,
+    /// The actions of the controls, along with whether each one's control is
+    /// enabled.
+    actions: Vec<(Action, bool)>,
+}
+
+/// A single control: the keys that trigger it, and the action it performs.
+#[derive(Debug, Clone)]
+pub(crate) struct ControlDisplay {
+    pub(crate) action: &'static str,
+    /// The actions performed by the control's keys.
+    pub(crate) actions: Vec<Action>,
+    pub(crate) keys: Vec<KeyDisplay>,
+    /// Whether the control currently does anything in its view.
+    ///
+    /// How disabled controls are displayed is decided by the view rendering
+    /// them; see [`DisabledControls`].
+    pub(crate) enabled: bool,
+}
+
+/// How a list of controls displays the controls that are currently disabled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum DisabledControls {
+    /// Disabled controls are displayed dimmed, so that the set of controls
+    /// doesn't change as the view's contents do.
+    Dim,
+    /// Disabled controls are not displayed at all.
+    Hide,
+}
+
+/// A key or keys that trigger a control.
+///
+/// The keys are the ones bound to the control's actions, and their names are
+/// derived from them when the control is rendered, so that a control's hint
+/// always matches the keys that are handled, including remapped ones. When
+/// UTF-8 output is enabled and every key has a glyph (such as an arrow), the
+/// glyphs are displayed instead of the keys' names.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyDisplay {
+    pub(crate) keys: Vec<Key>,
+}
+
+impl Controls {
+    /// Terminals narrower than this many columns display only the keys for
+    /// view-specific controls, rather than the keys and their actions.
+    const COMPACT_WIDTH: u16 = 80;
+
+    pub(in crate::view) fn new(
+        view_controls: &[ControlDisplay],
+        bindings: &KeyBindings,
+        area: &layout::Rect,
+        styles: &view::Styles,
+        state: &state::State,
+        disabled: DisabledControls,
+    ) -> Self {
+        let paused = state.is_paused();
+        let view_frozen = state.is_view_frozen();
+        let compact = area.width < Self::COMPACT_WIDTH;
+        let pause_action = if paused {
+            "resume updates"
+        } else {
+            "pause updates"
+        };
+        let freeze_action = if view_frozen {
+            "unfreeze view"
+        } else {
+            "freeze view"
+        };
+        let universal_controls = [
+            ControlDisplay::new(pause_action, &[Action::TogglePause], bindings),
+            ControlDisplay::new(freeze_action, &[Action::FreezeView], bindings),
+            ControlDisplay::new("quit", &[Action::Quit], bindings),
+            ControlDisplay::new("all actions", &[Action::CommandPalette], bindings),
+        ];
+        let actions = view_controls
+            .iter()
+            .chain(universal_controls.iter())
+            .flat_map(|control| {
+                let enabled = control.enabled;
+                control.actions.iter().map(move |&action| (action, enabled))
+            })
+            .collect();
+        let controls: Vec<_> = view_controls
+            .iter()
+            .filter(|control| control.enabled || disabled == DisabledControls::Dim)
+            .map(|control| {
+                let mut spans = if compact {
+                    control.to_compact_spans(styles)
+                } else {
+                    control.to_spans(styles)
+                };
+                if !control.enabled {
+                    for span in &mut spans.0 {
+                        span.style = span.style.patch(styles.disabled());
+                    }
+                }
+                spans
+            })
+            // The universal controls are always displayed in full, so that
+            // how to pause and quit remains discoverable.
+            .chain(universal_controls.iter().map(|c| c.to_spans(styles)))
+            .collect();
+
+        let mut first_line = Vec::with_capacity(7);
+        if paused {
+            first_line.push(Span::styled("(paused)", styles.paused()));
+            first_line.push(Span::raw(" "));
+        }
+        if state.is_clock_frozen() {
+            first_line.push(Span::styled("(clock frozen)", styles.paused()));
+            first_line.push(Span::raw(" "));
+        }
+        if view_frozen {
+            first_line.push(Span::styled("(view frozen)", styles.paused()));
+            first_line.push(Span::raw(" "));
+        }
+        first_line.push(Span::raw("controls: "));
+        let full_height = if compact {
+            // Compact controls are only displayed on terminals narrower than
+            // the narrowest one which displays them in full.
+            0
+        } else {
+            Self::wrap(first_line.clone(), controls.clone(), Self::COMPACT_WIDTH).len() as u16
+        };
+        let lines = Self::wrap(first_line, controls, area.width);
+
+        Self {
+            lines,
+            full_height,
+            stable_height: false,
+            actions,
+        }
+    }
+
+    /// Returns the actions of the controls, along with whether each one's
+    /// control is enabled, so that the command palette can tell which actions
+    /// do anything in the view displaying them.
+    pub(in crate::view) fn actions(&self) -> Vec<(Action, bool)> {
+        self.actions.clone()
+    }
+
+    /// Reserves as many lines as the controls need on the narrowest terminal
+    /// that displays them in full, so that the layout below the controls
+    /// doesn't shift up and down as the terminal is resized.
+    ///
+    /// This should be used by views whose set of controls doesn't change as
+    /// the view's contents do, such as views using [`DisabledControls::Dim`].
+    pub(in crate::view) fn with_stable_height(mut self) -> Self {
+        self.stable_height = true;
+        self
+    }
+
+    /// Places `controls` after `first_line`, wrapping them onto as many lines
+    /// as are needed to fit in `width` columns.
+    fn wrap(
+        first_line: Vec<Span<'static>>,
+        controls: Vec<Spans<'static>>,
+        width: u16,
+    ) -> Vec<Spans<'static>> {
+        let separator = Span::raw(", ");
+        let mut lines = vec![Spans::from(first_line)];
+        for (idx, control) in controls.into_iter().enumerate() {
+            let current_line = lines.last_mut().expect("lines is never empty");
+            // The first control in the list is always placed on the first line,
+            // even if it doesn't fit, since there's nothing else we can do.
+            if idx == 0 {
+                current_line.0.extend(control.0);
+                continue;
+            }
+
+            let line_width = current_line.width() + separator.width() + control.width();
+            if line_width <= width as usize {
+                current_line.0.push(separator.clone());
+                current_line.0.extend(control.0);
+            } else {
+                lines.push(control);
+            }
+        }
+        lines
+    }
+
+    /// Adds a line displaying `notice` below the controls, if there is one.
+    pub(in crate::view) fn with_notice(
+        mut self,
+        notice: Option<&Notice>,
+        styles: &view::Styles,
+    ) -> Self {
+        if let Some(notice) = notice {
+            self.lines.push(Spans::from(vec![notice.render(styles)]));
+        }
+        self
+    }
+
+    /// Returns the number of lines needed to display the controls, or the
+    /// number of lines reserved for them if they have a stable height.
+    pub(crate) fn height(&self) -> u16 {
+        let height = self.lines.len() as u16;
+        if self.stable_height {
+            cmp::max(height, self.full_height)
+        } else {
+            height
+        }
+    }
+
+    pub(crate) fn into_widget(self) -> Paragraph<'static> {
+        Paragraph::new(Text::from(self.lines))
+    }
+}
+
+impl ControlDisplay {
+    /// Returns a control that performs `action`, displaying the keys that
+    /// are bound to `actions`.
+    ///
+    /// If a single action is provided, all of the keys bound to it are
+    /// displayed. If multiple actions are provided (such as for scrolling up
+    /// and down), the first key bound to each action is displayed together,
+    /// like `up, down`.
+    pub(crate) fn new(action: &'static str, actions: &[Action], bindings: &KeyBindings) -> Self {
+        let keys = match actions {
+            [action] => bindings
+                .keys(*action)
+                .iter()
+                .map(|&key| KeyDisplay { keys: vec![key] })
+                .collect(),
+            actions => {
+                let keys = actions
+                    .iter()
+                    .filter_map(|action| bindings.keys(*action).first().copied())
+                    .collect();
+                vec![KeyDisplay { keys }]
+            }
+        };
+        Self {
+            action,
+            actions: actions.to_vec(),
+            keys,
+            enabled: true,
+        }
+    }
+
+    /// Returns a control that performs `action`, displaying the keys bound
+    /// to `actions` other than the first key bound to each, such as the vim
+    /// aliases for keys which are displayed by another control.
+    ///
+    /// Returns `None` if no other keys are bound to `actions`.
+    pub(crate) fn aliases(
+        action: &'static str,
+        actions: &[Action],
+        bindings: &KeyBindings,
+    ) -> Option<Self> {
+        let keys: Vec<_> = actions
+            .iter()
+            .flat_map(|action| bindings.keys(*action).iter().skip(1).copied())
+            .collect();
+        if keys.is_empty() {
+            return None;
+        }
+        Some(Self {
+            action,
+            actions: actions.to_vec(),
+            keys: vec![KeyDisplay { keys }],
+            enabled: true,
+        })
+    }
+
+    /// Sets whether the control currently does anything in its view.
+    pub(crate) fn enabled(self, enabled: bool) -> Self {
+        Self { enabled, ..self }
+    }
+
+    /// Renders the control's keys followed by its action, like `q = quit`.
+    pub(crate) fn to_spans(&self, styles: &view::Styles) -> Spans<'static> {
+        let mut spans = self.key_spans(styles);
+        spans.push(Span::raw(" = "));
+        spans.push(Span::raw(self.action));
+        Spans::from(spans)
+    }
+
+    /// Renders only the control's keys.
+    fn to_compact_spans(&self, styles: &view::Styles) -> Spans<'static> {
+        Spans::from(self.key_spans(styles))
+    }
+
+    fn key_spans(&self, styles: &view::Styles) -> Vec<Span<'static>> {
+        let mut spans = Vec::with_capacity(self.keys.len() * 2 + 2);
+        for (idx, key) in self.keys.iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::raw(" or "));
+            }
+            spans.push(bold(key.display(styles)));
+        }
+        spans
+    }
+}
+
+impl KeyDisplay {
+    /// Returns the keys' names, like `up, down`, or their glyphs, like `↑↓`.
+    fn display(&self, styles: &view::Styles) -> String {
+        let names = self
+            .keys
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let glyphs = self
+            .keys
+            .iter()
+            .map(Key::glyph)
+            .collect::<Option<Vec<_>>>()
+            .map(|glyphs| glyphs.concat());
+        match glyphs {
+            Some(glyphs) => styles.if_utf8(&glyphs, &names).to_string(),
+            None => names,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! A developer-facing overlay listing the most referenced interned strings,
+//! and a dump of every interned string to a file, for diagnosing why the
+//! interner isn't shrinking.
+//!
+//! These are only available in debug builds.
+use crate::{
+    intern,
+    view::{self, bold},
+};
+use color_eyre::eyre::WrapErr;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    thread,
+};
+use tui::{
+    layout,
+    text::{Span, Spans},
+    widgets::{Clear, List, ListItem},
+};
+
+/// The number of interned strings to list.
+const MAX_STRINGS: usize = 20;
+
+/// The file that interned strings are dumped to, in the current directory.
+const DUMP_PATH: &str = "tokio-console-interned-strings.txt";
+
+pub(super) fn render<B: tui::backend::Backend>(
+    styles: &view::Styles,
+    frame: &mut tui::terminal::Frame<B>,
+    area: layout::Rect,
+    strings: &intern::Strings,
+) {
+    let items = strings
+        .most_referenced(MAX_STRINGS)
+        .into_iter()
+        .map(|(string, refs)| {
+            ListItem::new(Spans::from(vec![
+                bold(format!("{:>6} ", refs)),
+                Span::raw(format!("{:?}", &*string)),
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    // add 2 for the top and bottom borders
+    let height = (items.len() as u16 + 2).min(area.height);
+    let width = area.width.saturating_sub(4).min(80);
+    let overlay_area = layout::Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let (hits, misses) = strings.hit_miss_ratio();
+    let block = styles.border_block().title(Spans::from(vec![bold(format!(
+        "Interned strings ({}, {} bytes, {} hits, {} misses) ",
+        strings.len(),
+        strings.bytes(),
+        hits,
+        misses
+    ))]));
+    frame.render_widget(Clear, overlay_area);
+    frame.render_widget(List::new(items).block(block), overlay_area);
+}
+
+/// Writes every interned string to [`DUMP_PATH`], with the number of
+/// references to each of them outside of the interner, most referenced first.
+///
+/// Only the strings and their reference counts are copied here, since the
+/// interned strings can't be shared with another thread. Sorting and writing
+/// them happens on a separate thread, so that dumping a large interner doesn't
+/// stall the UI. The outcome is logged, since this is only a debugging aid.
+pub(super) fn dump(strings: &intern::Strings) {
+    let (hits, misses) = strings.hit_miss_ratio();
+    let summary = format!(
+        "{} interned strings, {} bytes, {} hits, {} misses",
+        strings.len(),
+        strings.bytes(),
+        hits,
+        misses
+    );
+    let mut snapshot = strings
+        .iter()
+        .map(|(string, refs)| (string.to_owned(), refs))
+        .collect::<Vec<_>>();
+
+    let spawned = thread::Builder::new()
+        .name("interner-dump".into())
+        .spawn(move || {
+            snapshot
+                .sort_unstable_by(|(a_str, a), (b_str, b)| b.cmp(a).then_with(|| a_str.cmp(b_str)));
+            match write_dump(Path::new(DUMP_PATH), &summary, &snapshot) {
+                Ok(()) => tracing::info!(path = DUMP_PATH, "{}", summary),
+                Err(error) => tracing::warn!(%error, "failed to dump interned strings"),
+            }
+        });
+    if let Err(error) = spawned {
+        tracing::warn!(%error, "failed to spawn a thread to dump interned strings");
+    }
+}
+
+fn write_dump(path: &Path, summary: &str, strings: &[(String, usize)]) -> color_eyre::Result<()> {
+    let write = || -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "# {}", summary)?;
+        for (string, refs) in strings {
+            writeln!(file, "{:>6} {:?}", refs, string)?;
+        }
+        file.flush()
+    };
+    write().wrap_err_with(|| format!("failed to write `{}`", path.display()))
+}
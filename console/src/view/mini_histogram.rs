@@ -20,6 +20,12 @@ pub(crate) struct MiniHistogram<'a> {
     style: Style,
     /// Values for the buckets of the histogram
     data: &'a [u64],
+    /// Values for the buckets of a second histogram, which is drawn behind
+    /// the first to compare them. Its buckets must cover the same ranges of
+    /// values as the first histogram's.
+    overlay: &'a [u64],
+    /// The style the second histogram is drawn in
+    overlay_style: Style,
     /// Metadata about the histogram
     metadata: HistogramMetadata,
     /// The maximum value to take to compute the maximum bar height (if nothing is specified, the
@@ -49,6 +55,8 @@ impl<'a> Default for MiniHistogram<'a> {
             block: None,
             style: Default::default(),
             data: &[],
+            overlay: &[],
+            overlay_style: Default::default(),
             metadata: Default::default(),
             max: None,
             bar_set: symbols::bar::NINE_LEVELS,
@@ -144,35 +152,43 @@ impl<'a> MiniHistogram<'a> {
     }
 
     fn render_bars(&mut self, area: tui::layout::Rect, buf: &mut tui::buffer::Buffer) {
+        // Both histograms are scaled to the same maximum, so that their bars
+        // can be compared.
         let max = match self.max {
             Some(v) => v,
-            None => *self.data.iter().max().unwrap_or(&1u64),
+            None => *self.data.iter().chain(self.overlay).max().unwrap_or(&1u64),
         };
-        let max_index = std::cmp::min(area.width as usize, self.data.len());
-        let mut data = self
-            .data
-            .iter()
-            .take(max_index)
-            .map(|e| {
-                if max != 0 {
-                    let r = e * u64::from(area.height) * 8 / max;
-                    // This is the only difference in the bar rendering logic
-                    // between MiniHistogram and Sparkline. At least render a
-                    // ONE_EIGHT, if the value is greater than 0, even if it's
-                    // relatively very small.
-                    if *e > 0 && r == 0 {
-                        1
-                    } else {
-                        r
-                    }
-                } else {
-                    0
-                }
-            })
-            .collect::<Vec<u64>>();
+        let heights = |data: &[u64]| {
+            data.iter()
+                .take(area.width as usize)
+                .map(
+                    |e| match (e * u64::from(area.height) * 8).checked_div(max) {
+                        // This is the only difference in the bar rendering logic
+                        // between MiniHistogram and Sparkline. At least render a
+                        // ONE_EIGHT, if the value is greater than 0, even if it's
+                        // relatively very small.
+                        Some(0) if *e > 0 => 1,
+                        Some(r) => r,
+                        None => 0,
+                    },
+                )
+                .collect::<Vec<u64>>()
+        };
+        let mut data = heights(self.data);
+        let mut overlay = heights(self.overlay);
+        let columns = std::cmp::max(data.len(), overlay.len());
+        data.resize(columns, 0);
+        overlay.resize(columns, 0);
         for j in (0..area.height).rev() {
-            for (i, d) in data.iter_mut().enumerate() {
-                let symbol = match *d {
+            for (i, (d, o)) in data.iter_mut().zip(&mut overlay).enumerate() {
+                // The overlay is drawn behind the bars, so it's only visible
+                // where it's taller than them.
+                let (level, style) = if *d > 0 || *o == 0 {
+                    (*d, self.style)
+                } else {
+                    (*o, self.overlay_style)
+                };
+                let symbol = match level {
                     0 => self.bar_set.empty,
                     1 => self.bar_set.one_eighth,
                     2 => self.bar_set.one_quarter,
@@ -185,13 +201,10 @@ impl<'a> MiniHistogram<'a> {
                 };
                 buf.get_mut(area.left() + i as u16, area.top() + j)
                     .set_symbol(symbol)
-                    .set_style(self.style);
+                    .set_style(style);
 
-                if *d > 8 {
-                    *d -= 8;
-                } else {
-                    *d = 0;
-                }
+                *d = d.saturating_sub(8);
+                *o = o.saturating_sub(8);
             }
         }
     }
@@ -221,6 +234,16 @@ impl<'a> MiniHistogram<'a> {
         self
     }
 
+    /// Draws a second histogram behind the first in `style`, to compare them.
+    ///
+    /// Each of `overlay`'s buckets must cover the same range of values as the
+    /// bucket at the same index in the first histogram's data.
+    pub fn overlay(mut self, overlay: &'a [u64], style: Style) -> MiniHistogram<'a> {
+        self.overlay = overlay;
+        self.overlay_style = style;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn metadata(mut self, metadata: HistogramMetadata) -> MiniHistogram<'a> {
         self.metadata = metadata;
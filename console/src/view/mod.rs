@@ -1,26 +1,48 @@
-use crate::view::{resources::ResourcesTable, table::TableListState, tasks::TasksTable};
-use crate::{input, state::State};
-use std::{borrow::Cow, cmp};
+use crate::view::{
+    async_ops::AsyncOpsTable, controls::ControlDisplay, resources::ResourcesTable,
+    table::TableListState, tasks::TasksTable,
+};
+use crate::{
+    clipboard::Clipboard,
+    config::SavedState,
+    folded,
+    input::{self, Action, KeyBindings},
+    state::State,
+};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    cmp,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
 use tui::{
     layout,
-    style::{self, Style},
-    text::Span,
+    style::{self, Color, Modifier, Style},
+    text::{Span, Spans},
 };
 
+mod async_op;
+mod async_ops;
+mod confirm;
+mod controls;
+mod interner_debug;
 mod mini_histogram;
+mod palette;
 mod resources;
 mod styles;
 mod table;
 mod task;
+mod task_groups;
 mod tasks;
-pub(crate) use self::styles::{Palette, Styles};
-pub(crate) use self::table::SortBy;
+mod top_tasks;
+mod warnings;
+pub(crate) use self::styles::{parse_color, Palette, Styles, Theme};
+pub(crate) use self::table::{SortBy, SortDirection};
+pub(crate) use self::top_tasks::TopMetric;
 
+/// The narrowest a table column of durations is.
 const DUR_LEN: usize = 10;
-// This data is only updated every second, so it doesn't make a ton of
-// sense to have a lot of precision in timestamps (and this makes sure
-// there's room for the unit!)
-const DUR_PRECISION: usize = 4;
 const TABLE_HIGHLIGHT_SYMBOL: &str = ">> ";
 
 pub struct View {
@@ -33,8 +55,39 @@ pub struct View {
     /// it to remain sorted that way when we return to it.
     tasks_list: TableListState<TasksTable>,
     resources_list: TableListState<ResourcesTable>,
+    async_ops_list: TableListState<AsyncOpsTable>,
+    warnings_list: self::warnings::WarningsView,
     state: ViewState,
+    clipboard: Clipboard,
+    time_display: TimeDisplay,
+    /// Which panes the task view displays, which is kept when switching
+    /// between tasks.
+    task_panes: self::task::PaneVisibility,
+    /// Whether the interned strings debug overlay is displayed.
+    show_interner_debug: bool,
+    /// The command palette, if it is open.
+    palette: Option<self::palette::CommandPalette>,
+    /// The action chosen in the command palette, until it is taken by
+    /// [`View::take_palette_action`] to be run.
+    palette_action: Option<Action>,
+    /// The prompt asking whether to run a destructive action, if it is open.
+    confirmation: Option<self::confirm::Confirmation>,
+    /// The destructive action confirmed in the prompt, until it is run by
+    /// [`View::run_confirmed`].
+    confirmed: Option<Action>,
+    /// What the last confirmed destructive action cleared, until it is
+    /// restored by [`View::undo`].
+    cleared: Option<self::confirm::Cleared>,
+    /// The actions of the controls displayed by the current view, as of the
+    /// last time it was drawn, and whether each one's control is enabled.
+    shown_actions: Vec<(Action, bool)>,
+    /// How often the screen is redrawn, other than in response to input.
+    refresh_interval: Duration,
+    /// Whether the stacks of exported busy time start with the tasks'
+    /// targets.
+    folded_by_target: bool,
     pub(crate) styles: Styles,
+    pub(crate) bindings: KeyBindings,
 }
 
 pub(crate) enum ViewState {
@@ -42,8 +95,16 @@ pub(crate) enum ViewState {
     TasksList,
     /// The table list of all resources.
     ResourcesList,
+    /// The table list of all async ops.
+    AsyncOpsList,
+    /// The summary of all warnings.
+    WarningsList,
+    /// The tasks list, collapsed into groups of tasks.
+    TaskGroups(self::task_groups::TaskGroupsView),
     /// Inspecting a single task instance.
     TaskInstance(self::task::TaskView),
+    /// Inspecting a single async op.
+    AsyncOpInstance(self::async_op::AsyncOpView),
 }
 
 /// The outcome of the update_input method
@@ -62,40 +123,350 @@ pub(crate) struct Width {
     curr: u16,
 }
 
-macro_rules! key {
-    ($code:ident) => {
-        input::Event::Key(input::KeyEvent {
-            code: input::KeyCode::$code,
-            ..
-        })
-    };
-    (Char($code:literal)) => {
-        input::Event::Key(input::KeyEvent {
-            code: input::KeyCode::Char($code),
-            ..
-        })
-    };
+/// Whether points in time are displayed relative to the current time, or as
+/// absolute wall-clock times.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum TimeDisplay {
+    /// Times are displayed as the time elapsed since then, like `1.5s ago`.
+    Relative,
+    /// Times are displayed as RFC 3339 timestamps in UTC.
+    Absolute,
+}
+
+/// How many digits are displayed after the decimal point of durations, in
+/// each place they are displayed.
+///
+/// This can be set in the `[precision]` section of the config file:
+///
+/// ```toml
+/// [precision]
+/// list = 2
+/// histogram = 3
+/// list_unit = "ms"
+/// ```
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct DurPrecision {
+    /// Durations in the tables of tasks, resources and async ops.
+    pub(crate) list: usize,
+    /// Durations in the task and async op details views.
+    pub(crate) detail: usize,
+    /// The labels of the poll times histogram.
+    pub(crate) histogram: usize,
+    /// The unit that each table's durations are displayed in when the console
+    /// starts. Each table's unit can then be changed separately.
+    pub(crate) list_unit: DurationUnit,
+}
+
+/// The unit that a table's durations are displayed in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+pub(crate) enum DurationUnit {
+    /// Each duration is displayed in whichever unit suits it, like `1.5s` or
+    /// `20.1µs`.
+    #[serde(rename = "auto")]
+    Auto,
+    /// Every duration is displayed in milliseconds, so that a column's values
+    /// can be compared at a glance.
+    #[serde(rename = "ms")]
+    Millis,
+    /// Every duration is displayed in microseconds.
+    #[serde(rename = "us")]
+    Micros,
+}
+
+/// A short-lived status message, displayed in response to a user action (such
+/// as the outcome of copying to the clipboard).
+#[derive(Debug)]
+pub(crate) struct Notice {
+    text: String,
+    is_error: bool,
+    shown_at: Instant,
 }
 
 impl View {
-    pub fn new(styles: Styles) -> Self {
-        Self {
+    pub(crate) const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+    /// The file that the tasks' busy time is exported to, in the current
+    /// directory.
+    const FOLDED_PATH: &'static str = "tokio-console-busy.folded";
+    /// The refresh intervals that can be switched between while the console is
+    /// running, shortest first.
+    const REFRESH_INTERVALS: &'static [Duration] = &[
+        Duration::from_millis(100),
+        Duration::from_millis(250),
+        Duration::from_millis(500),
+        Duration::from_secs(1),
+        Duration::from_secs(2),
+        Duration::from_secs(5),
+        Duration::from_secs(10),
+    ];
+
+    pub fn new(styles: Styles, bindings: KeyBindings, saved: &SavedState) -> Self {
+        let mut view = Self {
             state: ViewState::TasksList,
-            tasks_list: TableListState::<TasksTable>::default(),
-            resources_list: TableListState::<ResourcesTable>::default(),
+            tasks_list: TableListState::<TasksTable>::from_saved(saved.tasks.as_ref()),
+            resources_list: TableListState::<ResourcesTable>::from_saved(saved.resources.as_ref()),
+            async_ops_list: TableListState::<AsyncOpsTable>::from_saved(saved.async_ops.as_ref()),
+            warnings_list: self::warnings::WarningsView::default(),
+            clipboard: Clipboard::default(),
+            time_display: TimeDisplay::Relative,
+            task_panes: self::task::PaneVisibility::default(),
+            show_interner_debug: false,
+            palette: None,
+            palette_action: None,
+            confirmation: None,
+            confirmed: None,
+            cleared: None,
+            shown_actions: Vec::new(),
+            refresh_interval: Self::DEFAULT_REFRESH_INTERVAL,
+            folded_by_target: false,
             styles,
+            bindings,
+        };
+        let unit = view.styles.precision.list_unit;
+        view.tasks_list.set_dur_unit(unit);
+        view.resources_list.set_dur_unit(unit);
+        view.async_ops_list.set_dur_unit(unit);
+        view
+    }
+
+    /// Displays only the async ops table's columns at the given indices, or
+    /// every column if `columns` is `None`.
+    pub(crate) fn with_async_op_columns(mut self, columns: Option<Vec<usize>>) -> Self {
+        if let Some(columns) = columns {
+            self.async_ops_list.set_visible_columns(&columns);
         }
+        self
+    }
+
+    /// Sets the direction that table columns are sorted in until they have
+    /// been sorted in the other direction.
+    pub(crate) fn with_sort_direction(mut self, direction: SortDirection) -> Self {
+        self.tasks_list.set_default_direction(direction);
+        self.resources_list.set_default_direction(direction);
+        self.async_ops_list.set_default_direction(direction);
+        self
+    }
+
+    /// Sets whether the stacks of busy time exported from the tasks list start
+    /// with the tasks' targets, as with `--folded-by-target`.
+    pub(crate) fn with_folded_by_target(mut self, by_target: bool) -> Self {
+        self.folded_by_target = by_target;
+        self
+    }
+
+    /// Sets the metric that the top tasks panel ranks tasks by, and how many
+    /// tasks it lists.
+    pub(crate) fn with_top_tasks(mut self, metric: TopMetric, count: usize) -> Self {
+        self.tasks_list.top.set(metric, count);
+        self
+    }
+
+    /// Briefly highlights cells in the tasks table whose busy time or poll
+    /// count changed by more than `threshold_percent` since the last update.
+    pub(crate) fn with_change_highlights(mut self, threshold_percent: Option<f64>) -> Self {
+        self.tasks_list.changes.set_threshold(threshold_percent);
+        self
+    }
+
+    /// Sets how often the screen is redrawn, clamped to between 100ms and
+    /// 10s.
+    pub(crate) fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.set_refresh_interval(interval);
+        self
+    }
+
+    /// Returns how often the screen should be redrawn, other than in response
+    /// to input.
+    ///
+    /// Updates from the target are applied as soon as they're received, so
+    /// this only affects how often they're displayed.
+    pub(crate) fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    fn set_refresh_interval(&mut self, interval: Duration) {
+        let min = Self::REFRESH_INTERVALS[0];
+        let max = Self::REFRESH_INTERVALS[Self::REFRESH_INTERVALS.len() - 1];
+        self.refresh_interval = interval.clamp(min, max);
     }
 
     pub(crate) fn update_input(&mut self, event: input::Event, state: &State) -> UpdateKind {
         use ViewState::*;
         let mut update_kind = UpdateKind::Other;
+        if let Some(ref mut palette) = self.palette {
+            match palette.update_input(&event, &self.bindings, &self.shown_actions) {
+                self::palette::PaletteInput::Continue => {}
+                self::palette::PaletteInput::Close => self.palette = None,
+                self::palette::PaletteInput::Run(action) => {
+                    self.palette = None;
+                    self.palette_action = Some(action);
+                }
+                self::palette::PaletteInput::Inapplicable(action) => {
+                    self.palette = None;
+                    self.notify(Notice::error(format!(
+                        "{} does nothing in this view",
+                        action.name().replace('_', " ")
+                    )));
+                }
+            }
+            return update_kind;
+        }
+
+        if let Some(ref confirmation) = self.confirmation {
+            match confirmation.update_input(&event) {
+                self::confirm::ConfirmInput::Continue => {}
+                self::confirm::ConfirmInput::Cancel => self.confirmation = None,
+                self::confirm::ConfirmInput::Confirm(action) => {
+                    self.confirmation = None;
+                    self.confirmed = Some(action);
+                }
+            }
+            return update_kind;
+        }
+
+        if !self.is_editing_text() && self.bindings.is(Action::CommandPalette, &event) {
+            self.palette = Some(self::palette::CommandPalette::default());
+            return update_kind;
+        }
+
+        if !self.is_editing_text() {
+            if let Some(action) = self.destructive_action(&event) {
+                self.confirm(action, state);
+                return update_kind;
+            }
+        }
+
+        if !self.is_editing_text() {
+            let curr = self.refresh_interval;
+            if self.bindings.is(Action::RefreshLonger, &event) {
+                if let Some(&next) = Self::REFRESH_INTERVALS.iter().find(|&&i| i > curr) {
+                    self.refresh_interval = next;
+                }
+                return update_kind;
+            }
+            if self.bindings.is(Action::RefreshShorter, &event) {
+                if let Some(&next) = Self::REFRESH_INTERVALS.iter().rev().find(|&&i| i < curr) {
+                    self.refresh_interval = next;
+                }
+                return update_kind;
+            }
+        }
+
+        if !self.is_editing_text() && self.bindings.is(Action::ToggleTimeDisplay, &event) {
+            self.time_display = self.time_display.toggle();
+            return update_kind;
+        }
+
+        if !self.is_editing_text() {
+            let more = self.bindings.is(Action::MorePrecision, &event);
+            if more || self.bindings.is(Action::LessPrecision, &event) {
+                let precision = match self.state {
+                    TaskInstance(_) | AsyncOpInstance(_) => &mut self.styles.precision.detail,
+                    _ => &mut self.styles.precision.list,
+                };
+                *precision = DurPrecision::step(*precision, more);
+                return update_kind;
+            }
+        }
+
+        if !self.is_editing_text() && self.bindings.is(Action::CycleDurationUnit, &event) {
+            // The task groups are another way of displaying the tasks list, so
+            // they share its unit.
+            match self.state {
+                TasksList | TaskGroups(_) => self.tasks_list.cycle_dur_unit(),
+                ResourcesList => self.resources_list.cycle_dur_unit(),
+                AsyncOpsList => self.async_ops_list.cycle_dur_unit(),
+                _ => {}
+            }
+            return update_kind;
+        }
+
+        if cfg!(debug_assertions) && self.bindings.is(Action::ToggleInternerDebug, &event) {
+            self.show_interner_debug = !self.show_interner_debug;
+            return update_kind;
+        }
+
+        if cfg!(debug_assertions) && self.bindings.is(Action::DumpInterner, &event) {
+            interner_debug::dump(state.strings());
+            return update_kind;
+        }
+
         match self.state {
             TasksList => {
                 // The enter key changes views, so handle here since we can
                 // mutate the currently selected view.
                 match event {
-                    key!(Enter) => {
+                    _ if self.tasks_list.is_editing_search() => {
+                        self.tasks_list.update_search_input(event);
+                    }
+                    _ if self.tasks_list.is_resizing() => {
+                        self.tasks_list.update_resize_input(event, &self.bindings);
+                    }
+                    _ if self.tasks_list.notes.is_editing() => {
+                        self.tasks_list.notes.update_input(event);
+                    }
+                    _ if self.tasks_list.is_editing_goto() => {
+                        if let Some(id) = self.tasks_list.update_goto_input(event) {
+                            if let Some(task) = state.tasks_state().task(id) {
+                                self.tasks_list.select_item(&task);
+                                update_kind = UpdateKind::SelectTask(id);
+                                self.state = TaskInstance(self::task::TaskView::new(
+                                    task,
+                                    state.task_details_ref(),
+                                ));
+                            } else {
+                                self.tasks_list
+                                    .set_notice(Notice::error(format!("no such task: {}", id)));
+                            }
+                        }
+                    }
+                    _ if self.bindings.is(Action::Search, &event) => {
+                        self.tasks_list.start_search();
+                    }
+                    _ if self.bindings.is(Action::ToggleFuzzySearch, &event) => {
+                        self.tasks_list.toggle_fuzzy();
+                    }
+                    _ if self.bindings.is(Action::FilterCompleted, &event) => {
+                        self.tasks_list.toggle_filter();
+                    }
+                    _ if self.bindings.is(Action::CycleTaskKinds, &event) => {
+                        self.tasks_list.cycle_kind();
+                    }
+                    _ if self.bindings.is(Action::CopyTable, &event) => {
+                        self.tasks_list
+                            .copy(&self.styles, state, &mut self.clipboard);
+                    }
+                    _ if self.bindings.is(Action::GotoTask, &event) => {
+                        self.tasks_list.start_goto();
+                    }
+                    _ if self.bindings.is(Action::ExportFolded, &event) => {
+                        self.export_folded(state);
+                    }
+                    _ if self.bindings.is(Action::CycleTag, &event) => {
+                        if let Some(task) = self.tasks_list.selected_item().upgrade() {
+                            self.tasks_list.tags.cycle(task.borrow().id());
+                        }
+                    }
+                    _ if self.bindings.is(Action::ToggleTopTasks, &event) => {
+                        self.tasks_list.top.toggle();
+                    }
+                    _ if self.tasks_list.top.is_shown()
+                        && self.bindings.is(Action::CycleTopMetric, &event) =>
+                    {
+                        self.tasks_list.top.cycle_metric();
+                    }
+                    _ if self.bindings.is(Action::EditNote, &event) => {
+                        if let Some(task) = self.tasks_list.selected_item().upgrade() {
+                            self.tasks_list.notes.start_editing(task.borrow().id());
+                        }
+                    }
+                    _ if self.bindings.is(Action::ResizeColumns, &event) => {
+                        self.tasks_list.start_resizing();
+                    }
+                    _ if self.bindings.is(Action::Back, &event) => {
+                        self.tasks_list.clear_search();
+                    }
+                    _ if self.bindings.is(Action::ViewDetails, &event) => {
                         if let Some(task) = self.tasks_list.selected_item().upgrade() {
                             update_kind = UpdateKind::SelectTask(task.borrow().id());
                             self.state = TaskInstance(self::task::TaskView::new(
@@ -104,37 +475,231 @@ impl View {
                             ));
                         }
                     }
-                    key!(Char('r')) => {
+                    _ if self.bindings.is(Action::ResourcesView, &event) => {
                         self.state = ResourcesList;
                     }
+                    _ if self.bindings.is(Action::AsyncOpsView, &event) => {
+                        self.state = AsyncOpsList;
+                    }
+                    _ if self.bindings.is(Action::WarningsView, &event) => {
+                        self.state = WarningsList;
+                    }
+                    _ if self.bindings.is(Action::GroupTasks, &event) => {
+                        self.state = TaskGroups(self::task_groups::TaskGroupsView::new(
+                            self::task_groups::GroupBy::Target,
+                        ));
+                    }
                     _ => {
                         // otherwise pass on to view
-                        self.tasks_list.update_input(event);
+                        self.tasks_list.update_input(event, &self.bindings);
                     }
                 }
             }
             ResourcesList => {
                 match event {
-                    key!(Char('t')) => {
+                    _ if self.resources_list.is_resizing() => {
+                        self.resources_list
+                            .update_resize_input(event, &self.bindings);
+                    }
+                    _ if self.bindings.is(Action::ResizeColumns, &event) => {
+                        self.resources_list.start_resizing();
+                    }
+                    _ if self.bindings.is(Action::TasksView, &event) => {
+                        self.state = TasksList;
+                    }
+                    _ if self.bindings.is(Action::AsyncOpsView, &event) => {
+                        self.state = AsyncOpsList;
+                    }
+                    _ if self.bindings.is(Action::WarningsView, &event) => {
+                        self.state = WarningsList;
+                    }
+                    _ if self.bindings.is(Action::CopyTable, &event) => {
+                        self.resources_list
+                            .copy(&self.styles, state, &mut self.clipboard);
+                    }
+                    _ if self.bindings.is(Action::ViewDetails, &event) => {
+                        // Resources don't have a details view, so their async
+                        // ops are listed inline instead.
+                        if let Some(resource) = self.resources_list.selected_item().upgrade() {
+                            self.resources_list.toggle_expanded(resource.borrow().id());
+                        }
+                    }
+                    _ => {
+                        // otherwise pass on to view
+                        self.resources_list.update_input(event, &self.bindings);
+                    }
+                }
+            }
+            AsyncOpsList => {
+                match event {
+                    _ if self.async_ops_list.is_resizing() => {
+                        self.async_ops_list
+                            .update_resize_input(event, &self.bindings);
+                    }
+                    _ if self.bindings.is(Action::ResizeColumns, &event) => {
+                        self.async_ops_list.start_resizing();
+                    }
+                    _ if self.bindings.is(Action::TasksView, &event) => {
                         self.state = TasksList;
                     }
+                    _ if self.bindings.is(Action::ResourcesView, &event) => {
+                        self.state = ResourcesList;
+                    }
+                    _ if self.bindings.is(Action::WarningsView, &event) => {
+                        self.state = WarningsList;
+                    }
+                    _ if self.bindings.is(Action::ViewDetails, &event) => {
+                        if let Some(async_op) = self.async_ops_list.selected_item().upgrade() {
+                            self.state =
+                                AsyncOpInstance(self::async_op::AsyncOpView::new(async_op));
+                        }
+                    }
+                    _ if self.bindings.is(Action::CopyTable, &event) => {
+                        self.async_ops_list
+                            .copy(&self.styles, state, &mut self.clipboard);
+                    }
+                    _ if self.bindings.is(Action::HideColumn, &event) => {
+                        self.async_ops_list.hide_selected_column();
+                    }
+                    _ if self.bindings.is(Action::ShowAllColumns, &event) => {
+                        self.async_ops_list.show_all_columns();
+                    }
+                    _ if self.bindings.is(Action::FilterUnpolled, &event) => {
+                        self.async_ops_list.toggle_filter();
+                    }
                     _ => {
                         // otherwise pass on to view
-                        self.resources_list.update_input(event);
+                        self.async_ops_list.update_input(event, &self.bindings);
                     }
                 }
             }
+            WarningsList => match event {
+                _ if self.bindings.is(Action::TasksView, &event) => {
+                    self.state = TasksList;
+                }
+                _ if self.bindings.is(Action::ResourcesView, &event) => {
+                    self.state = ResourcesList;
+                }
+                _ if self.bindings.is(Action::AsyncOpsView, &event) => {
+                    self.state = AsyncOpsList;
+                }
+                _ => {
+                    let id = self
+                        .warnings_list
+                        .update_input(event, &self.bindings, state);
+                    // The task may have been pruned since the list was drawn.
+                    if let Some(task) = id.and_then(|id| state.tasks_state().task(id)) {
+                        update_kind = UpdateKind::SelectTask(task.borrow().id());
+                        self.state =
+                            TaskInstance(self::task::TaskView::new(task, state.task_details_ref()));
+                    }
+                }
+            },
+            TaskGroups(ref mut view) => match event {
+                _ if self.bindings.is(Action::TasksView, &event)
+                    || self.bindings.is(Action::Back, &event) =>
+                {
+                    self.state = TasksList;
+                }
+                _ if self.bindings.is(Action::ResourcesView, &event) => {
+                    self.state = ResourcesList;
+                }
+                _ if self.bindings.is(Action::AsyncOpsView, &event) => {
+                    self.state = AsyncOpsList;
+                }
+                _ if self.bindings.is(Action::WarningsView, &event) => {
+                    self.state = WarningsList;
+                }
+                _ if self.bindings.is(Action::GroupTasks, &event) => {
+                    // Grouping cycles from targets, to locations, and back to
+                    // the ungrouped task list.
+                    self.state = match view.group_by() {
+                        self::task_groups::GroupBy::Target => {
+                            TaskGroups(self::task_groups::TaskGroupsView::new(
+                                self::task_groups::GroupBy::Location,
+                            ))
+                        }
+                        self::task_groups::GroupBy::Location => TasksList,
+                    };
+                }
+                _ => {
+                    let id = view.update_input(event, &self.bindings, state);
+                    if let Some(task) = id.and_then(|id| state.tasks_state().task(id)) {
+                        update_kind = UpdateKind::SelectTask(task.borrow().id());
+                        self.state =
+                            TaskInstance(self::task::TaskView::new(task, state.task_details_ref()));
+                    }
+                }
+            },
+            AsyncOpInstance(ref view) => match event {
+                _ if self.bindings.is(Action::Back, &event) => {
+                    self.state = AsyncOpsList;
+                }
+                _ if self.bindings.is(Action::ViewDetails, &event) => {
+                    // The task may have been pruned, or may not be known yet
+                    // if the op hasn't been polled.
+                    let task = view.task_id().and_then(|id| state.tasks_state().task(id));
+                    if let Some(task) = task {
+                        update_kind = UpdateKind::SelectTask(task.borrow().id());
+                        self.state =
+                            TaskInstance(self::task::TaskView::new(task, state.task_details_ref()));
+                    }
+                }
+                _ => {}
+            },
             TaskInstance(ref mut view) => {
                 // The escape key changes views, so handle here since we can
                 // mutate the currently selected view.
                 match event {
-                    key!(Esc) => {
+                    _ if self.bindings.is(Action::Back, &event) => {
                         self.state = TasksList;
                         update_kind = UpdateKind::ExitTaskView;
                     }
+                    _ if self.bindings.is(Action::CycleTaskPanes, &event) => {
+                        self.task_panes = self.task_panes.next();
+                    }
+                    _ if self.bindings.is(Action::ViewParent, &event) => {
+                        let parent_id = view.parent_id();
+                        // The parent may have been pruned after it completed.
+                        match parent_id.map(|id| state.tasks_state().task(id)) {
+                            Some(Some(task)) => {
+                                self.tasks_list.select_item(&task);
+                                update_kind = UpdateKind::SelectTask(task.borrow().id());
+                                self.state = TaskInstance(self::task::TaskView::new(
+                                    task,
+                                    state.task_details_ref(),
+                                ));
+                            }
+                            Some(None) => {
+                                view.set_notice(Notice::error("parent no longer tracked"))
+                            }
+                            None => view.set_notice(Notice::error("the task has no parent")),
+                        }
+                    }
                     _ => {
                         // otherwise pass on to view
-                        view.update_input(event);
+                        let now = state
+                            .display_now()
+                            .expect("task view implies we've received an update");
+                        let lint_now = state.last_updated_at().unwrap_or(now);
+                        let note = self.tasks_list.notes.get(view.task_id());
+                        let child = view.update_input(
+                            event,
+                            now,
+                            lint_now,
+                            self.time_display,
+                            note,
+                            &self.bindings,
+                            &mut self.clipboard,
+                        );
+                        // The child may have been pruned since the view was drawn.
+                        if let Some(task) = child.and_then(|id| state.tasks_state().task(id)) {
+                            update_kind = UpdateKind::SelectTask(task.borrow().id());
+                            self.state = TaskInstance(self::task::TaskView::new(
+                                task,
+                                state.task_details_ref(),
+                            ));
+                        }
                     }
                 }
             }
@@ -148,33 +713,477 @@ impl View {
         area: layout::Rect,
         state: &mut State,
     ) {
-        match self.state {
+        self.shown_actions = match self.state {
             ViewState::TasksList => {
-                self.tasks_list.render(&self.styles, frame, area, state);
+                self.tasks_list
+                    .render(&self.styles, &self.bindings, frame, area, state)
             }
             ViewState::ResourcesList => {
-                self.resources_list.render(&self.styles, frame, area, state);
+                self.resources_list
+                    .render(&self.styles, &self.bindings, frame, area, state)
+            }
+            ViewState::AsyncOpsList => {
+                self.async_ops_list
+                    .render(&self.styles, &self.bindings, frame, area, state)
+            }
+            ViewState::WarningsList => {
+                self.warnings_list
+                    .render(&self.styles, &self.bindings, frame, area, state)
+            }
+            ViewState::TaskGroups(ref mut view) => {
+                let unit = self.tasks_list.dur_unit();
+                view.render(&self.styles, &self.bindings, frame, area, state, unit)
             }
             ViewState::TaskInstance(ref mut view) => {
                 let now = state
-                    .last_updated_at()
+                    .display_now()
                     .expect("task view implies we've received an update");
-                view.render(&self.styles, frame, area, now);
+                let note = self.tasks_list.notes.get(view.task_id());
+                view.render(
+                    &self.styles,
+                    &self.bindings,
+                    frame,
+                    area,
+                    state,
+                    now,
+                    self.time_display,
+                    self.task_panes,
+                    note,
+                )
             }
+            ViewState::AsyncOpInstance(ref view) => view.render(
+                &self.styles,
+                &self.bindings,
+                frame,
+                area,
+                state,
+                self.time_display,
+            ),
+        };
+        // The views' controls don't include switching between the top-level
+        // views, which are listed separately.
+        if !matches!(
+            self.state,
+            ViewState::TaskInstance(_) | ViewState::AsyncOpInstance(_)
+        ) {
+            let views = [
+                Action::TasksView,
+                Action::ResourcesView,
+                Action::AsyncOpsView,
+                Action::WarningsView,
+            ];
+            self.shown_actions
+                .extend(views.iter().map(|&action| (action, true)));
+        }
+
+        if self.show_interner_debug {
+            interner_debug::render(&self.styles, frame, area, state.strings());
+        }
+
+        if let Some(ref palette) = self.palette {
+            palette.render(
+                &self.styles,
+                &self.bindings,
+                frame,
+                area,
+                &self.shown_actions,
+            );
+        }
+
+        if let Some(ref confirmation) = self.confirmation {
+            confirmation.render(&self.styles, frame, area);
         }
 
         state.retain_active();
     }
 
+    /// Returns the view state which should be restored the next time the
+    /// console starts.
+    pub(crate) fn saved_state(&self) -> SavedState {
+        SavedState {
+            tasks: Some(self.tasks_list.saved_sort()),
+            resources: Some(self.resources_list.saved_sort()),
+            async_ops: Some(self.async_ops_list.saved_sort()),
+            ..SavedState::default()
+        }
+    }
+
+    /// Writes the busy time of every tracked task, summed by spawn location, to
+    /// a file in the current directory in the folded stacks format.
+    fn export_folded(&mut self, state: &State) {
+        let now = match state.display_now() {
+            Some(now) => now,
+            None => {
+                self.tasks_list
+                    .set_notice(Notice::error("no updates have been received"));
+                return;
+            }
+        };
+        let path = Path::new(Self::FOLDED_PATH);
+        let notice = match folded::write(path, state.tasks_state(), now, self.folded_by_target) {
+            Ok(()) => Notice::info(format!("wrote busy time by location to {}", path.display())),
+            Err(error) => {
+                tracing::warn!(%error, "failed to export busy time");
+                Notice::error(format!("{:#}", error))
+            }
+        };
+        self.tasks_list.set_notice(notice);
+    }
+
+    /// Returns `true` if text is being typed into the view, in which case key
+    /// presses should not trigger any other actions.
+    pub(crate) fn is_editing_text(&self) -> bool {
+        self.palette.is_some()
+            || self.confirmation.is_some()
+            || matches!(self.state, ViewState::TasksList)
+                && (self.tasks_list.is_editing_search()
+                    || self.tasks_list.is_editing_goto()
+                    || self.tasks_list.notes.is_editing())
+    }
+
+    /// Returns the key press that runs the action chosen in the command
+    /// palette, if one was just chosen.
+    ///
+    /// The action's first key is pressed, so that the action is handled
+    /// exactly as it would be if it was pressed, wherever it's handled. Only
+    /// actions which do something in the current view are chosen, and no two
+    /// of those share a key, so the key runs the chosen action.
+    pub(crate) fn take_palette_action(&mut self) -> Option<input::Event> {
+        let action = self.palette_action.take()?;
+        let key = self.bindings.keys(action).first().copied();
+        if key.is_none() {
+            self.notify(Notice::error(format!(
+                "{} has no key bound to it",
+                action.name().replace('_', " ")
+            )));
+        }
+        key.map(input::Key::to_event)
+    }
+
+    /// Returns the destructive action whose key was pressed, if it does
+    /// anything in the current view.
+    ///
+    /// Pins and acknowledgements can be cleared from any view, but tags and
+    /// notes are only displayed in the tasks list.
+    fn destructive_action(&self, event: &input::Event) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .map(|&(action, _)| action)
+            .filter(|action| action.is_destructive())
+            .find(|&action| self.bindings.is(action, event))
+            .filter(|action| {
+                !matches!(action, Action::ClearTags | Action::ClearNotes)
+                    || matches!(self.state, ViewState::TasksList)
+            })
+    }
+
+    /// Opens the prompt asking whether to run the destructive `action`, unless
+    /// there's nothing for it to clear.
+    fn confirm(&mut self, action: Action, state: &State) {
+        let count = match action {
+            Action::ClearTags => self.tasks_list.tags.len(),
+            Action::ClearNotes => self.tasks_list.notes.len(),
+            Action::UnpinAll => state.tasks_state().pinned_count(),
+            Action::ClearAcknowledged => state.acknowledgements().len(),
+            _ => return,
+        };
+        if count == 0 {
+            let none = self::confirm::things(action, 0);
+            self.notify(Notice::info(format!("there are {} to clear", none)));
+            return;
+        }
+        self.confirmation = Some(self::confirm::Confirmation::new(action, count));
+    }
+
+    /// Runs the destructive action confirmed in the prompt, if one was just
+    /// confirmed, keeping what it cleared so that it can be undone.
+    ///
+    /// This replaces whatever the previous destructive action cleared, so
+    /// only the last one can be undone.
+    pub(crate) fn run_confirmed(&mut self, state: &mut State) {
+        use self::confirm::Cleared;
+        let cleared = match self.confirmed.take() {
+            Some(Action::ClearTags) => Cleared::Tags(self.tasks_list.tags.take()),
+            Some(Action::ClearNotes) => Cleared::Notes(self.tasks_list.notes.take()),
+            Some(Action::UnpinAll) => Cleared::Pins(state.tasks_state_mut().unpin_all()),
+            Some(Action::ClearAcknowledged) => {
+                Cleared::Acknowledged(state.clear_acknowledged_warnings())
+            }
+            _ => return,
+        };
+        let action = cleared.action();
+        let (_, verbed) = self::confirm::verbs(action);
+        let mut text = format!("{} {}", verbed, cleared.describe());
+        if let Some(key) = self.bindings.keys(Action::Undo).first() {
+            text.push_str(&format!(", press {} to undo", key));
+        }
+        self.notify(Notice::info(text));
+        self.cleared = Some(cleared);
+    }
+
+    /// Restores what the last confirmed destructive action cleared, if it
+    /// hasn't been restored already.
+    ///
+    /// Tags and notes which have been added since are kept, and pins are only
+    /// restored for tasks which are still tracked.
+    pub(crate) fn undo(&mut self, state: &mut State) {
+        use self::confirm::Cleared;
+        let cleared = match self.cleared.take() {
+            Some(cleared) => cleared,
+            None => {
+                self.notify(Notice::error("nothing to undo"));
+                return;
+            }
+        };
+        let text = format!("restored {}", cleared.describe());
+        match cleared {
+            Cleared::Tags(tags) => self.tasks_list.tags.restore(tags),
+            Cleared::Notes(notes) => self.tasks_list.notes.restore(notes),
+            Cleared::Pins(pins) => state.tasks_state_mut().pin_all(pins),
+            Cleared::Acknowledged(acks) => state.restore_acknowledged_warnings(acks),
+        }
+        self.notify(Notice::info(text));
+    }
+
+    /// Displays `notice` in the current view, if it displays notices.
+    fn notify(&mut self, notice: Notice) {
+        match self.state {
+            ViewState::TasksList => self.tasks_list.set_notice(notice),
+            ViewState::ResourcesList => self.resources_list.set_notice(notice),
+            ViewState::AsyncOpsList => self.async_ops_list.set_notice(notice),
+            ViewState::TaskInstance(ref mut view) => view.set_notice(notice),
+            _ => {}
+        }
+    }
+
+    /// Returns the ID of the task being inspected, if the task view is
+    /// displayed.
+    pub(crate) fn current_task_id(&self) -> Option<u64> {
+        match self.state {
+            ViewState::TaskInstance(ref view) => Some(view.task_id()),
+            _ => None,
+        }
+    }
+
+    /// Returns the ID of the task being inspected, or of the selected task in
+    /// the task list.
+    pub(crate) fn selected_task_id(&self) -> Option<u64> {
+        match self.state {
+            ViewState::TasksList => self
+                .tasks_list
+                .selected_item()
+                .upgrade()
+                .map(|task| task.borrow().id()),
+            _ => self.current_task_id(),
+        }
+    }
+
+    /// Forgets the rows of every table, after the state has been reset, such
+    /// as after reconnecting to a target which may have restarted. The task
+    /// or async op being inspected, if any, is closed, returning to its list.
+    pub(crate) fn reset(&mut self) {
+        match self.state {
+            ViewState::TaskInstance(_) => self.state = ViewState::TasksList,
+            ViewState::AsyncOpInstance(_) => self.state = ViewState::AsyncOpsList,
+            _ => {}
+        }
+        self.tasks_list.forget_rows();
+        self.resources_list.forget_rows();
+        self.async_ops_list.forget_rows();
+        // What was cleared refers to tasks by their IDs, which may have been
+        // reused by a restarted target.
+        self.cleared = None;
+    }
+
+    /// Displays `notice` in the task view, if it is open.
+    pub(crate) fn set_notice(&mut self, notice: Notice) {
+        if let ViewState::TaskInstance(ref mut view) = self.state {
+            view.set_notice(notice);
+        }
+    }
+
     pub(crate) fn current_view(&self) -> &ViewState {
         &self.state
     }
+
+    /// Returns the controls for switching between the top-level views.
+    pub(crate) fn views_controls(&self) -> Spans<'static> {
+        let tasks = ControlDisplay::new("tasks", &[Action::TasksView], &self.bindings);
+        let resources = ControlDisplay::new("resources", &[Action::ResourcesView], &self.bindings);
+        let async_ops = ControlDisplay::new("async ops", &[Action::AsyncOpsView], &self.bindings);
+        let warnings = ControlDisplay::new("warnings", &[Action::WarningsView], &self.bindings);
+        let mut spans = vec![Span::raw("views: ")];
+        for (idx, control) in [tasks, resources, async_ops, warnings].iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::raw(", "));
+            }
+            spans.extend(control.to_spans(&self.styles).0);
+        }
+        Spans::from(spans)
+    }
 }
 
 pub(crate) fn bold<'a>(text: impl Into<Cow<'a, str>>) -> Span<'a> {
     Span::styled(text, Style::default().add_modifier(style::Modifier::BOLD))
 }
 
+// === impl TimeDisplay ===
+
+impl TimeDisplay {
+    fn toggle(self) -> Self {
+        match self {
+            Self::Relative => Self::Absolute,
+            Self::Absolute => Self::Relative,
+        }
+    }
+
+    /// Formats `time`, which is relative to `now` when displaying relative
+    /// times.
+    pub(crate) fn format(self, now: SystemTime, time: SystemTime) -> String {
+        match self {
+            Self::Relative => format!("{:?} ago", now.duration_since(time).unwrap_or_default()),
+            Self::Absolute => humantime::format_rfc3339_millis(time).to_string(),
+        }
+    }
+}
+
+// === impl DurPrecision ===
+
+impl DurPrecision {
+    /// Durations are stored in nanoseconds, so more digits than this never
+    /// display anything different.
+    const MAX: usize = 9;
+
+    /// Returns `precision` with one more digit if `more` is `true`, or one
+    /// fewer digit otherwise.
+    fn step(precision: usize, more: bool) -> usize {
+        if more {
+            cmp::min(precision + 1, Self::MAX)
+        } else {
+            precision.saturating_sub(1)
+        }
+    }
+
+    /// Returns how many characters wide table columns of durations displayed
+    /// in `unit` are, so that there's room for every digit (and the unit!)
+    pub(crate) fn list_len(&self, unit: DurationUnit) -> usize {
+        match unit {
+            // The decimal point, and either four digits before it and a one
+            // character unit (`s`) or three digits and a two character unit.
+            DurationUnit::Auto => cmp::max(DUR_LEN, self.list + 6),
+            // The decimal point, a two character unit, and enough digits
+            // before the decimal point for a few hours. Longer durations are
+            // cut off, but they're rarely what a fixed unit is used to compare.
+            DurationUnit::Millis => cmp::max(DUR_LEN, self.list + 10),
+            DurationUnit::Micros => cmp::max(DUR_LEN, self.list + 13),
+        }
+    }
+
+    /// Formats `dur` for a table whose durations are displayed in `unit`.
+    pub(crate) fn list_dur(&self, unit: DurationUnit, dur: Duration) -> String {
+        unit.format(dur, self.list)
+    }
+
+    /// Clamps each precision to at most [`DurPrecision::MAX`].
+    pub(crate) fn validate(&mut self) {
+        for precision in [&mut self.list, &mut self.detail, &mut self.histogram] {
+            if *precision > Self::MAX {
+                tracing::warn!(
+                    precision = *precision,
+                    "duration precision is over {} digits, using {}",
+                    Self::MAX,
+                    Self::MAX
+                );
+                *precision = Self::MAX;
+            }
+        }
+    }
+}
+
+impl Default for DurPrecision {
+    fn default() -> Self {
+        Self {
+            // Tables are only updated every second, so it doesn't make a ton
+            // of sense to have a lot of precision (and this makes sure
+            // there's room for the unit!)
+            list: 4,
+            detail: 4,
+            histogram: 2,
+            list_unit: DurationUnit::Auto,
+        }
+    }
+}
+
+// === impl DurationUnit ===
+
+impl DurationUnit {
+    /// Returns the unit after this one, cycling back to [`DurationUnit::Auto`]
+    /// after the last.
+    fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Millis,
+            Self::Millis => Self::Micros,
+            Self::Micros => Self::Auto,
+        }
+    }
+
+    /// Formats `dur` in this unit, with `precision` digits after the decimal
+    /// point.
+    ///
+    /// In a fixed unit, a duration too short to display any nonzero digit
+    /// with `precision` digits is displayed with as many more digits as it
+    /// needs, so that it isn't mistaken for zero.
+    pub(crate) fn format(self, dur: Duration, precision: usize) -> String {
+        let (nanos_per_unit, digits, unit) = match self {
+            Self::Auto => return format!("{:.prec$?}", dur, prec = precision),
+            Self::Millis => (1_000_000.0, 6, "ms"),
+            Self::Micros => (1_000.0, 3, "µs"),
+        };
+        let value = dur.as_nanos() as f64 / nanos_per_unit;
+        let mut precision = precision;
+        // Durations are whole nanoseconds, so `digits` digits always display
+        // a nonzero duration.
+        while value > 0.0 && precision < digits && value * 10f64.powi(precision as i32) < 0.5 {
+            precision += 1;
+        }
+        format!("{:.prec$}{}", value, unit, prec = precision)
+    }
+}
+
+impl Notice {
+    /// How long a notice remains visible after it was created.
+    const DURATION: Duration = Duration::from_secs(3);
+
+    pub(crate) fn info(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            is_error: false,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub(crate) fn error(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            is_error: true,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= Self::DURATION
+    }
+
+    pub(crate) fn render(&self, styles: &Styles) -> Span<'static> {
+        let style = if self.is_error {
+            styles.fg_or(Color::Red, Modifier::BOLD)
+        } else {
+            styles.fg(Color::Green)
+        };
+        Span::styled(self.text.clone(), style)
+    }
+}
+
 impl Width {
     pub(crate) fn new(curr: u16) -> Self {
         Self { curr }
@@ -0,0 +1,221 @@
+//! The command palette: an overlay listing every action along with the keys
+//! bound to it, which can be fuzzily searched, and runs the selected action as
+//! if its key had been pressed. Actions which do nothing in the current view
+//! are listed, dimmed, but aren't run.
+//!
+//! This makes actions discoverable once there are more of them than a view's
+//! controls can display.
+use crate::{
+    fuzzy,
+    input::{self, Action, KeyBindings, KeyCode, KeyModifiers},
+    view::{self, bold},
+};
+use tui::{
+    layout,
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Clear, List, ListItem, ListState, Paragraph},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct CommandPalette {
+    query: String,
+    /// The index of the selected entry, among the entries matching the query.
+    selected: usize,
+}
+
+/// The outcome of a key press while the command palette is open.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum PaletteInput {
+    /// The palette stays open.
+    Continue,
+    /// The palette was closed without running an action.
+    Close,
+    /// The palette was closed to run the selected action.
+    Run(Action),
+    /// The palette was closed without running the selected action, since it
+    /// does nothing in the current view.
+    Inapplicable(Action),
+}
+
+/// An action matching the palette's query.
+struct Entry {
+    action: Action,
+    name: String,
+    /// Whether the action does anything in the current view.
+    applicable: bool,
+}
+
+impl CommandPalette {
+    /// The most entries that are listed at once. The selection scrolls through
+    /// the rest.
+    const MAX_ENTRIES: u16 = 15;
+
+    /// Handles a key press, where `applicable` lists the actions of the
+    /// current view's controls, and whether each one is enabled.
+    pub(crate) fn update_input(
+        &mut self,
+        event: &input::Event,
+        bindings: &KeyBindings,
+        applicable: &[(Action, bool)],
+    ) -> PaletteInput {
+        let key = match event {
+            input::Event::Key(key) => key,
+            _ => return PaletteInput::Continue,
+        };
+        if key.code == KeyCode::Esc || bindings.is(Action::CommandPalette, event) {
+            return PaletteInput::Close;
+        }
+
+        let len = self.entries(applicable).len();
+        match key.code {
+            KeyCode::Enter => {
+                return match self.entries(applicable).get(self.selected) {
+                    Some(entry) if entry.applicable => PaletteInput::Run(entry.action),
+                    Some(entry) => PaletteInput::Inapplicable(entry.action),
+                    None => PaletteInput::Close,
+                };
+            }
+            KeyCode::Up if len > 0 => {
+                self.selected = self.selected.checked_sub(1).unwrap_or(len - 1);
+            }
+            KeyCode::Down if len > 0 => {
+                self.selected = (self.selected + 1) % len;
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.query.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        PaletteInput::Continue
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &self,
+        styles: &view::Styles,
+        bindings: &KeyBindings,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        applicable: &[(Action, bool)],
+    ) {
+        let entries = self.entries(applicable);
+        let keys = entries
+            .iter()
+            .map(|entry| {
+                bindings
+                    .keys(entry.action)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect::<Vec<_>>();
+        let name_width = entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
+        let highlight = Style::default().add_modifier(Modifier::BOLD);
+        let items = entries
+            .iter()
+            .zip(keys)
+            .map(|(entry, keys)| {
+                let mut name =
+                    super::tasks::highlight_fuzzy_match(&entry.name, &self.query, highlight);
+                let padding = name_width.saturating_sub(entry.name.len()) + 2;
+                name.0.push(Span::raw(" ".repeat(padding)));
+                name.0.push(Span::raw(keys));
+                if !entry.applicable {
+                    for span in &mut name.0 {
+                        span.style = span.style.patch(styles.disabled());
+                    }
+                }
+                ListItem::new(name)
+            })
+            .collect::<Vec<_>>();
+
+        // Add 3 for the borders and the query.
+        let height = (items.len() as u16).clamp(1, Self::MAX_ENTRIES) + 3;
+        let height = height.min(area.height);
+        let width = area.width.saturating_sub(4).min(70);
+        let overlay_area = layout::Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+        let block = styles
+            .border_block()
+            .title(Spans::from(vec![bold("Command palette ")]));
+        let inner = block.inner(overlay_area);
+        frame.render_widget(Clear, overlay_area);
+        frame.render_widget(block, overlay_area);
+        if inner.height == 0 {
+            return;
+        }
+
+        let query_area = layout::Rect { height: 1, ..inner };
+        let list_area = layout::Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        let cursor = styles.if_utf8("\u{258F}", "_");
+        let query = Spans::from(vec![
+            Span::raw("> "),
+            Span::raw(self.query.clone()),
+            Span::raw(cursor),
+        ]);
+        frame.render_widget(Paragraph::new(query), query_area);
+        if items.is_empty() {
+            frame.render_widget(Paragraph::new("no matching actions"), list_area);
+            return;
+        }
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected.min(items.len() - 1)));
+        let list = List::new(items)
+            .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut list_state);
+    }
+
+    /// Returns the actions matching the query, with the actions that do
+    /// anything in the current view first, and the best matches first among
+    /// those. Otherwise, actions are listed in the order they're declared.
+    fn entries(&self, applicable: &[(Action, bool)]) -> Vec<Entry> {
+        let mut entries = Action::ALL
+            .iter()
+            .filter_map(|&(action, _)| {
+                let name = action.name().replace('_', " ");
+                let score = if self.query.is_empty() {
+                    0
+                } else {
+                    fuzzy::fuzzy_match(&name, &self.query)?.score
+                };
+                let applicable = action.is_global()
+                    || applicable
+                        .iter()
+                        .any(|&(a, enabled)| a == action && enabled);
+                Some((
+                    score,
+                    Entry {
+                        action,
+                        name,
+                        applicable,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(score, entry)| (!entry.applicable, -score));
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
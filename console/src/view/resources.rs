@@ -1,30 +1,40 @@
 use crate::{
+    input::{Action, KeyBindings},
     state::{
-        resources::{Resource, SortBy},
+        async_ops::{AsyncOpsState, ResourcePolls},
+        resources::{Resource, ResourcesState, SortBy},
         State,
     },
     view::{
         self, bold,
+        controls::{Controls, DisabledControls},
         table::{self, TableList, TableListState},
-        DUR_LEN, DUR_PRECISION,
     },
 };
-
+use std::{cmp, collections::HashMap, time::SystemTime};
 use tui::{
     layout,
-    style::{self, Color, Style},
-    text::Spans,
-    widgets::{Cell, Paragraph, Row, Table},
+    style::{self, Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Cell, List, ListItem, Row, Table},
 };
 
 #[derive(Debug, Default)]
 pub(crate) struct ResourcesTable {}
 
+/// The number of resources listed in the panel of resources whose async ops
+/// are polled the most.
+const MAX_POLLED_RESOURCES: usize = 5;
+
+/// The number of async ops listed below an expanded resource.
+const MAX_EXPANDED_ASYNC_OPS: usize = 5;
+
 impl TableList for ResourcesTable {
     type Row = Resource;
     type Sort = SortBy;
 
     const HEADER: &'static [&'static str] = &[
+        "Warn",
         "ID",
         "Kind",
         "Total",
@@ -33,35 +43,68 @@ impl TableList for ResourcesTable {
         "Location",
         "Attributes",
     ];
+    const RIGHT_ALIGNED: &'static [usize] = &[1, 3];
+
+    fn text_row(
+        resource: &Resource,
+        styles: &view::Styles,
+        unit: view::DurationUnit,
+        now: SystemTime,
+        _: &State,
+    ) -> Vec<String> {
+        let warnings = match resource.warnings().len() {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        vec![
+            warnings,
+            resource.id().to_string(),
+            resource.kind().to_owned(),
+            table::dur_text(styles, unit, resource.total(now)),
+            resource.target().to_owned(),
+            resource.concrete_type().to_owned(),
+            resource.location().to_owned(),
+            table::spans_text(resource.formatted_attributes()),
+        ]
+    }
 
     fn render<B: tui::backend::Backend>(
         table_list_state: &mut TableListState<Self>,
         styles: &view::Styles,
+        bindings: &KeyBindings,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
         state: &mut State,
-    ) {
-        let now = if let Some(now) = state.last_updated_at() {
+    ) -> Vec<(Action, bool)> {
+        let now = if let Some(now) = state.display_now() {
             now
         } else {
             // If we have never gotten an update yet, skip...
-            return;
+            return Vec::new();
         };
+        let unit = table_list_state.dur_unit();
 
-        table_list_state
-            .sorted_items
-            .extend(state.resources_state_mut().take_new_resources());
-        table_list_state
-            .sort_by
-            .sort(now, &mut table_list_state.sorted_items);
+        // A frozen view keeps its rows and their order until it's unfrozen.
+        if !state.is_view_frozen() {
+            table_list_state
+                .sorted_items
+                .extend(state.resources_state_mut().take_new_resources());
+            table_list_state
+                .sort_by
+                .sort(now, &mut table_list_state.sorted_items);
+        }
 
-        let mut id_width = view::Width::new(Self::HEADER[0].len() as u16);
-        let mut kind_width = view::Width::new(Self::HEADER[1].len() as u16);
-        let mut target_width = view::Width::new(Self::HEADER[3].len() as u16);
-        let mut type_width = view::Width::new(Self::HEADER[4].len() as u16);
-        let mut location_width = view::Width::new(Self::HEADER[5].len() as u16);
+        let mut warn_width = view::Width::new(table_list_state.header_width(0));
+        let mut id_width = view::Width::new(table_list_state.header_width(1));
+        let mut kind_width = view::Width::new(table_list_state.header_width(2));
+        let mut target_width = view::Width::new(table_list_state.header_width(4));
+        let mut type_width = view::Width::new(table_list_state.header_width(5));
+        let mut location_width = view::Width::new(table_list_state.header_width(6));
+        let async_ops_state = state.async_ops_state();
+        let expanded = table_list_state.expanded();
 
         let rows = {
+            let warn_width = &mut warn_width;
             let id_width = &mut id_width;
             let kind_width = &mut kind_width;
             let target_width = &mut target_width;
@@ -75,7 +118,35 @@ impl TableList for ResourcesTable {
                     let resource = resource.upgrade()?;
                     let resource = resource.borrow();
 
+                    let n_warnings = resource.warnings().len();
+                    let warnings = if n_warnings > 0 {
+                        let n_warnings = n_warnings.to_string();
+                        warn_width.update_len(n_warnings.len() + 2); // add 2 for the warning icon + whitespace
+                        Cell::from(Spans::from(vec![
+                            styles.warning_narrow(),
+                            Span::from(n_warnings),
+                        ]))
+                    } else {
+                        Cell::from("")
+                    };
+
+                    // An expanded resource lists its async ops below its
+                    // attributes, which is the widest column.
+                    let mut attributes = Text::from(Spans::from(
+                        resource
+                            .formatted_attributes()
+                            .iter()
+                            .flatten()
+                            .cloned()
+                            .collect::<Vec<_>>(),
+                    ));
+                    if expanded.contains(&resource.id()) {
+                        attributes.extend(async_op_lines(styles, &resource, async_ops_state));
+                    }
+                    let height = attributes.height() as u16;
+
                     let mut row = Row::new(vec![
+                        warnings,
                         Cell::from(id_width.update_str(format!(
                             "{:>width$}",
                             resource.id(),
@@ -83,23 +154,16 @@ impl TableList for ResourcesTable {
                         ))),
                         Cell::from(kind_width.update_str(resource.kind()).to_owned()),
                         Cell::from(styles.time_units(format!(
-                            "{:>width$.prec$?}",
-                            resource.total(now),
-                            width = DUR_LEN,
-                            prec = DUR_PRECISION,
+                            "{:>width$}",
+                            styles.precision.list_dur(unit, resource.total(now)),
+                            width = styles.precision.list_len(unit)
                         ))),
                         Cell::from(target_width.update_str(resource.target()).to_owned()),
                         Cell::from(type_width.update_str(resource.concrete_type()).to_owned()),
-                        Cell::from(location_width.update_str(resource.location().to_owned())),
-                        Cell::from(Spans::from(
-                            resource
-                                .formatted_attributes()
-                                .iter()
-                                .flatten()
-                                .cloned()
-                                .collect::<Vec<_>>(),
-                        )),
+                        Cell::from(location_width.update_str(resource.location()).to_owned()),
+                        Cell::from(attributes),
                     ]);
+                    row = row.height(height);
 
                     if resource.dropped() {
                         row = row.style(styles.terminated());
@@ -109,26 +173,7 @@ impl TableList for ResourcesTable {
                 })
         };
 
-        let (selected_style, header_style) = if let Some(cyan) = styles.color(Color::Cyan) {
-            (Style::default().fg(cyan), Style::default())
-        } else {
-            (
-                Style::default().remove_modifier(style::Modifier::REVERSED),
-                Style::default().add_modifier(style::Modifier::REVERSED),
-            )
-        };
-        let header_style = header_style.add_modifier(style::Modifier::BOLD);
-
-        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
-            let cell = Cell::from(value);
-            if idx == table_list_state.selected_column {
-                cell.style(selected_style)
-            } else {
-                cell
-            }
-        }))
-        .height(1)
-        .style(header_style);
+        let header = table_list_state.header(styles);
 
         let table = if table_list_state.sort_descending {
             Table::new(rows)
@@ -141,45 +186,213 @@ impl TableList for ResourcesTable {
             table_list_state.len()
         ))]);
 
+        let warnings = state
+            .resources_state()
+            .warnings()
+            .map(|warning| {
+                ListItem::new(Text::from(Spans::from(vec![
+                    styles.warning_wide(),
+                    Span::from(format!("{} {}", warning.count(), warning.summary())),
+                ])))
+            })
+            .collect::<Vec<_>>();
+
+        let polled = most_polled(
+            state.async_ops_state().polls_by_resource(),
+            state.resources_state(),
+        );
+
         let layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
             .margin(0);
 
+        let view_controls = if table_list_state.is_resizing() {
+            table::resize_controls(bindings)
+        } else {
+            table::view_controls(bindings, "expand/collapse async ops")
+        };
+        let controls = Controls::new(
+            &view_controls,
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Dim,
+        )
+        .with_notice(table_list_state.notice(), styles);
+        // add 2 for the top and bottom borders
+        let warnings_height = if warnings.is_empty() {
+            0
+        } else {
+            warnings.len() as u16 + 2
+        };
+        let polled_height = if polled.is_empty() {
+            0
+        } else {
+            polled.len() as u16 + 2
+        };
         let chunks = layout
             .constraints(
                 [
-                    layout::Constraint::Length(1),
-                    layout::Constraint::Min(area.height - 1),
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Length(warnings_height),
+                    layout::Constraint::Length(polled_height),
+                    layout::Constraint::Min(
+                        area.height
+                            .saturating_sub(controls.height() + warnings_height + polled_height),
+                    ),
                 ]
                 .as_ref(),
             )
             .split(area);
         let controls_area = chunks[0];
-        let tasks_area = chunks[1];
+        let warnings_area = chunks[1];
+        let polled_area = chunks[2];
+        let tasks_area = chunks[3];
 
         let attributes_width = layout::Constraint::Percentage(100);
         let widths = &[
+            warn_width.constraint(),
             id_width.constraint(),
             kind_width.constraint(),
-            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(styles.precision.list_len(unit) as u16),
             target_width.constraint(),
             type_width.constraint(),
             location_width.constraint(),
             attributes_width,
         ];
+        let widths = table_list_state.column_widths(widths, tasks_area.width);
 
         let table = table
             .header(header)
             .block(block)
-            .widths(widths)
+            .widths(&widths)
             .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
             .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
 
         frame.render_stateful_widget(table, tasks_area, &mut table_list_state.table_state);
-        frame.render_widget(Paragraph::new(table::controls(styles)), controls_area);
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        if !warnings.is_empty() {
+            let block = styles
+                .border_block()
+                .title(Spans::from(vec![bold("Warnings")]));
+            frame.render_widget(List::new(warnings).block(block), warnings_area);
+        }
+        if !polled.is_empty() {
+            let block = styles.border_block().title(Spans::from(vec![bold(
+                "Most polled resources (by async ops)",
+            )]));
+            frame.render_widget(List::new(polled).block(block), polled_area);
+        }
 
         table_list_state
             .sorted_items
             .retain(|t| t.upgrade().is_some());
+        let resources_state = state.resources_state();
+        table_list_state.retain_expanded(|&id| resources_state.contains(id));
+        actions
     }
 }
+
+/// Returns a line for each of the async ops performed on `resource`, with its
+/// source and whether it's being polled, for listing below the resource
+/// while it's expanded.
+///
+/// At most [`MAX_EXPANDED_ASYNC_OPS`] async ops are listed, lowest ID first,
+/// followed by a line saying how many more there are.
+fn async_op_lines(
+    styles: &view::Styles,
+    resource: &Resource,
+    async_ops: &AsyncOpsState,
+) -> Vec<Spans<'static>> {
+    let mut ops = async_ops.resource_async_ops(resource).collect::<Vec<_>>();
+    if ops.is_empty() {
+        return vec![Spans::from(Span::styled("no async ops", styles.disabled()))];
+    }
+    ops.sort_unstable_by_key(|async_op| async_op.borrow().id());
+
+    let arrow = styles.if_utf8("\u{21B3} ", "> ");
+    let mut lines = ops
+        .iter()
+        .take(MAX_EXPANDED_ASYNC_OPS)
+        .map(|async_op| {
+            let async_op = async_op.borrow();
+            let (op_state, style) = if async_op.dropped() {
+                ("dropped", styles.dropped())
+            } else if async_op.is_polling() {
+                ("polling", styles.fg_or(Color::Green, Modifier::BOLD))
+            } else if async_op.polls() == 0 {
+                ("not yet polled", Style::default())
+            } else {
+                ("idle", Style::default())
+            };
+            Spans::from(vec![
+                Span::raw(arrow.to_owned()),
+                bold(async_op.id().to_string()),
+                Span::raw(format!(" {} ", async_op.source())),
+                Span::styled(op_state, style),
+                Span::raw(format!(" ({} polls)", async_op.polls())),
+            ])
+        })
+        .collect::<Vec<_>>();
+    if ops.len() > MAX_EXPANDED_ASYNC_OPS {
+        lines.push(Spans::from(Span::styled(
+            format!("+{} more", ops.len() - MAX_EXPANDED_ASYNC_OPS),
+            styles.disabled(),
+        )));
+    }
+    lines
+}
+
+/// Lists the resources whose async ops were polled most often since the last
+/// update, with their total polls, for spotting contended resources.
+///
+/// Async ops whose resource isn't known are listed together as an unknown
+/// resource. Resources whose async ops have never been polled aren't listed.
+fn most_polled(
+    polls: &HashMap<Option<u64>, ResourcePolls>,
+    resources: &ResourcesState,
+) -> Vec<ListItem<'static>> {
+    let mut polled = polls
+        .iter()
+        .filter(|(_, polls)| polls.polls > 0)
+        .collect::<Vec<_>>();
+    // Rates aren't `Ord`, so sort by thousandths of a poll per second.
+    polled.sort_unstable_by_key(|&(&resource_id, polls)| {
+        let rate = polls.rate.map(|rate| (rate * 1000.0) as u64);
+        (cmp::Reverse((rate, polls.polls)), resource_id)
+    });
+    polled.truncate(MAX_POLLED_RESOURCES);
+
+    polled
+        .into_iter()
+        .map(|(&resource_id, polls)| {
+            let rate = polls
+                .rate
+                .map(|rate| format!("{:.1}", rate))
+                .unwrap_or_else(|| "-".to_owned());
+            let resource = match resource_id.and_then(|id| resources.resource(id)) {
+                Some(resource) => {
+                    let resource = resource.borrow();
+                    format!(
+                        "resource {} ({} {})",
+                        resource.id(),
+                        resource.kind(),
+                        resource.concrete_type()
+                    )
+                }
+                None => "unknown resource".to_owned(),
+            };
+            let ops = if polls.async_ops == 1 { "op" } else { "ops" };
+            ListItem::new(Spans::from(vec![
+                bold(format!("{:>8} polls/s", rate)),
+                Span::raw(format!(
+                    " {:>10} polls by {:>4} async {:<3}  ",
+                    polls.polls, polls.async_ops, ops
+                )),
+                Span::raw(resource),
+            ]))
+        })
+        .collect()
+}
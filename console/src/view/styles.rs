@@ -1,4 +1,4 @@
-use crate::config;
+use crate::{config, view, warnings::Severity};
 use std::{borrow::Cow, str::FromStr};
 use tui::{
     style::{Color, Modifier, Style},
@@ -9,12 +9,15 @@ use tui::{
 pub struct Styles {
     palette: Palette,
     toggles: config::ColorToggles,
+    theme: Theme,
     pub(crate) utf8: bool,
+    pub(crate) precision: view::DurPrecision,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 #[repr(u8)]
 pub enum Palette {
+    #[default]
     NoColors,
     /// Use ANSI 8 color palette only.
     Ansi8,
@@ -26,6 +29,28 @@ pub enum Palette {
     All,
 }
 
+/// Colors which replace the built-in colors for some kinds of text, set in the
+/// `[theme]` section of the config file.
+///
+/// Each color which isn't set keeps the built-in color for each place it is
+/// used, so the default theme displays the built-in palette unchanged.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Theme {
+    /// Warning icons and messages, and the badges of warnings which aren't
+    /// critical.
+    pub(crate) warning: Option<Color>,
+    /// The badges of critical warnings.
+    pub(crate) critical: Option<Color>,
+    /// Durations, by unit: picoseconds, nanoseconds, microseconds,
+    /// milliseconds and seconds.
+    pub(crate) time_units: Option<[Color; 5]>,
+    pub(crate) borders: Option<Color>,
+    /// The selected column of a table.
+    pub(crate) selection: Option<Color>,
+    /// The badge displayed while updates are paused.
+    pub(crate) paused_badge: Option<Color>,
+}
+
 fn fg_style(color: Color) -> Style {
     Style::default().fg(color)
 }
@@ -37,10 +62,25 @@ impl Styles {
         Self {
             palette: config.determine_palette(),
             toggles: config.toggles(),
+            theme: Theme::default(),
             utf8: config.is_utf8(),
+            precision: view::DurPrecision::default(),
         }
     }
 
+    /// Sets how many digits are displayed after the decimal point of
+    /// durations.
+    pub(crate) fn with_precision(mut self, precision: view::DurPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Sets the colors which replace the built-in colors.
+    pub(crate) fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
     pub fn error_init(&self) -> color_eyre::Result<()> {
         use color_eyre::config::{HookBuilder, Theme};
 
@@ -74,9 +114,17 @@ impl Styles {
             }
         }
 
-        let style = match self.palette {
-            Palette::NoColors => return Span::raw(text),
-            Palette::Ansi8 | Palette::Ansi16 => match text.as_ref() {
+        let style = match (self.palette, self.theme.time_units) {
+            (Palette::NoColors, _) => return Span::raw(text),
+            (_, Some([ps, ns, us, ms, s])) => match text.as_ref() {
+                t if t.ends_with("ps") => self.fg(ps),
+                t if t.ends_with("ns") => self.fg(ns),
+                t if t.ends_with("µs") || t.ends_with("us") => self.fg(us),
+                t if t.ends_with("ms") => self.fg(ms),
+                t if t.ends_with('s') => self.fg(s),
+                _ => Style::default(),
+            },
+            (Palette::Ansi8 | Palette::Ansi16, None) => match text.as_ref() {
                 s if s.ends_with("ps") => fg_style(Color::Blue),
                 s if s.ends_with("ns") => fg_style(Color::Green),
                 s if s.ends_with("µs") || s.ends_with("us") => fg_style(Color::Yellow),
@@ -84,7 +132,7 @@ impl Styles {
                 s if s.ends_with('s') => fg_style(Color::Magenta),
                 _ => Style::default(),
             },
-            Palette::Ansi256 | Palette::All => match text.as_ref() {
+            (Palette::Ansi256 | Palette::All, None) => match text.as_ref() {
                 s if s.ends_with("ps") => fg_style(Color::Indexed(40)), // green 3
                 s if s.ends_with("ns") => fg_style(Color::Indexed(41)), // spring green 3
                 s if s.ends_with("µs") || s.ends_with("us") => fg_style(Color::Indexed(42)), // spring green 2
@@ -105,6 +153,60 @@ impl Styles {
         Style::default().add_modifier(Modifier::DIM)
     }
 
+    /// Returns the style for rows representing things which have been dropped
+    /// but are still displayed, such as completed async ops.
+    ///
+    /// This is like [`Styles::terminated`], but crossed out, so that dropped
+    /// rows can be told apart even when dimmed text isn't supported.
+    pub fn dropped(&self) -> Style {
+        if !self.toggles.color_terminated {
+            return Style::default();
+        }
+
+        self.terminated().add_modifier(Modifier::CROSSED_OUT)
+    }
+
+    /// Returns the style for controls that don't currently do anything.
+    pub fn disabled(&self) -> Style {
+        Style::default().add_modifier(Modifier::DIM)
+    }
+
+    /// Returns the style for table cells whose values just jumped.
+    ///
+    /// This is reversed, so that it stands out even when colors are disabled.
+    pub fn changed(&self) -> Style {
+        Style::default().add_modifier(Modifier::REVERSED)
+    }
+
+    /// Returns the style for the badge displayed while updates are paused.
+    ///
+    /// This is reversed, so that it stands out even when colors are disabled.
+    pub fn paused(&self) -> Style {
+        self.fg(self.paused_color())
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    }
+
+    /// Returns the color of the badge displayed while updates are paused.
+    pub fn paused_color(&self) -> Color {
+        self.theme.paused_badge.unwrap_or(Color::Red)
+    }
+
+    /// Returns the style for a histogram drawn behind another to compare them,
+    /// such as the poll times recorded before the histogram was reset.
+    pub fn baseline(&self) -> Style {
+        self.fg_or(Color::DarkGray, Modifier::DIM)
+    }
+
+    /// Returns the style for warning messages, such as events being dropped.
+    pub fn warning(&self) -> Style {
+        self.fg(self.theme.warning.unwrap_or(Color::LightYellow))
+    }
+
+    /// Returns the color of the selected column of a table.
+    pub fn selection(&self) -> Color {
+        self.theme.selection.unwrap_or(Color::Cyan)
+    }
+
     pub fn fg(&self, color: Color) -> Style {
         if let Some(color) = self.color(color) {
             Style::default().fg(color)
@@ -113,17 +215,66 @@ impl Styles {
         }
     }
 
+    /// Returns a style using `color`, or `modifier` instead if `color` isn't
+    /// supported, such as when colors are disabled by `--no-colors`.
+    ///
+    /// This is for text whose color means something that would otherwise be
+    /// lost without colors.
+    pub fn fg_or(&self, color: Color, modifier: Modifier) -> Style {
+        match self.color(color) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default().add_modifier(modifier),
+        }
+    }
+
     pub fn warning_wide(&self) -> Span<'static> {
         Span::styled(
             self.if_utf8("\u{26A0} ", "/!\\ "),
-            self.fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            self.warning().add_modifier(Modifier::BOLD),
         )
     }
 
     pub fn warning_narrow(&self) -> Span<'static> {
         Span::styled(
             self.if_utf8("\u{26A0} ", "! "),
-            self.fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            self.warning().add_modifier(Modifier::BOLD),
+        )
+    }
+
+    /// Returns a badge displaying a count of warnings, colored by the
+    /// `severity` of the most serious of them.
+    ///
+    /// If colors are disabled, the badge is bold and reversed instead, so that
+    /// it still stands out, although severities can't be told apart.
+    pub fn warning_badge(&self, count: usize, severity: Severity) -> Span<'static> {
+        let color = match severity {
+            Severity::Critical => self.theme.critical.unwrap_or(Color::Red),
+            Severity::Warning => self.theme.warning.unwrap_or(Color::Yellow),
+            Severity::Info => Color::Blue,
+        };
+        let style = match self.color(color) {
+            Some(color) => Style::default()
+                .fg(Color::Black)
+                .bg(color)
+                .add_modifier(Modifier::BOLD),
+            None => Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        };
+        Span::styled(format!(" {} ", count), style)
+    }
+
+    /// Returns the marker displayed next to pinned tasks.
+    pub fn pinned(&self) -> Span<'static> {
+        Span::styled(
+            self.if_utf8("\u{2605} ", "* "),
+            self.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )
+    }
+
+    /// Returns the marker displayed next to tasks with notes.
+    pub fn noted(&self) -> Span<'static> {
+        Span::styled(
+            self.if_utf8("\u{270E} ", "~ "),
+            self.fg(Color::Yellow).add_modifier(Modifier::BOLD),
         )
     }
 
@@ -160,9 +311,11 @@ impl Styles {
 
     pub fn border_block(&self) -> tui::widgets::Block<'_> {
         if self.utf8 {
+            let style = self.theme.borders.map(|color| self.fg(color));
             tui::widgets::Block::default()
                 .borders(tui::widgets::Borders::ALL)
                 .border_type(tui::widgets::BorderType::Rounded)
+                .border_style(style.unwrap_or_default())
         } else {
             // TODO(eliza): configure an ascii-art border set instead?
             Default::default()
@@ -170,6 +323,47 @@ impl Styles {
     }
 }
 
+// === impl Theme ===
+
+/// Parses a color for the `[theme]` section of the config file.
+///
+/// Colors may be one of the 16 ANSI color names, like `red` or `light-blue`,
+/// an ANSI 256-color index, like `208`, or an RGB color, like `#ff8700`.
+pub(crate) fn parse_color(s: &str) -> Result<Color, String> {
+    let name = s.trim().to_ascii_lowercase().replace(['-', '_', ' '], "");
+    let color = match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+            match (channel(1), channel(3), channel(5)) {
+                (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+                _ => return Err(format!("invalid RGB color `{}`", s)),
+            }
+        }
+        index => index
+            .parse()
+            .map(Color::Indexed)
+            .map_err(|_| format!("unknown color `{}`", s))?,
+    };
+    Ok(color)
+}
+
 // === impl Palette ===
 
 impl FromStr for Palette {
@@ -187,9 +381,3 @@ impl FromStr for Palette {
         }
     }
 }
-
-impl Default for Palette {
-    fn default() -> Self {
-        Self::NoColors
-    }
-}
@@ -1,29 +1,95 @@
 use crate::{
-    input, state,
-    view::{self, bold},
+    clipboard::Clipboard,
+    config::{SavedSort, SavedWidth},
+    dump,
+    input::{self, Action, KeyBindings},
+    state,
+    view::{self, controls::ControlDisplay, top_tasks::TopTasks, Notice},
+};
+use std::{
+    cmp,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    str::FromStr,
+    time::SystemTime,
 };
-use std::convert::TryFrom;
 use tui::{
     layout,
-    text::{self, Span, Spans, Text},
-    widgets::TableState,
+    style::{self, Color, Style},
+    text::Span,
+    widgets::{Cell, Row, TableState},
 };
 
 use std::cell::RefCell;
-use std::rc::Weak;
+use std::rc::{Rc, Weak};
 
 pub(crate) trait TableList {
     type Row;
     type Sort: SortBy + TryFrom<usize>;
     const HEADER: &'static [&'static str];
+    /// The indices of the columns which are right-aligned when the table is
+    /// copied as text.
+    const RIGHT_ALIGNED: &'static [usize];
+
+    /// Returns `true` if `row` matches the search `query`, which has already
+    /// been converted to lowercase.
+    ///
+    /// By default, every row matches.
+    fn matches(_row: &Self::Row, _query: &str) -> bool {
+        true
+    }
+
+    /// Returns how well `row` fuzzily matches the search `query`, which has
+    /// already been converted to lowercase, where higher is better, or `None`
+    /// if it doesn't match.
+    ///
+    /// By default, every row that [`TableList::matches`] the query scores the
+    /// same.
+    fn fuzzy_score(row: &Self::Row, query: &str) -> Option<i64> {
+        Some(0).filter(|_| Self::matches(row, query))
+    }
+
+    /// Returns `true` if `row` is displayed while the table's filter is
+    /// toggled on.
+    ///
+    /// By default, every row is displayed.
+    fn filter(_row: &Self::Row) -> bool {
+        true
+    }
+
+    /// The names of the kinds of rows that can be displayed on their own,
+    /// which are cycled through in order, starting with every row.
+    ///
+    /// By default, rows aren't divided into kinds.
+    const KINDS: &'static [&'static str] = &["all"];
+
+    /// Returns `true` if `row` is of the kind at index `kind` in
+    /// [`TableList::KINDS`].
+    ///
+    /// By default, every row is of every kind.
+    fn is_kind(_row: &Self::Row, _kind: usize) -> bool {
+        true
+    }
+
+    /// Returns the plain-text contents of each of `row`'s cells, in the same
+    /// order as [`TableList::HEADER`], for copying the table as text.
+    fn text_row(
+        row: &Self::Row,
+        styles: &view::Styles,
+        unit: view::DurationUnit,
+        now: SystemTime,
+        state: &state::State,
+    ) -> Vec<String>;
 
     fn render<B: tui::backend::Backend>(
         state: &mut TableListState<Self>,
         styles: &view::Styles,
+        bindings: &KeyBindings,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
         state: &mut state::State,
-    ) where
+    ) -> Vec<(Action, bool)>
+    where
         Self: Sized;
 }
 
@@ -31,59 +97,852 @@ pub(crate) trait SortBy {
     fn as_column(&self) -> usize;
 }
 
+/// The direction a table is sorted in, by the values in its sort column.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum SortDirection {
+    /// The highest values are at the top.
+    Descending,
+    /// The lowest values are at the top.
+    Ascending,
+}
+
 pub(crate) struct TableListState<T: TableList> {
     pub(crate) sorted_items: Vec<Weak<RefCell<T::Row>>>,
     pub(crate) sort_by: T::Sort,
     pub(crate) selected_column: usize,
     pub(crate) sort_descending: bool,
+    /// The value of `sort_descending` that each column was last sorted with,
+    /// keyed by column index, so that selecting a column again restores the
+    /// direction it was sorted in.
+    directions: HashMap<usize, bool>,
+    /// The direction that columns which haven't been sorted by before are
+    /// sorted in.
+    default_direction: SortDirection,
     pub(crate) table_state: TableState,
+    /// Set when `g` was the last key pressed, so that a second `g` scrolls to
+    /// the top of the table.
+    pending_g: bool,
+    /// The current search query, if any.
+    search: String,
+    /// Whether the search query is being edited.
+    editing_search: bool,
+    /// Whether items are searched with [`TableList::fuzzy_score`], rather
+    /// than [`TableList::matches`].
+    fuzzy: bool,
+    /// Whether only the items matching [`TableList::filter`] are displayed.
+    filtered: bool,
+    /// The index in [`TableList::KINDS`] of the kind of items displayed.
+    kind: usize,
+    /// The indices of the columns which aren't displayed.
+    ///
+    /// The table is still sorted by its sort column while it is hidden.
+    hidden_columns: HashSet<usize>,
+    /// Items which don't match the current search query, filter or kind.
+    hidden_items: Vec<Weak<RefCell<T::Row>>>,
+    /// The ID typed into the goto prompt, if it is open.
+    goto: Option<String>,
+    notice: Option<Notice>,
+    pub(crate) changes: ChangeHighlights,
+    /// The IDs of the rows which are expanded to display more about them, for
+    /// tables whose rows can be expanded.
+    expanded: HashSet<u64>,
+    /// The unit that the table's durations are displayed in.
+    dur_unit: view::DurationUnit,
+    /// The widths that columns have been resized to, keyed by column index.
+    widths: HashMap<usize, u16>,
+    /// The width each column would have had if it hadn't been resized, as of
+    /// the last time the table was rendered, or `None` for columns which fill
+    /// the remaining space.
+    derived_widths: Vec<Option<u16>>,
+    /// Whether the selected column is being resized.
+    resizing: bool,
+    pub(crate) tags: RowTags,
+    pub(crate) notes: RowNotes,
+    /// The panel listing the tasks with the most of a metric, for the tasks
+    /// table.
+    pub(crate) top: TopTasks,
+}
+
+/// The colors that rows have been tagged with to make them stand out, which
+/// last until they're cleared or the rows are removed.
+#[derive(Debug, Default)]
+pub(crate) struct RowTags {
+    /// The index in [`RowTags::COLORS`] of the color each tagged row is
+    /// tagged with, keyed by the row's ID.
+    tags: HashMap<u64, usize>,
+}
+
+/// Free-text notes attached to rows, such as to remember why a task is being
+/// watched, which last until they're cleared or the rows are removed.
+#[derive(Debug, Default)]
+pub(crate) struct RowNotes {
+    /// The note attached to each row, keyed by the row's ID.
+    notes: HashMap<u64, String>,
+    /// The ID of the row whose note is being edited, and the text typed so
+    /// far, if the note prompt is open.
+    editing: Option<(u64, String)>,
+}
+
+/// Tracks the values of a table's cells between frames, so that cells whose
+/// values jumped since the last update can be briefly highlighted.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeHighlights {
+    /// The relative change, as a percentage of the previous value, above
+    /// which a cell is highlighted, or `None` if changes aren't highlighted.
+    threshold_percent: Option<f64>,
+    /// The last value of each tracked cell, keyed by the row's ID and the
+    /// cell's column.
+    cells: HashMap<(u64, usize), ChangedCell>,
+}
+
+#[derive(Debug)]
+struct ChangedCell {
+    value: f64,
+    /// The number of frames for which the cell remains highlighted.
+    frames_left: u8,
+}
+
+impl<T> TableListState<T>
+where
+    T: TableList,
+    T::Sort: Default,
+{
+    /// Returns a new table, sorted by the column saved by a previous run of
+    /// the console, if there is one.
+    ///
+    /// If the saved column doesn't exist (such as if the saved state is from
+    /// an older version of the console), the table uses its default sorting.
+    pub(in crate::view) fn from_saved(saved: Option<&SavedSort>) -> Self {
+        let mut table = Self::default();
+        if let Some(saved) = saved {
+            if let Ok(sort_by) = T::Sort::try_from(saved.column) {
+                table.sort_by = sort_by;
+                table.selected_column = saved.column;
+                table.sort_descending = saved.descending;
+            }
+            let highest_first = saved.highest_first.iter().map(|&column| (column, false));
+            let lowest_first = saved.lowest_first.iter().map(|&column| (column, true));
+            table.directions = highest_first
+                .chain(lowest_first)
+                .filter(|&(column, _)| T::Sort::try_from(column).is_ok())
+                .collect();
+            table
+                .directions
+                .insert(table.sort_by.as_column(), table.sort_descending);
+            table.widths = saved
+                .widths
+                .iter()
+                .filter(|saved| saved.column < T::HEADER.len())
+                .map(|saved| (saved.column, saved.width))
+                .collect();
+        }
+        table
+    }
 }
 
 impl<T: TableList> TableListState<T> {
+    /// The most rows that are copied by [`TableListState::copy`], so that
+    /// copying a huge table doesn't produce megabytes of text.
+    const MAX_COPIED_ROWS: usize = 200;
+
+    /// The narrowest and widest that a column can be resized to.
+    const MIN_COLUMN_WIDTH: u16 = 1;
+    const MAX_COLUMN_WIDTH: u16 = 200;
+
+    pub(in crate::view) fn saved_sort(&self) -> SavedSort {
+        let mut directions = self.directions.clone();
+        directions.insert(self.sort_by.as_column(), self.sort_descending);
+        let (lowest_first, highest_first): (Vec<_>, Vec<_>) = directions
+            .into_iter()
+            .partition(|&(_, descending)| descending);
+        let columns = |directions: Vec<(usize, bool)>| {
+            let mut columns = directions
+                .into_iter()
+                .map(|(column, _)| column)
+                .collect::<Vec<_>>();
+            columns.sort_unstable();
+            columns
+        };
+        let mut widths = self
+            .widths
+            .iter()
+            .map(|(&column, &width)| SavedWidth { column, width })
+            .collect::<Vec<_>>();
+        widths.sort_unstable_by_key(|saved| saved.column);
+        SavedSort {
+            column: self.sort_by.as_column(),
+            descending: self.sort_descending,
+            highest_first: columns(highest_first),
+            lowest_first: columns(lowest_first),
+            widths,
+        }
+    }
+
+    /// Sets the direction that columns which haven't been sorted by before are
+    /// sorted in, including the current sort column if it hasn't been.
+    pub(in crate::view) fn set_default_direction(&mut self, direction: SortDirection) {
+        self.default_direction = direction;
+        self.sort_descending = self
+            .directions
+            .get(&self.sort_by.as_column())
+            .copied()
+            .unwrap_or_else(|| direction.sort_descending());
+    }
+
+    /// Forgets the table's rows, such as when the state they came from is
+    /// reset, along with the tags, notes and expanded rows, which refer to rows
+    /// by ID. The table's sorting, search and columns are kept.
+    pub(in crate::view) fn forget_rows(&mut self) {
+        self.sorted_items.clear();
+        self.hidden_items.clear();
+        self.expanded.clear();
+        self.tags.clear();
+        self.notes.clear();
+        self.table_state.select(None);
+    }
+
+    /// Returns the unit that the table's durations are displayed in.
+    pub(in crate::view) fn dur_unit(&self) -> view::DurationUnit {
+        self.dur_unit
+    }
+
+    pub(in crate::view) fn set_dur_unit(&mut self, unit: view::DurationUnit) {
+        self.dur_unit = unit;
+    }
+
+    /// Displays the table's durations in the next unit.
+    pub(in crate::view) fn cycle_dur_unit(&mut self) {
+        self.dur_unit = self.dur_unit.next();
+    }
+
+    /// Returns the table's header row.
+    ///
+    /// The header of the column the table is sorted by is highlighted, and
+    /// marked with an arrow indicating the sort direction.
+    pub(in crate::view) fn header(&self, styles: &view::Styles) -> Row<'static> {
+        let (selected_style, header_style) = if let Some(color) = styles.color(styles.selection()) {
+            (Style::default().fg(color), Style::default())
+        } else {
+            (
+                Style::default().remove_modifier(style::Modifier::REVERSED),
+                Style::default().add_modifier(style::Modifier::REVERSED),
+            )
+        };
+        let header_style = header_style.add_modifier(style::Modifier::BOLD);
+
+        // The rows are displayed in reverse order unless `sort_descending` is
+        // set, so that by default, the highest values are at the top.
+        let arrow = if self.resizing {
+            styles.if_utf8("\u{2194}", "<>")
+        } else if self.sort_descending {
+            styles.if_utf8("\u{25B2}", "^")
+        } else {
+            styles.if_utf8("\u{25BC}", "v")
+        };
+        let columns = T::HEADER
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| self.is_column_visible(idx));
+        // The sort column may be hidden, in which case the selected column is
+        // highlighted without an arrow, since the table isn't sorted by it.
+        let sort_column = self.sort_by.as_column();
+        Row::new(columns.map(|(idx, &value)| {
+            if idx == self.selected_column && idx != sort_column && !self.resizing {
+                Cell::from(value).style(selected_style)
+            } else if idx == self.selected_column {
+                Cell::from(format!("{} {}", value, arrow)).style(selected_style)
+            } else {
+                Cell::from(value)
+            }
+        }))
+        .height(1)
+        .style(header_style)
+    }
+
+    /// Returns the width needed to display the header of the column at `idx`,
+    /// including the sort direction arrow if it is the selected column.
+    ///
+    /// This is the same while the column is being resized, since the arrow is
+    /// replaced with one marking the column being resized.
+    pub(in crate::view) fn header_width(&self, idx: usize) -> u16 {
+        let len = T::HEADER[idx].len() as u16;
+        if idx == self.selected_column {
+            len + 2
+        } else {
+            len
+        }
+    }
+
     pub(in crate::view) fn len(&self) -> usize {
         self.sorted_items.len()
     }
 
-    pub(in crate::view) fn update_input(&mut self, event: input::Event) {
-        // Clippy likes to remind us that we could use an `if let` here, since
-        // the match only has one arm...but this is a `match` because I
-        // anticipate adding more cases later...
-        #[allow(clippy::single_match)]
-        match event {
-            input::Event::Key(event) => self.key_input(event),
-            _ => {
-                // do nothing for now
-                // TODO(eliza): mouse input would be cool...
-            }
-        }
-    }
-
-    pub(in crate::view) fn key_input(&mut self, input::KeyEvent { code, .. }: input::KeyEvent) {
-        use input::KeyCode::*;
-        let header_len = T::HEADER.len();
-        match code {
-            Left => {
-                if self.selected_column == 0 {
-                    self.selected_column = header_len - 1;
-                } else {
-                    self.selected_column -= 1;
+    /// Returns the number of items which are hidden by the current search
+    /// query, filter or kind, as of the last call to
+    /// [`TableListState::apply_search`].
+    pub(in crate::view) fn hidden_len(&self) -> usize {
+        self.hidden_items.len()
+    }
+
+    /// Returns `true` if the column at `idx` is displayed.
+    pub(in crate::view) fn is_column_visible(&self, idx: usize) -> bool {
+        !self.hidden_columns.contains(&idx)
+    }
+
+    /// Returns the number of columns which are displayed.
+    pub(in crate::view) fn visible_columns(&self) -> usize {
+        T::HEADER.len() - self.hidden_columns.len()
+    }
+
+    /// Displays only the columns at the given indices, or every column if
+    /// none of them exist.
+    pub(in crate::view) fn set_visible_columns(&mut self, columns: &[usize]) {
+        self.hidden_columns = (0..T::HEADER.len())
+            .filter(|idx| !columns.contains(idx))
+            .collect();
+        if self.hidden_columns.len() == T::HEADER.len() {
+            self.hidden_columns.clear();
+        }
+        if !self.is_column_visible(self.selected_column) {
+            self.selected_column = self.step_column(true);
+        }
+    }
+
+    /// Hides the selected column, and selects the next displayed column
+    /// without changing how the table is sorted.
+    ///
+    /// The last displayed column can't be hidden.
+    pub(in crate::view) fn hide_selected_column(&mut self) {
+        if self.visible_columns() <= 1 {
+            self.set_notice(Notice::error("can't hide the only displayed column"));
+            return;
+        }
+        self.hidden_columns.insert(self.selected_column);
+        self.selected_column = self.step_column(true);
+    }
+
+    pub(in crate::view) fn show_all_columns(&mut self) {
+        self.hidden_columns.clear();
+    }
+
+    /// Returns the constraints for the widths of the displayed columns, given
+    /// the width of every column if it hasn't been resized, in the same order
+    /// as [`TableList::HEADER`].
+    ///
+    /// Columns which have been resized are as wide as they were resized to.
+    /// If the columns are wider than the table's `area_width` in total, every
+    /// column is narrowed in proportion to its width, so that the columns
+    /// keep their relative widths when the terminal is too narrow for them.
+    /// Tables whose columns haven't been resized are displayed as they
+    /// otherwise would be, with the columns that don't fit left off the end.
+    pub(in crate::view) fn column_widths(
+        &mut self,
+        widths: &[layout::Constraint],
+        area_width: u16,
+    ) -> Vec<layout::Constraint> {
+        use layout::Constraint;
+        self.derived_widths = widths
+            .iter()
+            .map(|width| match *width {
+                Constraint::Length(len) => Some(len),
+                _ => None,
+            })
+            .collect();
+        let mut constraints = widths
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| self.is_column_visible(idx))
+            .map(|(idx, &width)| {
+                self.widths
+                    .get(&idx)
+                    .map(|&width| Constraint::Length(width))
+                    .unwrap_or(width)
+            })
+            .collect::<Vec<_>>();
+        if self.widths.is_empty() {
+            return constraints;
+        }
+
+        // Leave room for the borders, the highlight symbol, and the space
+        // between each pair of columns.
+        let spacing = constraints.len().saturating_sub(1) as u16;
+        let available =
+            area_width.saturating_sub(2 + view::TABLE_HIGHLIGHT_SYMBOL.len() as u16 + spacing);
+        let total: u32 = constraints
+            .iter()
+            .map(|width| match *width {
+                Constraint::Length(len) => len as u32,
+                _ => 0,
+            })
+            .sum();
+        if total > available as u32 {
+            for width in &mut constraints {
+                if let Constraint::Length(len) = width {
+                    *len = cmp::max(1, (*len as u32 * available as u32 / total) as u16);
                 }
             }
-            Right => {
-                if self.selected_column == header_len - 1 {
-                    self.selected_column = 0;
-                } else {
-                    self.selected_column += 1;
+        }
+        constraints
+    }
+
+    /// Returns `true` if the selected column is being resized, in which case
+    /// all key presses should be passed to
+    /// [`TableListState::update_resize_input`].
+    pub(in crate::view) fn is_resizing(&self) -> bool {
+        self.resizing
+    }
+
+    /// Starts resizing the selected column.
+    pub(in crate::view) fn start_resizing(&mut self) {
+        self.resizing = true;
+    }
+
+    /// Handles a key press while the selected column is being resized.
+    ///
+    /// The keys which otherwise select the column to the left or right make
+    /// the column narrower or wider, and [`Action::ResetColumnWidth`] returns
+    /// it to the width it would have if it was never resized. Pressing
+    /// [`Action::ResizeColumns`] again, enter or escape stops resizing.
+    pub(in crate::view) fn update_resize_input(
+        &mut self,
+        event: input::Event,
+        bindings: &KeyBindings,
+    ) {
+        if bindings.is(Action::SelectColumnLeft, &event) {
+            self.resize_selected_column(-1);
+        } else if bindings.is(Action::SelectColumnRight, &event) {
+            self.resize_selected_column(1);
+        } else if bindings.is(Action::ResetColumnWidth, &event) {
+            self.widths.remove(&self.selected_column);
+        } else if bindings.is(Action::ResizeColumns, &event)
+            || bindings.is(Action::ViewDetails, &event)
+            || bindings.is(Action::Back, &event)
+        {
+            self.resizing = false;
+        }
+    }
+
+    /// Makes the selected column `delta` characters wider, or narrower if it
+    /// is negative.
+    ///
+    /// A column which fills the remaining space can't be resized, since it
+    /// doesn't have a width of its own.
+    fn resize_selected_column(&mut self, delta: i32) {
+        let idx = self.selected_column;
+        let derived = self.derived_widths.get(idx).copied().flatten();
+        let width = match self.widths.get(&idx).copied().or(derived) {
+            Some(width) => width,
+            None => {
+                self.set_notice(Notice::error(format!(
+                    "the {} column fills the remaining space, and can't be resized",
+                    T::HEADER[idx]
+                )));
+                return;
+            }
+        };
+        let width = (width as i32 + delta)
+            .clamp(Self::MIN_COLUMN_WIDTH as i32, Self::MAX_COLUMN_WIDTH as i32);
+        self.widths.insert(idx, width as u16);
+    }
+
+    /// Returns the header of the column the table is sorted by, if that
+    /// column is hidden.
+    pub(in crate::view) fn hidden_sort_column(&self) -> Option<&'static str> {
+        let column = self.sort_by.as_column();
+        Some(T::HEADER[column]).filter(|_| !self.is_column_visible(column))
+    }
+
+    /// Returns the index of the next displayed column to the right of the
+    /// selected column, or to the left if `right` is `false`, wrapping
+    /// around at either end.
+    fn step_column(&self, right: bool) -> usize {
+        let len = T::HEADER.len();
+        let mut idx = self.selected_column;
+        for _ in 0..len {
+            idx = if right {
+                (idx + 1) % len
+            } else {
+                (idx + len - 1) % len
+            };
+            if self.is_column_visible(idx) {
+                break;
+            }
+        }
+        idx
+    }
+
+    /// Returns the current search query, or `None` if the table isn't being
+    /// searched.
+    pub(in crate::view) fn search(&self) -> Option<&str> {
+        if self.search.is_empty() && !self.editing_search {
+            None
+        } else {
+            Some(&self.search)
+        }
+    }
+
+    /// Returns the lowercase search query that items are matched against, or
+    /// `None` if every item matches, such as when the query is empty or only
+    /// whitespace.
+    pub(in crate::view) fn search_query(&self) -> Option<String> {
+        Some(self.search.trim().to_lowercase()).filter(|query| !query.is_empty())
+    }
+
+    /// Returns `true` if searches match fuzzily.
+    pub(in crate::view) fn is_fuzzy(&self) -> bool {
+        self.fuzzy
+    }
+
+    /// Toggles between matching searches fuzzily and as substrings.
+    pub(in crate::view) fn toggle_fuzzy(&mut self) {
+        self.fuzzy = !self.fuzzy;
+        let mode = if self.fuzzy { "fuzzy" } else { "substring" };
+        self.set_notice(Notice::info(format!("searching by {} match", mode)));
+    }
+
+    /// Returns `true` if the search query is being edited, in which case all
+    /// key presses should be passed to [`TableListState::update_search_input`].
+    pub(in crate::view) fn is_editing_search(&self) -> bool {
+        self.editing_search
+    }
+
+    /// Starts editing the search query.
+    pub(in crate::view) fn start_search(&mut self) {
+        self.editing_search = true;
+    }
+
+    /// Clears the search query, displaying every item again.
+    pub(in crate::view) fn clear_search(&mut self) {
+        self.search.clear();
+        self.editing_search = false;
+    }
+
+    /// Returns `true` if only the items matching [`TableList::filter`] are
+    /// displayed.
+    pub(in crate::view) fn is_filtered(&self) -> bool {
+        self.filtered
+    }
+
+    /// Toggles whether only the items matching [`TableList::filter`] are
+    /// displayed.
+    pub(in crate::view) fn toggle_filter(&mut self) {
+        self.filtered = !self.filtered;
+    }
+
+    /// Returns the name of the kind of items displayed, or `None` if every
+    /// item is displayed.
+    pub(in crate::view) fn kind(&self) -> Option<&'static str> {
+        Some(T::KINDS[self.kind]).filter(|_| self.kind != 0)
+    }
+
+    /// Displays only the next kind of items in [`TableList::KINDS`], or every
+    /// item after the last kind.
+    pub(in crate::view) fn cycle_kind(&mut self) {
+        self.kind = (self.kind + 1) % T::KINDS.len();
+    }
+
+    /// Handles a key press while the search query is being edited.
+    ///
+    /// Typed characters are added to the query, and backspace removes them.
+    /// Enter stops editing the query, keeping the current search, and escape
+    /// clears the search.
+    pub(in crate::view) fn update_search_input(&mut self, event: input::Event) {
+        use input::{Event, KeyCode, KeyEvent, KeyModifiers};
+        if let Event::Key(KeyEvent { code, modifiers }) = event {
+            match code {
+                KeyCode::Char(c) if (modifiers - KeyModifiers::SHIFT).is_empty() => {
+                    self.search.push(c)
                 }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Enter => self.editing_search = false,
+                KeyCode::Esc => self.clear_search(),
+                _ => {}
             }
-            Char('i') => self.sort_descending = !self.sort_descending,
-            Down => self.scroll_next(),
-            Up => self.scroll_prev(),
-            _ => {} // do nothing for now...
         }
+    }
 
-        if let Ok(sort_by) = T::Sort::try_from(self.selected_column) {
+    /// Returns the ID typed into the goto prompt so far, or `None` if the
+    /// prompt isn't open.
+    pub(in crate::view) fn goto(&self) -> Option<&str> {
+        self.goto.as_deref()
+    }
+
+    /// Returns `true` if the goto prompt is open, in which case all key
+    /// presses should be passed to [`TableListState::update_goto_input`].
+    pub(in crate::view) fn is_editing_goto(&self) -> bool {
+        self.goto.is_some()
+    }
+
+    /// Opens the goto prompt.
+    pub(in crate::view) fn start_goto(&mut self) {
+        self.goto = Some(String::new());
+    }
+
+    /// Handles a key press while the goto prompt is open.
+    ///
+    /// Only digits are added to the ID, and backspace removes them. Escape
+    /// closes the prompt. Enter closes the prompt and returns the typed ID, or
+    /// `None` if nothing was typed.
+    pub(in crate::view) fn update_goto_input(&mut self, event: input::Event) -> Option<u64> {
+        use input::{Event, KeyCode, KeyEvent};
+        let goto = self.goto.as_mut()?;
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            match code {
+                KeyCode::Char(c) if c.is_ascii_digit() => goto.push(c),
+                KeyCode::Backspace => {
+                    goto.pop();
+                }
+                KeyCode::Enter => {
+                    let goto = self.goto.take()?;
+                    if goto.is_empty() {
+                        return None;
+                    }
+                    return match goto.parse() {
+                        Ok(id) => Some(id),
+                        Err(_) => {
+                            self.set_notice(Notice::error(format!("invalid ID: {}", goto)));
+                            None
+                        }
+                    };
+                }
+                KeyCode::Esc => self.goto = None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Selects the row for `item`, if it's displayed.
+    pub(in crate::view) fn select_item(&mut self, item: &Rc<RefCell<T::Row>>) {
+        let idx = self.sorted_items.iter().position(|row| {
+            row.upgrade()
+                .map(|row| Rc::ptr_eq(&row, item))
+                .unwrap_or(false)
+        });
+        if let Some(idx) = idx {
+            let selected = if self.sort_descending {
+                idx
+            } else {
+                self.sorted_items.len() - idx - 1
+            };
+            self.table_state.select(Some(selected));
+        }
+    }
+
+    /// Returns the IDs of the rows which are expanded.
+    pub(in crate::view) fn expanded(&self) -> &HashSet<u64> {
+        &self.expanded
+    }
+
+    /// Expands the row with the given ID, or collapses it if it's already
+    /// expanded.
+    pub(in crate::view) fn toggle_expanded(&mut self, id: u64) {
+        if !self.expanded.remove(&id) {
+            self.expanded.insert(id);
+        }
+    }
+
+    /// Collapses the expanded rows whose IDs don't match `f`, such as rows
+    /// which have been removed from the table.
+    pub(in crate::view) fn retain_expanded(&mut self, f: impl FnMut(&u64) -> bool) {
+        self.expanded.retain(f);
+    }
+
+    /// Displays `notice` until it expires.
+    pub(in crate::view) fn set_notice(&mut self, notice: Notice) {
+        self.notice = Some(notice);
+    }
+
+    /// Returns the current notice, if there is one and it hasn't expired.
+    pub(in crate::view) fn notice(&mut self) -> Option<&Notice> {
+        if self
+            .notice
+            .as_ref()
+            .map(Notice::is_expired)
+            .unwrap_or(false)
+        {
+            self.notice = None;
+        }
+        self.notice.as_ref()
+    }
+
+    /// Copies the displayed rows to the clipboard as a plain-text table, in
+    /// the order they are displayed.
+    ///
+    /// At most [`Self::MAX_COPIED_ROWS`] rows are copied, followed by a note
+    /// of how many were left out.
+    pub(in crate::view) fn copy(
+        &mut self,
+        styles: &view::Styles,
+        state: &state::State,
+        clipboard: &mut Clipboard,
+    ) {
+        let now = match state.display_now() {
+            Some(now) => now,
+            None => return,
+        };
+        let mut items = self
+            .sorted_items
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>();
+        // The rows are displayed in reverse order unless `sort_descending` is
+        // set.
+        if !self.sort_descending {
+            items.reverse();
+        }
+        // Hidden columns aren't copied either.
+        let rows = items
+            .iter()
+            .take(Self::MAX_COPIED_ROWS)
+            .map(|item| {
+                T::text_row(&item.borrow(), styles, self.dur_unit, now, state)
+                    .into_iter()
+                    .enumerate()
+                    .filter(|&(idx, _)| self.is_column_visible(idx))
+                    .map(|(_, cell)| cell)
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        let columns = T::HEADER
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| self.is_column_visible(idx))
+            .map(|(idx, &header)| (header, T::RIGHT_ALIGNED.contains(&idx)))
+            .collect::<Vec<_>>();
+
+        let mut text = dump::format_table(&columns, &rows);
+        let omitted = items.len() - rows.len();
+        if omitted > 0 {
+            text.push_str(&format!("... and {} more rows\n", omitted));
+        }
+        self.notice = Some(match clipboard.set_text(text) {
+            Ok(()) if omitted > 0 => Notice::info(format!(
+                "copied the first {} of {} rows to the clipboard",
+                rows.len(),
+                items.len()
+            )),
+            Ok(()) => Notice::info(format!("copied {} rows to the clipboard", rows.len())),
+            Err(error) => {
+                tracing::warn!(%error, "failed to copy table");
+                Notice::error(format!("{:#}", error))
+            }
+        });
+    }
+
+    /// Hides any items which don't match the current search query, the kind
+    /// of items displayed or, if it is toggled on, the table's filter, and
+    /// shows any previously hidden items which do.
+    ///
+    /// This should be called before the table is sorted and rendered.
+    pub(in crate::view) fn apply_search(&mut self) {
+        let query = self.search_query();
+        let filtered = self.filtered;
+        let kind = self.kind;
+        let fuzzy = self.fuzzy;
+        let matches = |item: &Weak<RefCell<T::Row>>| {
+            item.upgrade()
+                .map(|item| {
+                    let item = item.borrow();
+                    let matches_query = match query.as_deref() {
+                        None => true,
+                        Some(query) if fuzzy => T::fuzzy_score(&item, query).is_some(),
+                        Some(query) => T::matches(&item, query),
+                    };
+                    (!filtered || T::filter(&item)) && T::is_kind(&item, kind) && matches_query
+                })
+                .unwrap_or(false)
+        };
+
+        let (shown, hidden): (Vec<_>, Vec<_>) = self
+            .sorted_items
+            .drain(..)
+            .chain(self.hidden_items.drain(..))
+            .filter(|item| item.upgrade().is_some())
+            .partition(matches);
+        self.sorted_items = shown;
+        self.hidden_items = hidden;
+
+        // If items were hidden, the selected row may no longer exist.
+        match self.table_state.selected() {
+            Some(_) if self.sorted_items.is_empty() => self.table_state.select(None),
+            Some(i) if i >= self.sorted_items.len() => {
+                self.table_state.select(Some(self.sorted_items.len() - 1))
+            }
+            _ => {}
+        }
+    }
+
+    /// Sorts the items by how well they match the search, best first, if the
+    /// search is fuzzy.
+    ///
+    /// Items which match equally well stay in the order they were sorted in,
+    /// so this should be called after the table is sorted by its column.
+    pub(in crate::view) fn sort_by_match(&mut self) {
+        let query = match self.search_query() {
+            Some(query) if self.fuzzy => query,
+            _ => return,
+        };
+        // Rows are displayed in reverse order unless `sort_descending` is set,
+        // so the best match is sorted last unless it is.
+        let best_first = self.sort_descending;
+        self.sorted_items.sort_by_cached_key(|item| {
+            let score = item
+                .upgrade()
+                .and_then(|item| T::fuzzy_score(&item.borrow(), &query))
+                .unwrap_or(i64::MIN);
+            if best_first {
+                score.saturating_neg()
+            } else {
+                score
+            }
+        });
+    }
+
+    pub(in crate::view) fn update_input(&mut self, event: input::Event, bindings: &KeyBindings) {
+        // `gg` scrolls to the top of the table. Any other key cancels a
+        // pending `g`.
+        let is_g = input::is_char(&event, 'g');
+        let pending_g = std::mem::replace(&mut self.pending_g, false);
+        if is_g {
+            if pending_g {
+                self.scroll_top();
+            } else {
+                self.pending_g = true;
+            }
+        } else if bindings.is(Action::SelectColumnLeft, &event) {
+            self.select_column(self.step_column(false));
+        } else if bindings.is(Action::SelectColumnRight, &event) {
+            self.select_column(self.step_column(true));
+        } else if bindings.is(Action::InvertSort, &event) {
+            self.sort_descending = !self.sort_descending;
+        } else if bindings.is(Action::ScrollDown, &event) {
+            self.scroll_next();
+        } else if bindings.is(Action::ScrollUp, &event) {
+            self.scroll_prev();
+        } else if bindings.is(Action::ScrollTop, &event) {
+            self.scroll_top();
+        } else if bindings.is(Action::ScrollBottom, &event) {
+            self.scroll_bottom();
+        }
+        // TODO(eliza): mouse input would be cool...
+    }
+
+    /// Selects the column at `idx`, and sorts the table by it, in the
+    /// direction it was last sorted in.
+    fn select_column(&mut self, idx: usize) {
+        self.selected_column = idx;
+        if let Ok(sort_by) = T::Sort::try_from(idx) {
+            self.directions
+                .insert(self.sort_by.as_column(), self.sort_descending);
             self.sort_by = sort_by;
+            self.sort_descending = self
+                .directions
+                .get(&idx)
+                .copied()
+                .unwrap_or_else(|| self.default_direction.sort_descending());
         }
     }
 
@@ -130,6 +989,14 @@ impl<T: TableList> TableListState<T> {
         })
     }
 
+    pub(in crate::view) fn scroll_top(&mut self) {
+        self.scroll_with(|_, _| 0)
+    }
+
+    pub(in crate::view) fn scroll_bottom(&mut self) {
+        self.scroll_with(|resources, _| resources.len() - 1)
+    }
+
     pub(in crate::view) fn selected_item(&self) -> Weak<RefCell<T::Row>> {
         self.table_state
             .selected()
@@ -147,28 +1014,108 @@ impl<T: TableList> TableListState<T> {
     pub(in crate::view) fn render<B: tui::backend::Backend>(
         &mut self,
         styles: &view::Styles,
+        bindings: &KeyBindings,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
         state: &mut state::State,
-    ) {
-        T::render(self, styles, frame, area, state)
+    ) -> Vec<(Action, bool)> {
+        T::render(self, styles, bindings, frame, area, state)
     }
 }
 
-pub(in crate::view) fn controls(styles: &view::Styles) -> Text {
-    tui::text::Text::from(Spans::from(vec![
-        Span::raw("controls: "),
-        bold(styles.if_utf8("\u{2190}\u{2192}", "left, right")),
-        text::Span::raw(" = select column (sort), "),
-        bold(styles.if_utf8("\u{2191}\u{2193}", "up, down")),
-        text::Span::raw(" = scroll, "),
-        bold(styles.if_utf8("\u{21B5}", "enter")),
-        text::Span::raw(" = view details, "),
-        bold("i"),
-        text::Span::raw(" = invert sort (highest/lowest), "),
-        bold("q"),
-        text::Span::raw(" = quit"),
-    ]))
+/// Returns the controls shared by every table, where `details` is what the
+/// [`Action::ViewDetails`] key does in the table.
+pub(in crate::view) fn view_controls(
+    bindings: &KeyBindings,
+    details: &'static str,
+) -> Vec<ControlDisplay> {
+    let mut controls = vec![
+        ControlDisplay::new(
+            "select column (sort)",
+            &[Action::SelectColumnLeft, Action::SelectColumnRight],
+            bindings,
+        ),
+        ControlDisplay::new("scroll", &[Action::ScrollUp, Action::ScrollDown], bindings),
+        ControlDisplay::new(
+            "scroll to top/bottom",
+            &[Action::ScrollTop, Action::ScrollBottom],
+            bindings,
+        ),
+    ];
+    // The vim aliases are listed after the scrolling controls, which only
+    // display the first key bound to each action.
+    controls.extend(ControlDisplay::aliases(
+        "scroll up/down/to bottom (and gg = scroll to top)",
+        &[Action::ScrollUp, Action::ScrollDown, Action::ScrollBottom],
+        bindings,
+    ));
+    controls.extend([
+        ControlDisplay::new(details, &[Action::ViewDetails], bindings),
+        ControlDisplay::new(
+            "invert sort (highest/lowest)",
+            &[Action::InvertSort],
+            bindings,
+        ),
+        ControlDisplay::new(
+            "retain dropped (longer/shorter)",
+            &[Action::RetainLonger, Action::RetainShorter],
+            bindings,
+        ),
+        ControlDisplay::new(
+            "refresh interval (longer/shorter)",
+            &[Action::RefreshLonger, Action::RefreshShorter],
+            bindings,
+        ),
+        ControlDisplay::new("rate window", &[Action::CycleRateWindow], bindings),
+        ControlDisplay::new(
+            "duration precision (more/fewer digits)",
+            &[Action::MorePrecision, Action::LessPrecision],
+            bindings,
+        ),
+        ControlDisplay::new("duration unit", &[Action::CycleDurationUnit], bindings),
+        ControlDisplay::new(
+            "show acknowledged warnings",
+            &[Action::ClearAcknowledged],
+            bindings,
+        ),
+        ControlDisplay::new("copy table", &[Action::CopyTable], bindings),
+        ControlDisplay::new("resize columns", &[Action::ResizeColumns], bindings),
+    ]);
+    controls
+}
+
+/// Returns the controls displayed instead of a table's usual controls while
+/// one of its columns is being resized.
+pub(in crate::view) fn resize_controls(bindings: &KeyBindings) -> Vec<ControlDisplay> {
+    vec![
+        ControlDisplay::new(
+            "column width (narrower/wider)",
+            &[Action::SelectColumnLeft, Action::SelectColumnRight],
+            bindings,
+        ),
+        ControlDisplay::new("reset column width", &[Action::ResetColumnWidth], bindings),
+        ControlDisplay::new("done resizing", &[Action::ResizeColumns], bindings),
+    ]
+}
+
+/// Formats a duration for a table copied as text, in the unit the table
+/// displays it in.
+pub(in crate::view) fn dur_text(
+    styles: &view::Styles,
+    unit: view::DurationUnit,
+    dur: std::time::Duration,
+) -> String {
+    styles.precision.list_dur(unit, dur)
+}
+
+/// Returns the text of a cell made up of several groups of spans (such as a
+/// task's fields), for a table copied as text.
+pub(in crate::view) fn spans_text(spans: &[Vec<Span<'_>>]) -> String {
+    spans
+        .iter()
+        .flatten()
+        .map(|span| span.content.as_ref())
+        .collect()
 }
 
 impl<T> Default for TableListState<T>
@@ -185,6 +1132,259 @@ where
             table_state: Default::default(),
             selected_column,
             sort_descending: false,
+            directions: HashMap::new(),
+            default_direction: SortDirection::Descending,
+            pending_g: false,
+            search: String::new(),
+            editing_search: false,
+            fuzzy: false,
+            filtered: false,
+            kind: 0,
+            hidden_columns: HashSet::new(),
+            hidden_items: Vec::new(),
+            goto: None,
+            notice: None,
+            changes: ChangeHighlights::default(),
+            expanded: HashSet::new(),
+            dur_unit: view::DurationUnit::Auto,
+            widths: HashMap::new(),
+            derived_widths: Vec::new(),
+            resizing: false,
+            tags: RowTags::default(),
+            notes: RowNotes::default(),
+            top: TopTasks::default(),
         }
     }
 }
+
+// === impl SortDirection ===
+
+impl SortDirection {
+    /// Returns the value of [`TableListState::sort_descending`] that sorts a
+    /// table in this direction.
+    ///
+    /// Rows are displayed in reverse order unless `sort_descending` is set, so
+    /// it is only set when the lowest values are at the top.
+    fn sort_descending(self) -> bool {
+        self == SortDirection::Ascending
+    }
+}
+
+impl FromStr for SortDirection {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            s if s.eq_ignore_ascii_case("descending") => Ok(SortDirection::Descending),
+            s if s.eq_ignore_ascii_case("ascending") => Ok(SortDirection::Ascending),
+            _ => Err("expected `descending` or `ascending`"),
+        }
+    }
+}
+
+// === impl RowTags ===
+
+impl RowTags {
+    /// The colors rows can be tagged with, in the order they are cycled
+    /// through.
+    const COLORS: [Color; 4] = [
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightBlue,
+        Color::LightMagenta,
+    ];
+
+    /// Returns the color the row with the given ID is tagged with, if it is
+    /// tagged.
+    pub(in crate::view) fn get(&self, id: u64) -> Option<Color> {
+        self.tags.get(&id).map(|&idx| Self::COLORS[idx])
+    }
+
+    /// Tags the row with the given ID with the next color, or removes its tag
+    /// if it has the last color.
+    pub(in crate::view) fn cycle(&mut self, id: u64) {
+        match self.tags.get(&id).map(|&idx| idx + 1) {
+            Some(next) if next == Self::COLORS.len() => {
+                self.tags.remove(&id);
+            }
+            next => {
+                self.tags.insert(id, next.unwrap_or(0));
+            }
+        }
+    }
+
+    /// Removes the tags from every row.
+    pub(in crate::view) fn clear(&mut self) {
+        self.tags.clear();
+    }
+
+    /// Removes the tags from every row, returning them so that they can be
+    /// [restored](RowTags::restore).
+    pub(in crate::view) fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+
+    /// Tags the rows tagged in `tags` again, except for those which have been
+    /// tagged since.
+    pub(in crate::view) fn restore(&mut self, tags: Self) {
+        for (id, idx) in tags.tags {
+            self.tags.entry(id).or_insert(idx);
+        }
+    }
+
+    /// Returns the number of tagged rows.
+    pub(in crate::view) fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Removes the tags from the rows whose IDs don't match `f`, such as rows
+    /// which have been removed from the table.
+    pub(in crate::view) fn retain(&mut self, mut f: impl FnMut(&u64) -> bool) {
+        self.tags.retain(|id, _| f(id));
+    }
+}
+
+// === impl RowNotes ===
+
+impl RowNotes {
+    /// Returns the note attached to the row with the given ID, if it has one.
+    pub(in crate::view) fn get(&self, id: u64) -> Option<&str> {
+        self.notes.get(&id).map(String::as_str)
+    }
+
+    /// Returns the ID of the row whose note is being edited and the text typed
+    /// so far, or `None` if the note prompt isn't open.
+    pub(in crate::view) fn editing(&self) -> Option<(u64, &str)> {
+        self.editing.as_ref().map(|(id, text)| (*id, text.as_str()))
+    }
+
+    /// Returns `true` if the note prompt is open, in which case all key
+    /// presses should be passed to [`RowNotes::update_input`].
+    pub(in crate::view) fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Opens the note prompt for the row with the given ID, starting from its
+    /// current note.
+    pub(in crate::view) fn start_editing(&mut self, id: u64) {
+        let text = self.get(id).unwrap_or_default().to_owned();
+        self.editing = Some((id, text));
+    }
+
+    /// Handles a key press while the note prompt is open.
+    ///
+    /// Enter closes the prompt and saves the note, or removes it if the text
+    /// is empty. Escape closes the prompt without changing the note.
+    pub(in crate::view) fn update_input(&mut self, event: input::Event) {
+        use input::{Event, KeyCode, KeyEvent};
+        let text = match self.editing {
+            Some((_, ref mut text)) => text,
+            None => return,
+        };
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            match code {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => {
+                    if let Some((id, text)) = self.editing.take() {
+                        let text = text.trim();
+                        if text.is_empty() {
+                            self.notes.remove(&id);
+                        } else {
+                            self.notes.insert(id, text.to_owned());
+                        }
+                    }
+                }
+                KeyCode::Esc => self.editing = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Removes the notes from the rows whose IDs don't match `f`, such as rows
+    /// which have been removed from the table.
+    pub(in crate::view) fn retain(&mut self, mut f: impl FnMut(&u64) -> bool) {
+        self.notes.retain(|id, _| f(id));
+    }
+
+    /// Removes every note, and closes the note prompt.
+    pub(in crate::view) fn clear(&mut self) {
+        self.notes.clear();
+        self.editing = None;
+    }
+
+    /// Removes every note, and closes the note prompt, returning the notes so
+    /// that they can be [restored](RowNotes::restore).
+    pub(in crate::view) fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+
+    /// Attaches the notes in `notes` to their rows again, except for rows
+    /// which have been given a new note since.
+    pub(in crate::view) fn restore(&mut self, notes: Self) {
+        for (id, note) in notes.notes {
+            self.notes.entry(id).or_insert(note);
+        }
+    }
+
+    /// Returns the number of rows with notes.
+    pub(in crate::view) fn len(&self) -> usize {
+        self.notes.len()
+    }
+}
+
+// === impl ChangeHighlights ===
+
+impl ChangeHighlights {
+    /// The number of frames a changed cell stays highlighted for.
+    const FRAMES: u8 = 2;
+
+    pub(in crate::view) fn set_threshold(&mut self, threshold_percent: Option<f64>) {
+        self.threshold_percent = threshold_percent;
+    }
+
+    /// Records the current value of each of a frame's tracked cells, keyed by
+    /// row ID and column, and returns the cells which should be highlighted.
+    ///
+    /// Only the cells passed to this method are remembered, so cells for rows
+    /// which are no longer displayed are forgotten.
+    pub(in crate::view) fn update(
+        &mut self,
+        values: impl Iterator<Item = ((u64, usize), f64)>,
+    ) -> HashSet<(u64, usize)> {
+        let threshold_percent = match self.threshold_percent {
+            Some(threshold_percent) => threshold_percent,
+            None => return HashSet::new(),
+        };
+
+        let mut highlighted = HashSet::new();
+        let mut cells = HashMap::with_capacity(self.cells.len());
+        for (key, value) in values {
+            let mut frames_left = match self.cells.remove(&key) {
+                Some(prev) if changed_by(prev.value, value) > threshold_percent => Self::FRAMES,
+                Some(prev) => prev.frames_left,
+                // Nothing to compare new cells with, so they aren't highlighted.
+                None => 0,
+            };
+            if frames_left > 0 {
+                highlighted.insert(key);
+                frames_left -= 1;
+            }
+            cells.insert(key, ChangedCell { value, frames_left });
+        }
+        self.cells = cells;
+        highlighted
+    }
+}
+
+/// Returns how much `value` changed from `prev`, as a percentage of `prev`.
+fn changed_by(prev: f64, value: f64) -> f64 {
+    // There's no meaningful relative change from zero, and treating it as an
+    // infinite change would highlight every task's first poll.
+    if prev == 0.0 {
+        return 0.0;
+    }
+    ((value - prev) / prev).abs() * 100.0
+}
@@ -1,47 +1,429 @@
 use crate::{
-    input,
+    clipboard::Clipboard,
+    input::{self, Action, KeyBindings},
     state::{
-        tasks::{Details, Task},
-        DetailsRef,
+        tasks::{Details, DetailsStatus, HistogramBucket, Task},
+        DetailsRef, State,
     },
-    util::Percentage,
+    util::{self, Percentage},
     view::{
         self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
         mini_histogram::{HistogramMetadata, MiniHistogram},
+        Notice, TimeDisplay,
     },
+    warnings::Linter,
 };
+use color_eyre::eyre::WrapErr;
+use hdrhistogram::Histogram;
 use std::{
     cell::RefCell,
+    cmp,
+    fmt::Write,
+    fs::File,
+    io::{self, BufWriter, Write as _},
+    path::Path,
     rc::Rc,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 use tui::{
     layout::{self, Layout},
+    style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Block, List, ListItem, Paragraph},
+    widgets::{List, ListItem, Paragraph, Wrap},
 };
 
 pub(crate) struct TaskView {
     task: Rc<RefCell<Task>>,
     details: DetailsRef,
+    notice: Option<Notice>,
+    /// The index of the first async op displayed in the async ops pane.
+    async_ops_offset: usize,
+    /// The index of the first warning displayed in the warnings pane.
+    warnings_offset: usize,
+    /// Whether the pane listing the tasks spawned by this task is displayed.
+    show_children: bool,
+    /// The IDs of the tasks spawned by this task, as of the last time the
+    /// view was drawn.
+    children: Vec<u64>,
+    /// The index in `children` of the selected child.
+    selected_child: usize,
+    /// The index of the first child displayed in the children pane.
+    children_offset: usize,
+    /// The task's poll times histogram when it was last reset, which is
+    /// subtracted from the histogram when it is displayed.
+    poll_times_baseline: Option<Histogram<u64>>,
+    /// Whether the poll times from before the histogram was reset are drawn
+    /// behind the current ones, and listed next to them in the percentiles
+    /// pane.
+    compare_baseline: bool,
+    /// The percentiles displayed in the poll times percentiles pane.
+    ///
+    /// The 100th percentile is displayed as the maximum poll time.
+    percentiles: Vec<f64>,
+}
+
+/// Which of the task view's panes are displayed.
+///
+/// The fields pane is always displayed, and takes up whatever room the other
+/// panes leave. The warnings pane is displayed whenever the task has warnings.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct PaneVisibility {
+    /// The task and waker stats.
+    stats: bool,
+    /// The poll times percentiles and histogram.
+    poll_times: bool,
+    async_ops: bool,
 }
 
 impl TaskView {
+    /// The maximum number of async ops displayed at once. If the task has
+    /// more async ops than this, the pane can be scrolled.
+    const MAX_ASYNC_OPS: usize = 6;
+
+    /// The maximum number of warnings displayed at once. If the task has more
+    /// warnings than this, the pane can be scrolled.
+    const MAX_WARNINGS: usize = 4;
+
+    /// The maximum number of children displayed at once. If the task has
+    /// more children than this, the pane scrolls to keep the selected child
+    /// in view.
+    const MAX_CHILDREN: usize = 6;
+
+    /// The percentiles displayed by default in the poll times percentiles pane.
+    const DEFAULT_PERCENTILES: &'static [f64] =
+        &[10.0, 25.0, 50.0, 75.0, 90.0, 95.0, 99.0, 99.9, 100.0];
+
+    /// The width of the longest poll time displayed in the percentiles pane,
+    /// like `123.4567ms`.
+    const PERCENTILE_VALUE_WIDTH: u16 = 10;
+
     pub(super) fn new(task: Rc<RefCell<Task>>, details: DetailsRef) -> Self {
-        TaskView { task, details }
+        TaskView {
+            task,
+            details,
+            notice: None,
+            async_ops_offset: 0,
+            warnings_offset: 0,
+            show_children: false,
+            children: Vec::new(),
+            selected_child: 0,
+            children_offset: 0,
+            poll_times_baseline: None,
+            compare_baseline: false,
+            percentiles: Self::DEFAULT_PERCENTILES.to_vec(),
+        }
+    }
+
+    /// Sets the percentiles displayed in the poll times percentiles pane.
+    ///
+    /// The 100th percentile is displayed as the maximum poll time.
+    #[allow(dead_code)]
+    pub(crate) fn percentiles(mut self, percentiles: &[f64]) -> Self {
+        self.percentiles = percentiles.to_vec();
+        self
+    }
+
+    /// Returns the width of the poll times percentiles pane, which is wide
+    /// enough for its title and for the longest of its lines.
+    fn percentiles_width(&self) -> u16 {
+        // Compared poll times are listed after the current ones, separated by
+        // a space.
+        let compared_width = if self.comparing_baseline().is_some() {
+            Self::PERCENTILE_VALUE_WIDTH + 1
+        } else {
+            0
+        };
+        let label_width = self
+            .percentiles
+            .iter()
+            .map(|&percentile| percentile_label(percentile).len() as u16)
+            .max()
+            .unwrap_or_default();
+        // add 2 for the `: ` separator, and 2 for the left and right borders.
+        let line_width = label_width + 2 + Self::PERCENTILE_VALUE_WIDTH + compared_width + 2;
+        // 24 chars is long enough for the title "Poll Times Percentiles"
+        cmp::max(24, line_width)
+    }
+
+    pub(crate) fn task_id(&self) -> u64 {
+        self.task.borrow().id()
+    }
+
+    pub(crate) fn parent_id(&self) -> Option<u64> {
+        self.task.borrow().parent_id()
+    }
+
+    /// Handles an input event, returning the ID of a child task to view, if
+    /// one was chosen from the children pane.
+    ///
+    /// Durations are copied as of `now`, and warnings as of `lint_now`, the
+    /// time of the update they were checked at.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn update_input(
+        &mut self,
+        event: input::Event,
+        now: SystemTime,
+        lint_now: SystemTime,
+        time_display: TimeDisplay,
+        note: Option<&str>,
+        bindings: &KeyBindings,
+        clipboard: &mut Clipboard,
+    ) -> Option<u64> {
+        if bindings.is(Action::ToggleChildren, &event) {
+            self.show_children = !self.show_children;
+        } else if self.show_children && bindings.is(Action::ViewDetails, &event) {
+            return self.children.get(self.selected_child).copied();
+        } else if self.show_children && bindings.is(Action::ScrollDown, &event) {
+            // This is clamped to the number of children when rendering.
+            self.selected_child += 1;
+        } else if self.show_children && bindings.is(Action::ScrollUp, &event) {
+            self.selected_child = self.selected_child.saturating_sub(1);
+        } else if bindings.is(Action::CopyDetails, &event) {
+            self.copy_details(now, lint_now, time_display, note, clipboard);
+        } else if bindings.is(Action::CopyLocation, &event) {
+            self.copy_location(clipboard);
+        } else if bindings.is(Action::ResetHistogram, &event) {
+            self.reset_histogram();
+        } else if bindings.is(Action::CompareHistogram, &event) {
+            self.toggle_compare_baseline();
+        } else if bindings.is(Action::ExportHistogram, &event) {
+            self.export_histogram();
+        } else if bindings.is(Action::ScrollDown, &event) {
+            // This is clamped to the number of async ops when rendering.
+            self.async_ops_offset += 1;
+        } else if bindings.is(Action::ScrollUp, &event) {
+            self.async_ops_offset = self.async_ops_offset.saturating_sub(1);
+        } else if bindings.is(Action::ScrollWarningsDown, &event) {
+            // This is clamped to the number of warnings when rendering.
+            self.warnings_offset += 1;
+        } else if bindings.is(Action::ScrollWarningsUp, &event) {
+            self.warnings_offset = self.warnings_offset.saturating_sub(1);
+        }
+        None
+    }
+
+    /// Resets the displayed poll times histogram, so that only polls recorded
+    /// from now on are displayed.
+    ///
+    /// This only changes what the console displays: the histogram recorded by
+    /// the instrumented application is not reset.
+    fn reset_histogram(&mut self) {
+        let task_id = self.task.borrow().id();
+        let baseline = self
+            .details
+            .borrow()
+            .as_ref()
+            .filter(|details| details.task_id() == task_id)
+            .and_then(|details| details.poll_times_histogram().cloned());
+        if baseline.is_some() {
+            self.poll_times_baseline = baseline;
+            self.notice = Some(Notice::info("reset the displayed poll times histogram"));
+        }
+    }
+
+    /// Toggles comparing the displayed poll times histogram with the one from
+    /// before it was reset.
+    fn toggle_compare_baseline(&mut self) {
+        if self.poll_times_baseline.is_none() {
+            self.notice = Some(Notice::error(
+                "reset the poll times histogram to compare with it",
+            ));
+            return;
+        }
+        self.compare_baseline = !self.compare_baseline;
+    }
+
+    /// Returns the poll times histogram from before it was reset, if the
+    /// current one is being compared with it.
+    fn comparing_baseline(&self) -> Option<&Histogram<u64>> {
+        self.poll_times_baseline
+            .as_ref()
+            .filter(|_| self.compare_baseline)
+    }
+
+    /// Writes the buckets of the displayed poll times histogram to a CSV file
+    /// in the current directory, named after the task's ID.
+    ///
+    /// Each row has a column naming the histogram it's from, so that other
+    /// histograms can be added to the same file. If the histogram was reset,
+    /// only the polls displayed since then are written.
+    fn export_histogram(&mut self) {
+        let task_id = self.task.borrow().id();
+        let details_ref = self.details.borrow();
+        let details = match details_ref
+            .as_ref()
+            .filter(|details| details.task_id() == task_id)
+        {
+            Some(details) => details,
+            None => {
+                self.notice = Some(Notice::error("the task's details haven't been received"));
+                return;
+            }
+        };
+        let buckets = match &self.poll_times_baseline {
+            Some(baseline) => details.since_baseline(baseline).poll_time_buckets(),
+            None => details.poll_time_buckets(),
+        };
+        if buckets.is_empty() {
+            self.notice = Some(Notice::error("no poll times have been recorded"));
+            return;
+        }
+
+        let path = format!("tokio-console-task-{}-histograms.csv", task_id);
+        let histograms = [("poll_times", buckets)];
+        self.notice = Some(match write_histograms_csv(Path::new(&path), &histograms) {
+            Ok(()) => Notice::info(format!("wrote the poll times histogram to {}", path)),
+            Err(error) => {
+                tracing::warn!(%error, "failed to export histograms");
+                Notice::error(format!("{:#}", error))
+            }
+        });
+    }
+
+    pub(crate) fn set_notice(&mut self, notice: Notice) {
+        self.notice = Some(notice);
+    }
+
+    /// Copies a plain-text summary of the task's details to the clipboard.
+    fn copy_details(
+        &mut self,
+        now: SystemTime,
+        lint_now: SystemTime,
+        time_display: TimeDisplay,
+        note: Option<&str>,
+        clipboard: &mut Clipboard,
+    ) {
+        let text = self.details_text(now, lint_now, time_display, note);
+        self.notice = Some(match clipboard.set_text(text) {
+            Ok(()) => Notice::info("copied task details to the clipboard"),
+            Err(error) => {
+                tracing::warn!(%error, "failed to copy task details");
+                Notice::error(format!("{:#}", error))
+            }
+        });
     }
 
-    pub(crate) fn update_input(&mut self, _event: input::Event) {
-        // TODO :D
+    /// Copies only the task's spawn location to the clipboard, such as to
+    /// paste into an editor.
+    fn copy_location(&mut self, clipboard: &mut Clipboard) {
+        let location = {
+            let task = self.task.borrow();
+            if !task.has_location() {
+                self.notice = Some(Notice::error("no location to copy"));
+                return;
+            }
+            task.location().trim().to_owned()
+        };
+        self.notice = Some(match clipboard.set_text(location) {
+            Ok(()) => Notice::info("copied the task's location to the clipboard"),
+            Err(error) => {
+                tracing::warn!(%error, "failed to copy task location");
+                Notice::error(format!("{:#}", error))
+            }
+        });
     }
 
+    /// Formats the task's overview, waker stats, active warnings, and the note
+    /// attached to it, if any, as plain text.
+    fn details_text(
+        &self,
+        now: SystemTime,
+        lint_now: SystemTime,
+        time_display: TimeDisplay,
+        note: Option<&str>,
+    ) -> String {
+        let task = &*self.task.borrow();
+        let total = task.total(now);
+        let percent = |amt: Duration| amt.as_secs_f64().percent_of(total.as_secs_f64());
+
+        // Writing to a `String` never fails, so the results are ignored.
+        let mut text = String::new();
+        let _ = write!(text, "ID: {} ({:?}", task.id(), task.state());
+        if task.is_completed() {
+            let _ = writeln!(
+                text,
+                ", idle for {:?} before completing)",
+                task.time_in_state(now)
+            );
+        } else {
+            let _ = writeln!(text, " for {:?})", task.time_in_state(now));
+        }
+        if let Some(name) = task.name() {
+            let _ = writeln!(text, "Name: {}", name);
+        }
+        let _ = writeln!(text, "Target: {}", task.target());
+        let _ = writeln!(text, "Location: {}", task.location());
+        let _ = write!(text, "Total Time: {:?}", total);
+        if time_display == TimeDisplay::Absolute {
+            let _ = write!(
+                text,
+                " (since {})",
+                time_display.format(now, task.created_at())
+            );
+        }
+        text.push('\n');
+        let busy = task.busy(now);
+        let _ = writeln!(text, "Busy: {:?} ({:.2}%)", busy, percent(busy));
+        let idle = task.idle(now);
+        let _ = writeln!(text, "Idle: {:?} ({:.2}%)", idle, percent(idle));
+        let _ = writeln!(
+            text,
+            "Current wakers: {} (clones: {}, drops: {})",
+            task.waker_count(),
+            task.waker_clones(),
+            task.waker_drops()
+        );
+        let _ = write!(text, "Woken: {} times", task.wakes());
+        if let Some(last_wake) = task.last_wake() {
+            let _ = write!(
+                text,
+                ", last woken: {}",
+                time_display.format(now, last_wake)
+            );
+        }
+        text.push('\n');
+        if let (Some(scheduled), Some(average)) =
+            (task.scheduled(now), task.scheduled_per_wake(now))
+        {
+            let _ = writeln!(text, "Scheduled: {:?} ({:?} per wake)", scheduled, average);
+        }
+        if task.self_wakes() > 0 {
+            let _ = writeln!(
+                text,
+                "Self Wakes: {} times ({}%)",
+                task.self_wakes(),
+                task.self_wake_percent()
+            );
+        }
+
+        let warnings = task.warnings();
+        if !warnings.is_empty() {
+            text.push_str("Warnings:\n");
+            for linter in warnings {
+                let _ = writeln!(text, "- {}", linter.format(task, lint_now));
+            }
+        }
+        if let Some(note) = note {
+            let _ = writeln!(text, "Note: {}", note);
+        }
+
+        text
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn render<B: tui::backend::Backend>(
         &mut self,
         styles: &view::Styles,
+        bindings: &KeyBindings,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
+        state: &State,
         now: SystemTime,
-    ) {
+        time_display: TimeDisplay,
+        panes: PaneVisibility,
+        note: Option<&str>,
+    ) -> Vec<(Action, bool)> {
         // Rows with the following info:
         // - Task main attributes
         // - task metadata
@@ -53,61 +435,197 @@ impl TaskView {
         let details = details_ref
             .as_ref()
             .filter(|details| details.task_id() == task.id());
+        let since_baseline;
+        let details = match (details, &self.poll_times_baseline) {
+            (Some(details), Some(baseline)) => {
+                since_baseline = details.since_baseline(baseline);
+                Some(&since_baseline)
+            }
+            (details, _) => details,
+        };
 
-        let warnings: Vec<_> = task
-            .warnings()
+        // Warnings are formatted as of the update they were checked at, even
+        // while the clock is frozen.
+        let lint_now = state.last_updated_at().unwrap_or(now);
+        let all_warnings = task.warnings();
+        self.warnings_offset = cmp::min(
+            self.warnings_offset,
+            all_warnings.len().saturating_sub(Self::MAX_WARNINGS),
+        );
+        let more_warnings = all_warnings.len() > self.warnings_offset + Self::MAX_WARNINGS;
+        let warnings: Vec<_> = all_warnings
             .iter()
+            .skip(self.warnings_offset)
+            .take(Self::MAX_WARNINGS)
             .map(|linter| {
                 ListItem::new(Text::from(Spans::from(vec![
                     styles.warning_wide(),
                     // TODO(eliza): it would be nice to handle singular vs plural...
-                    Span::from(linter.format(task)),
+                    Span::from(linter.format(task, lint_now)),
                 ])))
             })
             .collect();
 
-        let (controls_area, stats_area, poll_dur_area, fields_area, warnings_area) =
-            if warnings.is_empty() {
-                let chunks = Layout::default()
-                    .direction(layout::Direction::Vertical)
-                    .constraints(
-                        [
-                            // controls
-                            layout::Constraint::Length(1),
-                            // task stats
-                            layout::Constraint::Length(8),
-                            // poll duration
-                            layout::Constraint::Length(9),
-                            // fields
-                            layout::Constraint::Percentage(60),
-                        ]
-                        .as_ref(),
-                    )
-                    .split(area);
-                (chunks[0], chunks[1], chunks[2], chunks[3], None)
-            } else {
-                let chunks = Layout::default()
-                    .direction(layout::Direction::Vertical)
-                    .constraints(
-                        [
-                            // controls
-                            layout::Constraint::Length(1),
-                            // warnings (add 2 for top and bottom borders)
-                            layout::Constraint::Length(warnings.len() as u16 + 2),
-                            // task stats
-                            layout::Constraint::Length(8),
-                            // poll duration
-                            layout::Constraint::Length(9),
-                            // fields
-                            layout::Constraint::Percentage(60),
-                        ]
-                        .as_ref(),
-                    )
-                    .split(area);
+        let task_total = task.total(now);
+        let mut async_ops = state
+            .async_ops_state()
+            .task_async_ops(task.id())
+            .collect::<Vec<_>>();
+        async_ops.sort_unstable_by_key(|async_op| async_op.borrow().id());
+        self.async_ops_offset = cmp::min(
+            self.async_ops_offset,
+            async_ops.len().saturating_sub(Self::MAX_ASYNC_OPS),
+        );
+        let async_ops_items: Vec<_> = if async_ops.is_empty() {
+            vec![ListItem::new(Span::raw("no async operations"))]
+        } else {
+            async_ops
+                .iter()
+                .skip(self.async_ops_offset)
+                .take(Self::MAX_ASYNC_OPS)
+                .map(|async_op| {
+                    let async_op = async_op.borrow();
+                    let op_total = async_op.total(now);
+                    let mut spans = vec![bold(format!("{} ", async_op.id()))];
+                    spans.extend(share_of_task(styles, op_total, task_total).0);
+                    spans.extend(vec![
+                        Span::raw(format!(" {} ", async_op.source())),
+                        dur(styles, op_total),
+                        Span::raw(format!(" ({} polls)", async_op.polls())),
+                    ]);
+                    let item = ListItem::new(Spans::from(spans));
+                    if async_op.dropped() {
+                        item.style(styles.dropped())
+                    } else {
+                        item
+                    }
+                })
+                .collect()
+        };
 
-                (chunks[0], chunks[2], chunks[3], chunks[4], Some(chunks[1]))
-            };
+        self.children = state.tasks_state().children(task.id()).collect();
+        self.selected_child = cmp::min(self.selected_child, self.children.len().saturating_sub(1));
+        // Scroll just far enough to keep the selected child displayed.
+        self.children_offset = self.children_offset.clamp(
+            (self.selected_child + 1).saturating_sub(Self::MAX_CHILDREN),
+            self.selected_child,
+        );
+        let children_items: Vec<_> = if self.children.is_empty() {
+            vec![ListItem::new(Span::raw("no spawned tasks"))]
+        } else {
+            self.children
+                .iter()
+                .enumerate()
+                .skip(self.children_offset)
+                .take(Self::MAX_CHILDREN)
+                .filter_map(|(idx, &id)| {
+                    let child = state.tasks_state().task(id)?;
+                    let child = child.borrow();
+                    let mut spans = vec![
+                        child.state().render(styles),
+                        Span::raw(" "),
+                        bold(format!("{} ", child.id())),
+                    ];
+                    if let Some(name) = child.name() {
+                        spans.push(Span::raw(format!("{} ", name)));
+                    }
+                    spans.push(Span::raw(child.location().to_owned()));
+                    let item = ListItem::new(Spans::from(spans));
+                    Some(if idx == self.selected_child {
+                        item.style(Style::default().add_modifier(Modifier::REVERSED))
+                    } else if child.is_completed() {
+                        item.style(styles.terminated())
+                    } else {
+                        item
+                    })
+                })
+                .collect()
+        };
+
+        if self
+            .notice
+            .as_ref()
+            .map(Notice::is_expired)
+            .unwrap_or(false)
+        {
+            self.notice = None;
+        }
+        let controls = Controls::new(
+            &view_controls(
+                bindings,
+                ControlsContext {
+                    has_warnings: !all_warnings.is_empty(),
+                    more_warnings: all_warnings.len() > Self::MAX_WARNINGS,
+                    more_async_ops: async_ops.len() > Self::MAX_ASYNC_OPS,
+                    showing_children: self.show_children,
+                    has_children: !self.children.is_empty(),
+                    has_parent: self.parent_id().is_some(),
+                    has_baseline: self.poll_times_baseline.is_some(),
+                    comparing_baseline: self.comparing_baseline().is_some(),
+                },
+            ),
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Dim,
+        )
+        .with_stable_height()
+        .with_notice(self.notice.as_ref(), styles);
 
+        // add 2 for top and bottom borders
+        let async_ops_height = async_ops_items.len() as u16 + 2;
+        let children_height = children_items.len() as u16 + 2;
+        let poll_dur_height = cmp::max(9, self.percentiles.len() as u16 + 2);
+
+        // Each pane that is displayed adds a constraint, and gets the index of
+        // its chunk.
+        let mut constraints = vec![
+            // controls
+            layout::Constraint::Length(controls.height()),
+        ];
+        let mut pane = |visible: bool, constraint| {
+            visible.then(|| {
+                constraints.push(constraint);
+                constraints.len() - 1
+            })
+        };
+        // warnings (add 2 for top and bottom borders)
+        let warnings_idx = pane(
+            !warnings.is_empty(),
+            layout::Constraint::Length(warnings.len() as u16 + 2),
+        );
+        // note (add 2 for top and bottom borders)
+        let note_idx = pane(note.is_some(), layout::Constraint::Length(3));
+        // task stats (add 2 for top and bottom borders)
+        let stats_idx = pane(panes.stats, layout::Constraint::Length(11));
+        let poll_dur_idx = pane(
+            panes.poll_times,
+            layout::Constraint::Length(poll_dur_height),
+        );
+        let async_ops_idx = pane(
+            panes.async_ops,
+            layout::Constraint::Length(async_ops_height),
+        );
+        let children_idx = pane(
+            self.show_children,
+            layout::Constraint::Length(children_height),
+        );
+        let fields_idx = constraints.len();
+        constraints.push(layout::Constraint::Percentage(60));
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(constraints)
+            .split(area);
+        let controls_area = chunks[0];
+        let warnings_area = warnings_idx.map(|idx| chunks[idx]);
+        let note_area = note_idx.map(|idx| chunks[idx]);
+        let async_ops_area = async_ops_idx.map(|idx| chunks[idx]);
+        let children_area = children_idx.map(|idx| chunks[idx]);
+        let fields_area = chunks[fields_idx];
+
+        // If the stats aren't displayed, they're laid out in an empty area, so
+        // that the overview can still be built below.
         let stats_area = Layout::default()
             .direction(layout::Direction::Horizontal)
             .constraints(
@@ -117,43 +635,51 @@ impl TaskView {
                 ]
                 .as_ref(),
             )
-            .split(stats_area);
+            .split(stats_idx.map(|idx| chunks[idx]).unwrap_or_default());
 
         // Only split the histogram area in half if we're also drawing a
         // sparkline (which requires UTF-8 characters).
-        let poll_dur_area = if styles.utf8 {
-            Layout::default()
-                .direction(layout::Direction::Horizontal)
-                .constraints(
-                    [
-                        // 24 chars is long enough for the title "Poll Times Percentiles"
-                        layout::Constraint::Length(24),
-                        layout::Constraint::Min(50),
-                    ]
-                    .as_ref(),
-                )
-                .split(poll_dur_area)
+        let poll_dur_area = poll_dur_idx.map(|idx| {
+            if styles.utf8 {
+                Layout::default()
+                    .direction(layout::Direction::Horizontal)
+                    .constraints(
+                        [
+                            layout::Constraint::Length(self.percentiles_width()),
+                            layout::Constraint::Min(50),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(chunks[idx])
+            } else {
+                vec![chunks[idx]]
+            }
+        });
+
+        // Just preallocate capacity for ID, name, target, location, total,
+        // busy, idle, and the busy/idle bar.
+        let mut overview = Vec::with_capacity(8);
+        let in_state = if task.is_completed() {
+            " (idle for "
         } else {
-            vec![poll_dur_area]
+            " for "
         };
-
-        let percentiles_area = poll_dur_area[0];
-
-        let controls = Spans::from(vec![
-            Span::raw("controls: "),
-            bold(styles.if_utf8("\u{238B} esc", "esc")),
-            Span::raw(" = return to task list, "),
-            bold("q"),
-            Span::raw(" = quit"),
-        ]);
-
-        // Just preallocate capacity for ID, name, target, total, busy, and idle.
-        let mut overview = Vec::with_capacity(7);
-        overview.push(Spans::from(vec![
+        let mut id = vec![
             bold("ID: "),
             Span::raw(format!("{} ", task.id())),
             task.state().render(styles),
-        ]));
+            Span::raw(format!(" {}{}", task.state().name(), in_state)),
+            dur(styles, task.time_in_state(now)),
+        ];
+        if task.is_completed() {
+            id.push(Span::raw(" before completing)"));
+        }
+        if state.tasks_state().is_pinned(task.id()) {
+            id.push(Span::raw(" "));
+            id.push(styles.pinned());
+            id.push(Span::raw("pinned"));
+        }
+        overview.push(Spans::from(id));
 
         if let Some(name) = task.name() {
             overview.push(Spans::from(vec![bold("Name: "), Span::raw(name)]));
@@ -164,9 +690,32 @@ impl TaskView {
             Span::raw(task.target()),
         ]));
 
+        const LOCATION: &str = "Location: ";
+        // Subtract 2 for the left and right borders.
+        let location_max_width = (stats_area[0].width as usize).saturating_sub(LOCATION.len() + 2);
+        // Keep the start of the path, which usually names the crate, as well
+        // as the end, which names the file and line.
+        let location = if task.has_location() {
+            let location = util::truncate_middle(
+                task.location(),
+                location_max_width,
+                location_max_width / 3,
+                styles.if_utf8("\u{2026}", "..."),
+            );
+            Span::raw(location.into_owned())
+        } else {
+            // Distinguish a location the target didn't record from a path
+            // which happens to look odd.
+            Span::styled(
+                "not recorded by the target",
+                styles.fg_or(Color::Yellow, Modifier::ITALIC),
+            )
+        };
+        overview.push(Spans::from(vec![bold(LOCATION), location]));
+
         overview.push(Spans::from(vec![
-            bold("Location: "),
-            Span::raw(task.location()),
+            bold("Spawned children: "),
+            Span::raw(self.children.len().to_string()),
         ]));
 
         let total = task.total(now);
@@ -180,9 +729,29 @@ impl TaskView {
             ])
         };
 
-        overview.push(Spans::from(vec![bold("Total Time: "), dur(styles, total)]));
-        overview.push(dur_percent("Busy: ", task.busy(now)));
-        overview.push(dur_percent("Idle: ", task.idle(now)));
+        let mut total_time = vec![bold("Total Time: "), dur(styles, total)];
+        if time_display == TimeDisplay::Absolute {
+            total_time.push(Span::from(format!(
+                " (since {})",
+                time_display.format(now, task.created_at())
+            )));
+        }
+        overview.push(Spans::from(total_time));
+        let busy = task.busy(now);
+        let idle = task.idle(now);
+        overview.push(dur_percent("Busy: ", busy));
+        overview.push(dur_percent("Idle: ", idle));
+        // Subtract 2 for the left and right borders.
+        let bar_width = stats_area[0].width.saturating_sub(2);
+        overview.push(time_bar(
+            styles,
+            bar_width,
+            total,
+            &[
+                (busy, styles.if_utf8("\u{2588}", "#"), Color::Green),
+                (idle, styles.if_utf8("\u{2591}", "-"), Color::Blue),
+            ],
+        ));
 
         let mut waker_stats = vec![Spans::from(vec![
             bold("Current wakers: "),
@@ -198,16 +767,35 @@ impl TaskView {
             Span::from(format!("{} times", task.wakes())),
         ];
 
-        // If the task has been woken, add the time since wake to its stats as well.
-        if let Some(since) = task.since_wake(now) {
+        // If the task has been woken, add the time of the last wake to its
+        // stats as well.
+        if let Some(last_wake) = task.last_wake() {
             wakeups.reserve(3);
             wakeups.push(Span::raw(", "));
             wakeups.push(bold("last woken:"));
-            wakeups.push(Span::from(format!(" {:?} ago", since)));
+            wakeups.push(Span::from(format!(
+                " {}",
+                time_display.format(now, last_wake)
+            )));
         }
 
         waker_stats.push(Spans::from(wakeups));
 
+        // How long the task waits to be polled after being woken, which is
+        // only known if the target records it.
+        if let (Some(scheduled), Some(average)) =
+            (task.scheduled(now), task.scheduled_per_wake(now))
+        {
+            waker_stats.push(Spans::from(vec![
+                bold("Scheduled: "),
+                dur(styles, scheduled),
+                Span::raw(" ("),
+                bold("per wake: "),
+                dur(styles, average),
+                Span::raw(")"),
+            ]));
+        }
+
         if task.self_wakes() > 0 {
             waker_stats.push(Spans::from(vec![
                 bold("Self Wakes: "),
@@ -222,10 +810,29 @@ impl TaskView {
         let mut fields = Text::default();
         fields.extend(task.formatted_fields().iter().cloned().map(Spans::from));
 
-        // If UTF-8 is disabled we can't draw the histogram sparklne.
-        if styles.utf8 {
-            let sparkline_area = poll_dur_area[1];
+        // If there are no details to display, say why in the poll times
+        // histogram, or in the percentiles pane if there's no histogram.
+        let details_problem = details_problem(state.task_details_status());
+        let mut histogram_title = vec![Span::raw("Poll Times Histogram")];
+        if details.is_some() && details_problem.is_some() {
+            histogram_title.push(Span::styled(" (not updating)", styles.warning()));
+        }
+        if self.comparing_baseline().is_some() {
+            histogram_title.push(Span::styled(" vs. before reset", styles.baseline()));
+        }
+        let details_status = match (details, details_problem) {
+            (Some(_), _) => None,
+            (None, Some(problem)) => Some(Span::styled(problem, styles.warning())),
+            (None, None) => Some(Span::raw(format!(
+                "loading details{}",
+                styles.if_utf8("\u{2026}", "...")
+            ))),
+        };
+        let has_histogram = matches!(poll_dur_area, Some(ref area) if area.len() > 1);
 
+        // If UTF-8 is disabled we can't draw the histogram sparklne, and the
+        // poll times area isn't split.
+        if let Some(&sparkline_area) = poll_dur_area.as_ref().and_then(|area| area.get(1)) {
             // Bit of a deadlock: We cannot know the highest bucket value without determining the number of buckets,
             // and we cannot determine the number of buckets without knowing the width of the chart area which depends on
             // the number of digits in the highest bucket value.
@@ -233,43 +840,253 @@ impl TaskView {
             // If we overshoot, there will be empty columns/buckets at the right end of the chart.
             // If we undershoot, the rightmost 1-2 columns/buckets will be hidden.
             // We could get the max bucket value from the previous render though...
-            let (chart_data, metadata) = details
-                .map(|d| d.make_chart_data(sparkline_area.width - 3))
-                .unwrap_or_default();
+            let block = styles.border_block().title(histogram_title);
+            if let Some(status) = details_status.clone() {
+                let status = Paragraph::new(Spans::from(status))
+                    .block(block)
+                    .wrap(Wrap { trim: true });
+                frame.render_widget(status, sparkline_area);
+            } else {
+                let width = sparkline_area.width - 3;
+                let (chart_data, baseline_data, metadata) =
+                    match (details, self.comparing_baseline()) {
+                        (Some(details), Some(baseline)) => {
+                            details.make_comparison_chart_data(baseline, width)
+                        }
+                        (Some(details), None) => {
+                            let (data, metadata) = details.make_chart_data(width);
+                            (data, Vec::new(), metadata)
+                        }
+                        (None, _) => Default::default(),
+                    };
 
-            let histogram_sparkline = MiniHistogram::default()
-                .block(styles.border_block().title("Poll Times Histogram"))
-                .data(&chart_data)
-                .metadata(metadata)
-                .duration_precision(2);
+                let histogram_sparkline = MiniHistogram::default()
+                    .block(block)
+                    .data(&chart_data)
+                    .overlay(&baseline_data, styles.baseline())
+                    .metadata(metadata)
+                    .duration_precision(styles.precision.histogram);
 
-            frame.render_widget(histogram_sparkline, sparkline_area);
+                frame.render_widget(histogram_sparkline, sparkline_area);
+            }
         }
 
         if let Some(warnings_area) = warnings_area {
-            let warnings = List::new(warnings).block(styles.border_block().title("Warnings"));
+            let mut title = vec![Span::raw("Warnings ")];
+            if let Some(severity) = all_warnings.iter().map(Linter::severity).max() {
+                title.push(styles.warning_badge(all_warnings.len(), severity));
+            }
+            if more_warnings {
+                title.push(Span::raw(styles.if_utf8(" \u{25BC} more", " v more")));
+            }
+            let warnings = List::new(warnings).block(styles.border_block().title(title));
             frame.render_widget(warnings, warnings_area);
         }
 
+        if let (Some(note), Some(note_area)) = (note, note_area) {
+            let title = vec![styles.noted(), Span::raw("Note")];
+            let note = Paragraph::new(Span::raw(note)).block(styles.border_block().title(title));
+            frame.render_widget(note, note_area);
+        }
+
         let task_widget = Paragraph::new(overview).block(styles.border_block().title("Task"));
         let wakers_widget = Paragraph::new(waker_stats).block(styles.border_block().title("Waker"));
+        let async_ops_widget = List::new(async_ops_items).block(
+            styles
+                .border_block()
+                .title(format!("Async Ops ({})", async_ops.len())),
+        );
         let fields_widget = Paragraph::new(fields).block(styles.border_block().title("Fields"));
-        let percentiles_widget = Paragraph::new(
-            details
-                .map(|details| details.make_percentiles_widget(styles))
-                .unwrap_or_default(),
-        )
-        .block(styles.border_block().title("Poll Times Percentiles"));
+        let percentiles = match (details, details_status) {
+            (Some(details), _) => details.make_percentiles_widget(
+                styles,
+                &self.percentiles,
+                self.comparing_baseline(),
+            ),
+            (None, Some(status)) if !has_histogram => Text::from(Spans::from(status)),
+            (None, _) => Text::default(),
+        };
+        let percentiles_widget = Paragraph::new(percentiles)
+            .block(styles.border_block().title("Poll Times Percentiles"))
+            // The labels are padded to right-align them, so keep the padding.
+            .wrap(Wrap { trim: false });
 
-        frame.render_widget(Block::default().title(controls), controls_area);
-        frame.render_widget(task_widget, stats_area[0]);
-        frame.render_widget(wakers_widget, stats_area[1]);
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        if stats_idx.is_some() {
+            frame.render_widget(task_widget, stats_area[0]);
+            frame.render_widget(wakers_widget, stats_area[1]);
+        }
+        if let Some(async_ops_area) = async_ops_area {
+            frame.render_widget(async_ops_widget, async_ops_area);
+        }
+        if let Some(children_area) = children_area {
+            let children_widget = List::new(children_items).block(
+                styles
+                    .border_block()
+                    .title(format!("Spawned Children ({})", self.children.len())),
+            );
+            frame.render_widget(children_widget, children_area);
+        }
         frame.render_widget(fields_widget, fields_area);
-        frame.render_widget(percentiles_widget, percentiles_area);
+        if let Some(poll_dur_area) = poll_dur_area {
+            frame.render_widget(percentiles_widget, poll_dur_area[0]);
+        }
+        actions
+    }
+}
+
+/// Describes why the task's details couldn't be fetched, and whether they
+/// will be retried, or returns `None` if they haven't failed.
+fn details_problem(status: &DetailsStatus) -> Option<String> {
+    match status {
+        DetailsStatus::Failed {
+            error,
+            retry_at: Some(retry_at),
+        } => Some(format!(
+            "details unavailable (retrying in {:.0?}): {}",
+            retry_at.saturating_duration_since(Instant::now()),
+            error
+        )),
+        DetailsStatus::Failed {
+            error,
+            retry_at: None,
+        } => Some(format!("details unavailable: {}", error)),
+        _ => None,
     }
 }
 
+/// What's currently displayed in the task view, which decides which of the
+/// view's controls are enabled.
+struct ControlsContext {
+    has_warnings: bool,
+    more_warnings: bool,
+    more_async_ops: bool,
+    showing_children: bool,
+    has_children: bool,
+    has_parent: bool,
+    has_baseline: bool,
+    comparing_baseline: bool,
+}
+
+fn view_controls(bindings: &KeyBindings, cx: ControlsContext) -> Vec<ControlDisplay> {
+    vec![
+        ControlDisplay::new("return to task list", &[Action::Back], bindings),
+        ControlDisplay::new("copy details", &[Action::CopyDetails], bindings),
+        ControlDisplay::new("copy location", &[Action::CopyLocation], bindings),
+        ControlDisplay::new("open in editor", &[Action::OpenInEditor], bindings),
+        ControlDisplay::new(
+            "reset histogram (display only)",
+            &[Action::ResetHistogram],
+            bindings,
+        ),
+        ControlDisplay::new(
+            if cx.comparing_baseline {
+                "stop comparing histograms"
+            } else {
+                "compare with histogram before reset"
+            },
+            &[Action::CompareHistogram],
+            bindings,
+        )
+        .enabled(cx.has_baseline),
+        ControlDisplay::new("export histogram", &[Action::ExportHistogram], bindings),
+        ControlDisplay::new(
+            "acknowledge warnings",
+            &[Action::AcknowledgeWarnings],
+            bindings,
+        )
+        .enabled(cx.has_warnings),
+        ControlDisplay::new(
+            "pin (toggle/unpin all)",
+            &[Action::TogglePin, Action::UnpinAll],
+            bindings,
+        ),
+        ControlDisplay::new(
+            "absolute/relative times",
+            &[Action::ToggleTimeDisplay],
+            bindings,
+        ),
+        ControlDisplay::new("freeze clock", &[Action::FreezeClock], bindings),
+        ControlDisplay::new(
+            "duration precision (more/fewer digits)",
+            &[Action::MorePrecision, Action::LessPrecision],
+            bindings,
+        ),
+        ControlDisplay::new("cycle panes", &[Action::CycleTaskPanes], bindings),
+        ControlDisplay::new("spawned children", &[Action::ToggleChildren], bindings),
+        if cx.showing_children {
+            ControlDisplay::new(
+                "select child",
+                &[Action::ScrollUp, Action::ScrollDown],
+                bindings,
+            )
+            .enabled(cx.has_children)
+        } else {
+            ControlDisplay::new(
+                "scroll async ops",
+                &[Action::ScrollUp, Action::ScrollDown],
+                bindings,
+            )
+            .enabled(cx.more_async_ops)
+        },
+        ControlDisplay::new("view child", &[Action::ViewDetails], bindings)
+            .enabled(cx.showing_children && cx.has_children),
+        ControlDisplay::new("view parent", &[Action::ViewParent], bindings).enabled(cx.has_parent),
+        ControlDisplay::new(
+            "scroll warnings",
+            &[Action::ScrollWarningsUp, Action::ScrollWarningsDown],
+            bindings,
+        )
+        .enabled(cx.more_warnings),
+    ]
+}
+
+/// Writes the buckets of each of `histograms`, with the histogram's name, to
+/// `path` as CSV.
+fn write_histograms_csv(
+    path: &Path,
+    histograms: &[(&str, Vec<HistogramBucket>)],
+) -> color_eyre::Result<()> {
+    let write = || -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "histogram,lower_ns,upper_ns,count")?;
+        for (name, buckets) in histograms {
+            for bucket in buckets {
+                writeln!(
+                    file,
+                    "{},{},{},{}",
+                    name, bucket.lower, bucket.upper, bucket.count
+                )?;
+            }
+        }
+        file.flush()
+    };
+    write().wrap_err_with(|| format!("failed to write `{}`", path.display()))
+}
+
 impl Details {
+    /// Returns these details with the polls recorded in `baseline` removed
+    /// from the poll times histogram.
+    ///
+    /// If the baseline can't be subtracted, the histogram is left unchanged.
+    fn since_baseline(&self, baseline: &Histogram<u64>) -> Details {
+        let poll_times_histogram = self.poll_times_histogram().cloned().map(|mut histogram| {
+            if let Err(error) = histogram.subtract(baseline) {
+                tracing::warn!(%error, "failed to subtract poll times baseline");
+            }
+            histogram
+        });
+        Details {
+            task_id: self.task_id(),
+            poll_times_histogram,
+        }
+    }
+
+    /// Percentiles are only displayed once a task has been polled at least this
+    /// many times, since percentiles of a handful of samples are meaningless.
+    const MIN_PERCENTILE_SAMPLES: u64 = 10;
+
     /// From the histogram, build a visual representation by trying to make as
     // many buckets as the width of the render area.
     fn make_chart_data(&self, width: u16) -> (Vec<u64>, HistogramMetadata) {
@@ -314,19 +1131,122 @@ impl Details {
             .unwrap_or_default()
     }
 
-    /// Get the important percentile values from the histogram
-    fn make_percentiles_widget(&self, styles: &view::Styles) -> Text<'static> {
+    /// Builds the bars for the poll times histogram and for `baseline` over
+    /// the same ranges of poll times, so that the two can be drawn on top of
+    /// each other.
+    ///
+    /// The histograms' own buckets may not line up, so each recorded value is
+    /// counted in the bar whose range of times includes it, and the bars span
+    /// the lowest to the highest value in either histogram.
+    fn make_comparison_chart_data(
+        &self,
+        baseline: &Histogram<u64>,
+        width: u16,
+    ) -> (Vec<u64>, Vec<u64>, HistogramMetadata) {
+        let histograms = [self.poll_times_histogram(), Some(baseline)];
+        let recorded = || {
+            histograms
+                .iter()
+                .flatten()
+                .filter(|histogram| !histogram.is_empty())
+        };
+        let (min, max) = match (
+            recorded().map(|histogram| histogram.min()).min(),
+            recorded().map(|histogram| histogram.max()).max(),
+        ) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Default::default(),
+        };
+        let step_size = ((max - min) as f64 / width as f64).ceil() as u64 + 1;
+        let len = ((max - min) / step_size) as usize + 1;
+        let bars = |histogram: Option<&Histogram<u64>>| {
+            let mut bars = vec![0; len];
+            for value in histogram
+                .iter()
+                .flat_map(|histogram| histogram.iter_recorded())
+            {
+                let idx = (value.value_iterated_to().clamp(min, max) - min) / step_size;
+                bars[idx as usize] += value.count_at_value();
+            }
+            bars
+        };
+        let data = bars(histograms[0]);
+        let baseline = bars(histograms[1]);
+        let bucket_counts = || data.iter().chain(&baseline).copied();
+        let metadata = HistogramMetadata {
+            max_value: max,
+            min_value: min,
+            max_bucket: bucket_counts().max().unwrap_or_default(),
+            min_bucket: bucket_counts().min().unwrap_or_default(),
+        };
+        (data, baseline, metadata)
+    }
+
+    /// Get the important percentile values from the histogram, and from
+    /// `baseline`, if the histogram is being compared with it.
+    fn make_percentiles_widget(
+        &self,
+        styles: &view::Styles,
+        percentiles: &[f64],
+        baseline: Option<&Histogram<u64>>,
+    ) -> Text<'static> {
         let mut text = Text::default();
         let histogram = self.poll_times_histogram();
+        if let (Some(histogram), None) = (histogram, baseline) {
+            if histogram.len() < Self::MIN_PERCENTILE_SAMPLES {
+                // This is split over two lines, since the pane is narrow.
+                text.extend([
+                    Spans::from("insufficient samples"),
+                    Spans::from(format!("(n={})", histogram.len())),
+                ]);
+                return text;
+            }
+        }
+        let label_width = percentiles
+            .iter()
+            .map(|&percentile| percentile_label(percentile).len())
+            .max()
+            .unwrap_or_default();
+        // When comparing, the values are padded so that the compared values
+        // line up, and a histogram with too few samples is listed as `-`, so
+        // that the other histogram's percentiles are still displayed.
+        let value = |histogram: &Histogram<u64>, percentile: f64, style: Style| {
+            let mut value = if histogram.len() < Self::MIN_PERCENTILE_SAMPLES {
+                Span::raw("-")
+            } else if percentile >= 100.0 {
+                // The value at the 100th percentile is only the highest
+                // equivalent value, so use the recorded maximum instead.
+                dur(styles, Duration::from_nanos(histogram.max()))
+            } else {
+                let value = histogram.value_at_percentile(percentile);
+                dur(styles, Duration::from_nanos(value))
+            };
+            if baseline.is_some() {
+                value.content = format!(
+                    "{:<width$}",
+                    value.content,
+                    width = TaskView::PERCENTILE_VALUE_WIDTH as usize
+                )
+                .into();
+            }
+            value.style = value.style.patch(style);
+            value
+        };
         let percentiles = histogram.iter().flat_map(|histogram| {
-            let pairs = [10f64, 25f64, 50f64, 75f64, 90f64, 95f64, 99f64]
-                .iter()
-                .map(move |i| (*i, histogram.value_at_percentile(*i)));
-            pairs.map(|pair| {
-                Spans::from(vec![
-                    bold(format!("p{:>2}: ", pair.0)),
-                    dur(styles, Duration::from_nanos(pair.1)),
-                ])
+            percentiles.iter().map(move |&percentile| {
+                let mut spans = vec![
+                    bold(format!(
+                        "{:>width$}: ",
+                        percentile_label(percentile),
+                        width = label_width
+                    )),
+                    value(histogram, percentile, Style::default()),
+                ];
+                if let Some(baseline) = baseline {
+                    spans.push(Span::raw(" "));
+                    spans.push(value(baseline, percentile, styles.baseline()));
+                }
+                Spans::from(spans)
             })
         });
         text.extend(percentiles);
@@ -334,10 +1254,131 @@ impl Details {
     }
 }
 
+/// Returns the label for `percentile` in the poll times percentiles pane, like
+/// `p99.9`, or `max` for the 100th percentile.
+fn percentile_label(percentile: f64) -> String {
+    if percentile >= 100.0 {
+        "max".to_string()
+    } else {
+        format!("p{}", percentile)
+    }
+}
+
 fn dur(styles: &view::Styles, dur: std::time::Duration) -> Span<'static> {
-    const DUR_PRECISION: usize = 4;
     // TODO(eliza): can we not have to use `format!` to make a string here? is
     // there a way to just give TUI a `fmt::Debug` implementation, or does it
     // have to be given a string in order to do layout stuff?
-    styles.time_units(format!("{:.prec$?}", dur, prec = DUR_PRECISION))
+    styles.time_units(format!("{:.prec$?}", dur, prec = styles.precision.detail))
+}
+
+/// Renders a bar `width` characters wide, divided into segments proportional
+/// to each of the `segments`' share of `total`.
+///
+/// Each segment is drawn using its symbol and color. Segments are rounded at
+/// their cumulative boundaries, so that rounding never makes the segments
+/// add up to more or less than `width`.
+/// The width of the bar showing the share of a task's time that one of its
+/// async ops has existed for.
+const ASYNC_OP_BAR_WIDTH: u16 = 10;
+
+/// Displays how much of a task's `task_total` time one of its async ops has
+/// existed for, as a percentage and a bar.
+///
+/// A task may await several async ops at once, such as in a `select!`, so the
+/// percentages of a task's ops can add up to more than 100%. An op can even
+/// outlive the time the task has been tracked for, so while the percentage is
+/// displayed as is, the bar is capped at 100%.
+fn share_of_task(
+    styles: &view::Styles,
+    op_total: Duration,
+    task_total: Duration,
+) -> Spans<'static> {
+    let task_secs = task_total.as_secs_f64();
+    let percent = if task_secs > 0.0 {
+        op_total.as_secs_f64() / task_secs * 100.0
+    } else {
+        0.0
+    };
+    let bar = time_bar(
+        styles,
+        ASYNC_OP_BAR_WIDTH,
+        task_total,
+        &[
+            (op_total, styles.if_utf8("\u{2588}", "#"), Color::Cyan),
+            (task_total, styles.if_utf8("\u{2591}", "-"), Color::DarkGray),
+        ],
+    );
+    let mut spans = bar.0;
+    spans.push(Span::raw(format!(" {:>5.1}%", percent)));
+    Spans::from(spans)
+}
+
+fn time_bar(
+    styles: &view::Styles,
+    width: u16,
+    total: Duration,
+    segments: &[(Duration, &str, Color)],
+) -> Spans<'static> {
+    let total = total.as_secs_f64();
+    if total <= 0.0 {
+        return Spans::default();
+    }
+
+    let mut spans = Vec::with_capacity(segments.len());
+    let mut elapsed = 0.0;
+    let mut start = 0;
+    for &(amt, symbol, color) in segments {
+        elapsed += amt.as_secs_f64();
+        let end = ((elapsed / total).min(1.0) * width as f64).round() as usize;
+        let len = end.saturating_sub(start);
+        if len > 0 {
+            spans.push(Span::styled(symbol.repeat(len), styles.fg(color)));
+        }
+        start = start.max(end);
+    }
+    Spans::from(spans)
+}
+
+// === impl PaneVisibility ===
+
+impl PaneVisibility {
+    /// The combinations of panes which are cycled through, starting with
+    /// every pane, and hiding more of them to give the fields more room.
+    const CYCLE: &'static [PaneVisibility] = &[
+        PaneVisibility {
+            stats: true,
+            poll_times: true,
+            async_ops: true,
+        },
+        PaneVisibility {
+            stats: true,
+            poll_times: false,
+            async_ops: true,
+        },
+        PaneVisibility {
+            stats: true,
+            poll_times: false,
+            async_ops: false,
+        },
+        PaneVisibility {
+            stats: false,
+            poll_times: false,
+            async_ops: false,
+        },
+    ];
+
+    /// Returns the next combination of panes to display.
+    pub(crate) fn next(self) -> Self {
+        let idx = Self::CYCLE
+            .iter()
+            .position(|&panes| panes == self)
+            .map_or(0, |idx| (idx + 1) % Self::CYCLE.len());
+        Self::CYCLE[idx]
+    }
+}
+
+impl Default for PaneVisibility {
+    fn default() -> Self {
+        Self::CYCLE[0]
+    }
 }
@@ -0,0 +1,343 @@
+//! The task list, collapsed into one row for each group of tasks with the
+//! same target or spawn location, which can be expanded to list the group's
+//! tasks.
+use crate::{
+    input::{self, Action, KeyBindings},
+    state::{
+        tasks::{Task, TaskState},
+        State,
+    },
+    view::{
+        self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
+        TABLE_HIGHLIGHT_SYMBOL,
+    },
+};
+use std::{
+    cmp,
+    collections::{BTreeMap, HashSet},
+    time::{Duration, SystemTime},
+};
+use tui::{
+    layout::{self, Layout},
+    style::{self, Style},
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct TaskGroupsView {
+    group_by: GroupBy,
+    /// The groups which have been expanded to list their tasks.
+    expanded: HashSet<GroupKey>,
+    table_state: TableState,
+}
+
+/// What tasks are grouped by.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub(crate) enum GroupBy {
+    #[default]
+    Target,
+    Location,
+}
+
+/// Identifies a group of tasks.
+///
+/// Blocking tasks are grouped separately from other tasks with the same
+/// target or location, since their busy time means something different.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+struct GroupKey {
+    name: String,
+    is_blocking: bool,
+}
+
+/// The aggregate stats of a group of tasks, recomputed each time the groups
+/// are displayed.
+#[derive(Debug)]
+struct Group {
+    key: GroupKey,
+    running: usize,
+    busy: Duration,
+    polls: u64,
+    /// The IDs of the group's tasks, along with each task's busy time.
+    tasks: Vec<(u64, Duration)>,
+}
+
+/// A row of the groups table.
+#[derive(Debug, Clone)]
+enum GroupRow {
+    Group(GroupKey),
+    Task(u64),
+}
+
+impl TaskGroupsView {
+    pub(crate) fn new(group_by: GroupBy) -> Self {
+        Self {
+            group_by,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn group_by(&self) -> GroupBy {
+        self.group_by
+    }
+
+    /// Handles an input event, returning the ID of the task to inspect if one
+    /// was selected.
+    pub(crate) fn update_input(
+        &mut self,
+        event: input::Event,
+        bindings: &KeyBindings,
+        state: &State,
+    ) -> Option<u64> {
+        let now = state.display_now()?;
+        let rows = self.rows(&groups(state, self.group_by, now));
+        if rows.is_empty() {
+            self.table_state.select(None);
+            return None;
+        }
+
+        let len = rows.len();
+        let selected = self.table_state.selected().unwrap_or(0).min(len - 1);
+        let selected = if bindings.is(Action::ViewDetails, &event) {
+            match rows[selected] {
+                GroupRow::Group(ref key) => {
+                    if !self.expanded.remove(key) {
+                        self.expanded.insert(key.clone());
+                    }
+                    selected
+                }
+                GroupRow::Task(id) => return Some(id),
+            }
+        } else if bindings.is(Action::ScrollDown, &event) {
+            (selected + 1) % len
+        } else if bindings.is(Action::ScrollUp, &event) {
+            selected.checked_sub(1).unwrap_or(len - 1)
+        } else if bindings.is(Action::ScrollTop, &event) {
+            0
+        } else if bindings.is(Action::ScrollBottom, &event) {
+            len - 1
+        } else {
+            return None;
+        };
+        self.table_state.select(Some(selected));
+        None
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        bindings: &KeyBindings,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &State,
+        unit: view::DurationUnit,
+    ) -> Vec<(Action, bool)> {
+        let now = match state.display_now() {
+            Some(now) => now,
+            // If we have never gotten an update yet, skip...
+            None => return Vec::new(),
+        };
+        let groups = groups(state, self.group_by, now);
+        let rows = self.rows(&groups);
+        let selected = match rows.len() {
+            0 => None,
+            len => Some(self.table_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.table_state.select(selected);
+
+        let tasks_state = state.tasks_state();
+        let dur_cell = |dur: Duration| -> Cell<'static> {
+            Cell::from(styles.time_units(format!(
+                "{:>width$}",
+                styles.precision.list_dur(unit, dur),
+                width = styles.precision.list_len(unit)
+            )))
+        };
+        let mut name_width = view::Width::new(HEADER[0].len() as u16);
+        let table_rows = rows
+            .iter()
+            .filter_map(|row| match row {
+                GroupRow::Group(key) => {
+                    let group = groups.iter().find(|group| &group.key == key)?;
+                    let marker = if self.expanded.contains(key) {
+                        styles.if_utf8("\u{25BC} ", "v ")
+                    } else {
+                        styles.if_utf8("\u{25B6} ", "> ")
+                    };
+                    let mut name = format!("{}{}", marker, key.name);
+                    if key.is_blocking {
+                        name.push_str(" (blocking)");
+                    }
+                    name_width.update_len(name.chars().count());
+                    Some(Row::new(vec![
+                        Cell::from(bold(name)),
+                        Cell::from(format!("{} ({} running)", group.tasks.len(), group.running)),
+                        dur_cell(group.busy),
+                        Cell::from(group.polls.to_string()),
+                    ]))
+                }
+                GroupRow::Task(id) => {
+                    let task = tasks_state.task(*id)?;
+                    let task = task.borrow();
+                    let name = format!("    {} {}", task.id(), task.name().unwrap_or(""));
+                    name_width.update_len(name.chars().count());
+                    let mut row = Row::new(vec![
+                        Cell::from(name),
+                        Cell::from(task.state().render(styles)),
+                        dur_cell(task.busy(now)),
+                        Cell::from(task.total_polls().to_string()),
+                    ]);
+                    if task.state() == TaskState::Completed {
+                        row = row.style(styles.terminated());
+                    }
+                    Some(row)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let controls = Controls::new(
+            &view_controls(bindings, self.group_by, !rows.is_empty()),
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Hide,
+        );
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let table_area = chunks[1];
+
+        let title = format!(
+            "Task groups ({}) by {} ",
+            groups.len(),
+            self.group_by.name()
+        );
+        let block = styles.border_block().title(vec![bold(title)]);
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        if table_rows.is_empty() {
+            frame.render_widget(Paragraph::new("no tasks").block(block), table_area);
+            return actions;
+        }
+
+        let header = Row::new(HEADER.iter().map(|&h| Cell::from(h)))
+            .height(1)
+            .style(Style::default().add_modifier(style::Modifier::BOLD));
+        let widths = [
+            name_width.constraint(),
+            layout::Constraint::Length(TASKS_LEN),
+            layout::Constraint::Length(styles.precision.list_len(unit) as u16),
+            layout::Constraint::Percentage(100),
+        ];
+        let table = Table::new(table_rows)
+            .header(header)
+            .block(block)
+            .widths(&widths)
+            .highlight_symbol(TABLE_HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+        actions
+    }
+
+    /// Returns the rows of the table: a row for each group, followed by a row
+    /// for each of its tasks if it is expanded.
+    fn rows(&self, groups: &[Group]) -> Vec<GroupRow> {
+        let mut rows = Vec::new();
+        for group in groups {
+            rows.push(GroupRow::Group(group.key.clone()));
+            if self.expanded.contains(&group.key) {
+                rows.extend(group.tasks.iter().map(|&(id, _)| GroupRow::Task(id)));
+            }
+        }
+        rows
+    }
+}
+
+const HEADER: &[&str] = &["Group", "Tasks", "Busy", "Polls"];
+
+/// Wide enough for a group's task count and running count.
+const TASKS_LEN: u16 = 20;
+
+/// Groups every task, with the busiest groups and the busiest tasks within
+/// each group first.
+fn groups(state: &State, group_by: GroupBy, now: SystemTime) -> Vec<Group> {
+    let mut groups = BTreeMap::<GroupKey, Group>::new();
+    for task in state.tasks_state().all_tasks() {
+        let task = match task.upgrade() {
+            Some(task) => task,
+            None => continue,
+        };
+        let task = task.borrow();
+        let key = GroupKey {
+            name: group_by.key(&task).to_owned(),
+            is_blocking: task.is_blocking(),
+        };
+        let group = groups.entry(key.clone()).or_insert_with(|| Group {
+            key,
+            running: 0,
+            busy: Duration::ZERO,
+            polls: 0,
+            tasks: Vec::new(),
+        });
+        let busy = task.busy(now);
+        if task.is_running() {
+            group.running += 1;
+        }
+        group.busy += busy;
+        group.polls += task.total_polls();
+        group.tasks.push((task.id(), busy));
+    }
+
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    for group in &mut groups {
+        group.tasks.sort_by_key(|&(_, busy)| cmp::Reverse(busy));
+    }
+    groups.sort_by_key(|group| cmp::Reverse(group.busy));
+    groups
+}
+
+fn view_controls(bindings: &KeyBindings, group_by: GroupBy, has_rows: bool) -> Vec<ControlDisplay> {
+    let next = match group_by {
+        GroupBy::Target => "group by location",
+        GroupBy::Location => "ungroup",
+    };
+    vec![
+        ControlDisplay::new("return to task list", &[Action::Back], bindings),
+        ControlDisplay::new("scroll", &[Action::ScrollUp, Action::ScrollDown], bindings)
+            .enabled(has_rows),
+        ControlDisplay::new(
+            "expand/collapse group, or view task",
+            &[Action::ViewDetails],
+            bindings,
+        )
+        .enabled(has_rows),
+        ControlDisplay::new(next, &[Action::GroupTasks], bindings),
+    ]
+}
+
+// === impl GroupBy ===
+
+impl GroupBy {
+    fn key<'a>(&self, task: &'a Task) -> &'a str {
+        match self {
+            GroupBy::Target => task.target(),
+            GroupBy::Location => task.location(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GroupBy::Target => "target",
+            GroupBy::Location => "location",
+        }
+    }
+}
@@ -1,19 +1,24 @@
 use crate::{
+    fuzzy,
+    input::{Action, KeyBindings},
     state::{
-        tasks::{SortBy, Task, TaskState},
+        tasks::{SortBy, Task, TaskCounts, TaskState, TasksState},
         State,
     },
+    util::percentage,
     view::{
         self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
         table::{self, TableList, TableListState},
-        DUR_LEN, DUR_PRECISION,
     },
+    warnings::{Linter, Severity},
 };
+use std::{borrow::Cow, collections::VecDeque, rc::Weak, time::SystemTime};
 use tui::{
     layout,
     style::{self, Color, Style},
     text::{Span, Spans, Text},
-    widgets::{self, Cell, ListItem, Paragraph, Row, Table},
+    widgets::{self, Cell, ListItem, Row, Table},
 };
 
 #[derive(Debug, Default)]
@@ -24,61 +29,190 @@ impl TableList for TasksTable {
     type Sort = SortBy;
 
     const HEADER: &'static [&'static str] = &[
-        "Warn", "ID", "State", "Name", "Total", "Busy", "Idle", "Polls", "Target", "Location",
+        "Warn",
+        "ID",
+        "State",
+        "Name",
+        "Total",
+        "Busy",
+        "Idle",
+        "Polls",
+        "Poll min/avg/max",
+        "Activity",
+        "Wakes/s",
+        "Target",
+        "Location",
         "Fields",
     ];
+    const RIGHT_ALIGNED: &'static [usize] = &[1, 4, 5, 6, 7, 8, 10];
+
+    /// Tasks match a search if their name or target contains the query.
+    fn matches(task: &Task, query: &str) -> bool {
+        let contains = |s: &str| s.to_lowercase().contains(query);
+        task.name().map(contains).unwrap_or(false) || contains(task.target())
+    }
+
+    /// Tasks are scored by whichever of their name or target matches the
+    /// query best.
+    fn fuzzy_score(task: &Task, query: &str) -> Option<i64> {
+        task.name()
+            .into_iter()
+            .chain(Some(task.target()))
+            .filter_map(|text| fuzzy::fuzzy_match(text, query))
+            .map(|found| found.score)
+            .max()
+    }
+
+    /// When filtered, only completed tasks are displayed.
+    fn filter(task: &Task) -> bool {
+        task.is_completed()
+    }
+
+    /// Blocking tasks' busy and idle times mean something different from
+    /// async tasks', so they can be displayed separately.
+    const KINDS: &'static [&'static str] = &["all", "async only", "blocking only"];
+
+    fn is_kind(task: &Task, kind: usize) -> bool {
+        match kind {
+            1 => !task.is_blocking(),
+            2 => task.is_blocking(),
+            _ => true,
+        }
+    }
+
+    fn text_row(
+        task: &Task,
+        styles: &view::Styles,
+        unit: view::DurationUnit,
+        now: SystemTime,
+        _: &State,
+    ) -> Vec<String> {
+        let warnings = match task.warnings().len() {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        vec![
+            warnings,
+            task.id().to_string(),
+            task.state().name().to_owned(),
+            task.name().unwrap_or_default().to_owned(),
+            table::dur_text(styles, unit, task.total(now)),
+            table::dur_text(styles, unit, task.busy(now)),
+            table::dur_text(styles, unit, task.idle(now)),
+            task.total_polls().to_string(),
+            poll_times(task, now, "-")
+                .map(|times| times.join("/"))
+                .unwrap_or_else(|| "-".to_owned()),
+            sparkline(task.recent_polls(), styles),
+            task.wake_rate()
+                .map(|rate| format!("{:.1}", rate))
+                .unwrap_or_else(|| "-".to_owned()),
+            task.target().to_owned(),
+            task.location().to_owned(),
+            table::spans_text(task.formatted_fields()),
+        ]
+    }
 
     fn render<B: tui::backend::Backend>(
         table_list_state: &mut TableListState<Self>,
         styles: &view::Styles,
+        bindings: &KeyBindings,
         frame: &mut tui::terminal::Frame<B>,
         area: layout::Rect,
         state: &mut State,
-    ) {
-        let state_len: u16 = Self::HEADER[2].len() as u16;
-        let now = if let Some(now) = state.last_updated_at() {
+    ) -> Vec<(Action, bool)> {
+        let state_len = table_list_state.header_width(2);
+        let now = if let Some(now) = state.display_now() {
             now
         } else {
             // If we have never gotten an update yet, skip...
-            return;
+            return Vec::new();
         };
+        let unit = table_list_state.dur_unit();
 
-        table_list_state
-            .sorted_items
-            .extend(state.tasks_state_mut().take_new_tasks());
+        // A frozen view keeps its rows and their order until it's unfrozen.
+        let frozen = state.is_view_frozen();
+        if !frozen {
+            table_list_state
+                .sorted_items
+                .extend(state.tasks_state_mut().take_new_tasks());
+        }
+        table_list_state.apply_search();
 
-        table_list_state
-            .sort_by
-            .sort(now, &mut table_list_state.sorted_items);
+        if !frozen {
+            table_list_state
+                .sort_by
+                .sort(now, &mut table_list_state.sorted_items);
+            table_list_state.sort_by_match();
+        }
+
+        // The columns whose changes are highlighted.
+        const BUSY: usize = SortBy::Busy as usize;
+        const POLLS: usize = SortBy::Polls as usize;
+        let changed = table_list_state.changes.update(
+            table_list_state
+                .sorted_items
+                .iter()
+                .filter_map(Weak::upgrade)
+                .flat_map(|task| {
+                    let task = task.borrow();
+                    [
+                        ((task.id(), BUSY), task.busy(now).as_secs_f64()),
+                        ((task.id(), POLLS), task.total_polls() as f64),
+                    ]
+                }),
+        );
+        let highlight_changed = |cell: Cell<'static>, id: u64, column: usize| {
+            if changed.contains(&(id, column)) {
+                cell.style(styles.changed())
+            } else {
+                cell
+            }
+        };
 
         let dur_cell = |dur: std::time::Duration| -> Cell<'static> {
             Cell::from(styles.time_units(format!(
-                "{:>width$.prec$?}",
-                dur,
-                width = DUR_LEN,
-                prec = DUR_PRECISION,
+                "{:>width$}",
+                styles.precision.list_dur(unit, dur),
+                width = styles.precision.list_len(unit)
             )))
         };
 
         // Start out wide enough to display the column headers...
-        let mut warn_width = view::Width::new(Self::HEADER[0].len() as u16);
-        let mut id_width = view::Width::new(Self::HEADER[1].len() as u16);
-        let mut name_width = view::Width::new(Self::HEADER[3].len() as u16);
-        let mut polls_width = view::Width::new(Self::HEADER[7].len() as u16);
-        let mut target_width = view::Width::new(Self::HEADER[8].len() as u16);
-        let mut location_width = view::Width::new(Self::HEADER[9].len() as u16);
+        let mut warn_width = view::Width::new(table_list_state.header_width(0));
+        let mut id_width = view::Width::new(table_list_state.header_width(1));
+        let mut name_width = view::Width::new(table_list_state.header_width(3));
+        let mut polls_width = view::Width::new(table_list_state.header_width(7));
+        let mut poll_times_width = view::Width::new(table_list_state.header_width(8));
+        let activity_len = table_list_state
+            .header_width(9)
+            .max(Task::POLL_SAMPLES as u16);
+        let mut wake_rate_width = view::Width::new(table_list_state.header_width(10));
+        let mut target_width = view::Width::new(table_list_state.header_width(11));
+        let mut location_width = view::Width::new(table_list_state.header_width(12));
+
+        let query = table_list_state.search_query();
+        let fuzzy = table_list_state.is_fuzzy();
+        let highlight = Style::default().add_modifier(style::Modifier::REVERSED);
 
         let mut num_idle = 0;
         let mut num_running = 0;
         let rows = {
+            let query = query.as_deref();
+            let tasks_state = state.tasks_state();
             let id_width = &mut id_width;
             let target_width = &mut target_width;
             let location_width = &mut location_width;
             let name_width = &mut name_width;
             let polls_width = &mut polls_width;
+            let poll_times_width = &mut poll_times_width;
+            let wake_rate_width = &mut wake_rate_width;
             let warn_width = &mut warn_width;
             let num_running = &mut num_running;
             let num_idle = &mut num_idle;
+            let tags = &table_list_state.tags;
+            let notes = &table_list_state.notes;
+            let name_counts = tasks_state.name_counts();
 
             table_list_state
                 .sorted_items
@@ -106,21 +240,71 @@ impl TableList for TasksTable {
                         Cell::from("")
                     };
 
+                    // Pinned and annotated tasks are marked before their
+                    // IDs, so that they stand out once they have completed.
+                    let markers = [
+                        tasks_state.is_pinned(task.id()).then(|| styles.pinned()),
+                        notes.get(task.id()).map(|_| styles.noted()),
+                    ];
+                    let marker_len = markers
+                        .iter()
+                        .flatten()
+                        .map(|m| m.content.chars().count())
+                        .sum::<usize>();
+                    let id = format!(
+                        "{:>width$}",
+                        task.id(),
+                        width = (id_width.chars() as usize).saturating_sub(marker_len)
+                    );
+                    id_width.update_len(id.len() + marker_len);
+                    let id = Cell::from(Spans::from(
+                        markers
+                            .iter()
+                            .flatten()
+                            .cloned()
+                            .chain(Some(Span::raw(id)))
+                            .collect::<Vec<_>>(),
+                    ));
+
+                    // Tasks which share a name are told apart by their IDs,
+                    // and unique names are displayed as they are.
+                    let name = match task.name() {
+                        Some(name) if name_counts.get(name).is_some_and(|&n| n > 1) => {
+                            Cow::Owned(format!("{} #{}", name, task.id()))
+                        }
+                        name => Cow::Borrowed(name.unwrap_or("")),
+                    };
+
                     let mut row = Row::new(vec![
                         warnings,
-                        Cell::from(id_width.update_str(format!(
-                            "{:>width$}",
-                            task.id(),
-                            width = id_width.chars() as usize
-                        ))),
+                        id,
                         Cell::from(task.state().render(styles)),
-                        Cell::from(name_width.update_str(task.name().unwrap_or("").to_string())),
+                        Cell::from(highlight_matches(
+                            &name_width.update_str(name),
+                            query,
+                            fuzzy,
+                            highlight,
+                        )),
                         dur_cell(task.total(now)),
-                        dur_cell(task.busy(now)),
+                        highlight_changed(dur_cell(task.busy(now)), task.id(), BUSY),
                         dur_cell(task.idle(now)),
-                        Cell::from(polls_width.update_str(task.total_polls().to_string())),
-                        Cell::from(target_width.update_str(task.target()).to_owned()),
-                        Cell::from(location_width.update_str(task.location().to_owned())),
+                        highlight_changed(polls_cell(&task, polls_width, styles), task.id(), POLLS),
+                        poll_times_cell(&task, now, poll_times_width, styles),
+                        Cell::from(sparkline(task.recent_polls(), styles)),
+                        Cell::from(wake_rate_width.update_str(format!(
+                            "{:>width$}",
+                            task.wake_rate()
+                                .map(|rate| format!("{:.1}", rate))
+                                .unwrap_or_else(|| styles.if_utf8("\u{2013}", "-").to_owned()),
+                            width = wake_rate_width.chars() as usize
+                        ))),
+                        Cell::from(highlight_matches(
+                            target_width.update_str(task.target()),
+                            query,
+                            fuzzy,
+                            highlight,
+                        )),
+                        Cell::from(location_width.update_str(task.location()).to_owned()),
                         Cell::from(Spans::from(
                             task.formatted_fields()
                                 .iter()
@@ -129,33 +313,22 @@ impl TableList for TasksTable {
                                 .collect::<Vec<_>>(),
                         )),
                     ]);
+                    // Tagged rows stay underlined when colors are disabled, so
+                    // that they can still be told apart.
+                    let mut row_style = Style::default();
+                    if let Some(color) = tags.get(task.id()) {
+                        row_style =
+                            row_style.patch(styles.fg_or(color, style::Modifier::UNDERLINED));
+                    }
                     if state == TaskState::Completed {
-                        row = row.style(styles.terminated());
+                        row_style = row_style.patch(styles.terminated());
                     }
+                    row = row.style(row_style);
                     Some(row)
                 })
         };
 
-        let (selected_style, header_style) = if let Some(cyan) = styles.color(Color::Cyan) {
-            (Style::default().fg(cyan), Style::default())
-        } else {
-            (
-                Style::default().remove_modifier(style::Modifier::REVERSED),
-                Style::default().add_modifier(style::Modifier::REVERSED),
-            )
-        };
-        let header_style = header_style.add_modifier(style::Modifier::BOLD);
-
-        let header = Row::new(Self::HEADER.iter().enumerate().map(|(idx, &value)| {
-            let cell = Cell::from(value);
-            if idx == table_list_state.selected_column {
-                cell.style(selected_style)
-            } else {
-                cell
-            }
-        }))
-        .height(1)
-        .style(header_style);
+        let header = table_list_state.header(styles);
 
         let table = if table_list_state.sort_descending {
             Table::new(rows)
@@ -163,65 +336,214 @@ impl TableList for TasksTable {
             Table::new(rows.rev())
         };
 
-        let block = styles.border_block().title(vec![
+        let mut title = vec![
             bold(format!("Tasks ({}) ", table_list_state.len())),
             TaskState::Running.render(styles),
             Span::from(format!(" Running ({}) ", num_running)),
             TaskState::Idle.render(styles),
             Span::from(format!(" Idle ({})", num_idle)),
-        ]);
+        ];
+        if let Some(kind) = table_list_state.kind() {
+            title.push(bold(format!(" {}", kind)));
+        }
+        if table_list_state.is_filtered() {
+            title.push(bold(" completed only"));
+        }
+        if let Some(search) = table_list_state.search() {
+            title.push(bold(if table_list_state.is_fuzzy() {
+                " fuzzy search: "
+            } else {
+                " search: "
+            }));
+            title.push(Span::from(search.to_owned()));
+            if table_list_state.is_editing_search() {
+                title.push(Span::from("_"));
+            }
+        }
+        if let Some(goto) = table_list_state.goto() {
+            title.push(bold(" go to task: "));
+            title.push(Span::from(format!("{}_", goto)));
+        }
+        if let Some((id, note)) = table_list_state.notes.editing() {
+            title.push(bold(format!(" note for task {}: ", id)));
+            title.push(Span::from(format!("{}_", note)));
+        }
+        let block = styles.border_block().title(title);
 
         /* TODO: use this to adjust the max size of name and target columns...
         // How many characters wide are the fixed-length non-field columns?
         let fixed_col_width = id_width.chars()
             + STATE_LEN
             + name_width.chars()
-            + DUR_LEN as u16
-            + DUR_LEN as u16
-            + DUR_LEN as u16
+            + styles.precision.list_len(unit) as u16
+            + styles.precision.list_len(unit) as u16
+            + styles.precision.list_len(unit) as u16
             + POLLS_LEN as u16
             + target_width.chars();
         */
-        let warnings = state
-            .tasks_state()
+        let lint_now = state.last_updated_at().unwrap_or(now);
+        let tasks_state = state.tasks_state();
+        let warnings = tasks_state
             .warnings()
             .map(|warning| {
-                ListItem::new(Text::from(Spans::from(vec![
+                let mut spans = vec![
                     styles.warning_wide(),
                     // TODO(eliza): it would be nice to handle singular vs plural...
                     Span::from(format!("{} {}", warning.count(), warning.summary())),
-                ])))
+                ];
+                // If many of the warned tasks were spawned in the same place,
+                // point that out, since they likely share a cause.
+                let (locations, rest) =
+                    tasks_state.top_warning_locations(warning, MAX_WARNING_LOCATIONS);
+                if matches!(locations.first(), Some((_, count)) if *count > 1) {
+                    let mut breakdown = locations
+                        .iter()
+                        .map(|(location, count)| format!("{} at {}", count, location.trim_end()))
+                        .collect::<Vec<_>>();
+                    if rest > 0 {
+                        breakdown.push(format!("+{} more", rest));
+                    }
+                    spans.push(Span::from(format!(" ({})", breakdown.join(", "))));
+                }
+                ListItem::new(Text::from(Spans::from(spans)))
             })
+            .chain(tasks_state.locations.warnings().map(|warning| {
+                // Name the worst location, since that's where to start.
+                let warned = tasks_state.locations.warned(warning);
+                let mut text = format!("{} {}", warning.count(), warning.summary());
+                if let Some(worst) = warned.first() {
+                    text.push_str(&format!(": {}", warning.format(worst, lint_now)));
+                }
+                if warned.len() > 1 {
+                    text.push_str(&format!(" (+{} more)", warned.len() - 1));
+                }
+                ListItem::new(Text::from(Spans::from(vec![
+                    styles.warning_wide(),
+                    Span::from(text),
+                ])))
+            }))
             .collect::<Vec<_>>();
 
         let layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
             .margin(0);
 
-        let (controls_area, tasks_area, warnings_area) = if warnings.is_empty() {
-            let chunks = layout
-                .constraints(
-                    [
-                        layout::Constraint::Length(1),
-                        layout::Constraint::Min(area.height - 1),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-            (chunks[0], chunks[1], None)
+        let mut view_controls = table::view_controls(bindings, "view details");
+        view_controls.push(ControlDisplay::new(
+            "pin (toggle/unpin all)",
+            &[Action::TogglePin, Action::UnpinAll],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "tag (cycle color/clear all)",
+            &[Action::CycleTag, Action::ClearTags],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "note (edit/clear all)",
+            &[Action::EditNote, Action::ClearNotes],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "search (start/fuzzy or substring)",
+            &[Action::Search, Action::ToggleFuzzySearch],
+            bindings,
+        ));
+        let filter = if table_list_state.is_filtered() {
+            "show all tasks"
         } else {
-            let chunks = layout
-                .constraints(
-                    [
-                        layout::Constraint::Length(1),
-                        layout::Constraint::Length(warnings.len() as u16 + 2),
-                        layout::Constraint::Min(area.height - 1),
-                    ]
-                    .as_ref(),
-                )
-                .split(area);
-            (chunks[0], chunks[2], Some(chunks[1]))
+            "show completed only"
+        };
+        view_controls.push(ControlDisplay::new(
+            filter,
+            &[Action::FilterCompleted],
+            bindings,
+        ));
+        let next_kind = match table_list_state.kind() {
+            None => "show async only",
+            Some(kind) if kind == Self::KINDS[1] => "show blocking only",
+            Some(_) => "show async and blocking",
+        };
+        view_controls.push(ControlDisplay::new(
+            next_kind,
+            &[Action::CycleTaskKinds],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "go to task",
+            &[Action::GotoTask],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "export busy time by location",
+            &[Action::ExportFolded],
+            bindings,
+        ));
+        view_controls.push(ControlDisplay::new(
+            "group by target",
+            &[Action::GroupTasks],
+            bindings,
+        ));
+        let top = if table_list_state.top.is_shown() {
+            "hide top tasks"
+        } else {
+            "show top tasks"
         };
+        view_controls.push(ControlDisplay::new(
+            top,
+            &[Action::ToggleTopTasks],
+            bindings,
+        ));
+        view_controls.push(
+            ControlDisplay::new("top tasks metric", &[Action::CycleTopMetric], bindings)
+                .enabled(table_list_state.top.is_shown()),
+        );
+        if table_list_state.is_resizing() {
+            view_controls = table::resize_controls(bindings);
+        }
+        let controls = Controls::new(
+            &view_controls,
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Dim,
+        )
+        .with_notice(table_list_state.notice(), styles);
+        let busy_gauge = busy_gauge(tasks_state, styles, now);
+        let severity = tasks_state.warnings().map(Linter::severity).max();
+        let stats_strip = stats_strip(
+            tasks_state.counts(),
+            severity,
+            state.dropped_events(),
+            styles,
+        );
+        // add 2 for the top and bottom borders
+        let warnings_height = if warnings.is_empty() {
+            0
+        } else {
+            warnings.len() as u16 + 2
+        };
+        let chunks = layout
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Length(1),
+                    layout::Constraint::Length(warnings_height),
+                    layout::Constraint::Min(
+                        area.height
+                            .saturating_sub(controls.height() + warnings_height + 2),
+                    ),
+                    layout::Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let busy_area = chunks[1];
+        let warnings_area = chunks[2];
+        let (tasks_area, top_area) = table_list_state.top.split(chunks[3]);
+        let stats_area = chunks[4];
 
         // Fill all remaining characters in the frame with the task's fields.
         //
@@ -235,34 +557,320 @@ impl TableList for TasksTable {
             id_width.constraint(),
             layout::Constraint::Length(state_len),
             name_width.constraint(),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
-            layout::Constraint::Length(DUR_LEN as u16),
+            layout::Constraint::Length(styles.precision.list_len(unit) as u16),
+            layout::Constraint::Length(styles.precision.list_len(unit) as u16),
+            layout::Constraint::Length(styles.precision.list_len(unit) as u16),
             polls_width.constraint(),
+            poll_times_width.constraint(),
+            layout::Constraint::Length(activity_len),
+            wake_rate_width.constraint(),
             target_width.constraint(),
             location_width.constraint(),
             fields_width,
         ];
+        let widths = table_list_state.column_widths(widths, tasks_area.width);
 
         let table = table
             .header(header)
             .block(block)
-            .widths(widths)
+            .widths(&widths)
             .highlight_symbol(view::TABLE_HIGHLIGHT_SYMBOL)
             .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
 
         frame.render_stateful_widget(table, tasks_area, &mut table_list_state.table_state);
-        frame.render_widget(Paragraph::new(table::controls(styles)), controls_area);
+        if let Some(top_area) = top_area {
+            table_list_state
+                .top
+                .render(styles, frame, top_area, state.tasks_state(), now, unit);
+        }
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        frame.render_widget(busy_gauge, busy_area);
+        frame.render_widget(widgets::Paragraph::new(stats_strip), stats_area);
 
-        if let Some(area) = warnings_area {
+        if !warnings.is_empty() {
             let block = styles
                 .border_block()
                 .title(Spans::from(vec![bold("Warnings")]));
-            frame.render_widget(widgets::List::new(warnings).block(block), area);
+            frame.render_widget(widgets::List::new(warnings).block(block), warnings_area);
         }
 
         table_list_state
             .sorted_items
             .retain(|t| t.upgrade().is_some());
+        let tasks_state = state.tasks_state();
+        table_list_state
+            .tags
+            .retain(|&id| tasks_state.task(id).is_some());
+        table_list_state
+            .notes
+            .retain(|&id| tasks_state.task(id).is_some());
+        actions
+    }
+}
+
+/// How many of the most common spawn locations to list after a warning, so
+/// that the warning fits on one line.
+const MAX_WARNING_LOCATIONS: usize = 3;
+
+/// Renders the number of times a task was polled in each recent update as a
+/// row of bars, scaled relative to the task's busiest update.
+///
+/// Tasks which have not been sampled enough times yet are displayed as a
+/// placeholder.
+fn sparkline(samples: &VecDeque<u64>, styles: &view::Styles) -> String {
+    if samples.len() < 2 {
+        return "-".to_owned();
+    }
+
+    let bars = styles
+        .if_utf8("▁▂▃▄▅▆▇█", ".:-=+*#@")
+        .chars()
+        .collect::<Vec<_>>();
+    let max = samples.iter().copied().max().unwrap_or(0);
+    samples
+        .iter()
+        .map(|&polls| {
+            if polls == 0 {
+                ' '
+            } else {
+                let idx = (polls * bars.len() as u64 - 1) / max;
+                bars[idx as usize]
+            }
+        })
+        .collect()
+}
+
+/// Returns the cell displaying a task's poll count, followed by the trend of
+/// its recent polls if it has been sampled enough times.
+fn polls_cell(task: &Task, polls_width: &mut view::Width, styles: &view::Styles) -> Cell<'static> {
+    let polls = task.total_polls().to_string();
+    // add 2 for the space and the trend arrow, so that the column doesn't
+    // change width as tasks gain trends.
+    polls_width.update_len(polls.len() + 2);
+    let mut spans = vec![Span::raw(polls)];
+    if let Some(trend) = task.poll_trend() {
+        spans.push(Span::raw(" "));
+        spans.push(trend.render(styles));
+    }
+    Cell::from(Spans::from(spans))
+}
+
+/// Returns the shortest, average and longest of `task`'s poll times, with
+/// `dash` in place of the shortest and longest if the target didn't send them,
+/// or `None` if the task has never been polled.
+///
+/// These are displayed without any digits after the decimal point, regardless
+/// of the list precision, to keep the column narrow.
+fn poll_times(task: &Task, now: SystemTime, dash: &str) -> Option<[String; 3]> {
+    let coarse = |dur| format!("{:.0?}", dur);
+    let average = coarse(task.average_poll(now)?);
+    let (shortest, longest) = match task.poll_range() {
+        Some((shortest, longest)) => (coarse(shortest), coarse(longest)),
+        None => (dash.to_owned(), dash.to_owned()),
+    };
+    Some([shortest, average, longest])
+}
+
+fn poll_times_cell(
+    task: &Task,
+    now: SystemTime,
+    poll_times_width: &mut view::Width,
+    styles: &view::Styles,
+) -> Cell<'static> {
+    let dash = styles.if_utf8("\u{2013}", "-");
+    let mut spans = match poll_times(task, now, dash) {
+        Some(times) => {
+            let mut spans = Vec::with_capacity(5);
+            for time in times {
+                if !spans.is_empty() {
+                    spans.push(Span::raw("/"));
+                }
+                spans.push(styles.time_units(time));
+            }
+            spans
+        }
+        None => vec![Span::raw(dash)],
+    };
+    let len = spans
+        .iter()
+        .map(|span| span.content.chars().count())
+        .sum::<usize>();
+    poll_times_width.update_len(len);
+    let padding = (poll_times_width.chars() as usize).saturating_sub(len);
+    spans.insert(0, Span::raw(" ".repeat(padding)));
+    Cell::from(Spans::from(spans))
+}
+
+/// Returns `text`, with each occurrence of the lowercase search `query`
+/// displayed using the `highlight` style, ignoring case.
+///
+/// If `fuzzy` is set, each of the characters the query fuzzily matched is
+/// highlighted instead. If there is no query, `text` is returned unstyled.
+fn highlight_matches(
+    text: &str,
+    query: Option<&str>,
+    fuzzy: bool,
+    highlight: Style,
+) -> Spans<'static> {
+    let query = match query {
+        Some(query) => query,
+        None => return Spans::from(text.to_owned()),
+    };
+    if fuzzy {
+        return highlight_fuzzy_match(text, query, highlight);
+    }
+
+    // Returns the length in bytes of the match of `query` at the start of
+    // `text`, if there is one. Characters are compared one at a time, so
+    // that the match ends on a character boundary in `text`, even if
+    // lowercasing a character changes its length.
+    let match_len = |text: &str| {
+        let mut text_chars = text.char_indices();
+        let mut len = 0;
+        for expected in query.chars() {
+            let (idx, c) = text_chars.next()?;
+            if !c.to_lowercase().eq(expected.to_lowercase()) {
+                return None;
+            }
+            len = idx + c.len_utf8();
+        }
+        Some(len)
+    };
+
+    let mut spans = Vec::new();
+    let mut unmatched_start = 0;
+    let mut idx = 0;
+    while idx < text.len() {
+        if let Some(len) = match_len(&text[idx..]) {
+            if unmatched_start < idx {
+                spans.push(Span::raw(text[unmatched_start..idx].to_owned()));
+            }
+            spans.push(Span::styled(text[idx..idx + len].to_owned(), highlight));
+            idx += len;
+            unmatched_start = idx;
+        } else {
+            idx += text[idx..].chars().next().map(char::len_utf8).unwrap_or(1);
+        }
+    }
+    if unmatched_start < text.len() {
+        spans.push(Span::raw(text[unmatched_start..].to_owned()));
+    }
+    Spans::from(spans)
+}
+
+/// Returns `text`, with each of the characters that `query` fuzzily matched
+/// displayed using the `highlight` style.
+pub(in crate::view) fn highlight_fuzzy_match(
+    text: &str,
+    query: &str,
+    highlight: Style,
+) -> Spans<'static> {
+    let indices = match fuzzy::fuzzy_match(text, query) {
+        Some(found) => found.indices,
+        None => return Spans::from(text.to_owned()),
+    };
+    // Adjacent matched characters share a span, as do the characters between
+    // them.
+    let mut spans = Vec::new();
+    let mut unmatched_start = 0;
+    let mut matched: Option<(usize, usize)> = None;
+    for idx in indices {
+        let end = idx + text[idx..].chars().next().map(char::len_utf8).unwrap_or(1);
+        match matched {
+            Some((start, prev_end)) if prev_end == idx => matched = Some((start, end)),
+            _ => {
+                if let Some((start, prev_end)) = matched {
+                    spans.push(Span::styled(text[start..prev_end].to_owned(), highlight));
+                    unmatched_start = prev_end;
+                }
+                if unmatched_start < idx {
+                    spans.push(Span::raw(text[unmatched_start..idx].to_owned()));
+                }
+                matched = Some((idx, end));
+            }
+        }
+    }
+    if let Some((start, end)) = matched {
+        spans.push(Span::styled(text[start..end].to_owned(), highlight));
+        unmatched_start = end;
+    }
+    if unmatched_start < text.len() {
+        spans.push(Span::raw(text[unmatched_start..].to_owned()));
+    }
+    Spans::from(spans)
+}
+
+/// Summarizes the counts of all tracked tasks on one line, below the table.
+///
+/// The count of tasks with warnings is colored by `severity`, the most
+/// serious of the tasks' current warnings.
+fn stats_strip(
+    counts: TaskCounts,
+    severity: Option<Severity>,
+    dropped_events: u64,
+    styles: &view::Styles,
+) -> Spans<'static> {
+    let mut spans = vec![
+        bold(format!("{} tasks: ", counts.total)),
+        TaskState::Running.render(styles),
+        Span::from(format!(" {} running, ", counts.running)),
+        TaskState::Idle.render(styles),
+        Span::from(format!(" {} idle, ", counts.idle)),
+        TaskState::Completed.render(styles),
+        Span::from(format!(" {} completed", counts.completed)),
+    ];
+    if counts.blocking > 0 {
+        spans.push(Span::from(format!(", {} blocking", counts.blocking)));
+    }
+    if let (true, Some(severity)) = (counts.warned > 0, severity) {
+        spans.push(Span::from(" | "));
+        spans.push(styles.warning_badge(counts.warned, severity));
+        spans.push(Span::from(" with warnings"));
     }
+    if dropped_events > 0 {
+        spans.push(Span::from(" | "));
+        spans.push(Span::styled(
+            format!("{} dropped events", dropped_events),
+            styles.warning(),
+        ));
+    }
+    Spans::from(spans)
+}
+
+/// Returns a gauge displaying the proportion of time that all tracked
+/// tasks have spent busy, rather than idle.
+fn busy_gauge(
+    tasks_state: &TasksState,
+    styles: &view::Styles,
+    now: SystemTime,
+) -> widgets::Gauge<'static> {
+    let busy = tasks_state.total_busy(now);
+    let idle = tasks_state.total_idle(now);
+    let total = (busy + idle).as_secs_f64();
+    let busy_percent = percentage(total, busy.as_secs_f64());
+
+    let mut label = format!(
+        "all tasks busy: {:.prec$?}, idle: {:.prec$?} ({:.2}% busy)",
+        busy,
+        idle,
+        busy_percent,
+        prec = styles.precision.list,
+    );
+    let blocking = tasks_state.blocking_count();
+    if blocking > 0 {
+        label.push_str(&format!(", excluding {} blocking tasks", blocking));
+    }
+    if let Some(rate) = tasks_state.wake_rate() {
+        label.push_str(&format!(" | wakes: {:.1}/s", rate));
+        if let Some(trend) = tasks_state.wake_trend() {
+            label.push(' ');
+            label.push_str(trend.symbol(styles));
+        }
+    }
+
+    widgets::Gauge::default()
+        .gauge_style(styles.fg(Color::Green))
+        .ratio(busy_percent / 100.0)
+        .label(Span::raw(label))
 }
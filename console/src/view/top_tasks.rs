@@ -0,0 +1,245 @@
+//! A panel next to the tasks list, listing the tasks with the most of a
+//! metric like busy time, for a quick overview of which tasks are doing the
+//! most however the list is sorted or filtered.
+use crate::{
+    state::tasks::{Task, TasksState},
+    util,
+    view::{self, bold},
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tui::{
+    layout,
+    text::{Span, Spans},
+    widgets::Paragraph,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// What the top tasks panel ranks tasks by.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum TopMetric {
+    Busy,
+    Polls,
+    Wakes,
+    /// How long tasks have spent waiting to be polled after being woken.
+    Scheduled,
+}
+
+/// Whether the top tasks panel is displayed, and what it lists.
+#[derive(Debug)]
+pub(crate) struct TopTasks {
+    shown: bool,
+    metric: TopMetric,
+    /// How many tasks are listed.
+    count: usize,
+}
+
+// === impl TopMetric ===
+
+impl TopMetric {
+    /// Every metric, in the order they are cycled through.
+    const ALL: [TopMetric; 4] = [
+        TopMetric::Busy,
+        TopMetric::Polls,
+        TopMetric::Wakes,
+        TopMetric::Scheduled,
+    ];
+
+    fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    /// Returns how much of the metric `task` has as of `now`, in nanoseconds
+    /// for metrics which are durations, or `None` if the target hasn't sent
+    /// it.
+    fn value(self, task: &Task, now: SystemTime) -> Option<u128> {
+        match self {
+            TopMetric::Busy => Some(task.busy(now).as_nanos()),
+            TopMetric::Polls => Some(u128::from(task.total_polls())),
+            TopMetric::Wakes => Some(u128::from(task.wakes())),
+            TopMetric::Scheduled => task.scheduled(now).map(|dur| dur.as_nanos()),
+        }
+    }
+
+    fn is_duration(self) -> bool {
+        matches!(self, TopMetric::Busy | TopMetric::Scheduled)
+    }
+}
+
+impl fmt::Display for TopMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TopMetric::Busy => "busy",
+            TopMetric::Polls => "polls",
+            TopMetric::Wakes => "wakes",
+            TopMetric::Scheduled => "scheduled",
+        })
+    }
+}
+
+impl FromStr for TopMetric {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|metric| s.eq_ignore_ascii_case(&metric.to_string()))
+            .ok_or("expected one of `busy`, `polls`, `wakes` or `scheduled`")
+    }
+}
+
+// === impl TopTasks ===
+
+impl TopTasks {
+    /// The widest the panel is, including its borders.
+    const MAX_WIDTH: u16 = 44;
+
+    /// Sets what the panel lists, which is the `count` tasks with the most of
+    /// `metric`.
+    pub(in crate::view) fn set(&mut self, metric: TopMetric, count: usize) {
+        self.metric = metric;
+        self.count = count;
+    }
+
+    pub(in crate::view) fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    pub(in crate::view) fn toggle(&mut self) {
+        self.shown = !self.shown;
+    }
+
+    /// Ranks tasks by the next metric, or by the first after the last.
+    pub(in crate::view) fn cycle_metric(&mut self) {
+        self.metric = self.metric.next();
+    }
+
+    /// Splits `area` into the area the tasks table is drawn in, and the area
+    /// the panel is drawn in to its right, if the panel is displayed.
+    pub(in crate::view) fn split(
+        &self,
+        area: layout::Rect,
+    ) -> (layout::Rect, Option<layout::Rect>) {
+        if !self.shown {
+            return (area, None);
+        }
+        let width = Self::MAX_WIDTH.min(area.width / 3);
+        let table = layout::Rect {
+            width: area.width - width,
+            ..area
+        };
+        let panel = layout::Rect {
+            x: area.x + table.width,
+            width,
+            ..area
+        };
+        (table, Some(panel))
+    }
+
+    pub(in crate::view) fn render<B: tui::backend::Backend>(
+        &self,
+        styles: &view::Styles,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        tasks_state: &TasksState,
+        now: SystemTime,
+        unit: view::DurationUnit,
+    ) {
+        let block = styles.border_block().title(Spans::from(vec![bold(format!(
+            "Top {} by {}",
+            self.count, self.metric
+        ))]));
+        let inner_width = usize::from(area.width.saturating_sub(2));
+
+        let top = top(tasks_state, self.metric, self.count, now);
+        let lines = top
+            .into_iter()
+            .filter_map(|(id, value)| {
+                let task = tasks_state.task(id)?;
+                let task = task.borrow();
+                let value = if self.metric.is_duration() {
+                    let dur = Duration::from_nanos(value.min(u128::from(u64::MAX)) as u64);
+                    styles.precision.list_dur(unit, dur)
+                } else {
+                    value.to_string()
+                };
+                let id = id.to_string();
+                // Unnamed tasks are labeled with where they were spawned, keeping
+                // the start and end of the path like the task view does.
+                let label = task.name().unwrap_or_else(|| task.location());
+                // Leave a space on either side of the label.
+                let label_width = inner_width.saturating_sub(id.len() + value.chars().count() + 2);
+                let label = util::truncate_middle(
+                    label,
+                    label_width,
+                    label_width / 3,
+                    styles.if_utf8("\u{2026}", "..."),
+                );
+                let padding = label_width.saturating_sub(label.width());
+                Some(Spans::from(vec![
+                    bold(id),
+                    Span::raw(format!(" {}{} ", label, " ".repeat(padding))),
+                    Span::raw(value),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let text = if lines.is_empty() {
+            vec![Spans::from("no tasks to list yet")]
+        } else {
+            lines
+        };
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+impl Default for TopTasks {
+    fn default() -> Self {
+        Self {
+            shown: false,
+            metric: TopMetric::Busy,
+            count: 5,
+        }
+    }
+}
+
+/// Returns the IDs of the `count` tasks with the most of `metric` as of `now`,
+/// along with how much they have, most first. Tasks with the same amount are
+/// ordered by ID, so that they don't swap places between updates, and tasks
+/// without any of the metric are left out.
+///
+/// Rather than sorting every task, only the best `count` tasks seen so far
+/// are kept, in a heap whose root is the worst of them.
+fn top(tasks: &TasksState, metric: TopMetric, count: usize, now: SystemTime) -> Vec<(u64, u128)> {
+    if count == 0 {
+        return Vec::new();
+    }
+    // Ordering by `Reverse` makes this a min-heap, and among tasks with the
+    // same amount, the one with the highest ID is the worst.
+    // `count` comes from the command line unchecked, so the heap is never
+    // made larger than the number of tasks.
+    let tasks = tasks.all_tasks();
+    let mut heap = BinaryHeap::with_capacity(count.min(tasks.len()).saturating_add(1));
+    for task in tasks.filter_map(|task| task.upgrade()) {
+        let task = task.borrow();
+        match metric.value(&task, now) {
+            Some(value) if value > 0 => heap.push(Reverse((value, Reverse(task.id())))),
+            _ => continue,
+        }
+        if heap.len() > count {
+            heap.pop();
+        }
+    }
+    // Sorting the reversed entries in ascending order puts the best first.
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((value, Reverse(id)))| (id, value))
+        .collect()
+}
@@ -0,0 +1,224 @@
+//! A summary of every warning that currently applies to any tasks, from which
+//! the tasks with each warning can be inspected.
+use crate::{
+    input::{self, Action, KeyBindings},
+    state::State,
+    view::{
+        self, bold,
+        controls::{ControlDisplay, Controls, DisabledControls},
+        TABLE_HIGHLIGHT_SYMBOL,
+    },
+};
+use tui::{
+    layout::{self, Layout},
+    style::{self, Style},
+    text::{Span, Spans},
+    widgets::{List, ListItem, ListState, Paragraph},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct WarningsView {
+    /// The selected row of the warnings list.
+    warnings_state: ListState,
+    /// The summary of the warning whose tasks are listed, if one has been
+    /// selected.
+    ///
+    /// The warning is identified by its summary rather than its position in
+    /// the list, since warnings come and go as tasks are updated.
+    selected: Option<String>,
+    /// The selected row of the selected warning's tasks list.
+    tasks_state: ListState,
+}
+
+impl WarningsView {
+    /// Handles an input event, returning the ID of the task to inspect if one
+    /// was selected.
+    pub(crate) fn update_input(
+        &mut self,
+        event: input::Event,
+        bindings: &KeyBindings,
+        state: &State,
+    ) -> Option<u64> {
+        let warnings = warnings(state);
+        match self.selected {
+            None => {
+                if bindings.is(Action::ViewDetails, &event) {
+                    let selected = self.warnings_state.selected();
+                    if let Some((summary, _)) = selected.and_then(|idx| warnings.get(idx)) {
+                        self.selected = Some(summary.to_string());
+                        self.tasks_state.select(Some(0));
+                    }
+                } else {
+                    scroll(&mut self.warnings_state, warnings.len(), &event, bindings);
+                }
+                None
+            }
+            Some(ref selected) => {
+                let tasks = tasks_with(&warnings, selected);
+                if bindings.is(Action::Back, &event) {
+                    self.selected = None;
+                    None
+                } else if bindings.is(Action::ViewDetails, &event) {
+                    let selected = self.tasks_state.selected();
+                    selected.and_then(|idx| tasks.get(idx)).copied()
+                } else {
+                    scroll(&mut self.tasks_state, tasks.len(), &event, bindings);
+                    None
+                }
+            }
+        }
+    }
+
+    pub(crate) fn render<B: tui::backend::Backend>(
+        &mut self,
+        styles: &view::Styles,
+        bindings: &KeyBindings,
+        frame: &mut tui::terminal::Frame<B>,
+        area: layout::Rect,
+        state: &State,
+    ) -> Vec<(Action, bool)> {
+        let warnings = warnings(state);
+
+        let (title, items, list_state) = match self.selected {
+            None => {
+                let items = warnings
+                    .iter()
+                    .map(|(summary, tasks)| {
+                        ListItem::new(Spans::from(vec![
+                            styles.warning_wide(),
+                            Span::from(format!("{} {}", tasks.len(), summary)),
+                        ]))
+                    })
+                    .collect::<Vec<_>>();
+                let title = format!("Warnings ({}) ", warnings.len());
+                (title, items, &mut self.warnings_state)
+            }
+            Some(ref selected) => {
+                let tasks_state = state.tasks_state();
+                let items = tasks_with(&warnings, selected)
+                    .iter()
+                    .filter_map(|&id| {
+                        let task = tasks_state.task(id)?;
+                        let task = task.borrow();
+                        let mut spans = vec![bold(format!("{} ", id))];
+                        if let Some(name) = task.name() {
+                            spans.push(Span::raw(format!("{} ", name)));
+                        }
+                        spans.push(Span::raw(task.location().to_owned()));
+                        Some(ListItem::new(Spans::from(spans)))
+                    })
+                    .collect::<Vec<_>>();
+                let title = format!("Tasks with warning: {} ", selected);
+                (title, items, &mut self.tasks_state)
+            }
+        };
+        clamp_selection(list_state, items.len());
+
+        let controls = Controls::new(
+            &view_controls(bindings, self.selected.is_some(), !items.is_empty()),
+            bindings,
+            &area,
+            styles,
+            state,
+            DisabledControls::Hide,
+        );
+        let chunks = Layout::default()
+            .direction(layout::Direction::Vertical)
+            .constraints(
+                [
+                    layout::Constraint::Length(controls.height()),
+                    layout::Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let controls_area = chunks[0];
+        let list_area = chunks[1];
+
+        let block = styles.border_block().title(vec![bold(title)]);
+        let actions = controls.actions();
+        frame.render_widget(controls.into_widget(), controls_area);
+        if items.is_empty() {
+            let empty = if self.selected.is_some() {
+                "no tasks currently have this warning"
+            } else {
+                "no warnings detected"
+            };
+            frame.render_widget(Paragraph::new(empty).block(block), list_area);
+        } else {
+            let list = List::new(items)
+                .block(block)
+                .highlight_symbol(TABLE_HIGHLIGHT_SYMBOL)
+                .highlight_style(Style::default().add_modifier(style::Modifier::BOLD));
+            frame.render_stateful_widget(list, list_area, list_state);
+        }
+        actions
+    }
+}
+
+/// Returns the summary of each warning that currently applies to any tasks,
+/// along with the IDs of those tasks, with the most common warnings first.
+fn warnings(state: &State) -> Vec<(&str, Vec<u64>)> {
+    let mut warnings = state.tasks_state().warnings_overview().collect::<Vec<_>>();
+    warnings.sort_by_key(|(_, tasks)| std::cmp::Reverse(tasks.len()));
+    warnings
+}
+
+/// Returns the IDs of the tasks with the warning `summary`.
+fn tasks_with<'a>(warnings: &'a [(&str, Vec<u64>)], summary: &str) -> &'a [u64] {
+    warnings
+        .iter()
+        .find(|(s, _)| *s == summary)
+        .map(|(_, tasks)| &tasks[..])
+        .unwrap_or_default()
+}
+
+/// Moves the selection in a list of `len` items in response to a scroll key,
+/// wrapping around at either end.
+fn scroll(list_state: &mut ListState, len: usize, event: &input::Event, bindings: &KeyBindings) {
+    if len == 0 {
+        list_state.select(None);
+        return;
+    }
+
+    let selected = list_state.selected().unwrap_or(0);
+    let selected = if bindings.is(Action::ScrollDown, event) {
+        (selected + 1) % len
+    } else if bindings.is(Action::ScrollUp, event) {
+        selected.checked_sub(1).unwrap_or(len - 1)
+    } else if bindings.is(Action::ScrollTop, event) {
+        0
+    } else if bindings.is(Action::ScrollBottom, event) {
+        len - 1
+    } else {
+        return;
+    };
+    list_state.select(Some(selected));
+}
+
+/// Keeps the selection in a list within its `len` items, as items are removed
+/// from it.
+fn clamp_selection(list_state: &mut ListState, len: usize) {
+    let selected = match len {
+        0 => None,
+        len => Some(list_state.selected().unwrap_or(0).min(len - 1)),
+    };
+    list_state.select(selected);
+}
+
+fn view_controls(bindings: &KeyBindings, in_tasks: bool, has_items: bool) -> Vec<ControlDisplay> {
+    let scroll = ControlDisplay::new("scroll", &[Action::ScrollUp, Action::ScrollDown], bindings)
+        .enabled(has_items);
+    if in_tasks {
+        vec![
+            ControlDisplay::new("return to warnings", &[Action::Back], bindings),
+            scroll,
+            ControlDisplay::new("view task", &[Action::ViewDetails], bindings).enabled(has_items),
+        ]
+    } else {
+        vec![
+            scroll,
+            ControlDisplay::new("view tasks", &[Action::ViewDetails], bindings).enabled(has_items),
+        ]
+    }
+}
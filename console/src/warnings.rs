@@ -1,26 +1,42 @@
-use crate::state::tasks::Task;
-use std::{fmt::Debug, rc::Rc};
+use crate::state::{
+    async_ops::AsyncOp,
+    locations::SpawnLocation,
+    resources::Resource,
+    tasks::{Task, TaskState},
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryInto,
+    fmt::Debug,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
 
 /// A warning for a particular type of monitored entity (e.g. task or resource).
 ///
 /// This trait implements the logic for detecting a particular warning, and
 /// generating a warning message describing it. The [`Linter`] type wraps an
-/// instance of this trait to track active instances of the warning.
+/// instance of this trait to track active instances of the warning. Checks
+/// which detect several related warnings at once implement [`WarnMany`]
+/// instead.
 pub trait Warn<T>: Debug {
-    /// Returns `true` if the warning applies to `val`.
-    fn check(&self, val: &T) -> bool;
+    /// Returns `true` if the warning applies to `val` at the time `now`.
+    fn check(&self, val: &T, now: SystemTime) -> bool;
 
     /// Formats a description of the warning detected for a *specific* `val`.
     ///
     /// This may include dynamically formatted content specific to `val`, such
     /// as the specific numeric value that was over the line for detecting the
-    /// warning.
+    /// warning. `metric` is the value [`Warn::metric`] returned when the
+    /// warning was detected, so that the description matches what was
+    /// checked, rather than being recomputed from `val`.
     ///
     /// This should be a complete sentence describing the warning. For example,
     /// for the [`SelfWakePercent`] warning, this returns a string like:
     ///
     /// > "This task has woken itself for more than 50% of its total wakeups (86%)"
-    fn format(&self, val: &T) -> String;
+    fn format(&self, val: &T, metric: u64, now: SystemTime) -> String;
 
     /// Returns a string summarizing the warning *in general*, suitable for
     /// displaying in a list of all detected warnings.
@@ -44,49 +60,390 @@ pub trait Warn<T>: Debug {
     // TODO(eliza): it would be nice if we had separate plural and singular
     // versions of this, like "56 tasks have..." vs "1 task has...".
     fn summary(&self) -> &str;
+
+    /// Returns the value that the warning is based on for `val` at the time
+    /// `now`, such as the percentage of self-wakes for [`SelfWakePercent`].
+    ///
+    /// When a warning is acknowledged for an entity, it is suppressed until
+    /// this value changes, or until the warning no longer applies. By default,
+    /// this is a constant, so acknowledgements last until the warning no
+    /// longer applies.
+    fn metric(&self, _val: &T, _now: SystemTime) -> u64 {
+        0
+    }
+
+    /// Returns how severe the warning is for `val`, such as the percentage of
+    /// self-wakes for [`SelfWakePercent`], so that the entities with a
+    /// warning can be ranked with the worst offenders first. `metric` is the
+    /// value [`Warn::metric`] returned when the warning was detected.
+    ///
+    /// By default, every entity has a score of zero, for warnings that don't
+    /// have a meaningful measure of severity.
+    fn score(&self, _val: &T, _metric: u64) -> u64 {
+        0
+    }
+
+    /// Returns `true` if the warning may start applying to `val` without
+    /// `val` being updated, so that it must be checked again on every update.
+    ///
+    /// By default, warnings are only checked when the entity is updated.
+    fn recheck(&self, _val: &T) -> bool {
+        false
+    }
+
+    /// Returns how serious the warning is, which determines how counts of it
+    /// are highlighted.
+    ///
+    /// By default, warnings have [`Severity::Warning`].
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Forgets anything the warning remembers about the entity with the given
+    /// `id`, which is no longer tracked.
+    ///
+    /// By default, warnings don't remember anything about entities.
+    fn forget(&self, _id: u64) {}
+
+    /// Forgets everything the warning remembers about entities, such as after
+    /// reconnecting to a target whose IDs may now refer to different entities.
+    fn reset(&self) {}
+}
+
+/// A check which detects several related warnings in one pass over an entity,
+/// for warnings which would otherwise repeat the same work.
+///
+/// Each of the warnings, identified by its index from zero up to
+/// [`WarnMany::warnings`], is displayed, counted, and acknowledged separately,
+/// as though it were its own [`Warn`]. Checks which only detect one warning
+/// should implement [`Warn`] instead.
+pub trait WarnMany<T>: Debug {
+    /// Returns the number of distinct warnings this check detects.
+    fn warnings(&self) -> usize;
+
+    /// Returns the index of each warning that applies to `val` at the time
+    /// `now`, along with the warning's metric (see [`Warn::metric`]).
+    fn check(&self, val: &T, now: SystemTime) -> Vec<(usize, u64)>;
+
+    /// Formats a description of the warning at `idx`, detected for `val`
+    /// with the given `metric` (see [`Warn::format`]).
+    fn format(&self, idx: usize, val: &T, metric: u64, now: SystemTime) -> String;
+
+    /// Returns a string summarizing the warning at `idx` (see
+    /// [`Warn::summary`]).
+    fn summary(&self, idx: usize) -> &str;
+
+    /// Returns how severe the warning at `idx` is for `val`, which was
+    /// detected with the given `metric` (see [`Warn::score`]).
+    fn score(&self, _idx: usize, _val: &T, _metric: u64) -> u64 {
+        0
+    }
+
+    /// Returns `true` if any of the warnings may start applying to `val`
+    /// without `val` being updated (see [`Warn::recheck`]).
+    fn recheck(&self, _val: &T) -> bool {
+        false
+    }
+
+    /// Returns how serious the warning at `idx` is (see [`Warn::severity`]).
+    fn severity(&self, _idx: usize) -> Severity {
+        Severity::Warning
+    }
+
+    /// Forgets anything the check remembers about the entity with the given
+    /// `id` (see [`Warn::forget`]).
+    fn forget(&self, _id: u64) {}
+
+    /// Forgets everything the check remembers about entities (see
+    /// [`Warn::reset`]).
+    fn reset(&self) {}
+}
+
+/// How serious a warning is.
+///
+/// Severities are ordered from least to most serious, so the most serious of
+/// several warnings is their maximum.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Severity {
+    /// Something which is worth knowing about, but may well be intended.
+    Info,
+    /// Something which is likely to be a problem.
+    Warning,
+    /// Something which is almost certainly a bug, like a task which can never
+    /// be woken again.
+    Critical,
 }
 
+/// Adapts a [`Warn`] into a [`WarnMany`] which detects its one warning.
 #[derive(Debug)]
-pub(crate) struct Linter<T>(Rc<dyn Warn<T>>);
+struct Single<W>(W);
+
+impl<T, W: Warn<T>> WarnMany<T> for Single<W> {
+    fn warnings(&self) -> usize {
+        1
+    }
+
+    fn check(&self, val: &T, now: SystemTime) -> Vec<(usize, u64)> {
+        if self.0.check(val, now) {
+            vec![(0, self.0.metric(val, now))]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn format(&self, _: usize, val: &T, metric: u64, now: SystemTime) -> String {
+        self.0.format(val, metric, now)
+    }
+
+    fn summary(&self, _: usize) -> &str {
+        self.0.summary()
+    }
+
+    fn score(&self, _: usize, val: &T, metric: u64) -> u64 {
+        self.0.score(val, metric)
+    }
+
+    fn recheck(&self, val: &T) -> bool {
+        self.0.recheck(val)
+    }
+
+    fn severity(&self, _: usize) -> Severity {
+        self.0.severity()
+    }
+
+    fn forget(&self, id: u64) {
+        self.0.forget(id)
+    }
+
+    fn reset(&self) {
+        self.0.reset()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Linter<T> {
+    warn: Rc<dyn WarnMany<T>>,
+    /// Which of `warn`'s warnings this linter detects.
+    idx: usize,
+    /// The IDs of entities for which this warning has been acknowledged,
+    /// along with the warning's metric at the time it was acknowledged.
+    ///
+    /// Each of a check's warnings has its own map, which is shared by the
+    /// linter and every clone of it held by an entity, so it also identifies
+    /// the warning and counts the entities which have it.
+    acknowledged: Rc<RefCell<HashMap<u64, u64>>>,
+    /// For a warning held by an entity, the warning's metric when it was
+    /// detected.
+    metric: u64,
+}
+
+/// The result of checking whether a warning applies to an entity.
+#[derive(Debug)]
+pub(crate) enum Lint<T> {
+    /// The warning does not apply.
+    Ok,
+    /// The warning applies. The entity should hold the returned [`Linter`].
+    Warning(Linter<T>),
+    /// The warning applies, but it has been acknowledged for this entity.
+    Suppressed,
+}
 
 impl<T> Linter<T> {
     pub(crate) fn new<W>(warning: W) -> Self
     where
         W: Warn<T> + 'static,
     {
-        Self(Rc::new(warning))
+        Self {
+            warn: Rc::new(Single(warning)),
+            idx: 0,
+            acknowledged: Rc::new(RefCell::new(HashMap::new())),
+            metric: 0,
+        }
     }
 
-    /// Checks if the warning applies to a particular entity, returning a clone
-    /// of `Self` if it does.
+    /// Returns a linter for each of the warnings detected by `check`.
     ///
-    /// The cloned instance of `Self` should be held by the entity that
-    /// generated the warning, so that it can be formatted. Holding the clone of
-    /// `Self` will increment the warning count for that entity.
-    pub(crate) fn check(&self, val: &T) -> Option<Self> {
-        if self.0.check(val) {
-            Some(Self(self.0.clone()))
-        } else {
-            None
+    /// The linters should be kept next to each other, so that
+    /// [`Linter::lint_all`] only runs `check` once for each entity.
+    pub(crate) fn many<W>(check: W) -> Vec<Self>
+    where
+        W: WarnMany<T> + 'static,
+    {
+        let warn: Rc<dyn WarnMany<T>> = Rc::new(check);
+        (0..warn.warnings())
+            .map(|idx| Self {
+                warn: warn.clone(),
+                idx,
+                acknowledged: Rc::new(RefCell::new(HashMap::new())),
+                metric: 0,
+            })
+            .collect()
+    }
+
+    /// Returns whether this warning is among the `found` warnings of an
+    /// entity `val` with the given `id`, as returned by [`WarnMany::check`].
+    ///
+    /// If it is, and it has not been acknowledged for that entity, this
+    /// returns a clone of `Self`. The cloned instance of `Self` should be held
+    /// by the entity that generated the warning, so that it can be formatted.
+    /// Holding the clone of `Self` will increment the warning count for that
+    /// entity.
+    fn check(&self, id: u64, found: &[(usize, u64)]) -> Lint<T> {
+        let mut acknowledged = self.acknowledged.borrow_mut();
+        let metric = match found.iter().find(|&&(idx, _)| idx == self.idx) {
+            Some(&(_, metric)) => metric,
+            None => {
+                acknowledged.remove(&id);
+                return Lint::Ok;
+            }
+        };
+
+        if acknowledged.get(&id) == Some(&metric) {
+            return Lint::Suppressed;
         }
+
+        acknowledged.remove(&id);
+        Lint::Warning(Self {
+            warn: self.warn.clone(),
+            idx: self.idx,
+            acknowledged: self.acknowledged.clone(),
+            metric,
+        })
+    }
+
+    /// Returns `true` if `self` and `other` are warnings from the same check.
+    fn same_check(&self, other: &Self) -> bool {
+        // Compare only the addresses, since the vtables of the same type may
+        // differ between codegen units.
+        std::ptr::eq(
+            Rc::as_ptr(&self.warn) as *const (),
+            Rc::as_ptr(&other.warn) as *const (),
+        )
+    }
+
+    /// Acknowledges this warning for the entity with the given `id`, so that
+    /// it is suppressed until its metric changes from the one it was detected
+    /// with.
+    pub(crate) fn acknowledge(&self, id: u64) {
+        self.acknowledged.borrow_mut().insert(id, self.metric);
+    }
+
+    /// Acknowledges this warning for the entity with the given `id`, as it was
+    /// when its metric was `metric`, such as to restore an acknowledgement
+    /// saved by a previous session.
+    pub(crate) fn acknowledge_with(&self, id: u64, metric: u64) {
+        self.acknowledged.borrow_mut().insert(id, metric);
+    }
+
+    /// Returns the IDs of the entities this warning has been acknowledged
+    /// for, along with the metric it was acknowledged with.
+    pub(crate) fn acknowledgements(&self) -> Vec<(u64, u64)> {
+        let mut acknowledgements = self
+            .acknowledged
+            .borrow()
+            .iter()
+            .map(|(&id, &metric)| (id, metric))
+            .collect::<Vec<_>>();
+        acknowledgements.sort_unstable();
+        acknowledgements
+    }
+
+    /// Clears all acknowledgements of this warning.
+    pub(crate) fn clear_acknowledged(&self) {
+        self.acknowledged.borrow_mut().clear();
+    }
+
+    /// Forgets anything the check remembers about the entity with the given
+    /// `id`, which has been removed, along with its acknowledgement.
+    pub(crate) fn forget(&self, id: u64) {
+        self.acknowledged.borrow_mut().remove(&id);
+        self.warn.forget(id);
+    }
+
+    /// Forgets everything the check remembers about entities, which have all
+    /// been removed.
+    pub(crate) fn reset(&self) {
+        self.warn.reset();
+    }
+
+    /// Returns `true` if `self` and `other` are the same linter (or clones of
+    /// it).
+    pub(crate) fn is(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.acknowledged, &other.acknowledged)
     }
 
     /// Returns the number of monitored entities that currently have this warning.
     pub(crate) fn count(&self) -> usize {
-        Rc::strong_count(&self.0) - 1
+        Rc::strong_count(&self.acknowledged) - 1
     }
 
-    pub(crate) fn format(&self, val: &T) -> String {
-        debug_assert!(
-            self.0.check(val),
-            "tried to format a warning for a {} that did not have that warning!",
-            std::any::type_name::<T>()
-        );
-        self.0.format(val)
+    /// Returns `true` if any monitored entity currently has this warning, so
+    /// that it should be displayed.
+    pub(crate) fn is_active(&self) -> bool {
+        self.count() > 0
+    }
+
+    /// Returns `true` if the entity `val` must be checked for this warning on
+    /// every update, even if it wasn't updated.
+    pub(crate) fn needs_recheck(&self, val: &T) -> bool {
+        self.warn.recheck(val)
+    }
+
+    /// Returns how severe this warning is for the entity `val`, which holds
+    /// it.
+    pub(crate) fn score(&self, val: &T) -> u64 {
+        self.warn.score(self.idx, val, self.metric)
+    }
+
+    /// Describes this warning for the entity `val`, which holds it, as of
+    /// `now`, the time of the update it was checked at.
+    ///
+    /// This doesn't check the entity again, since checking may update the
+    /// warning's state, and the entity may no longer hold the warning as of
+    /// any other time.
+    pub(crate) fn format(&self, val: &T, now: SystemTime) -> String {
+        self.warn.format(self.idx, val, self.metric, now)
     }
 
     pub(crate) fn summary(&self) -> &str {
-        self.0.summary()
+        self.warn.summary(self.idx)
+    }
+
+    pub(crate) fn severity(&self) -> Severity {
+        self.warn.severity(self.idx)
+    }
+}
+
+impl<T: Debug> Linter<T> {
+    /// Runs each of `linters` on the entity `val` with the given `id`,
+    /// returning the warnings that apply to it and have not been
+    /// acknowledged.
+    ///
+    /// Adjacent linters for the warnings of the same check share one run of
+    /// the check.
+    ///
+    /// Entities should store the returned warnings, replacing any warnings
+    /// from a previous check.
+    pub(crate) fn lint_all(linters: &[Self], id: u64, val: &T, now: SystemTime) -> Vec<Self> {
+        let mut warnings = Vec::new();
+        let mut found = Vec::new();
+        for (i, lint) in linters.iter().enumerate() {
+            if i == 0 || !lint.same_check(&linters[i - 1]) {
+                tracing::debug!(?lint, ?val, "checking...");
+                found = lint.warn.check(val, now);
+            }
+            match lint.check(id, &found) {
+                Lint::Ok => {}
+                Lint::Warning(warning) => {
+                    tracing::info!(?warning, ?val, "found a warning!");
+                    warnings.push(warning);
+                }
+                Lint::Suppressed => {
+                    tracing::debug!(?lint, ?val, "warning was acknowledged");
+                }
+            }
+        }
+        warnings
     }
 }
 
@@ -120,33 +477,1275 @@ impl Warn<Task> for SelfWakePercent {
         self.description.as_str()
     }
 
-    fn check(&self, task: &Task) -> bool {
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
         let self_wakes = task.self_wake_percent();
         self_wakes > self.min_percent
     }
 
-    fn format(&self, task: &Task) -> String {
-        let self_wakes = task.self_wake_percent();
+    fn format(&self, _: &Task, self_wakes: u64, _: SystemTime) -> String {
         format!(
             "This task has woken itself for more than {}% of its total wakeups ({}%)",
             self.min_percent, self_wakes
         )
     }
+
+    fn metric(&self, task: &Task, _: SystemTime) -> u64 {
+        task.self_wake_percent()
+    }
+
+    fn score(&self, _: &Task, self_wakes: u64) -> u64 {
+        self_wakes
+    }
 }
 
-#[derive(Clone, Debug, Default)]
-pub(crate) struct LostWaker;
+/// Warns about tasks whose wakers show that something has gone wrong: tasks
+/// which have lost every waker, and so will never be woken again, and
+/// completed tasks which still have wakers, which are being kept alive
+/// elsewhere, such as by a resource the task registered with, and so prevent
+/// the task from being cleaned up.
+///
+/// Both are detected from the task's waker count, in one pass over the task.
+/// Each of them can be disabled.
+#[derive(Clone, Debug)]
+pub(crate) struct Wakers {
+    /// The warnings which are detected, in the order of their indices.
+    warnings: Vec<WakerWarning>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum WakerWarning {
+    Lost,
+    CompletedWithWakers,
+}
+
+impl Wakers {
+    /// Returns a check for lost wakers, if `lost`, and for completed tasks
+    /// with wakers, if `completed_with_wakers`.
+    pub(crate) fn new(lost: bool, completed_with_wakers: bool) -> Self {
+        let warnings = [
+            (lost, WakerWarning::Lost),
+            (completed_with_wakers, WakerWarning::CompletedWithWakers),
+        ]
+        .iter()
+        .filter(|&&(enabled, _)| enabled)
+        .map(|&(_, warning)| warning)
+        .collect();
+        Self { warnings }
+    }
+}
+
+impl WarnMany<Task> for Wakers {
+    fn warnings(&self) -> usize {
+        self.warnings.len()
+    }
+
+    fn check(&self, task: &Task, _: SystemTime) -> Vec<(usize, u64)> {
+        let wakers = task.waker_count();
+        let found = if task.is_completed() {
+            (!task.is_blocking() && wakers > 0).then_some(WakerWarning::CompletedWithWakers)
+        } else {
+            (wakers == 0 && !task.is_running() && !task.is_awakened()).then_some(WakerWarning::Lost)
+        };
+        found
+            .and_then(|found| self.warnings.iter().position(|&warning| warning == found))
+            .map(|idx| (idx, wakers))
+            .into_iter()
+            .collect()
+    }
+
+    fn format(&self, idx: usize, _: &Task, wakers: u64, _: SystemTime) -> String {
+        match self.warnings[idx] {
+            WakerWarning::Lost => {
+                "This task has lost its waker, and will never be woken again.".into()
+            }
+            WakerWarning::CompletedWithWakers => format!(
+                "This task has completed, but {} of its wakers still exist",
+                wakers
+            ),
+        }
+    }
 
-impl Warn<Task> for LostWaker {
+    fn summary(&self, idx: usize) -> &str {
+        match self.warnings[idx] {
+            WakerWarning::Lost => "tasks have lost their waker",
+            WakerWarning::CompletedWithWakers => "completed tasks still have wakers",
+        }
+    }
+
+    fn score(&self, _: usize, _: &Task, wakers: u64) -> u64 {
+        wakers
+    }
+
+    fn severity(&self, idx: usize) -> Severity {
+        match self.warnings[idx] {
+            WakerWarning::Lost => Severity::Critical,
+            // A waker may outlive its task for a while without anything being
+            // wrong, such as while a timer it was registered with is reset.
+            WakerWarning::CompletedWithWakers => Severity::Info,
+        }
+    }
+}
+
+/// Warns about tasks whose 99th percentile poll duration is over a threshold,
+/// even if their average poll duration is low, which indicates a task that
+/// occasionally blocks the executor.
+///
+/// The percentile is computed from the task's poll times histogram, which is
+/// only received for the task whose details are being viewed, so this warning
+/// is only detected for tasks that have been inspected.
+#[derive(Clone, Debug)]
+pub(crate) struct SlowPolls {
+    max_p99: Duration,
+    description: String,
+}
+
+impl SlowPolls {
+    pub(crate) const DEFAULT_MAX_P99: Duration = Duration::from_millis(10);
+    pub(crate) fn new(max_p99: Duration) -> Self {
+        Self {
+            max_p99,
+            description: format!(
+                "tasks have a 99th percentile poll duration over {}",
+                humantime::format_duration(max_p99)
+            ),
+        }
+    }
+}
+
+impl Default for SlowPolls {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_P99)
+    }
+}
+
+impl Warn<Task> for SlowPolls {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
+        matches!(task.poll_p99(), Some(p99) if p99 > self.max_p99)
+    }
+
+    fn format(&self, _: &Task, p99_nanos: u64, _: SystemTime) -> String {
+        format!(
+            "This task's 99th percentile poll duration is over {} ({:.2?}), so it may be blocking the executor",
+            humantime::format_duration(self.max_p99),
+            Duration::from_nanos(p99_nanos)
+        )
+    }
+
+    fn metric(&self, task: &Task, _: SystemTime) -> u64 {
+        task.poll_p99()
+            .map_or(0, |p99| p99.as_nanos().try_into().unwrap_or(u64::MAX))
+    }
+
+    fn score(&self, _: &Task, p99_nanos: u64) -> u64 {
+        p99_nanos
+    }
+}
+
+/// Warns about tasks which, on average, wait a long time to be polled after
+/// each time they're woken, which means the executor is too busy to run them
+/// promptly.
+///
+/// Unlike a task's total scheduled time, which grows with how often it is
+/// woken, this is the scheduling latency of each wakeup. Blocking tasks are
+/// skipped, since they aren't scheduled by the async executor, and so are
+/// tasks which haven't been woken enough times for their average to mean
+/// much.
+#[derive(Clone, Debug)]
+pub(crate) struct SlowScheduling {
+    max_average: Duration,
+    min_wakes: u64,
+    description: String,
+}
+
+impl SlowScheduling {
+    pub(crate) const DEFAULT_MAX_AVERAGE: Duration = Duration::from_millis(5);
+    pub(crate) const DEFAULT_MIN_WAKES: u64 = 10;
+    pub(crate) fn new(max_average: Duration, min_wakes: u64) -> Self {
+        Self {
+            max_average,
+            min_wakes,
+            description: format!(
+                "tasks have waited over {} on average to be polled after being woken",
+                humantime::format_duration(max_average)
+            ),
+        }
+    }
+}
+
+impl Default for SlowScheduling {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_AVERAGE, Self::DEFAULT_MIN_WAKES)
+    }
+}
+
+impl Warn<Task> for SlowScheduling {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        !task.is_blocking()
+            && task.wakes() >= self.min_wakes
+            && matches!(task.scheduled_per_wake(now), Some(average) if average > self.max_average)
+    }
+
+    fn format(&self, task: &Task, average_nanos: u64, _: SystemTime) -> String {
+        format!(
+            "This task has waited {:.2?} on average to be polled after each of its {} wakes, so the executor may be oversubscribed",
+            Duration::from_nanos(average_nanos),
+            task.wakes()
+        )
+    }
+
+    /// The average includes the wait the task may currently be in.
+    fn metric(&self, task: &Task, now: SystemTime) -> u64 {
+        task.scheduled_per_wake(now).map_or(0, |average| {
+            average.as_nanos().try_into().unwrap_or(u64::MAX)
+        })
+    }
+
+    fn score(&self, _: &Task, average_nanos: u64) -> u64 {
+        average_nanos
+    }
+
+    /// A task which has been woken keeps waiting without being updated until
+    /// it's polled, so it is rechecked while it waits, once it has been woken
+    /// enough times to be checked at all.
+    fn recheck(&self, task: &Task) -> bool {
+        !task.is_blocking()
+            && !task.is_completed()
+            && task.wakes() >= self.min_wakes
+            && task.is_awakened()
+            && !task.is_running()
+    }
+}
+
+/// Warns about tasks which have spent more than a percentage of their lives
+/// waiting to be polled after being woken, and have been waiting for longer
+/// than a window without being polled, which means the scheduler can't get to
+/// them, such as when other tasks are hogging its worker threads.
+///
+/// The console only sees a task's poll count when it's updated, so the lint
+/// remembers the poll count each task had when it was first seen waiting, and
+/// the window starts from then. Blocking tasks are skipped, since they aren't
+/// scheduled by the async scheduler.
+#[derive(Clone, Debug)]
+pub(crate) struct Starved {
+    min_percent: u64,
+    window: Duration,
+    description: String,
+    /// The poll count of each task which is waiting to be polled, and when it
+    /// was first seen waiting with that count, by task ID.
+    waiting: RefCell<HashMap<u64, (u64, SystemTime)>>,
+}
+
+impl Starved {
+    pub(crate) const DEFAULT_PERCENT: u64 = 50;
+    pub(crate) const DEFAULT_WINDOW: Duration = Duration::from_secs(1);
+    pub(crate) fn new(min_percent: u64, window: Duration) -> Self {
+        Self {
+            min_percent,
+            window,
+            description: format!(
+                "tasks have spent over {}% of their time waiting to be polled, and have not been polled for {}",
+                min_percent,
+                humantime::format_duration(window)
+            ),
+            waiting: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn scheduled_percent(task: &Task, now: SystemTime) -> Option<f64> {
+        let total = task.total(now).as_secs_f64();
+        if total == 0.0 {
+            return None;
+        }
+        Some(task.scheduled(now)?.as_secs_f64() / total * 100.0)
+    }
+
+    /// Returns how long `task` has been waiting to be polled without its poll
+    /// count advancing, as of `now`, remembering its poll count if it has
+    /// just started waiting or has been polled since it was last seen.
+    fn waited(&self, task: &Task, now: SystemTime) -> Option<Duration> {
+        let mut waiting = self.waiting.borrow_mut();
+        if !self.recheck(task) {
+            waiting.remove(&task.id());
+            return None;
+        }
+        let polls = task.total_polls();
+        let (seen_polls, since) = waiting.entry(task.id()).or_insert((polls, now));
+        if *seen_polls != polls {
+            *seen_polls = polls;
+            *since = now;
+        }
+        Some(now.duration_since(*since).unwrap_or_default())
+    }
+}
+
+impl Default for Starved {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PERCENT, Self::DEFAULT_WINDOW)
+    }
+}
+
+impl Warn<Task> for Starved {
     fn summary(&self) -> &str {
-        "tasks have lost their waker"
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        matches!(self.waited(task, now), Some(waited) if waited >= self.window)
+            && matches!(Self::scheduled_percent(task, now), Some(percent) if percent > self.min_percent as f64)
+    }
+
+    fn format(&self, task: &Task, _: u64, now: SystemTime) -> String {
+        let waited = self
+            .waiting
+            .borrow()
+            .get(&task.id())
+            .map(|&(_, since)| now.duration_since(since).unwrap_or_default())
+            .unwrap_or_default();
+        format!(
+            "This task has spent {:.0}% of the {:.0?} since it was spawned waiting to be polled, and has not been polled for {:.0?}, so the scheduler may be starving it",
+            Self::scheduled_percent(task, now).unwrap_or_default(),
+            task.total(now),
+            waited
+        )
+    }
+
+    /// Acknowledging the warning lasts until the task is polled again.
+    fn metric(&self, task: &Task, _: SystemTime) -> u64 {
+        task.total_polls()
+    }
+
+    /// A task waiting to be polled isn't updated until it's polled, so it is
+    /// rechecked while it waits.
+    fn recheck(&self, task: &Task) -> bool {
+        !task.is_blocking() && !task.is_completed() && task.is_awakened() && !task.is_running()
+    }
+
+    fn forget(&self, id: u64) {
+        self.waiting.borrow_mut().remove(&id);
     }
 
-    fn check(&self, task: &Task) -> bool {
-        !task.is_completed() && task.waker_count() == 0 && !task.is_running() && !task.is_awakened()
+    fn reset(&self) {
+        self.waiting.borrow_mut().clear();
+    }
+}
+
+/// Warns about tasks which have existed for longer than a grace period without
+/// ever being polled, such as a task which was spawned onto a runtime that has
+/// shut down.
+#[derive(Clone, Debug)]
+pub(crate) struct NeverPolled {
+    grace: Duration,
+    description: String,
+}
+
+impl NeverPolled {
+    pub(crate) const DEFAULT_GRACE: Duration = Duration::from_secs(1);
+    pub(crate) fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            description: format!(
+                "tasks have not been polled within {} of being spawned",
+                humantime::format_duration(grace)
+            ),
+        }
+    }
+}
+
+impl Default for NeverPolled {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_GRACE)
+    }
+}
+
+impl Warn<Task> for NeverPolled {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        self.recheck(task) && task.total(now) > self.grace
+    }
+
+    fn format(&self, task: &Task, _: u64, now: SystemTime) -> String {
+        format!(
+            "This task has never been polled since it was spawned {:.0?} ago",
+            task.total(now)
+        )
+    }
+
+    /// A task that is never polled is never updated, so it is rechecked until
+    /// it is first polled or completes.
+    fn recheck(&self, task: &Task) -> bool {
+        !task.is_completed() && task.total_polls() == 0
+    }
+}
+
+/// Warns about async tasks which have spent nearly all of their time busy, for
+/// longer than a minimum period, which usually means they're doing CPU-bound
+/// work that should be moved to a blocking thread or broken up with yields.
+///
+/// Unlike [`SlowPolls`], this doesn't need the task's poll times histogram:
+/// it only compares the task's busy time with its total time. Blocking tasks
+/// are skipped, since they're expected to be busy for their whole lives.
+#[derive(Clone, Debug)]
+pub(crate) struct CpuBound {
+    min_percent: u64,
+    min_total: Duration,
+    description: String,
+}
+
+impl CpuBound {
+    pub(crate) const DEFAULT_PERCENT: u64 = 90;
+    pub(crate) const DEFAULT_MIN_TOTAL: Duration = Duration::from_secs(10);
+    pub(crate) fn new(min_percent: u64, min_total: Duration) -> Self {
+        Self {
+            min_percent,
+            min_total,
+            description: format!(
+                "tasks have been busy over {}% of the time for at least {}",
+                min_percent,
+                humantime::format_duration(min_total)
+            ),
+        }
+    }
+
+    fn busy_percent(task: &Task, now: SystemTime) -> f64 {
+        let total = task.total(now).as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        task.busy(now).as_secs_f64() / total * 100.0
+    }
+}
+
+impl Default for CpuBound {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PERCENT, Self::DEFAULT_MIN_TOTAL)
+    }
+}
+
+impl Warn<Task> for CpuBound {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        !task.is_blocking()
+            && task.total(now) >= self.min_total
+            && Self::busy_percent(task, now) > self.min_percent as f64
+    }
+
+    fn format(&self, task: &Task, _: u64, now: SystemTime) -> String {
+        format!(
+            "This task has been busy for {:.0}% of the {:.0?} since it was spawned, so it may be CPU-bound and starving other tasks",
+            Self::busy_percent(task, now),
+            task.total(now)
+        )
+    }
+
+    /// A task which is being polled isn't updated until its poll ends, but
+    /// its busy time keeps growing, and an idle task's busy percentage keeps
+    /// falling, so every task is rechecked until it completes.
+    fn recheck(&self, task: &Task) -> bool {
+        !task.is_blocking() && !task.is_completed()
+    }
+}
+
+/// Warns about async tasks which have been running for longer than a minimum
+/// duration within their first few polls without yielding, such as a task
+/// which blocks its worker thread in a loop that never awaits anything.
+///
+/// By default, only a task's first poll is checked. Tasks which are polled a
+/// handful of times while they're set up, and only then block, are caught by
+/// raising the poll count ceiling. Blocking tasks are skipped, since they're
+/// expected to run for their whole lives without yielding.
+///
+/// The minimum duration may be overridden for the tasks whose targets start
+/// with a module path, such as `myapp::codec`, in which case the longest
+/// matching path is used.
+#[derive(Clone, Debug)]
+pub(crate) struct NeverYielded {
+    min_duration: Duration,
+    max_polls: u64,
+    /// Module paths, and the minimum duration for the targets they prefix.
+    overrides: Vec<(String, Duration)>,
+    description: String,
+}
+
+impl NeverYielded {
+    pub(crate) const DEFAULT_MIN_DURATION: Duration = Duration::from_secs(1);
+    pub(crate) const DEFAULT_MAX_POLLS: u64 = 1;
+    pub(crate) fn new(min_duration: Duration, max_polls: u64) -> Self {
+        let mut description = format!(
+            "tasks have been running for over {} without yielding",
+            humantime::format_duration(min_duration)
+        );
+        if max_polls > Self::DEFAULT_MAX_POLLS {
+            description.push_str(&format!(" within their first {} polls", max_polls));
+        }
+        Self {
+            min_duration,
+            max_polls,
+            overrides: Vec::new(),
+            description,
+        }
+    }
+
+    /// Uses `min_duration` for the tasks whose targets are in the module
+    /// `prefix`, unless a longer prefix also matches them.
+    pub(crate) fn with_override(
+        mut self,
+        prefix: impl Into<String>,
+        min_duration: Duration,
+    ) -> Self {
+        self.overrides.push((prefix.into(), min_duration));
+        self
+    }
+
+    /// Returns the minimum duration for tasks with `target`.
+    fn min_duration(&self, target: &str) -> Duration {
+        self.overrides
+            .iter()
+            .filter(|(prefix, _)| {
+                matches!(
+                    target.strip_prefix(prefix.as_str()),
+                    Some(rest) if rest.is_empty() || rest.starts_with("::")
+                )
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.min_duration, |&(_, min_duration)| min_duration)
+    }
+}
+
+impl Default for NeverYielded {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MIN_DURATION, Self::DEFAULT_MAX_POLLS)
+    }
+}
+
+impl Warn<Task> for NeverYielded {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, task: &Task, now: SystemTime) -> bool {
+        self.recheck(task) && task.time_in_state(now) > self.min_duration(task.target())
+    }
+
+    fn format(&self, task: &Task, _: u64, now: SystemTime) -> String {
+        match task.total_polls() {
+            1 => format!(
+                "This task has been running for {:.0?} without yielding since it was first polled",
+                task.time_in_state(now)
+            ),
+            polls => format!(
+                "This task has been running for {:.0?} without yielding since poll {} started",
+                task.time_in_state(now),
+                polls
+            ),
+        }
+    }
+
+    /// A task which is being polled isn't updated until its poll ends, so it
+    /// is rechecked while it is running one of its first polls.
+    fn recheck(&self, task: &Task) -> bool {
+        !task.is_blocking()
+            && task.state() == TaskState::Running
+            && task.total_polls() <= self.max_polls
+    }
+}
+
+/// Warns about tasks whose spawn location wasn't recorded, which usually means
+/// the target's instrumentation is incomplete, such as when it uses a version
+/// of its runtime which doesn't record where tasks are spawned.
+///
+/// Blocking tasks are skipped, since the runtime spawns them itself for
+/// blocking operations such as file I/O, and they may legitimately have no
+/// location in the target's code.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NoLocation;
+
+impl Warn<Task> for NoLocation {
+    fn summary(&self) -> &str {
+        "tasks have no recorded spawn location"
+    }
+
+    fn check(&self, task: &Task, _: SystemTime) -> bool {
+        !task.is_blocking() && !task.has_location()
+    }
+
+    fn format(&self, _: &Task, _: u64, _: SystemTime) -> String {
+        "The target didn't record where this task was spawned".into()
+    }
+
+    /// A missing location only makes the task harder to find, rather than
+    /// being a problem with the task.
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+}
+
+/// Warns about async ops which have existed for longer than a deadline without
+/// completing, such as a timer which was never reset or a receive on a channel
+/// that is never sent to.
+#[derive(Clone, Debug)]
+pub(crate) struct NeverCompleted {
+    deadline: Duration,
+    description: String,
+}
+
+impl NeverCompleted {
+    pub(crate) fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            description: format!(
+                "async ops have not completed within {}",
+                humantime::format_duration(deadline)
+            ),
+        }
+    }
+}
+
+impl Warn<AsyncOp> for NeverCompleted {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, async_op: &AsyncOp, now: SystemTime) -> bool {
+        !async_op.dropped() && async_op.total(now) > self.deadline
+    }
+
+    fn format(&self, async_op: &AsyncOp, _: u64, now: SystemTime) -> String {
+        format!(
+            "This async op has not completed within {} (alive for {:.0?})",
+            humantime::format_duration(self.deadline),
+            async_op.total(now)
+        )
+    }
+}
+
+/// Warns about async ops which have been polled far more than the other
+/// async ops on the same resource, which can mean that the op is spinning,
+/// being woken and polled again without making progress.
+#[derive(Clone, Debug)]
+pub(crate) struct PollOutlier {
+    factor: u64,
+    min_polls: u64,
+    description: String,
+}
+
+impl PollOutlier {
+    pub(crate) const DEFAULT_FACTOR: u64 = 100;
+    pub(crate) const DEFAULT_MIN_POLLS: u64 = 1000;
+    pub(crate) fn new(factor: u64, min_polls: u64) -> Self {
+        Self {
+            factor,
+            min_polls,
+            description: format!(
+                "async ops have been polled over {}x as often as the other async ops on their resource",
+                factor
+            ),
+        }
+    }
+
+    /// Returns the typical poll count the async op is compared with, which
+    /// is at least one, so that ops whose siblings have never been polled
+    /// still have something to be compared with.
+    fn typical(async_op: &AsyncOp) -> Option<u64> {
+        async_op.typical_sibling_polls().map(|polls| polls.max(1))
+    }
+}
+
+impl Default for PollOutlier {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_FACTOR, Self::DEFAULT_MIN_POLLS)
+    }
+}
+
+impl Warn<AsyncOp> for PollOutlier {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    /// Async ops which are the only op on their resource have nothing to be
+    /// compared with, so they are never warned about.
+    fn check(&self, async_op: &AsyncOp, _: SystemTime) -> bool {
+        let polls = async_op.polls();
+        match Self::typical(async_op) {
+            Some(typical) => polls >= self.min_polls && polls > typical.saturating_mul(self.factor),
+            None => false,
+        }
+    }
+
+    fn format(&self, async_op: &AsyncOp, _: u64, _: SystemTime) -> String {
+        format!(
+            "This async op ({}) has been polled {} times, while the other async ops on its resource have typically been polled {} times",
+            async_op.source(),
+            async_op.polls(),
+            async_op.typical_sibling_polls().unwrap_or_default()
+        )
+    }
+
+    fn score(&self, async_op: &AsyncOp, _: u64) -> u64 {
+        Self::typical(async_op).map_or(0, |typical| async_op.polls() / typical)
+    }
+}
+
+/// Warns about async ops which haven't been polled for a while, even though
+/// their task has been polled many times in the meantime, which can mean the
+/// task is holding on to a future without driving it, such as one it stored
+/// in a struct and forgot to poll.
+///
+/// The console only sees an async op's poll count when it's updated, so the
+/// window starts from the first update in which the op's current poll count
+/// was seen. Async ops with no task are skipped.
+#[derive(Clone, Debug)]
+pub(crate) struct StagnantPolls {
+    window: Duration,
+    min_task_polls: u64,
+    description: String,
+}
+
+impl StagnantPolls {
+    pub(crate) const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+    pub(crate) const DEFAULT_MIN_TASK_POLLS: u64 = 10;
+    pub(crate) fn new(window: Duration, min_task_polls: u64) -> Self {
+        Self {
+            window,
+            min_task_polls,
+            description: format!(
+                "async ops have not been polled for {}, while their task was polled at least {} times",
+                humantime::format_duration(window),
+                min_task_polls
+            ),
+        }
+    }
+}
+
+impl Default for StagnantPolls {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW, Self::DEFAULT_MIN_TASK_POLLS)
+    }
+}
+
+impl Warn<AsyncOp> for StagnantPolls {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, async_op: &AsyncOp, now: SystemTime) -> bool {
+        match async_op.stagnation() {
+            Some(stagnation) => {
+                stagnation.duration(now) >= self.window
+                    && stagnation.task_polls() >= self.min_task_polls
+            }
+            None => false,
+        }
+    }
+
+    fn format(&self, async_op: &AsyncOp, _: u64, now: SystemTime) -> String {
+        let stagnation = match async_op.stagnation() {
+            Some(stagnation) => stagnation,
+            None => return String::new(),
+        };
+        let task = match stagnation.task_name() {
+            Some(name) => format!("task {} ({})", stagnation.task_id(), name),
+            None => format!("task {}", stagnation.task_id()),
+        };
+        format!(
+            "This async op ({}) has been stuck at {} polls for {:.0?}, while its {} was polled {} times",
+            async_op.source(),
+            stagnation.polls(),
+            stagnation.duration(now),
+            task,
+            stagnation.task_polls()
+        )
+    }
+
+    /// Acknowledging the warning lasts until the async op is polled again.
+    fn metric(&self, async_op: &AsyncOp, _: SystemTime) -> u64 {
+        async_op.polls()
+    }
+
+    fn score(&self, async_op: &AsyncOp, _: u64) -> u64 {
+        async_op
+            .stagnation()
+            .map_or(0, |stagnation| stagnation.task_polls())
+    }
+}
+
+/// Warns about spawn locations which spawn many tasks that each complete
+/// quickly, since spawning and dropping tasks has a cost that may outweigh the
+/// work they do.
+///
+/// This is a warning about a location rather than a task, since each of the
+/// tasks is fine on its own.
+#[derive(Clone, Debug)]
+pub(crate) struct HighChurn {
+    min_spawns: usize,
+    max_lifetime: Duration,
+    description: String,
+}
+
+impl HighChurn {
+    pub(crate) const DEFAULT_MIN_SPAWNS: usize = 1000;
+    pub(crate) const DEFAULT_MAX_LIFETIME: Duration = Duration::from_millis(10);
+    pub(crate) fn new(min_spawns: usize, max_lifetime: Duration) -> Self {
+        Self {
+            min_spawns,
+            max_lifetime,
+            description: format!(
+                "locations have spawned over {} tasks in {}, living under {} on average",
+                min_spawns,
+                humantime::format_duration(SpawnLocation::WINDOW),
+                humantime::format_duration(max_lifetime)
+            ),
+        }
+    }
+}
+
+impl Default for HighChurn {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MIN_SPAWNS, Self::DEFAULT_MAX_LIFETIME)
+    }
+}
+
+impl Warn<SpawnLocation> for HighChurn {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, location: &SpawnLocation, _: SystemTime) -> bool {
+        location.spawns() > self.min_spawns
+            && matches!(location.average_lifetime(), Some(lifetime) if lifetime < self.max_lifetime)
+    }
+
+    fn format(&self, location: &SpawnLocation, _: u64, _: SystemTime) -> String {
+        format!(
+            "{} spawns {:.1} tasks per second, which live {:.1?} on average",
+            location.location(),
+            location.spawn_rate(),
+            location.average_lifetime().unwrap_or_default()
+        )
+    }
+
+    fn score(&self, location: &SpawnLocation, _: u64) -> u64 {
+        location.spawns() as u64
+    }
+
+    /// Spawns age out of the window without the location being updated.
+    fn recheck(&self, _: &SpawnLocation) -> bool {
+        true
+    }
+}
+
+/// Warns about resources with an unusually large number of pending async ops,
+/// such as a semaphore with many waiters or a channel that is not being
+/// received from quickly enough, which often indicates backpressure.
+#[derive(Clone, Debug)]
+pub(crate) struct PendingAsyncOps {
+    max_pending: usize,
+    description: String,
+}
+
+impl PendingAsyncOps {
+    pub(crate) const DEFAULT_MAX_PENDING: usize = 100;
+    pub(crate) fn new(max_pending: usize) -> Self {
+        Self {
+            max_pending,
+            description: format!(
+                "resources have an unusually high number of pending operations (over {})",
+                max_pending
+            ),
+        }
+    }
+}
+
+impl Default for PendingAsyncOps {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_PENDING)
+    }
+}
+
+impl Warn<Resource> for PendingAsyncOps {
+    fn summary(&self) -> &str {
+        self.description.as_str()
+    }
+
+    fn check(&self, resource: &Resource, _: SystemTime) -> bool {
+        !resource.dropped() && resource.pending_async_ops() > self.max_pending
+    }
+
+    fn format(&self, resource: &Resource, _: u64, _: SystemTime) -> String {
+        format!(
+            "This {} (ID {}) has {} pending async ops, more than {}",
+            resource.concrete_type(),
+            resource.id(),
+            resource.pending_async_ops(),
+            self.max_pending
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{test_util::*, State};
+    use console_api as proto;
+
+    /// Returns a state with the task `1`, created at zero and updated at
+    /// `now` with the given number of wakes and self-wakes.
+    fn woken_task(now: SystemTime, wakes: u64, self_wakes: u64) -> State {
+        let mut state = State::default();
+        send_task(&mut state, now, 1, woken(wakes, self_wakes));
+        state
+    }
+
+    fn woken(wakes: u64, self_wakes: u64) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            wakes,
+            self_wakes,
+            ..task_stats(at(0))
+        }
+    }
+
+    fn lint(state: &State, linter: &Linter<Task>, now: SystemTime) -> Vec<Linter<Task>> {
+        let task = state.tasks_state().task(1).expect("the task should exist");
+        let task = task.borrow();
+        Linter::lint_all(std::slice::from_ref(linter), 1, &task, now)
+    }
+
+    #[test]
+    fn self_wake_percent_is_ok_for_a_task_which_was_never_woken() {
+        let now = at(1_000);
+        let state = woken_task(now, 0, 0);
+        let task = state.tasks_state().task(1).unwrap();
+        assert_eq!(task.borrow().self_wake_percent(), 0);
+
+        let linter = Linter::new(SelfWakePercent::new(0));
+        assert!(lint(&state, &linter, now).is_empty());
+    }
+
+    #[test]
+    fn self_wake_percent_formats_the_checked_percentage() {
+        let now = at(1_000);
+        let mut state = woken_task(now, 10, 8);
+        let linter = Linter::new(SelfWakePercent::default());
+        let warnings = lint(&state, &linter, now);
+        assert_eq!(warnings.len(), 1);
+
+        // The task wakes itself even more often afterwards, but the warning
+        // still describes what was checked.
+        let later = at(2_000);
+        send_task(&mut state, later, 1, woken(10, 9));
+        let task = state.tasks_state().task(1).unwrap();
+        let task = task.borrow();
+        assert_eq!(task.self_wake_percent(), 90);
+        assert_eq!(
+            warnings[0].format(&task, later),
+            "This task has woken itself for more than 50% of its total wakeups (80%)"
+        );
+    }
+
+    #[test]
+    fn linters_are_only_active_while_an_entity_holds_their_warning() {
+        let now = at(1_000);
+        let state = woken_task(now, 10, 8);
+        let linter = Linter::new(SelfWakePercent::default());
+        assert!(!linter.is_active());
+
+        let warnings = lint(&state, &linter, now);
+        assert!(linter.is_active());
+        assert_eq!(linter.count(), 1);
+
+        drop(warnings);
+        assert!(!linter.is_active());
+    }
+
+    #[test]
+    fn acknowledgements_are_dropped_when_the_entity_is_forgotten() {
+        let now = at(1_000);
+        let state = woken_task(now, 10, 8);
+        let linter = Linter::new(SelfWakePercent::default());
+        let warnings = lint(&state, &linter, now);
+        warnings[0].acknowledge(1);
+        assert_eq!(linter.acknowledgements(), [(1, 80)]);
+
+        linter.forget(1);
+        assert!(linter.acknowledgements().is_empty());
+    }
+
+    #[test]
+    fn slow_scheduling_describes_and_scores_the_checked_average() {
+        let now = at(1_000);
+        let mut state = State::default();
+        let stats = proto::tasks::Stats {
+            wakes: 10,
+            scheduled_time: Some(Duration::from_millis(100).into()),
+            ..task_stats(at(0))
+        };
+        send_task(&mut state, now, 1, stats);
+        let warnings = lint(&state, &Linter::new(SlowScheduling::default()), now);
+        assert_eq!(warnings.len(), 1);
+
+        let task = state.tasks_state().task(1).unwrap();
+        let task = task.borrow();
+        let average = Duration::from_millis(10);
+        assert_eq!(warnings[0].score(&task), average.as_nanos() as u64);
+        assert_eq!(
+            warnings[0].format(&task, now),
+            "This task has waited 10.00ms on average to be polled after each of its 10 wakes, \
+             so the executor may be oversubscribed"
+        );
+    }
+
+    /// Stats for a task which was polled `polls` times, the last time from
+    /// `started` for 100ms, and woken 100ms after that poll ended, having
+    /// waited `scheduled` milliseconds to be polled before then.
+    fn polled_and_woken(polls: u64, started: u64, scheduled: u64) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            wakes: polls,
+            last_wake: Some(at(started + 200).into()),
+            scheduled_time: Some(Duration::from_millis(scheduled).into()),
+            poll_stats: Some(proto::PollStats {
+                polls,
+                last_poll_started: Some(at(started).into()),
+                last_poll_ended: Some(at(started + 100).into()),
+                ..Default::default()
+            }),
+            ..task_stats(at(0))
+        }
+    }
+
+    #[test]
+    fn starved_waits_for_the_window_without_poll_progress() {
+        let mut state = State::default();
+        send_task(&mut state, at(1_000), 1, polled_and_woken(1, 100, 100));
+        let linter = Linter::new(Starved::default());
+        // The task has spent 80% of its time waiting, but has only just been
+        // seen waiting.
+        assert!(lint(&state, &linter, at(1_000)).is_empty());
+
+        let now = at(3_000);
+        let warnings = lint(&state, &linter, now);
+        assert_eq!(warnings.len(), 1);
+        let task = state.tasks_state().task(1).unwrap();
+        assert_eq!(
+            warnings[0].format(&task.borrow(), now),
+            "This task has spent 93% of the 3s since it was spawned waiting to be polled, \
+             and has not been polled for 2s, so the scheduler may be starving it"
+        );
+
+        // Once the task is polled again, the window starts over.
+        let later = at(3_500);
+        send_task(&mut state, later, 1, polled_and_woken(2, 3_000, 2_800));
+        assert!(lint(&state, &linter, later).is_empty());
+        assert_eq!(lint(&state, &linter, at(4_500)).len(), 1);
+    }
+
+    #[test]
+    fn starved_forgets_waiting_tasks_when_the_state_is_reset() {
+        let mut state = State::default().with_task_linters(vec![Linter::new(Starved::default())]);
+        send_task(&mut state, at(1_000), 1, polled_and_woken(1, 100, 100));
+
+        // After reconnecting, the task with the same ID has only just been
+        // seen waiting, however long the task before it waited.
+        state.reset();
+        let now = at(3_000);
+        send_task(&mut state, now, 1, polled_and_woken(1, 100, 100));
+        let task = state.tasks_state().task(1).unwrap();
+        assert!(task.borrow().warnings().is_empty());
+    }
+
+    /// Stats for a task which is running its poll number `polls`, which
+    /// started at 100ms.
+    fn running(polls: u64) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            poll_stats: Some(proto::PollStats {
+                polls,
+                last_poll_started: Some(at(100).into()),
+                last_poll_ended: Some(at(50).into()),
+                ..Default::default()
+            }),
+            ..task_stats(at(0))
+        }
+    }
+
+    #[test]
+    fn never_yielded_checks_polls_up_to_the_ceiling() {
+        let now = at(2_100);
+        let mut state = State::default();
+        send_task(&mut state, now, 1, running(3));
+
+        let default = Linter::new(NeverYielded::default());
+        assert_eq!(
+            default.summary(),
+            "tasks have been running for over 1s without yielding"
+        );
+        assert!(lint(&state, &default, now).is_empty());
+
+        let linter = Linter::new(NeverYielded::new(Duration::from_secs(1), 3));
+        assert_eq!(
+            linter.summary(),
+            "tasks have been running for over 1s without yielding within their first 3 polls"
+        );
+        let warnings = lint(&state, &linter, now);
+        assert_eq!(warnings.len(), 1);
+        let task = state.tasks_state().task(1).unwrap();
+        assert_eq!(
+            warnings[0].format(&task.borrow(), now),
+            "This task has been running for 2s without yielding since poll 3 started"
+        );
+
+        send_task(&mut state, now, 1, running(4));
+        assert!(lint(&state, &linter, now).is_empty());
+    }
+
+    #[test]
+    fn never_yielded_uses_the_longest_matching_target_prefix() {
+        let secs = Duration::from_secs;
+        let never_yielded = NeverYielded::default()
+            .with_override("myapp::codec", secs(5))
+            .with_override("myapp", secs(2))
+            .with_override("myapp::codec::frame", secs(10));
+        assert_eq!(
+            never_yielded.min_duration("myapp::codec::frame::header"),
+            secs(10)
+        );
+        assert_eq!(never_yielded.min_duration("myapp::codec::frame"), secs(10));
+        assert_eq!(never_yielded.min_duration("myapp::codec"), secs(5));
+        assert_eq!(never_yielded.min_duration("myapp::control"), secs(2));
+        // Prefixes only match whole module names.
+        assert_eq!(never_yielded.min_duration("myapp::codecs"), secs(2));
+        assert_eq!(never_yielded.min_duration("myapplication"), secs(1));
+        assert_eq!(never_yielded.min_duration("other"), secs(1));
+
+        let now = at(2_100);
+        let mut state = State::default();
+        send_task(&mut state, now, 1, running(1));
+        let linter = Linter::new(NeverYielded::default().with_override("test", secs(5)));
+        assert!(lint(&state, &linter, now).is_empty());
+    }
+
+    #[test]
+    fn never_yielded_is_rechecked_until_it_warns() {
+        let mut state =
+            State::default().with_task_linters(vec![Linter::new(NeverYielded::default())]);
+        send_task(&mut state, at(500), 1, running(1));
+        let task = state.tasks_state().task(1).unwrap();
+        assert!(task.borrow().warnings().is_empty());
+        assert!(state.tasks_state().linters[0].needs_recheck(&task.borrow()));
+
+        // The task isn't updated while it runs, but is linted again as of
+        // each update's time.
+        let now = at(2_100);
+        let mut update = update(now);
+        update.task_update = Some(proto::tasks::TaskUpdate::default());
+        state.update(&styles(), &crate::view::ViewState::TasksList, update);
+        let task = task.borrow();
+        assert_eq!(task.warnings().len(), 1);
+        assert_eq!(
+            task.warnings()[0].format(&task, now),
+            "This task has been running for 2s without yielding since it was first polled"
+        );
+    }
+
+    #[test]
+    fn cpu_bound_clears_once_the_task_goes_idle() {
+        let mut state = State::default().with_task_linters(vec![Linter::new(CpuBound::default())]);
+        let stats = proto::tasks::Stats {
+            poll_stats: Some(proto::PollStats {
+                polls: 1,
+                last_poll_started: Some(at(500).into()),
+                last_poll_ended: Some(at(11_500).into()),
+                busy_time: Some(Duration::from_secs(11).into()),
+                ..Default::default()
+            }),
+            ..task_stats(at(0))
+        };
+        send_task(&mut state, at(11_500), 1, stats);
+        let task = state.tasks_state().task(1).unwrap();
+        assert_eq!(task.borrow().warnings().len(), 1);
+
+        // The task isn't updated while it's idle, but its busy percentage
+        // keeps falling.
+        let mut update = update(at(60_000));
+        update.task_update = Some(proto::tasks::TaskUpdate::default());
+        state.update(&styles(), &crate::view::ViewState::TasksList, update);
+        assert!(task.borrow().warnings().is_empty());
+    }
+
+    #[test]
+    fn high_churn_describes_durations_like_the_other_warnings() {
+        let linter = Linter::new(HighChurn::new(1000, Duration::from_millis(1_500)));
+        assert_eq!(
+            linter.summary(),
+            "locations have spawned over 1000 tasks in 10s, living under 1s 500ms on average"
+        );
+    }
+
+    fn waker_warnings(linters: &[Linter<Task>], stats: proto::tasks::Stats) -> Vec<String> {
+        let now = at(1_000);
+        let mut state = State::default();
+        send_task(&mut state, now, 1, stats);
+        let task = state.tasks_state().task(1).unwrap();
+        let task = task.borrow();
+        Linter::lint_all(linters, 1, &task, now)
+            .iter()
+            .map(|warning| warning.summary().to_owned())
+            .collect()
+    }
+
+    /// Stats for a task which was polled once, and has as many wakers as
+    /// were cloned and not dropped.
+    fn polled_with_wakers(clones: u64, drops: u64) -> proto::tasks::Stats {
+        proto::tasks::Stats {
+            waker_clones: clones,
+            waker_drops: drops,
+            poll_stats: Some(proto::PollStats {
+                polls: 1,
+                last_poll_started: Some(at(100).into()),
+                last_poll_ended: Some(at(200).into()),
+                ..Default::default()
+            }),
+            ..task_stats(at(0))
+        }
+    }
+
+    #[test]
+    fn waker_warnings_are_detected_in_one_check() {
+        let linters = Linter::many(Wakers::new(true, true));
+        assert_eq!(linters.len(), 2);
+
+        let lost = polled_with_wakers(1, 1);
+        assert_eq!(
+            waker_warnings(&linters, lost),
+            ["tasks have lost their waker"]
+        );
+        let completed = proto::tasks::Stats {
+            dropped_at: Some(at(500).into()),
+            ..polled_with_wakers(3, 1)
+        };
+        assert_eq!(
+            waker_warnings(&linters, completed),
+            ["completed tasks still have wakers"]
+        );
+        assert!(waker_warnings(&linters, polled_with_wakers(1, 0)).is_empty());
     }
 
-    fn format(&self, _: &Task) -> String {
-        "This task has lost its waker, and will never be woken again.".into()
+    #[test]
+    fn disabled_waker_warnings_are_not_detected() {
+        let linters = Linter::many(Wakers::new(false, true));
+        assert_eq!(linters.len(), 1);
+        assert!(waker_warnings(&linters, polled_with_wakers(1, 1)).is_empty());
+        assert!(Linter::<Task>::many(Wakers::new(false, false)).is_empty());
     }
 }